@@ -2,6 +2,7 @@
 
 use std::collections::HashSet;
 
+use crate::config::Config;
 use crate::parser::Message;
 use super::types::*;
 use super::util::clean_path;
@@ -27,7 +28,7 @@ fn context_chunk(context: String) -> Chunk {
 }
 
 pub fn classify_context(
-  message: &Message, fields: &mut HashSet<String>
+  _config: &Config, message: &Message, fields: &mut HashSet<String>
 ) -> Vec<Chunk> {
   let meta = &message.metadata;
 
@@ -2,6 +2,7 @@
 
 use std::collections::HashSet;
 
+use crate::config::Config;
 use crate::parser::{Message, MessageKind};
 use super::types::*;
 use super::util::clean_path;
@@ -54,7 +55,7 @@ fn extract_context(message: &Message) -> Option<Chunk> {
   })
 }
 
-pub fn classify_kelog(message: &Message, fields: &mut HashSet<String>) -> Vec<Chunk> {
+pub fn classify_kelog(_config: &Config, message: &Message, fields: &mut HashSet<String>) -> Vec<Chunk> {
   let mut ret = Vec::new();
   if !is_kelog(message) {
     return ret;
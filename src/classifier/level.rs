@@ -2,17 +2,49 @@
 
 use std::collections::HashSet;
 
-use crate::parser::{LogLevel, Message};
+use crate::config::{Config, LevelStyle};
+use crate::parser::{LogLevel, Message, MessageKind};
 use super::types::*;
 
+/// a one-character abbreviation for a level
+fn badge(level: LogLevel) -> &'static str {
+  match level {
+    LogLevel::Trace => "T",
+    LogLevel::Debug => "D",
+    LogLevel::Info => "I",
+    LogLevel::Notice => "N",
+    LogLevel::Warning => "W",
+    LogLevel::Error => "E",
+    LogLevel::Critical => "C",
+    LogLevel::Fatal => "F",
+    LogLevel::Plain => "-",
+    LogLevel::Int => "*"
+  }
+}
+
 pub fn classify_level(
-  message: &Message, _fields: &mut HashSet<String>
+  config: &Config, message: &Message, _fields: &mut HashSet<String>
 ) -> Vec<Chunk> {
-  let level = message.level.unwrap_or(LogLevel::Plain);
-  let level_str = level.to_string().to_lowercase();
+  let level = match message.level {
+    // internal notes are shown as `*` to set them apart from the app's logs,
+    // but internal warnings and errors are shown like any other
+    Some(level) if message.kind == MessageKind::Internal => {
+      if level < LogLevel::Warning { LogLevel::Int } else { level }
+    },
+    Some(level) => level,
+    None => LogLevel::Plain
+  };
+  let (kind, level_str) = match config.level_style {
+    LevelStyle::Text => (
+      ChunkKind::Level(level), level.to_string().to_lowercase()
+    ),
+    LevelStyle::Badge => (
+      ChunkKind::LevelBadge(level), badge(level).to_string()
+    )
+  };
 
   vec![Chunk {
-    kind: ChunkKind::Level(level),
+    kind,
     slot: ChunkSlot::Left,
     value: Some(level_str),
     weight: ChunkWeight::High.value(),
@@ -25,3 +57,66 @@ pub fn classify_level(
     ..Default::default()
   }]
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::classifier::classify_line;
+  use crate::style::StyleProfile;
+
+  fn level_chunk(args: &[&str], line: &str) -> Chunk {
+    classify_line(classify_level, args, line, None).remove(0)
+  }
+
+  #[test]
+  fn test_level_style() {
+    let line = r#"{"level":"warning","msg":"hello"}"#;
+
+    let chunk = level_chunk(&[], line);
+    assert_that!(chunk.kind).is_equal_to(ChunkKind::Level(LogLevel::Warning));
+    assert_that!(chunk.value).is_equal_to(Some("warning".to_string()));
+
+    let chunk = level_chunk(&["--level-style", "badge"], line);
+    assert_that!(chunk.kind)
+      .is_equal_to(ChunkKind::LevelBadge(LogLevel::Warning));
+    assert_that!(chunk.value).is_equal_to(Some("W".to_string()));
+
+    let chunk = level_chunk(&["--level-style", "badge"], "hello");
+    assert_that!(chunk.value).is_equal_to(Some("-".to_string()));
+
+    assert_that!(Config::from_iter_safe(vec!["", "--level-style", "icon"]))
+      .is_err();
+  }
+
+  #[test]
+  fn test_badge_style() {
+    // badges share the colors of the full level names
+    let profile = StyleProfile::default_normal();
+    assert_that!(profile.get_style(&ChunkKind::LevelBadge(LogLevel::Error)))
+      .is_equal_to(profile.get_style(&ChunkKind::Level(LogLevel::Error)));
+    assert_that!(profile.get_style(&ChunkKind::LevelBadge(LogLevel::Error)))
+      .is_not_equal_to(profile.get_base());
+  }
+
+  #[test]
+  fn test_trace() {
+    let line = r#"{"level":"trace","msg":"hello"}"#;
+
+    let chunk = level_chunk(&[], line);
+    assert_that!(chunk.kind).is_equal_to(ChunkKind::Level(LogLevel::Trace));
+    assert_that!(chunk.value).is_equal_to(Some("trace".to_string()));
+
+    let chunk = level_chunk(&["--level-style", "badge"], line);
+    assert_that!(chunk.kind).is_equal_to(ChunkKind::LevelBadge(LogLevel::Trace));
+    assert_that!(chunk.value).is_equal_to(Some("T".to_string()));
+
+    // trace has its own color, distinct from debug
+    let profile = StyleProfile::default_normal();
+    assert_that!(profile.get_style(&ChunkKind::Level(LogLevel::Trace)))
+      .is_not_equal_to(profile.get_style(&ChunkKind::Level(LogLevel::Debug)));
+  }
+}
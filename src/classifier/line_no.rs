@@ -0,0 +1,65 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::HashSet;
+
+use crate::config::Config;
+use crate::parser::Message;
+use super::types::*;
+
+/// minimum width of the line number column, so that messages stay aligned in
+/// all but very long inputs
+const WIDTH: usize = 6;
+
+/// with `--line-numbers`, shows the message's record number within its input
+///
+/// messages without one (e.g. internal messages) get a blank column of the same
+/// width
+pub fn classify_line_no(
+  config: &Config, message: &Message, _fields: &mut HashSet<String>
+) -> Vec<Chunk> {
+  if !config.line_numbers {
+    return Vec::new();
+  }
+
+  let line_no = message.reader_metadata.as_ref().and_then(|m| m.line_no);
+  let value = match line_no {
+    Some(line_no) => format!("{:>width$}", line_no, width = WIDTH),
+    None => " ".repeat(WIDTH)
+  };
+
+  vec![Chunk {
+    kind: ChunkKind::Context,
+    slot: ChunkSlot::Left,
+
+    alignment: ChunkAlignment::Right,
+    weight: ChunkWeight::Medium.value(),
+    pad_right: true,
+
+    value: Some(value),
+
+    ..Default::default()
+  }]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  use crate::classifier::classify_line;
+  use crate::parser::ReaderMetadata;
+
+  fn gutter(meta: Option<ReaderMetadata>) -> Option<String> {
+    classify_line(classify_line_no, &["--line-numbers"], "hello", meta)
+      .pop()
+      .and_then(|c| c.value)
+  }
+
+  #[test]
+  fn test_line_no() {
+    assert_that!(gutter(Some(ReaderMetadata::line(42))))
+      .is_equal_to(Some("    42".to_string()));
+    assert_that!(gutter(None)).is_equal_to(Some("      ".to_string()));
+  }
+}
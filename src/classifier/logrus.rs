@@ -2,6 +2,7 @@
 
 use std::collections::HashSet;
 
+use crate::config::Config;
 use crate::parser::Message;
 use super::types::*;
 
@@ -30,7 +31,7 @@ fn is_logrus(message: &Message) -> bool {
 }
 
 pub fn classify_logrus(
-  message: &Message, fields: &mut HashSet<String>
+  _config: &Config, message: &Message, fields: &mut HashSet<String>
 ) -> Vec<Chunk> {
   if !is_logrus(message) {
     return vec![];
@@ -4,21 +4,54 @@ use std::collections::HashSet;
 
 use serde_json::Value;
 
+use crate::config::{Config, FieldOrder};
 use crate::parser::Message;
+use crate::parser::util::nicer_to_string;
+use crate::renderer::glyphs::glyphs;
 use super::types::*;
 
-/// to_string with a special case for actual strings
-/// by default serde stringifies them json style i.e. quotes them
-/// this unpacks them to avoid printing undesired quotes
-fn nicer_to_string(value: &Value) -> String {
-  if let Some(val) = value.as_str() {
-    String::from(val)
-  } else {
-    value.to_string()
+/// shortens a value to at most `max_width` characters by replacing its middle
+/// with an ellipsis, as both ends of long ids and tokens tend to be the
+/// distinguishing parts
+///
+/// the ellipsis is shortened if needed to keep at least a character from
+/// each end, e.g. `...` at narrow widths with `--ascii`
+fn elide(value: String, max_width: usize, ellipsis: &str) -> String {
+  let len = value.chars().count();
+  if len <= max_width {
+    return value;
   }
+
+  let ellipsis_len = ellipsis.chars().count()
+    .min(max_width.saturating_sub(2))
+    .max(1);
+  let ellipsis: String = ellipsis.chars().take(ellipsis_len).collect();
+  let keep = max_width.saturating_sub(ellipsis_len);
+  let tail_len = keep / 2;
+  let head: String = value.chars().take(keep - tail_len).collect();
+  let tail: String = value.chars().skip(len - tail_len).collect();
+
+  format!("{}{}{}", head, ellipsis, tail)
 }
 
-fn field_to_chunk((key, val): (&String, &Value)) -> Chunk {
+fn value_kind(value: &Value) -> ValueKind {
+  match value {
+    Value::String(_) => ValueKind::String,
+    Value::Number(_) => ValueKind::Number,
+    Value::Bool(_) => ValueKind::Bool,
+    Value::Null => ValueKind::Null,
+    _ => ValueKind::Other
+  }
+}
+
+fn field_to_chunk(
+  (key, val): (&String, &Value), max_width: Option<usize>
+) -> Chunk {
+  let value = match max_width {
+    Some(max_width) => elide(nicer_to_string(val), max_width, glyphs().ellipsis),
+    None => nicer_to_string(val)
+  };
+
   Chunk {
     kind: ChunkKind::Field,
     slot: ChunkSlot::Center,
@@ -42,14 +75,14 @@ fn field_to_chunk((key, val): (&String, &Value)) -> Chunk {
         ..Default::default()
       },
       Chunk {
-        kind: ChunkKind::FieldValue,
+        kind: ChunkKind::FieldValue(value_kind(val)),
         slot: ChunkSlot::Left,
 
         pad_right: true,
         wrap: true,
 
         weight: ChunkWeight::Normal.value(),
-        value: Some(nicer_to_string(val)),
+        value: Some(value),
 
         ..Default::default()
       }
@@ -60,19 +93,97 @@ fn field_to_chunk((key, val): (&String, &Value)) -> Chunk {
 }
 
 pub fn classify_metadata(
-  message: &Message, fields: &mut HashSet<String>
+  config: &Config, message: &Message, fields: &mut HashSet<String>
 ) -> Vec<Chunk> {
-  let mut fields: Vec<Chunk> = message.metadata.iter()
+  let mut entries: Vec<(&String, &Value)> = message.metadata.iter()
     .filter(|(key, _)| !fields.contains(*key))
     .filter(|(_, val)| !nicer_to_string(val).is_empty())
-    .map(field_to_chunk)
     .collect();
 
-  // todo: hoisting out measure here could save up to 5% perf
-  // could also use .len() rather than .chars().count() for a rougher but faster
-  // alternative
-  // also consider a BinaryHeap or other sorted data structure?
-  fields.sort_by_key(|c| c.measure());
+  if config.field_order == FieldOrder::Alpha {
+    entries.sort_by_key(|(key, _)| *key);
+  }
+
+  let mut fields: Vec<Chunk> = entries.into_iter()
+    .map(|field| field_to_chunk(field, config.value_max_width))
+    .collect();
+
+  if config.field_order == FieldOrder::Width {
+    // todo: hoisting out measure here could save up to 5% perf
+    // could also use .len() rather than .chars().count() for a rougher but
+    // faster alternative
+    // also consider a BinaryHeap or other sorted data structure?
+    fields.sort_by_key(|c| c.measure());
+  }
 
   fields
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  use crate::classifier::classify_line;
+
+  fn field_keys(order: &str) -> Vec<String> {
+    let line = r#"{"msg": "hi", "zeta": "a", "alpha": "long value", "mid": 1}"#;
+
+    classify_line(classify_metadata, &["--field-order", order], line, None)
+      .iter()
+      .filter_map(|c| c.children[0].value.clone())
+      .collect()
+  }
+
+  #[test]
+  fn test_field_order() {
+    assert_that!(field_keys("original"))
+      .is_equal_to(vec!["zeta=".to_string(), "alpha=".to_string(), "mid=".to_string()]);
+    assert_that!(field_keys("alpha"))
+      .is_equal_to(vec!["alpha=".to_string(), "mid=".to_string(), "zeta=".to_string()]);
+    assert_that!(field_keys("width"))
+      .is_equal_to(vec!["mid=".to_string(), "zeta=".to_string(), "alpha=".to_string()]);
+  }
+
+  #[test]
+  fn test_value_kinds() {
+    let line = r#"{"msg":"hi","a":"x","b":1.5,"c":true,"d":null,"e":[1]}"#;
+    let args = &["--field-order", "original"];
+
+    let kinds: Vec<ChunkKind> = classify_line(
+      classify_metadata, args, line, None
+    ).iter().map(|c| c.children[1].kind).collect();
+
+    assert_that!(kinds).is_equal_to(vec![
+      ChunkKind::FieldValue(ValueKind::String),
+      ChunkKind::FieldValue(ValueKind::Number),
+      ChunkKind::FieldValue(ValueKind::Bool),
+      ChunkKind::FieldValue(ValueKind::Null),
+      ChunkKind::FieldValue(ValueKind::Other)
+    ]);
+  }
+
+  #[test]
+  fn test_elide() {
+    let elide = |s: &str, width| elide(String::from(s), width, "…");
+    assert_that!(elide("short", 10)).is_equal_to(String::from("short"));
+    assert_that!(elide("abcdefghijklmnop", 9))
+      .is_equal_to(String::from("abcd…mnop"));
+    assert_that!(elide("abcdefghijklmnop", 8))
+      .is_equal_to(String::from("abcd…nop"));
+    assert_that!(elide("abcdefghijklmnop", 3)).is_equal_to(String::from("a…p"));
+  }
+
+  #[test]
+  fn test_elide_ascii() {
+    let elide = |s: &str, width| elide(String::from(s), width, "...");
+    assert_that!(elide("abcdefghijklmnop", 9))
+      .is_equal_to(String::from("abc...nop"));
+    assert_that!(elide("abcdefghijklmnop", 5)).is_equal_to(String::from("a...p"));
+
+    // the ellipsis shrinks so both ends are still shown
+    assert_that!(elide("abcdefghijklmnop", 4)).is_equal_to(String::from("a..p"));
+    assert_that!(elide("abcdefghijklmnop", 3)).is_equal_to(String::from("a.p"));
+  }
+}
@@ -3,20 +3,28 @@
 mod context;
 mod kelog;
 mod level;
+mod line_no;
 mod logrus;
 mod metadata;
+mod quoted;
 mod source;
 mod text;
 mod timestamp;
 mod types;
+mod unparsed;
 mod util;
 
 use std::collections::HashSet;
 
 pub use types::*;
+pub use timestamp::relative_time;
+pub use source::strip_common;
+use crate::config::Config;
 use crate::parser::Message;
 
 static CLASSIFIERS: &[Classifier] = &[
+  unparsed::classify_unparsed,
+  line_no::classify_line_no,
   timestamp::classify_timestamp,
   level::classify_level,
   source::classify_source,
@@ -27,10 +35,33 @@ static CLASSIFIERS: &[Classifier] = &[
   metadata::classify_metadata
 ];
 
-pub fn classify(message: &Message) -> Vec<Chunk> {
+pub fn classify(config: &Config, message: &Message) -> Vec<Chunk> {
   let mut consumed_fields: HashSet<String> = HashSet::new();
 
-  CLASSIFIERS.iter()
-    .flat_map(|c| c(message, &mut consumed_fields))
-    .collect()
+  let chunks = CLASSIFIERS.iter()
+    .flat_map(|c| c(config, message, &mut consumed_fields))
+    .collect();
+
+  // splits up the text chunks from classify_text, so it runs last
+  quoted::classify_quoted(config, message, chunks)
+}
+
+/// parses a line with the given command line arguments and runs a single
+/// classifier over it, for testing classifiers in isolation
+#[cfg(test)]
+fn classify_line(
+  classifier: Classifier,
+  args: &[&str], line: &str, meta: Option<crate::parser::ReaderMetadata>
+) -> Vec<Chunk> {
+  use std::iter;
+  use std::sync::Arc;
+
+  use structopt::StructOpt;
+
+  let args = iter::once("").chain(args.iter().cloned());
+  let config = Arc::new(Config::from_iter_safe(args).unwrap());
+  let message = crate::parser::parse(Arc::clone(&config), line, meta)
+    .unwrap().unwrap();
+
+  classifier(&config, &message, &mut HashSet::new())
 }
@@ -0,0 +1,176 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use crate::config::Config;
+use crate::parser::Message;
+use super::types::*;
+
+/// true if the character at `index` is escaped by an odd number of backslashes
+fn is_escaped(chars: &[char], index: usize) -> bool {
+  chars[..index].iter().rev().take_while(|c| **c == '\\').count() % 2 == 1
+}
+
+/// true if a quote may open or close next to this character, i.e. it isn't
+/// part of a word like `don't`
+fn is_boundary(c: Option<&char>) -> bool {
+  match c {
+    Some(c) => !c.is_alphanumeric(),
+    None => true
+  }
+}
+
+/// splits a line into spans, returning (span, is_quoted) pairs
+///
+/// quotes only count at word boundaries and must be balanced, so apostrophes
+/// and stray quotes are left alone
+pub fn split_quoted(line: &str) -> Vec<(String, bool)> {
+  let chars: Vec<char> = line.chars().collect();
+
+  let mut spans = Vec::new();
+  let mut last = 0;
+  let mut i = 0;
+  while i < chars.len() {
+    let quote = chars[i];
+    let opens = (quote == '"' || quote == '\'')
+      && !is_escaped(&chars, i)
+      && is_boundary(i.checked_sub(1).and_then(|p| chars.get(p)));
+
+    if opens {
+      let close = (i + 1..chars.len()).find(|j| {
+        chars[*j] == quote
+          && !is_escaped(&chars, *j)
+          && is_boundary(chars.get(j + 1))
+      });
+
+      if let Some(close) = close {
+        if i > last {
+          spans.push((chars[last..i].iter().collect(), false));
+        }
+
+        spans.push((chars[i..=close].iter().collect(), true));
+        last = close + 1;
+        i = close + 1;
+        continue;
+      }
+    }
+
+    i += 1;
+  }
+
+  if last < chars.len() {
+    spans.push((chars[last..].iter().collect(), false));
+  }
+
+  spans
+}
+
+/// splits a text chunk into plain and quoted chunks, or returns None if it
+/// contains no quoted strings
+fn split_chunk(chunk: &Chunk) -> Option<Vec<Chunk>> {
+  let value = chunk.value.as_ref()?;
+  if !value.contains(&['"', '\''][..]) {
+    return None;
+  }
+
+  let spans = split_quoted(value);
+  if !spans.iter().any(|(_, quoted)| *quoted) {
+    return None;
+  }
+
+  // whitespace between spans becomes padding so the spans can still wrap
+  let mut chunks: Vec<Chunk> = Vec::new();
+  let mut pad_next = chunk.pad_left;
+  for (span, quoted) in spans {
+    let trimmed = if quoted { span.as_str() } else { span.trim() };
+    if trimmed.is_empty() {
+      pad_next = true;
+      continue;
+    }
+
+    chunks.push(Chunk {
+      kind: if quoted { ChunkKind::Quoted } else { ChunkKind::Text },
+      value: Some(trimmed.to_string()),
+      pad_left: pad_next || span.starts_with(char::is_whitespace),
+      pad_right: span.ends_with(char::is_whitespace),
+      break_after: false,
+      force_break_after: false,
+
+      ..chunk.clone()
+    });
+
+    pad_next = false;
+  }
+
+  if let Some(last) = chunks.last_mut() {
+    last.pad_right = chunk.pad_right;
+    last.break_after = chunk.break_after;
+    last.force_break_after = chunk.force_break_after;
+  }
+
+  Some(chunks)
+}
+
+/// highlights quoted strings within text chunks
+///
+/// unlike other classifiers this rewrites existing chunks, so it must run
+/// after `classify_text`
+pub fn classify_quoted(
+  _config: &Config, _message: &Message, chunks: Vec<Chunk>
+) -> Vec<Chunk> {
+  chunks.into_iter()
+    .flat_map(|chunk| match chunk.kind {
+      ChunkKind::Text => split_chunk(&chunk).unwrap_or_else(|| vec![chunk]),
+      _ => vec![chunk]
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  fn expected(spans: &[(&str, bool)]) -> Vec<(String, bool)> {
+    spans.iter().map(|(s, q)| (s.to_string(), *q)).collect()
+  }
+
+  #[test]
+  fn test_split_quoted() {
+    assert_that!(split_quoted("no quotes here")).is_equal_to(expected(&[
+      ("no quotes here", false)
+    ]));
+
+    assert_that!(split_quoted(r#"error opening file "/etc/app.conf": denied"#))
+      .is_equal_to(expected(&[
+        ("error opening file ", false),
+        (r#""/etc/app.conf""#, true),
+        (": denied", false)
+      ]));
+
+    assert_that!(split_quoted("loading 'a' and 'b'")).is_equal_to(expected(&[
+      ("loading ", false),
+      ("'a'", true),
+      (" and ", false),
+      ("'b'", true)
+    ]));
+  }
+
+  #[test]
+  fn test_split_quoted_prose() {
+    // apostrophes and unbalanced quotes are left alone
+    assert_that!(split_quoted("don't stop, it's fine")).is_equal_to(expected(&[
+      ("don't stop, it's fine", false)
+    ]));
+
+    assert_that!(split_quoted(r#"a "dangling quote"#)).is_equal_to(expected(&[
+      (r#"a "dangling quote"#, false)
+    ]));
+
+    // escaped quotes don't end the string
+    assert_that!(split_quoted(r#"got "say \"hi\"" back"#)).is_equal_to(expected(&[
+      ("got ", false),
+      (r#""say \"hi\"""#, true),
+      (" back", false)
+    ]));
+  }
+}
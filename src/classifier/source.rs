@@ -1,19 +1,106 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
+use crate::config::Config;
 use crate::parser::Message;
 use super::types::*;
 
+/// separators between levels of a source, e.g. `namespace/pod/container`;
+/// common prefixes and suffixes are preferentially cut at these
+static PATH_SEPARATORS: &[char] = &['/', ':'];
+
+/// separators between words in a source name, used if no path separator is
+/// available
+static WORD_SEPARATORS: &[char] = &['-', '.', '_'];
+
+/// length in bytes of the longest common prefix of two strings
+fn common_prefix_len(a: &str, b: &str) -> usize {
+  a.char_indices()
+    .zip(b.chars())
+    .take_while(|((_, x), y)| x == y)
+    .map(|((i, x), _)| i + x.len_utf8())
+    .last()
+    .unwrap_or(0)
+}
+
+/// length in bytes of the longest common suffix of two strings
+fn common_suffix_len(a: &str, b: &str) -> usize {
+  a.chars().rev()
+    .zip(b.chars().rev())
+    .take_while(|(x, y)| x == y)
+    .map(|(x, _)| x.len_utf8())
+    .sum()
+}
+
+/// strips the prefix and suffix shared by all known sources from `source`
+///
+/// cuts are only made at separators, preferring path separators so that e.g.
+/// `ns/web-1/app` and `ns/web-2/app` become `web-1` and `web-2`; a source is
+/// never stripped to nothing
+///
+/// the known sources depend on what has arrived so far, so this is applied by
+/// renderers (per `--strip-source-common`) rather than at classification time
+pub fn strip_common<'a>(sources: &BTreeSet<String>, source: &'a str) -> &'a str {
+  if sources.len() < 2 {
+    return source;
+  }
+
+  let first = match sources.iter().next() {
+    Some(first) => first,
+    None => return source
+  };
+
+  let prefix = sources.iter()
+    .map(|s| common_prefix_len(first, s))
+    .min()
+    .unwrap_or(0);
+  let prefix = &first[..prefix];
+  let prefix = prefix.rfind(PATH_SEPARATORS)
+    .or_else(|| prefix.rfind(WORD_SEPARATORS))
+    .map(|i| i + 1)
+    .unwrap_or(0);
+
+  let suffix = sources.iter()
+    .map(|s| common_suffix_len(first, s))
+    .min()
+    .unwrap_or(0);
+  let suffix = &first[first.len() - suffix..];
+  let suffix = suffix.find(PATH_SEPARATORS)
+    .or_else(|| suffix.find(WORD_SEPARATORS))
+    .map(|i| suffix.len() - i)
+    .unwrap_or(0);
+
+  if prefix + suffix >= source.len() {
+    return source;
+  }
+
+  &source[prefix..source.len() - suffix]
+}
+
+/// determines the displayed form of a source, per `--strip-source-prefix`
+///
+/// the full source is left in the message's reader metadata so filters still
+/// match against it
+fn display_source(config: &Config, source: &str) -> String {
+  match &config.strip_source_prefix {
+    Some(re) => match re.replace(source, "").to_string() {
+      ref s if s.is_empty() => source.to_string(),
+      s => s
+    },
+    None => source.to_string()
+  }
+}
+
 pub fn classify_source(
-  message: &Message, _fields: &mut HashSet<String>
+  config: &Config, message: &Message, _fields: &mut HashSet<String>
 ) -> Vec<Chunk> {
   if let Some(meta) = &message.reader_metadata {
     if let Some(source) = &meta.source {
       return vec![Chunk {
-        kind: ChunkKind::Context,
+        kind: ChunkKind::Source,
         slot: ChunkSlot::Right,
-        value: Some(source.to_string()),
+        value: Some(display_source(config, source)),
         weight: ChunkWeight::Normal.value(),
 
         pad_left: true,
@@ -28,3 +115,37 @@ pub fn classify_source(
 
   vec![]
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  fn sources(names: &[&str]) -> BTreeSet<String> {
+    names.iter().map(|s| s.to_string()).collect()
+  }
+
+  #[test]
+  fn test_strip_common_single() {
+    let seen = sources(&["default/web-1/nginx"]);
+    assert_that!(strip_common(&seen, "default/web-1/nginx"))
+      .is_equal_to("default/web-1/nginx");
+  }
+
+  #[test]
+  fn test_strip_common_prefix_suffix() {
+    let seen = sources(&["default/web-1/nginx", "default/web-2/nginx"]);
+    assert_that!(strip_common(&seen, "default/web-1/nginx"))
+      .is_equal_to("web-1");
+    assert_that!(strip_common(&seen, "default/web-2/nginx"))
+      .is_equal_to("web-2");
+  }
+
+  #[test]
+  fn test_strip_common_never_empty() {
+    let seen = sources(&["app-x", "app-x-x"]);
+    assert_that!(strip_common(&seen, "app-x")).is_equal_to("app-x");
+    assert_that!(strip_common(&seen, "app-x-x")).is_equal_to("x");
+  }
+}
@@ -2,10 +2,11 @@
 
 use std::collections::HashSet;
 
+use crate::config::Config;
 use crate::parser::Message;
 use super::types::*;
 
-pub fn classify_text(message: &Message, _fields: &mut HashSet<String>) -> Vec<Chunk> {
+pub fn classify_text(_config: &Config, message: &Message, _fields: &mut HashSet<String>) -> Vec<Chunk> {
   if let Some(text) = &message.text {
     let lines: Vec<&str> = text.lines().collect();
     let mut ret = Vec::new();
@@ -2,33 +2,63 @@
 
 use std::collections::HashSet;
 
-use chrono::Local;
+use chrono::{DateTime, Duration, Utc};
 
+use crate::config::Config;
 use crate::parser::Message;
 use super::types::*;
 
-pub fn classify_timestamp(message: &Message, _fields: &mut HashSet<String>) -> Vec<Chunk> {
-  let maybe_timestamp = if let Some(timestamp) = &message.timestamp {
-    Some(*timestamp)
-  } else if let Some(meta) = &message.reader_metadata {
-    if let Some(timestamp) = meta.timestamp {
-      Some(timestamp)
-    } else {
-      None
-    }
-  } else {
-    None
+/// the message's own timestamp, or else one from its reader
+fn get_timestamp(message: &Message) -> Option<DateTime<Utc>> {
+  message.timestamp.or_else(|| {
+    message.reader_metadata.as_ref().and_then(|meta| meta.timestamp)
+  })
+}
+
+/// formats the time elapsed since a message in its largest whole unit, e.g.
+/// `-3s`, `-2m`, `-1h`, or `-2y` (of 365 days); times in the future are
+/// prefixed with `+`
+fn format_relative(elapsed: Duration) -> String {
+  let seconds = elapsed.num_seconds();
+  let abs = seconds.abs();
+
+  let (value, unit) = match abs {
+    0..=59 => (abs, "s"),
+    60..=3599 => (abs / 60, "m"),
+    3600..=86399 => (abs / 3600, "h"),
+    86400..=31_535_999 => (abs / 86400, "d"),
+    _ => (abs / 31_536_000, "y")
   };
 
-  let formatted_date;
-  let formatted_time;
-  if let Some(timestamp) = maybe_timestamp {
-    let local = timestamp.with_timezone(&Local);
-    formatted_date = local.format("%Y-%m-%d").to_string();
-    formatted_time = local.format("%H:%M:%S").to_string();
+  let sign = match seconds {
+    0 => "",
+    s if s > 0 => "-",
+    _ => "+"
+  };
+
+  format!("{}{}{}", sign, value, unit)
+}
+
+/// the time elapsed between a message and `now`, for `--relative-time`
+pub fn relative_time(message: &Message, now: DateTime<Utc>) -> String {
+  match get_timestamp(message) {
+    Some(timestamp) => format_relative(now.signed_duration_since(timestamp)),
+    None => "-".to_string()
+  }
+}
+
+pub fn classify_timestamp(config: &Config, message: &Message, _fields: &mut HashSet<String>) -> Vec<Chunk> {
+  let (formatted_date, formatted_time) = if config.relative_time {
+    // dates are redundant with relative times, so they're hidden
+    (None, Some(relative_time(message, Utc::now())))
+  } else if let Some(timestamp) = get_timestamp(message) {
+    let timezone = config.timezone;
+    (
+      Some(timezone.format(timestamp, &config.date_format)),
+      Some(timezone.format(timestamp, config.time_format()))
+    )
   } else {
-    formatted_date = "-".to_string();
-    formatted_time = "-".to_string()
+    (Some("-".to_string()), Some("-".to_string()))
   };
 
   vec![
@@ -40,7 +70,8 @@ pub fn classify_timestamp(message: &Message, _fields: &mut HashSet<String>) -> V
       weight: ChunkWeight::Normal.value(),
       pad_right: true,
 
-      value: Some(formatted_date),
+      // an empty format hides the column entirely
+      value: formatted_date.filter(|_| !config.date_format.is_empty()),
 
       ..Default::default()
     },
@@ -52,9 +83,112 @@ pub fn classify_timestamp(message: &Message, _fields: &mut HashSet<String>) -> V
       weight: ChunkWeight::Medium.value(),
       pad_right: true,
 
-      value: Some(formatted_time),
+      value: formatted_time.filter(|_| {
+        config.relative_time || !config.time_format().is_empty()
+      }),
 
       ..Default::default()
     },
   ]
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::classifier::classify_line;
+  use crate::parser::parse;
+
+  fn values(args: &[&str], line: &str) -> Vec<Option<String>> {
+    classify_line(classify_timestamp, args, line, None)
+      .into_iter()
+      .map(|chunk| chunk.value)
+      .collect()
+  }
+
+  fn classify_in(timezone: &str) -> Vec<Option<String>> {
+    // `=` keeps negative offsets from being mistaken for flags
+    let timezone = format!("--timezone={}", timezone);
+    let line = r#"{"time":"2020-01-01T23:30:00Z","msg":"hello"}"#;
+
+    values(&[&timezone], line)
+  }
+
+  #[test]
+  fn test_formats() {
+    let args = &[
+      "--timezone", "utc", "--date-format", "", "--time-format", "%H:%M:%S%.3f"
+    ];
+    let line = r#"{"time":"2020-01-01T23:30:00.123456Z","msg":"hello"}"#;
+
+    assert_that!(values(args, line)).is_equal_to(vec![
+      None, Some(String::from("23:30:00.123"))
+    ]);
+  }
+
+  #[test]
+  fn test_precision() {
+    let args = &["--timezone", "utc", "--precision", "micros"];
+    let line = r#"{"time":"2020-01-01T23:30:00.123456Z","msg":"hello"}"#;
+
+    assert_that!(values(args, line)[1])
+      .is_equal_to(Some(String::from("23:30:00.123456")));
+  }
+
+  #[test]
+  fn test_invalid_format() {
+    let parsed = Config::from_iter_safe(vec!["", "--time-format", "%H:%Q"]);
+    assert_that!(parsed).is_err();
+  }
+
+  #[test]
+  fn test_format_relative() {
+    let format = |s| format_relative(Duration::seconds(s));
+
+    assert_that!(format(0)).is_equal_to(String::from("0s"));
+    assert_that!(format(3)).is_equal_to(String::from("-3s"));
+    assert_that!(format(59)).is_equal_to(String::from("-59s"));
+    assert_that!(format(150)).is_equal_to(String::from("-2m"));
+    assert_that!(format(3599)).is_equal_to(String::from("-59m"));
+    assert_that!(format(3600 * 5 + 10)).is_equal_to(String::from("-5h"));
+    assert_that!(format(86400 * 3)).is_equal_to(String::from("-3d"));
+    assert_that!(format(86400 * 364)).is_equal_to(String::from("-364d"));
+    assert_that!(format(86400 * 1200)).is_equal_to(String::from("-3y"));
+    assert_that!(format(-5)).is_equal_to(String::from("+5s"));
+  }
+
+  #[test]
+  fn test_relative_time() {
+    let config = Arc::new(
+      Config::from_iter_safe(vec!["", "--relative-time"]).unwrap()
+    );
+    let line = r#"{"time":"2020-01-01T23:30:00Z","msg":"hello"}"#;
+    let message = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+
+    let now = message.timestamp.unwrap() + Duration::seconds(90);
+    assert_that!(relative_time(&message, now)).is_equal_to(String::from("-1m"));
+
+    let chunks = classify_timestamp(&config, &message, &mut HashSet::new());
+    assert_that!(chunks[0].value).is_none();
+    assert_that!(chunks[1].value.as_ref().map(|v| v.starts_with('-')))
+      .is_equal_to(Some(true));
+  }
+
+  #[test]
+  fn test_timezone() {
+    assert_that!(classify_in("utc")).is_equal_to(vec![
+      Some(String::from("2020-01-01")), Some(String::from("23:30:00"))
+    ]);
+    assert_that!(classify_in("+0200")).is_equal_to(vec![
+      Some(String::from("2020-01-02")), Some(String::from("01:30:00"))
+    ]);
+    assert_that!(classify_in("-05:00")).is_equal_to(vec![
+      Some(String::from("2020-01-01")), Some(String::from("18:30:00"))
+    ]);
+  }
+}
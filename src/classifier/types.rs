@@ -3,6 +3,7 @@
 use std::collections::HashSet;
 use std::fmt;
 
+use crate::config::Config;
 use crate::parser::{LogLevel, Message};
 
 /// A ChunkKind is a loose category for types of chunks
@@ -12,22 +13,50 @@ use crate::parser::{LogLevel, Message};
 pub enum ChunkKind {
   Level(LogLevel),
 
+  /// A compact, single-character level for `--level-style badge`
+  LevelBadge(LogLevel),
+
   Date,
   Time,
   Text,
+
+  /// A quoted string within message text
+  Quoted,
+
   Context,
 
+  /// The source of a message, e.g. a pod; styled like `Context`
+  Source,
+
   /// A chunk containing an arbitrary key/value pair
   Field,
 
   /// A Field child containing a key
   FieldKey,
 
-  /// A Field child containing a value
-  FieldValue,
+  /// A Field child containing a value of the given JSON type
+  FieldValue(ValueKind),
 
   Spacer,
 
+  /// A badge counting consecutive repeats of a message
+  Repeat,
+
+  /// A gutter mark for messages no parser recognized
+  Unparsed,
+
+  Other
+}
+
+/// The JSON type of a field value, used to style values by type
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+pub enum ValueKind {
+  String,
+  Number,
+  Bool,
+  Null,
+
+  /// arrays and objects
   Other
 }
 
@@ -88,6 +117,10 @@ pub struct Chunk {
   pub weight: i8,
   pub value: Option<String>,
 
+  /// byte ranges within `value` to emphasize, e.g. search matches
+  /// these are set at render time rather than by classifiers
+  pub highlights: Vec<(usize, usize)>,
+
   pub children: Vec<Chunk>
 }
 
@@ -141,6 +174,7 @@ impl Default for Chunk {
 
       weight: ChunkWeight::Normal.value(),
       value: None,
+      highlights: Vec::new(),
 
       children: Vec::new()
     }
@@ -148,4 +182,6 @@ impl Default for Chunk {
 }
 
 /// Given some Message, a classifier generates chunks for display
-pub type Classifier = fn(message: &Message, consumed_fields: &mut HashSet<String>) -> Vec<Chunk>;
+pub type Classifier = fn(
+  config: &Config, message: &Message, consumed_fields: &mut HashSet<String>
+) -> Vec<Chunk>;
@@ -0,0 +1,67 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::HashSet;
+
+use crate::config::Config;
+use crate::parser::{Message, MessageKind};
+use crate::renderer::glyphs::glyphs;
+use super::types::*;
+
+/// with `--mark-unparsed`, adds a gutter mark to messages that fell through to
+/// the plain parser
+///
+/// other messages get a blank gutter of the same width so columns stay aligned
+pub fn classify_unparsed(
+  config: &Config, message: &Message, _fields: &mut HashSet<String>
+) -> Vec<Chunk> {
+  if !config.mark_unparsed {
+    return Vec::new();
+  }
+
+  let mark = glyphs().unparsed;
+  let value = if message.kind == MessageKind::Plain {
+    mark.to_string()
+  } else {
+    " ".repeat(mark.chars().count())
+  };
+
+  vec![Chunk {
+    kind: ChunkKind::Unparsed,
+    slot: ChunkSlot::Left,
+
+    alignment: ChunkAlignment::Left,
+    weight: ChunkWeight::High.value(),
+    pad_right: true,
+
+    value: Some(value),
+
+    ..Default::default()
+  }]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  use crate::classifier::classify_line;
+
+  fn gutter(args: &[&str], line: &str) -> Option<String> {
+    classify_line(classify_unparsed, args, line, None)
+      .pop()
+      .and_then(|c| c.value)
+  }
+
+  #[test]
+  fn test_mark_unparsed() {
+    let args = &["--mark-unparsed"];
+
+    assert_that!(gutter(args, "just some text"))
+      .is_equal_to(Some(glyphs().unparsed.to_string()));
+    assert_that!(gutter(args, r#"{"msg": "hello"}"#))
+      .is_equal_to(Some(" ".to_string()));
+
+    assert_that!(gutter(&[], "just some text")).is_none();
+  }
+}
@@ -1,30 +1,41 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
+use std::collections::HashMap;
+use std::env;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Write};
 use std::str::FromStr;
 use std::sync::Arc;
 
 use atty::{self, Stream};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use chrono::format::{Item, StrftimeItems};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 use serde::de::{self, Visitor, Deserializer};
 use shellexpand;
 use simple_error::SimpleError;
 use structopt::StructOpt;
 
-use crate::style::StyleConfig;
+use crate::filter::SearchScope;
+use crate::keys::KeyBindings;
+use crate::parser::{self, LogLevel, Parser};
+use crate::reader::history::HistoryLimit;
+use crate::style::{FieldColors, StyleConfig};
 use crate::reader;
 use crate::renderer;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RendererType {
   Auto,
   Plain,
   Raw,
   Json,
+  Ndjson,
+  Logfmt,
   Styled,
   Interactive
 }
@@ -52,6 +63,8 @@ impl RendererType {
       RendererType::Plain => renderer::plain_renderer,
       RendererType::Raw => renderer::raw_renderer,
       RendererType::Json => renderer::json_renderer,
+      RendererType::Ndjson => renderer::ndjson_renderer,
+      RendererType::Logfmt => renderer::logfmt_renderer,
       RendererType::Styled => renderer::styled_renderer,
       RendererType::Interactive => renderer::interactive_renderer,
     }
@@ -67,6 +80,8 @@ impl FromStr for RendererType {
       "plain" => Ok(RendererType::Plain),
       "raw" => Ok(RendererType::Raw),
       "json" => Ok(RendererType::Json),
+      "ndjson" => Ok(RendererType::Ndjson),
+      "logfmt" => Ok(RendererType::Logfmt),
       "styled" => Ok(RendererType::Styled),
       "interactive" => Ok(RendererType::Interactive),
       _ => bail!(format!("invalid renderer type: {}", s))
@@ -74,44 +89,312 @@ impl FromStr for RendererType {
   } 
 }
 
-fn get_auto_reader(config: Arc<Config>) -> reader::Reader {
+/// The direction in which the interactive renderer displays messages
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderOrder {
+  /// the latest message is at the bottom of the screen
+  OldestFirst,
+
+  /// the latest message is at the top of the screen
+  NewestFirst
+}
+
+impl FromStr for RenderOrder {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "oldest-first" | "oldest" => Ok(RenderOrder::OldestFirst),
+      "newest-first" | "newest" => Ok(RenderOrder::NewestFirst),
+      _ => bail!(format!("invalid order: {}", s))
+    }
+  }
+}
+
+/// How the interactive renderer groups messages
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+  /// messages from all sources are interleaved in arrival order
+  None,
+
+  /// messages are grouped by their source, e.g. a pod or container
+  Source
+}
+
+impl FromStr for GroupBy {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "none" | "interleaved" => Ok(GroupBy::None),
+      "source" => Ok(GroupBy::Source),
+      _ => bail!(format!("invalid grouping: {}", s))
+    }
+  }
+}
+
+/// Order in which message fields are displayed
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldOrder {
+  /// the order fields appear in the original message
+  Original,
+
+  /// shortest fields first, to pack them onto as few lines as possible
+  Width,
+
+  /// alphabetically by key
+  Alpha
+}
+
+impl FromStr for FieldOrder {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "original" => Ok(FieldOrder::Original),
+      "width" => Ok(FieldOrder::Width),
+      "alpha" => Ok(FieldOrder::Alpha),
+      _ => bail!(format!("invalid field order: {}", s))
+    }
+  }
+}
+
+/// Sub-second precision of displayed timestamps
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimePrecision {
+  Seconds,
+  Millis,
+  Micros,
+  Nanos
+}
+
+impl TimePrecision {
+  /// chrono format string for the time portion of a timestamp
+  pub fn time_format(self) -> &'static str {
+    match self {
+      TimePrecision::Seconds => "%H:%M:%S",
+      TimePrecision::Millis => "%H:%M:%S%.3f",
+      TimePrecision::Micros => "%H:%M:%S%.6f",
+      TimePrecision::Nanos => "%H:%M:%S%.9f"
+    }
+  }
+}
+
+impl FromStr for TimePrecision {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "seconds" | "s" => Ok(TimePrecision::Seconds),
+      "millis" | "ms" => Ok(TimePrecision::Millis),
+      "micros" | "us" => Ok(TimePrecision::Micros),
+      "nanos" | "ns" => Ok(TimePrecision::Nanos),
+      _ => bail!(format!("invalid precision: {}", s))
+    }
+  }
+}
+
+/// Timezone in which timestamps are displayed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DisplayTimezone {
+  Local,
+  Utc,
+
+  /// a fixed offset from UTC, e.g. `+02:00`
+  Fixed(FixedOffset)
+}
+
+impl DisplayTimezone {
+  /// formats a timestamp in this timezone using a chrono format string
+  pub fn format(self, timestamp: DateTime<Utc>, fmt: &str) -> String {
+    match self {
+      DisplayTimezone::Local => timestamp.with_timezone(&Local).format(fmt),
+      DisplayTimezone::Utc => timestamp.format(fmt),
+      DisplayTimezone::Fixed(offset) => timestamp.with_timezone(&offset).format(fmt)
+    }.to_string()
+  }
+}
+
+impl FromStr for DisplayTimezone {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    lazy_static! {
+      static ref RE: Regex = Regex::new(r"^([+-])(\d{2}):?(\d{2})$").unwrap();
+    }
+
+    match s.to_lowercase().as_str() {
+      "local" => return Ok(DisplayTimezone::Local),
+      "utc" | "z" => return Ok(DisplayTimezone::Utc),
+      _ => ()
+    };
+
+    let offset = RE.captures(s).and_then(|caps| {
+      let hours: i32 = caps[2].parse().ok()?;
+      let minutes: i32 = caps[3].parse().ok().filter(|m| *m < 60)?;
+      let seconds = (hours * 3600 + minutes * 60) * match &caps[1] {
+        "-" => -1,
+        _ => 1
+      };
+
+      FixedOffset::east_opt(seconds)
+    });
+
+    match offset {
+      Some(offset) => Ok(DisplayTimezone::Fixed(offset)),
+      None => bail!(format!(
+        "invalid timezone: {} (expected local, utc, or an offset like +0200)",
+        s
+      ))
+    }
+  }
+}
+
+impl Serialize for DisplayTimezone {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      DisplayTimezone::Local => serializer.serialize_str("local"),
+      DisplayTimezone::Utc => serializer.serialize_str("utc"),
+      DisplayTimezone::Fixed(offset) => {
+        serializer.serialize_str(&offset.to_string())
+      }
+    }
+  }
+}
+
+/// Separator between input records
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordSeparator {
+  /// `\n`, also stripping the `\r` of `\r\n` line endings
+  Lf,
+
+  /// `\r`
+  Cr,
+
+  /// `\r\n` only; bare `\n` is kept as part of the record
+  Crlf,
+
+  /// `\0`, e.g. from `find -print0`
+  Nul
+}
+
+impl FromStr for RecordSeparator {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "lf" => Ok(RecordSeparator::Lf),
+      "cr" => Ok(RecordSeparator::Cr),
+      "crlf" => Ok(RecordSeparator::Crlf),
+      "nul" => Ok(RecordSeparator::Nul),
+      _ => bail!(format!("invalid record separator: {}", s))
+    }
+  }
+}
+
+/// Input format to check lines against, per `--expect`
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpectFormat {
+  /// one JSON object per line
+  Json
+}
+
+impl FromStr for ExpectFormat {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "json" => Ok(ExpectFormat::Json),
+      _ => bail!(format!("invalid expected format: {}", s))
+    }
+  }
+}
+
+/// How message levels are displayed
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LevelStyle {
+  /// the full level name, e.g. `warning`
+  Text,
+
+  /// a single colored letter, e.g. `W`
+  Badge
+}
+
+impl FromStr for LevelStyle {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "text" => Ok(LevelStyle::Text),
+      "badge" => Ok(LevelStyle::Badge),
+      _ => bail!(format!("invalid level style: {}", s))
+    }
+  }
+}
+
+/// picks the reader type for `--reader auto`
+fn get_auto_reader_type(config: &Config) -> ReaderType {
   // TODO: is it possible to tell if stdin has some input?
   // TODO: consider detecting if k8s based on args and kubernetes::is_selector?
+  if config.file.is_some() {
+    return ReaderType::File;
+  }
+
   if !atty::is(Stream::Stdin) {
     // /dev/stdin should exist on all unixes
     if cfg!(unix) {
-      return reader::read_stdin_hack
+      return ReaderType::Hack
     } else {
-      return reader::read_stdin
+      return ReaderType::Stdin
     }
   }
 
-  if config.kubernetes.namespace.is_some() {
-    return reader::read_kubernetes_selector;
+  if config.kubernetes.namespace.is_some()
+    || config.kubernetes.namespace_selector.is_some()
+  {
+    return ReaderType::Kubernetes;
   }
 
-  reader::read_null
+  ReaderType::Null
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ReaderType {
   Auto,
   Stdin,
   Hack,
   Kubernetes,
-  Null
-  //Subprocess
+  Null,
+  Subprocess,
+  File
 }
 
 impl ReaderType {
+  /// resolves `auto` to the reader type that will actually be used
+  pub fn resolve(self, config: &Config) -> ReaderType {
+    match self {
+      ReaderType::Auto => get_auto_reader_type(config),
+      other => other
+    }
+  }
+
   pub fn get_reader(&self, config: Arc<Config>) -> reader::Reader {
-    match *self {
-      ReaderType::Auto => get_auto_reader(config),
+    match self.resolve(&config) {
       ReaderType::Stdin => reader::read_stdin,
       ReaderType::Hack => reader::read_stdin_hack,
       ReaderType::Kubernetes => reader::read_kubernetes_selector,
-      ReaderType::Null => reader::read_null
-      //ReaderType::Subprocess => ...
+      // auto never resolves to itself
+      ReaderType::Null | ReaderType::Auto => reader::read_null,
+      ReaderType::Subprocess => reader::read_subprocess,
+      ReaderType::File => reader::read_file
     }
   }
 }
@@ -126,14 +409,17 @@ impl FromStr for ReaderType {
       "hack" => Ok(ReaderType::Hack),
       "kubernetes" | "k8s" => Ok(ReaderType::Kubernetes),
       "null" => Ok(ReaderType::Null),
+      "subprocess" | "exec" => Ok(ReaderType::Subprocess),
+      "file" => Ok(ReaderType::File),
       _ => bail!(format!("invalid reader type: {}", s))
     }
   }
 }
 
 /// Kubernetes-specific config
-#[derive(Debug, StructOpt)]
+#[derive(Debug, StructOpt, Serialize)]
 #[structopt(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
 pub struct KubernetesConfig {
   /// kubectl path override
   /// 
@@ -141,10 +427,22 @@ pub struct KubernetesConfig {
   #[structopt(long, short = "k", env = "WD_KUBECTL")]
   pub kubectl: Option<String>,
 
+  /// Kubernetes context to use instead of the kubeconfig's current context
+  #[structopt(long, env = "WD_CONTEXT")]
+  pub context: Option<String>,
+
   /// Kubernetes namespace to use read
   #[structopt(long, short = "n", env = "WD_NAMESPACE")]
   pub namespace: Option<String>,
 
+  /// Label selector for namespaces to follow, e.g. `tenant=acme`
+  ///
+  /// Matching namespaces are rediscovered every poll interval, so pods in
+  /// newly-created namespaces are followed automatically. Overrides the
+  /// default namespace unless --namespace is also given.
+  #[structopt(long, env = "WD_NAMESPACE_SELECTOR")]
+  pub namespace_selector: Option<String>,
+
   /// Local kubernetes proxy port
   /// 
   /// A kubernetes API proxy will be spawned on this port over the loopback
@@ -152,9 +450,158 @@ pub struct KubernetesConfig {
   #[structopt(long, short = "p", env = "WD_K8S_PORT")]
   pub port: Option<u16>,
 
+  /// Show each container's logs from its previous instance, e.g. before a
+  /// crash, rather than following its current logs
+  #[structopt(long, short = "P")]
+  pub previous: bool,
+
+  /// Only show container logs newer than this, e.g. `5m` or `1h30m`
+  ///
+  /// If unset, logs are shown from the start of each container.
+  #[structopt(long, env = "WD_SINCE", parse(try_from_str = "parse_since"))]
+  pub since: Option<u64>,
+
+  /// Number of recent lines of each container's log to show before following
+  ///
+  /// If unset, all lines are shown.
+  #[structopt(long, env = "WD_TAIL")]
+  pub tail: Option<u64>,
+
+  /// Comma-separated pod labels to add as fields to each message, e.g. `app`
+  ///
+  /// Fields already present in a message aren't overwritten.
+  #[structopt(long, env = "WD_LABEL_FIELDS", raw(use_delimiter = "true"))]
+  pub label_fields: Vec<String>,
+
+  /// Poll for pod changes every --poll-interval seconds rather than streaming
+  /// them with the watch API
+  ///
+  /// Watching falls back to polling automatically if the stream fails, but
+  /// polling may be more reliable behind proxies that cut off long requests.
+  #[structopt(long)]
+  pub poll: bool,
+
   /// Poll interval while watching Kubernetes pods in seconds
   #[structopt(long, env = "WD_K8S_POLL_INTERVAL", default_value = "5")]
-  pub poll_interval: u64
+  pub poll_interval: u64,
+
+  /// Number of times to retry following a container's log after consecutive
+  /// failed attempts before giving up on it; 0 disables retries
+  #[structopt(
+    long = "k8s-max-retries",
+    env = "WD_K8S_MAX_RETRIES",
+    default_value = "3"
+  )]
+  #[serde(rename = "k8s-max-retries")]
+  pub max_retries: u32,
+
+  /// Delay between attempts to follow a container's log, in milliseconds
+  #[structopt(
+    long = "k8s-retry-backoff-ms",
+    env = "WD_K8S_RETRY_BACKOFF_MS",
+    default_value = "5000"
+  )]
+  #[serde(rename = "k8s-retry-backoff-ms")]
+  pub retry_backoff_ms: u64
+}
+
+/// parses a log level for use in a flag
+fn parse_level(s: &str) -> Result<LogLevel, String> {
+  s.parse::<LogLevel>().map_err(|_| format!("invalid log level: {}", s))
+}
+
+/// parses a replay speed, which must be positive
+fn parse_speed(s: &str) -> Result<f64, String> {
+  match s.parse::<f64>() {
+    Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+    Ok(_) => Err(String::from("replay speed must be greater than 0")),
+    Err(e) => Err(e.to_string())
+  }
+}
+
+/// parses a refresh rate, which must be positive
+fn parse_refresh_hz(s: &str) -> Result<f32, String> {
+  match s.parse::<f32>() {
+    // tiny rates would overflow the loop's sleep duration
+    Ok(hz) if hz >= 0.1 && hz.is_finite() => Ok(hz),
+    Ok(_) => Err(String::from("refresh rate must be at least 0.1")),
+    Err(e) => Err(e.to_string())
+  }
+}
+
+fn parse_max_entries(s: &str) -> Result<usize, String> {
+  match s.parse::<usize>() {
+    Ok(0) => Err(String::from("max entries must be at least 1")),
+    Ok(max) => Ok(max),
+    Err(e) => Err(e.to_string())
+  }
+}
+
+/// parses a duration like `90s`, `5m`, or `1h30m` into a number of seconds,
+/// which must be nonzero
+fn parse_since(s: &str) -> Result<u64, String> {
+  let invalid = || {
+    format!("invalid duration: {} (expected e.g. 90s, 5m, 1h30m)", s)
+  };
+
+  let mut seconds: u64 = 0;
+  let mut digits = String::new();
+  for c in s.chars() {
+    if c.is_ascii_digit() {
+      digits.push(c);
+      continue;
+    }
+
+    let unit = match c {
+      's' => 1,
+      'm' => 60,
+      'h' => 60 * 60,
+      _ => return Err(invalid())
+    };
+
+    let count: u64 = digits.parse().map_err(|_| invalid())?;
+    seconds = count.checked_mul(unit)
+      .and_then(|n| seconds.checked_add(n))
+      .ok_or_else(invalid)?;
+
+    digits.clear();
+  }
+
+  // a trailing number without a unit is ambiguous
+  if !digits.is_empty() || seconds == 0 {
+    return Err(invalid());
+  }
+
+  Ok(seconds)
+}
+
+/// checks that a date or time format string is valid, since chrono panics
+/// when formatting with an invalid one
+fn parse_time_format(s: &str) -> Result<String, String> {
+  if StrftimeItems::new(s).any(|item| item == Item::Error) {
+    Err(format!("invalid format string: {}", s))
+  } else {
+    Ok(s.to_string())
+  }
+}
+
+/// parses a channel capacity, which must be nonzero
+fn parse_capacity(s: &str) -> Result<usize, String> {
+  match s.parse::<usize>() {
+    Ok(0) => Err(String::from("channel capacity must be at least 1")),
+    Ok(capacity) => Ok(capacity),
+    Err(e) => Err(e.to_string())
+  }
+}
+
+/// parses a maximum field value width, which must leave room to show both
+/// ends of an elided value
+fn parse_value_width(s: &str) -> Result<usize, String> {
+  match s.parse::<usize>() {
+    Ok(width) if width < 3 => Err(String::from("value width must be at least 3")),
+    Ok(width) => Ok(width),
+    Err(e) => Err(e.to_string())
+  }
 }
 
 struct RegexFromStr;
@@ -179,6 +626,16 @@ impl<'de> Visitor<'de> for RegexFromStr {
   }
 }
 
+fn ser_regex<S>(regex: &Option<Regex>, serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer
+{
+  match regex {
+    Some(regex) => serializer.serialize_some(regex.as_str()),
+    None => serializer.serialize_none()
+  }
+}
+
 fn de_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
 where
   D: Deserializer<'de>
@@ -202,14 +659,28 @@ pub struct RegexMapping {
   /// Chrono isn't able to parse datetimes with missing fields (e.g. year), but
   /// some log formats (e.g. klog) leave certain fields out. This allows these
   /// formats to be parsed anyway.
-  pub datetime_prepend: Option<String>
+  pub datetime_prepend: Option<String>,
+
+  /// If set, datetimes using `datetime_prepend` that would be more than a day
+  /// in the future are instead parsed with the previous year
+  #[serde(default)]
+  pub datetime_rollover: bool
 }
 
 #[derive(Debug)]
 pub struct RegexConfig {
+  /// the path this config was loaded from
+  pub path: String,
+
   pub mappings: Vec<RegexMapping>
 }
 
+impl Serialize for RegexConfig {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.path)
+  }
+}
+
 impl FromStr for RegexConfig {
   type Err = SimpleError;
 
@@ -219,7 +690,7 @@ impl FromStr for RegexConfig {
     let reader = BufReader::new(file);
 
     match serde_yaml::from_reader(reader) {
-      Ok(mappings) => Ok(RegexConfig { mappings }),
+      Ok(mappings) => Ok(RegexConfig { path: path.to_string(), mappings }),
       Err(e) => Err(SimpleError::new(
         format!("error loading regexes {}: {:?}", path, e)
       ))
@@ -227,14 +698,109 @@ impl FromStr for RegexConfig {
   }
 }
 
-#[derive(Debug, StructOpt)]
+/// Custom level names loaded from a YAML file, mapping each alias to one of
+/// the built-in levels, e.g. `notice: info`
+#[derive(Debug)]
+pub struct LevelAliases {
+  /// the path these aliases were loaded from
+  pub path: String,
+
+  /// lowercase alias -> level
+  pub aliases: HashMap<String, LogLevel>
+}
+
+impl LevelAliases {
+  pub fn get(&self, s: &str) -> Option<LogLevel> {
+    self.aliases.get(&s.to_lowercase()).cloned()
+  }
+}
+
+impl Serialize for LevelAliases {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.path)
+  }
+}
+
+impl FromStr for LevelAliases {
+  type Err = SimpleError;
+
+  fn from_str(path: &str) -> Result<Self, Self::Err> {
+    let expanded_path = shellexpand::full(path).map_err(SimpleError::from)?;
+    let file = File::open(expanded_path.to_string()).map_err(SimpleError::from)?;
+    let reader = BufReader::new(file);
+
+    let raw: HashMap<String, String> = serde_yaml::from_reader(reader)
+      .map_err(|e| SimpleError::new(
+        format!("error loading level aliases {}: {:?}", path, e)
+      ))?;
+
+    let mut aliases = HashMap::new();
+    for (alias, level) in raw {
+      let level = level.parse::<LogLevel>().map_err(|_| SimpleError::new(
+        format!("invalid log level for alias {}: {}", alias, level)
+      ))?;
+
+      aliases.insert(alias.to_lowercase(), level);
+    }
+
+    Ok(LevelAliases { path: path.to_string(), aliases })
+  }
+}
+
+/// The parsers to try for each line, in order
+pub struct ParserList {
+  pub names: Vec<String>,
+  pub parsers: Vec<Parser>
+}
+
+impl fmt::Debug for ParserList {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_list().entries(&self.names).finish()
+  }
+}
+
+impl Serialize for ParserList {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.names.join(","))
+  }
+}
+
+impl FromStr for ParserList {
+  type Err = SimpleError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut names = Vec::new();
+    let mut parsers = Vec::new();
+    for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+      match parser::get_parser(name) {
+        Some(parser) => parsers.push(parser),
+        None => return Err(SimpleError::new(format!(
+          "invalid parser: {}, expected one of: {}",
+          name, parser::parser_names().join(", ")
+        )))
+      }
+
+      names.push(name.to_string());
+    }
+
+    if parsers.is_empty() {
+      return Err(SimpleError::new("at least one parser is required"));
+    }
+
+    Ok(ParserList { names, parsers })
+  }
+}
+
+#[derive(Debug, StructOpt, Serialize)]
 #[structopt(
   name = "woodchipper",
   rename_all = "kebab-case",
   raw(setting = "structopt::clap::AppSettings::ColoredHelp")
 )]
+#[serde(rename_all = "kebab-case")]
 pub struct Config {
-  /// Renderer to use, one of: auto, plain, json, styled, interactive
+  /// Renderer to use, one of: auto, plain, json, ndjson, logfmt, styled,
+  /// interactive
   /// 
   /// If auto, will is determined by terminal and whether or not output will be
   /// redirected. Automatic preference may be overridden with
@@ -243,7 +809,8 @@ pub struct Config {
   #[structopt(long, short, default_value = "auto", env = "WD_RENDERER")]
   pub renderer: RendererType,
 
-  /// Preferred renderer, one of: plain, json, styled, interactive
+  /// Preferred renderer, one of: plain, json, ndjson, logfmt, styled,
+  /// interactive
   ///
   /// When --renderer=auto, this controls the preferred default renderer if no
   /// conditions exist that would otherwise select a different renderer.
@@ -254,21 +821,70 @@ pub struct Config {
   #[structopt(long, default_value = "interactive", env = "WD_PREFERRED_RENDERER")]
   pub preferred_renderer: RendererType,
 
-  /// Reader to use, one of: auto, stdin, hack, kubernetes
+  /// Reader to use, one of: auto, stdin, hack, kubernetes, subprocess, file
   ///
   /// If auto, reader will be determined selected based on OS and renderer.
   ///{n}{n}
   /// - `stdin` reads from standard input{n}
   /// - `hack` reads from /dev/stdin to allow the interactive renderer to work{n}
   /// - `kubernetes` continuously follows Kubernetes pods{n}
-  /// - `auto` selects `hack` on unix, unless some Kubernetes flag is set
+  /// - `subprocess` runs the given command and reads its stdout and stderr{n}
+  /// - `file` reads and follows --file and any files given as arguments{n}
+  /// - `auto` selects `file` if --file is set, otherwise `hack` on unix,
+  ///   unless some Kubernetes flag is set
   #[structopt(long, short = "i", default_value = "auto", env = "WD_READER")]
   pub reader: ReaderType,
 
-  /// Kubernetes selector or subprocess args from which to capture log output.
-  /// If unset, assumes logs will be read from standard input.
+  /// A log file to read and follow, like `tail -F`
+  ///
+  /// The file is waited for if it doesn't exist yet, and reopened if it's
+  /// truncated or rotated. More files may be followed with `-i file`, e.g.
+  /// `woodchipper -i file a.log b.log`.
+  #[structopt(long, env = "WD_FILE")]
+  pub file: Option<String>,
+
+  /// Kubernetes selector, subprocess args, or files from which to capture log
+  /// output. If unset, assumes logs will be read from standard input.
   pub app: Vec<String>,
 
+  /// Minimum log level for lines read from a subprocess' stderr
+  ///
+  /// Lines written to stderr with a lower (or no) level are raised to this
+  /// level, e.g. `--stderr-level warning`.
+  #[structopt(
+    long,
+    env = "WD_STDERR_LEVEL",
+    parse(try_from_str = "parse_level")
+  )]
+  pub stderr_level: Option<LogLevel>,
+
+  /// Separator between input records, one of: lf, cr, crlf, nul
+  ///
+  /// Applies to standard input and subprocess output. With the default, `lf`,
+  /// a `\r` before the newline is also removed.
+  #[structopt(long, default_value = "lf", env = "WD_RECORD_SEPARATOR")]
+  pub record_separator: RecordSeparator,
+
+  /// If set, warns about input lines that aren't in this format, currently
+  /// only `json`
+  ///
+  /// Lines that fail to parse are still shown as plain text, preceded by an
+  /// internal warning with their line number, e.g. to catch stack traces in an
+  /// NDJSON stream.
+  #[structopt(long, env = "WD_EXPECT")]
+  pub expect: Option<ExpectFormat>,
+
+  /// If set, exits with a non-zero status if any message at or above this
+  /// level was read, e.g. `--fail-on-level error`
+  ///
+  /// A JSON summary of message counts by level is printed to stderr on exit.
+  #[structopt(
+    long,
+    env = "WD_FAIL_ON_LEVEL",
+    parse(try_from_str = "parse_level")
+  )]
+  pub fail_on_level: Option<LogLevel>,
+
   /// Fallback width for the styled renderer if no tty is detected
   ///
   /// Note that the plaintext renderer is recommended in most cases where
@@ -281,32 +897,632 @@ pub struct Config {
   )]
   pub fallback_width: usize,
 
+  /// Maximum width for the styled and interactive renderers
+  ///
+  /// If set, output is wrapped at this width even if the terminal is wider.
+  #[structopt(long, env = "WD_MAX_WIDTH")]
+  pub max_width: Option<usize>,
+
   /// Styled output configuration
   ///
   /// Must contain one of the following: `default`, `base16:<path to .yaml>`
   #[structopt(long, short = "s", default_value = "default", env = "WD_STYLE")]
   pub style: StyleConfig,
 
+  /// Level display style, one of: text, badge
+  ///
+  /// `badge` shows a single colored letter in place of the level name to save
+  /// space on narrow terminals.
+  #[structopt(long, default_value = "text", env = "WD_LEVEL_STYLE")]
+  pub level_style: LevelStyle,
+
+  /// If set, only levels at or above this one are colored, e.g.
+  /// `--colorize-from warning`; lower levels use the style's base color
+  #[structopt(
+    long,
+    env = "WD_COLORIZE_FROM",
+    parse(try_from_str = "parse_level")
+  )]
+  pub colorize_from: Option<LogLevel>,
+
+  /// If set, output is not colored
+  ///
+  /// Also enabled if the `NO_COLOR` or `WD_NO_COLOR` environment variables are
+  /// set to any value. The selected message in the interactive renderer is
+  /// shown in reverse video.
+  #[structopt(long)]
+  pub no_color: bool,
+
+  /// Precision of displayed times, one of: seconds, millis, micros, nanos
+  ///
+  /// Finer precisions help order closely-spaced messages, but only if the
+  /// source timestamps include fractional seconds.
+  #[structopt(long, default_value = "seconds", env = "WD_PRECISION")]
+  pub precision: TimePrecision,
+
+  /// Format of displayed dates, in chrono's strftime syntax
+  ///
+  /// An empty format hides dates entirely, e.g. `--date-format ''`.
+  #[structopt(
+    long,
+    default_value = "%Y-%m-%d",
+    env = "WD_DATE_FORMAT",
+    parse(try_from_str = "parse_time_format")
+  )]
+  pub date_format: String,
+
+  /// Format of displayed times, in chrono's strftime syntax, e.g.
+  /// `%H:%M:%S%.3f`; overrides --precision
+  #[structopt(
+    long,
+    env = "WD_TIME_FORMAT",
+    parse(try_from_str = "parse_time_format")
+  )]
+  pub time_format: Option<String>,
+
+  /// If set, times are shown relative to now, e.g. `-3s` or `-2m`, and dates
+  /// are hidden
+  ///
+  /// The interactive renderer redraws periodically to keep them current.
+  #[structopt(long)]
+  pub relative_time: bool,
+
+  /// Timezone for displayed timestamps, one of: local, utc, or a fixed offset
+  /// from UTC like `+0200` or `-05:30`
+  #[structopt(long, default_value = "local", env = "WD_TIMEZONE")]
+  pub timezone: DisplayTimezone,
+
+  /// If set, field values longer than this many characters are shortened by
+  /// eliding their middle, e.g. `eyJh…Qssw`
+  ///
+  /// Filters and searches still match the full value, and the JSON renderer
+  /// and raw view (`x`) show it unchanged.
+  #[structopt(
+    long,
+    env = "WD_VALUE_MAX_WIDTH",
+    parse(try_from_str = "parse_value_width")
+  )]
+  pub value_max_width: Option<usize>,
+
+  /// Order of message fields, one of: original, width, alpha
+  ///
+  /// `width` places the shortest fields first to use as few lines as possible,
+  /// `original` keeps the order fields appear in the message, and `alpha`
+  /// sorts them by key.
+  #[structopt(long, default_value = "width", env = "WD_FIELD_ORDER")]
+  pub field_order: FieldOrder,
+
+  /// If set, messages that no parser recognized (i.e. plain text) are marked
+  /// with a dot in the left gutter, to help spot where custom regexes are
+  /// needed
+  #[structopt(long)]
+  pub mark_unparsed: bool,
+
+  /// If set, shows each message's line number within the input, e.g. to find
+  /// it in the original file
+  ///
+  /// Numbers refer to input records and are unaffected by filtering. Only
+  /// messages read from standard input are numbered.
+  #[structopt(long)]
+  pub line_numbers: bool,
+
+  /// If set, only ASCII characters are used for decorations like repeat
+  /// badges and group headers, for terminals without unicode support
+  #[structopt(long)]
+  pub ascii: bool,
+
+  /// Colors for particular field keys, overriding the style's default
+  ///
+  /// A comma-separated list of `key=color` pairs, where colors may be a name
+  /// (e.g. `red`) or a hex RGB value, e.g. `error=red,trace_id=#5fafff`
+  #[structopt(long, env = "WD_FIELD_COLORS")]
+  pub field_colors: Option<FieldColors>,
+
+  /// A regex to remove from displayed message sources
+  ///
+  /// The first match is removed, e.g. `^my-app-` shortens a pod named
+  /// `my-app-5d8f9-x2v7q` to `5d8f9-x2v7q`. Filters still match against the
+  /// full source.
+  #[structopt(long, env = "WD_STRIP_SOURCE_PREFIX")]
+  #[serde(serialize_with = "ser_regex")]
+  pub strip_source_prefix: Option<Regex>,
+
+  /// If set, removes the prefix and suffix shared by all sources seen so far
+  /// from displayed message sources
+  #[structopt(long)]
+  pub strip_source_common: bool,
+
   /// A path to a regexes config file, which may contain custom parsing regexes
   /// for application-specific log formats.
   #[structopt(long, env = "WD_REGEXES")]
   pub regexes: Option<RegexConfig>,
 
+  /// Comma-separated list of parsers to try for each line, in order
+  ///
+  /// Available parsers are: cloud, winevent, json, syslog, cef, heroku, logrus
+  /// (or logfmt), klog, regex, access, plain. Removing unneeded parsers speeds
+  /// up parsing and avoids misdetection; lines matching none of them are
+  /// dropped, so `plain` should usually be last.
+  #[structopt(
+    long,
+    default_value = "cloud,winevent,json,syslog,cef,heroku,logrus,klog,regex,access,plain",
+    env = "WD_PARSERS"
+  )]
+  pub parsers: ParserList,
+
+  /// A path to a YAML file of custom level names, e.g. `notice: info`
+  ///
+  /// Each alias maps to one of the built-in levels (trace, debug, info,
+  /// notice, warning, error, critical, fatal) and is used in addition to the
+  /// usual level names.
+  #[structopt(long, env = "WD_LEVEL_ALIASES")]
+  pub level_aliases: Option<LevelAliases>,
+
+  /// If set, appends every raw line read to this file
+  ///
+  /// Lines are written exactly as they arrive, before any parsing, filtering
+  /// or rendering, e.g. to keep a copy of the logs while viewing them
+  /// interactively.
+  #[structopt(long, env = "WD_TEE")]
+  pub tee: Option<String>,
+
+  /// Limits how much of the input's initial backlog is kept, as a message
+  /// count or a duration
+  ///
+  /// A count (e.g. `1000`) keeps only that many of the most recent messages; a
+  /// duration (e.g. `30m`, units: s, m, h, d) keeps messages within that time
+  /// of the newest message. The backlog ends once input pauses briefly, or
+  /// after 5 seconds at most; messages arriving after that are always kept.
+  #[structopt(long, env = "WD_HISTORY")]
+  pub history: Option<HistoryLimit>,
+
+  /// If set, hides woodchipper's own status messages, e.g. "watching pods"
+  ///
+  /// Internal errors and warnings are still shown.
+  #[structopt(long)]
+  pub quiet_internal: bool,
+
+  /// If set, only messages matching this regex are shown
+  ///
+  /// Matches the fields given by `--search-scope`. Unlike interactive filters,
+  /// non-matching messages are dropped, so this also applies to the plain,
+  /// styled, and JSON renderers.
+  #[structopt(long, env = "WD_GREP")]
+  #[serde(serialize_with = "ser_regex")]
+  pub grep: Option<Regex>,
+
+  /// If set, messages matching this regex are hidden, as with `--grep`
+  #[structopt(long, env = "WD_EXCLUDE")]
+  #[serde(serialize_with = "ser_regex")]
+  pub exclude: Option<Regex>,
+
+  /// Number of messages to show after each message matched by `--grep` or
+  /// `--exclude`, like `grep -A`
+  #[structopt(long, short = "A")]
+  pub after: Option<usize>,
+
+  /// Number of messages to show before each message matched by `--grep` or
+  /// `--exclude`, like `grep -B`
+  #[structopt(long, short = "B")]
+  pub before: Option<usize>,
+
+  /// Number of messages to show on both sides of each message matched by
+  /// `--grep` or `--exclude`, like `grep -C`; `--after` and `--before` take
+  /// precedence
+  ///
+  /// This isn't named `--context` as grep's is, since that selects the
+  /// kubeconfig context.
+  #[structopt(long, short = "C")]
+  pub around: Option<usize>,
+
+  /// If set, plays back the input in real time, scaled by this factor
+  ///
+  /// Messages are delayed according to the gaps between their timestamps, e.g.
+  /// `1` replays at the original speed and `10` replays ten times faster. Gaps
+  /// are capped at 10 seconds.
+  #[structopt(
+    long,
+    env = "WD_REPLAY_SPEED",
+    parse(try_from_str = "parse_speed")
+  )]
+  pub replay_speed: Option<f64>,
+
   /// If set, attempts to ensure messages are displayed in semantic order by
   /// placing them in a priority queue for a short period before being written
   /// to the renderer. By default, messages are held for one second; this can be
   /// overridden with `--buffer-ms`.
   ///
   /// Particularly useful when paired with readers that ingest from multiple
-  /// sources at once, like the Kubernetes reader.
-  #[structopt(long, short = "o")]
+  /// sources at once, and always enabled for the Kubernetes reader. Also
+  /// available as `--reorder`.
+  #[structopt(long, short = "o", alias = "reorder")]
   pub ordered: bool,
 
   /// Sets the length of time, in milliseconds, that messages should be buffered
   /// to attempt to reorder them. If set, implies `--ordered`.
-  #[structopt(long)]
+  #[structopt(long, env = "WD_BUFFER_MS")]
   pub buffer_ms: Option<u64>,
 
+  /// Maximum number of messages queued between the reader and renderer
+  ///
+  /// If the renderer falls behind, the reader will stop reading until the
+  /// queue has room, unless `--drop-on-full` is set.
+  #[structopt(
+    long,
+    default_value = "10000",
+    env = "WD_CHANNEL_CAPACITY",
+    parse(try_from_str = "parse_capacity")
+  )]
+  pub channel_capacity: usize,
+
+  /// If set, discards the oldest queued messages rather than blocking the
+  /// reader when the renderer falls behind
+  ///
+  /// Useful for live streams where keeping up matters more than completeness.
+  #[structopt(long)]
+  pub drop_on_full: bool,
+
+  /// Display order for the interactive renderer, one of: oldest-first,
+  /// newest-first
+  ///
+  /// With newest-first, the latest message is pinned to the top of the screen
+  /// and older messages scroll downward.
+  #[structopt(long, default_value = "oldest-first", env = "WD_ORDER")]
+  pub order: RenderOrder,
+
+  /// Grouping for the interactive renderer, one of: none, source
+  ///
+  /// With `source`, messages are grouped under a header for each source (e.g.
+  /// a pod) rather than interleaved. May be toggled with `g`.
+  #[structopt(long, default_value = "none", env = "WD_GROUP_BY")]
+  pub group_by: GroupBy,
+
+  /// If set, collapses consecutive identical messages in the interactive
+  /// renderer into one, with a badge counting the repeats
+  #[structopt(long)]
+  pub dedup: bool,
+
+  /// Maximum number of messages the interactive renderer keeps in memory
+  ///
+  /// Once exceeded, the oldest messages are dropped, e.g. to bound memory use
+  /// when following logs for days. If unset, all messages are kept.
+  #[structopt(
+    long,
+    env = "WD_MAX_ENTRIES",
+    parse(try_from_str = "parse_max_entries")
+  )]
+  pub max_entries: Option<usize>,
+
+  /// Rate, in Hz, at which the interactive renderer checks for new messages
+  /// and keypresses
+  ///
+  /// Must be at least 0.1. Lower values use less CPU but feel less
+  /// responsive. Redraws after a resize and status bar updates are separately
+  /// limited to every 500ms.
+  #[structopt(
+    long,
+    default_value = "40",
+    env = "WD_REFRESH_HZ",
+    parse(try_from_str = "parse_refresh_hz")
+  )]
+  pub refresh_hz: f32,
+
+  /// If set, the interactive renderer doesn't capture the mouse, leaving the
+  /// terminal's own text selection working normally
+  ///
+  /// By default the mouse wheel moves the selection and clicking a message
+  /// selects it.
+  #[structopt(long)]
+  pub no_mouse: bool,
+
+  /// A path to a YAML file of interactive keybindings, e.g. `down: [j, down]`
+  ///
+  /// Maps action names (e.g. quit, filter, search, copy, page-up) to one or
+  /// more keys like `q`, `ctrl-f`, or `page-down`. Unlisted actions keep their
+  /// default keys; press `?` in the interactive viewer to see them.
+  #[structopt(long, default_value = "default", env = "WD_KEYBINDINGS")]
+  pub keybindings: KeyBindings,
+
+  /// Default message fields matched by interactive filters and searches, one
+  /// of: all, text, metadata
+  ///
+  /// `all` matches the message kind, level, text, and metadata; `text` matches
+  /// only the message text. May be changed in the filter and search bars with
+  /// `ctrl-t`.
+  #[structopt(long, default_value = "all", env = "WD_SEARCH_SCOPE")]
+  pub search_scope: SearchScope,
+
+  /// Number of unfiltered messages to show on either side of the selected
+  /// message when revealing its context in the interactive renderer
+  #[structopt(long, default_value = "5", env = "WD_REVEAL_LINES")]
+  pub reveal_lines: usize,
+
+  /// If set, writes the effective configuration (from flags, environment
+  /// variables, and defaults) to this path as YAML and exits
+  ///
+  /// This is a record of the settings in use, e.g. to attach to a bug report;
+  /// woodchipper can't load it. Each key is a flag name, and values can be
+  /// passed back as that flag, e.g. `max-entries: 500` as `--max-entries
+  /// 500`. Files like `--style` are recorded by name or path.
+  #[structopt(long)]
+  #[serde(skip)]
+  pub write_config: Option<String>,
+
+  /// If set, checks that the files given to other flags (e.g. `--regexes`,
+  /// `--style`, `--level-aliases`, `--keybindings`) load without errors, then
+  /// exits
+  ///
+  /// Files that fail to load or contain invalid regexes are reported with
+  /// their path, and woodchipper exits with a non-zero status.
+  #[structopt(long)]
+  #[serde(skip)]
+  pub validate: bool,
+
   #[structopt(flatten)]
+  #[serde(flatten)]
   pub kubernetes: KubernetesConfig
 }
+
+impl Config {
+  /// describes the config files that were loaded, for `--validate`
+  pub fn describe_files(&self) -> Vec<String> {
+    let mut lines = vec![format!("style: {}", self.style.name)];
+
+    if let Some(regexes) = &self.regexes {
+      lines.push(format!(
+        "regexes: {} ({} patterns)", regexes.path, regexes.mappings.len()
+      ));
+    }
+
+    if let Some(level_aliases) = &self.level_aliases {
+      lines.push(format!(
+        "level aliases: {} ({} aliases)",
+        level_aliases.path, level_aliases.aliases.len()
+      ));
+    }
+
+    if let Some(path) = &self.keybindings.path {
+      lines.push(format!(
+        "keybindings: {} ({} actions)", path, self.keybindings.remapped
+      ));
+    }
+
+    lines
+  }
+
+  /// writes this config to the given path as YAML
+  pub fn write_yaml(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    let yaml = serde_yaml::to_string(self)?;
+
+    let expanded_path = shellexpand::full(path)?;
+    let mut file = File::create(expanded_path.to_string())?;
+    writeln!(file, "{}", yaml)?;
+
+    Ok(())
+  }
+
+  /// the format of displayed times, per `--time-format` or `--precision`
+  pub fn time_format(&self) -> &str {
+    match &self.time_format {
+      Some(format) => format,
+      None => self.precision.time_format()
+    }
+  }
+
+  /// applies flags that modify the style, e.g. `--field-colors`; should be
+  /// called once after parsing
+  pub fn apply_style_flags(&mut self) {
+    if let Some(field_colors) = &self.field_colors {
+      self.style.set_field_colors(field_colors);
+    }
+
+    if let Some(level) = self.colorize_from {
+      self.style.set_colorize_from(level);
+    }
+
+    // per https://no-color.org/, any non-empty value disables colors
+    let no_color_env = ["NO_COLOR", "WD_NO_COLOR"].iter()
+      .any(|var| env::var_os(var).filter(|v| !v.is_empty()).is_some());
+    if self.no_color || no_color_env {
+      self.style.set_plain();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env;
+  use std::fs;
+  use std::process;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_refresh_hz() {
+    // the default matches the old fixed 25ms loop
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    assert_that!(config.refresh_hz).is_equal_to(40.0);
+
+    let parse = |hz: &str| Config::from_iter_safe(vec!["", "--refresh-hz", hz]);
+
+    assert_that!(parse("10").map(|c| c.refresh_hz)).is_ok_containing(10.0);
+    assert_that!(parse("0.1").map(|c| c.refresh_hz)).is_ok_containing(0.1);
+    assert_that!(parse("0.09")).is_err();
+    assert_that!(parse("1e-20")).is_err();
+    assert_that!(parse("0")).is_err();
+    assert_that!(parse("-1")).is_err();
+    assert_that!(parse("inf")).is_err();
+  }
+
+  #[test]
+  fn test_max_entries() {
+    let parse = |max: &str| Config::from_iter_safe(vec!["", "--max-entries", max]);
+
+    assert_that!(parse("1000").map(|c| c.max_entries))
+      .is_ok_containing(Some(1000));
+    assert_that!(parse("0")).is_err();
+    assert_that!(parse("-1")).is_err();
+  }
+
+  #[test]
+  fn test_parse_since() {
+    assert_that!(parse_since("90s")).is_ok_containing(90);
+    assert_that!(parse_since("5m")).is_ok_containing(300);
+    assert_that!(parse_since("2h")).is_ok_containing(7200);
+    assert_that!(parse_since("1h30m")).is_ok_containing(5400);
+
+    assert_that!(parse_since("")).is_err();
+    assert_that!(parse_since("5")).is_err();
+    assert_that!(parse_since("0s")).is_err();
+    assert_that!(parse_since("m")).is_err();
+    assert_that!(parse_since("5d")).is_err();
+    assert_that!(parse_since("-5m")).is_err();
+    assert_that!(parse_since("99999999999999999999h")).is_err();
+
+    let config = Config::from_iter_safe(vec!["", "--since", "5m"]).unwrap();
+    assert_that!(config.kubernetes.since).is_equal_to(Some(300));
+  }
+
+  #[test]
+  fn test_timezone() {
+    let parse = |tz: &str| tz.parse::<DisplayTimezone>().ok();
+
+    assert_that!(parse("local")).is_equal_to(Some(DisplayTimezone::Local));
+    assert_that!(parse("UTC")).is_equal_to(Some(DisplayTimezone::Utc));
+    assert_that!(parse("+0200"))
+      .is_equal_to(FixedOffset::east_opt(7200).map(DisplayTimezone::Fixed));
+    assert_that!(parse("-05:30"))
+      .is_equal_to(FixedOffset::west_opt(19800).map(DisplayTimezone::Fixed));
+    assert_that!(parse("Europe/Paris")).is_none();
+    assert_that!(parse("+2500")).is_none();
+    assert_that!(parse("+0160")).is_none();
+    assert_that!(parse("-05:99")).is_none();
+    assert_that!(parse("+0159"))
+      .is_equal_to(FixedOffset::east_opt(7140).map(DisplayTimezone::Fixed));
+  }
+
+  #[test]
+  fn test_precision() {
+    let format = |args: Vec<&str>| {
+      Config::from_iter_safe(args).map(|c| c.time_format().to_string())
+    };
+
+    assert_that!(format(vec![""]))
+      .is_ok_containing(String::from("%H:%M:%S"));
+    assert_that!(format(vec!["", "--precision", "millis"]))
+      .is_ok_containing(String::from("%H:%M:%S%.3f"));
+    assert_that!(format(vec!["", "--precision", "us"]))
+      .is_ok_containing(String::from("%H:%M:%S%.6f"));
+    assert_that!(format(vec!["", "--precision", "nanos"]))
+      .is_ok_containing(String::from("%H:%M:%S%.9f"));
+    assert_that!(format(vec!["", "--precision", "minutes"])).is_err();
+
+    // an explicit format wins
+    assert_that!(format(vec![
+      "", "--precision", "nanos", "--time-format", "%H:%M"
+    ])).is_ok_containing(String::from("%H:%M"));
+  }
+
+  #[test]
+  fn test_no_color() {
+    let mut config = Config::from_iter_safe(vec!["", "--no-color"]).unwrap();
+    config.apply_style_flags();
+
+    assert_that!(config.style.normal.get_base().is_plain()).is_true();
+    assert_that!(config.style.name).is_equal_to(String::from("default"));
+  }
+
+  #[test]
+  fn test_parsers_default() {
+    // the default is written out for --help, so make sure it's kept in sync
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    assert_that!(config.parsers.names).is_equal_to(
+      parser::parser_names().into_iter().map(String::from).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_parser_list() {
+    let parse = |s: &str| s.parse::<ParserList>().map(|list| list.names);
+
+    assert_that!(parse("json")).is_ok_containing(vec![String::from("json")]);
+    assert_that!(parse(" json , logfmt,plain,")).is_ok_containing(vec![
+      String::from("json"), String::from("logfmt"), String::from("plain")
+    ]);
+    assert_that!(parse("json,nope")).is_err();
+    assert_that!(parse("")).is_err();
+    assert_that!(parse(" , ")).is_err();
+
+    let list = "logfmt".parse::<ParserList>().unwrap();
+    assert_that!(list.parsers.len()).is_equal_to(1);
+  }
+
+  #[test]
+  fn test_write_yaml() {
+    let path = env::temp_dir().join(
+      format!("woodchipper-config-{}.yaml", process::id())
+    );
+    let path = path.to_str().unwrap();
+
+    let config = Config::from_iter_safe(vec![
+      "", "--max-entries", "500", "--refresh-hz", "10"
+    ]).unwrap();
+    assert_that!(config.write_yaml(path).is_ok()).is_true();
+
+    let written = fs::read_to_string(path).unwrap();
+    fs::remove_file(path).ok();
+
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&written).unwrap();
+    assert_that!(yaml["max-entries"].as_u64()).is_equal_to(Some(500));
+    assert_that!(yaml["refresh-hz"].as_f64()).is_equal_to(Some(10.0));
+  }
+
+  /// converts written config back into flags, as described for
+  /// `--write-config`
+  fn yaml_to_args(yaml: &serde_yaml::Value) -> Vec<String> {
+    let mut args = vec![String::new()];
+
+    for (key, value) in yaml.as_mapping().unwrap() {
+      let flag = format!("--{}", key.as_str().unwrap());
+      let values = match value {
+        serde_yaml::Value::Null | serde_yaml::Value::Bool(false) => continue,
+        serde_yaml::Value::Bool(true) => {
+          args.push(flag);
+          continue;
+        },
+        serde_yaml::Value::Sequence(values) => values.clone(),
+        value => vec![value.clone()]
+      };
+
+      for value in values {
+        let value = match value {
+          serde_yaml::Value::String(s) => s,
+          value => serde_yaml::to_string(&value).unwrap()
+            .trim_start_matches("---").trim().to_string()
+        };
+
+        args.push(flag.clone());
+        args.push(value);
+      }
+    }
+
+    args
+  }
+
+  #[test]
+  fn test_write_config_round_trip() {
+    let config = Config::from_iter_safe(vec![
+      "", "--max-entries", "500", "--refresh-hz", "12.5", "--dedup",
+      "--timezone", "+02:00", "--grep", "needle", "--parsers", "json,plain",
+      "--label-fields", "app", "--label-fields", "tier", "--order",
+      "newest-first", "--precision", "millis"
+    ]).unwrap();
+    let yaml = serde_yaml::to_value(&config).unwrap();
+
+    let args = yaml_to_args(&yaml);
+    let loaded = Config::from_iter_safe(args).unwrap();
+    assert_that!(serde_yaml::to_value(&loaded).unwrap()).is_equal_to(yaml);
+  }
+}
@@ -1,11 +1,14 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::marker::Sized;
+use std::str::FromStr;
 
 use regex::Regex;
+use serde::Serialize;
 use simple_error::{SimpleError, SimpleResult};
 
-use crate::parser::Message;
+use crate::parser::{Message, MappingField};
+use crate::parser::util::nicer_to_string;
 
 pub trait Filter {
   fn new(query: &str, inverted: bool) -> SimpleResult<Self> where Self: Sized;
@@ -28,81 +31,212 @@ pub trait Filter {
       pass
     }
   }
+
+  /// Returns the byte ranges within the message text matched by the filter,
+  /// if any, for highlighting
+  fn matches(&self, _message: &Message) -> Vec<(usize, usize)> {
+    Vec::new()
+  }
+}
+
+/// The parts of a message that text and regex filters match against
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+  /// the message kind, level, text, and metadata keys and values
+  All,
+
+  /// only the message text
+  Text,
+
+  /// only metadata keys and values
+  Metadata
+}
+
+impl SearchScope {
+  pub fn next(self) -> SearchScope {
+    match self {
+      SearchScope::All => SearchScope::Text,
+      SearchScope::Text => SearchScope::Metadata,
+      SearchScope::Metadata => SearchScope::All
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      SearchScope::All => "all",
+      SearchScope::Text => "text",
+      SearchScope::Metadata => "metadata"
+    }
+  }
+}
+
+impl FromStr for SearchScope {
+  type Err = SimpleError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "all" => Ok(SearchScope::All),
+      "text" => Ok(SearchScope::Text),
+      "metadata" | "meta" => Ok(SearchScope::Metadata),
+      _ => Err(SimpleError::new(format!("invalid search scope: {}", s)))
+    }
+  }
+}
+
+/// returns true if `pred` holds for any of the message's fields within the
+/// given scope
+///
+/// the kind and level are always tested in lowercase
+fn scope_matches<F>(message: &Message, scope: SearchScope, pred: F) -> bool
+where
+  F: Fn(&str) -> bool
+{
+  if scope == SearchScope::All {
+    if pred(&message.kind.to_string().to_lowercase()) {
+      return true;
+    }
+
+    if let Some(level) = message.level {
+      if pred(&level.to_string().to_lowercase()) {
+        return true;
+      }
+    }
+  }
+
+  if scope != SearchScope::Metadata {
+    if let Some(text) = &message.text {
+      if pred(text) {
+        return true;
+      }
+    }
+  }
+
+  if scope != SearchScope::Text {
+    for (k, v) in &message.metadata {
+      if pred(k) || pred(&v.to_string()) {
+        return true;
+      }
+    }
+  }
+
+  false
+}
+
+/// returns the message text if matches within it should be highlighted
+///
+/// inverted filters highlight messages that don't match, so there's nothing
+/// to highlight within them
+fn text_in_scope<'a>(
+  filter: &dyn Filter, message: &'a Message, scope: SearchScope
+) -> Option<&'a str> {
+  if filter.inverted() || scope == SearchScope::Metadata {
+    return None;
+  }
+
+  message.text.as_deref()
 }
 
 #[derive(Debug, Copy, Clone)]
 pub enum FilterMode {
   Text,
-  Regex
+  Regex,
+  Field
 }
 
 impl FilterMode {
-  pub fn parse(self, filter: &str, inverted: bool) -> SimpleResult<Box<dyn Filter>> {
+  pub fn parse(
+    self, filter: &str, inverted: bool, scope: SearchScope
+  ) -> SimpleResult<Box<dyn Filter>> {
     Ok(match self {
-      FilterMode::Text => Box::new(FullTextFilter::new(filter, inverted)?),
-      FilterMode::Regex => Box::new(RegexFilter::new(filter, inverted)?)
+      FilterMode::Text => Box::new(
+        FullTextFilter::new(filter, inverted)?.with_scope(scope)
+      ),
+      FilterMode::Regex => Box::new(
+        RegexFilter::new(filter, inverted)?.with_scope(scope)
+      ),
+      FilterMode::Field => Box::new(FieldFilter::new(filter, inverted)?)
     })
   }
 
   /// Given a FilterMode, return a different FilterMode (e.g. toggling between
   /// modes)
   pub fn next(self) -> FilterMode {
-    // will probably need to be smarter if more modes are added
     match self {
       FilterMode::Text => FilterMode::Regex,
-      FilterMode::Regex => FilterMode::Text
+      FilterMode::Regex => FilterMode::Field,
+      FilterMode::Field => FilterMode::Text
+    }
+  }
+
+  /// toggles directly between regex and plain text matching, without cycling
+  /// through any other modes
+  pub fn toggle_regex(self) -> FilterMode {
+    match self {
+      FilterMode::Regex => FilterMode::Text,
+      _ => FilterMode::Regex
     }
   }
 
   pub fn name(self) -> &'static str {
     match self {
       FilterMode::Text => "text",
-      FilterMode::Regex => "regex"
+      FilterMode::Regex => "regex",
+      FilterMode::Field => "field"
     }
   }
 }
 
 pub struct FullTextFilter {
   query: String,
-  inverted: bool
+  inverted: bool,
+  scope: SearchScope
+}
+
+impl FullTextFilter {
+  pub fn with_scope(mut self, scope: SearchScope) -> Self {
+    self.scope = scope;
+    self
+  }
 }
 
 impl Filter for FullTextFilter {
   fn new(query: &str, inverted: bool) -> SimpleResult<FullTextFilter> {
     Ok(FullTextFilter {
       query: query.to_lowercase(),
-      inverted
+      inverted,
+      scope: SearchScope::All
     })
   }
 
   fn filter_pass(&self, message: &Message) -> bool {
-    if message.kind.to_string().to_lowercase().contains(&self.query) {
-      return true;
-    }
+    scope_matches(message, self.scope, |s| {
+      s.to_lowercase().contains(&self.query)
+    })
+  }
 
-    if let Some(level) = message.level {
-      if level.to_string().to_lowercase().contains(&self.query) {
-        return true;
-      }
-    }
+  fn matches(&self, message: &Message) -> Vec<(usize, usize)> {
+    let text = match text_in_scope(self, message, self.scope) {
+      Some(text) => text,
+      None => return Vec::new()
+    };
 
-    if let Some(text) = &message.text {
-      if text.to_lowercase().contains(&self.query) {
-        return true;
+    // lowercasing may change byte lengths, so map each lowercased byte back
+    // to the start of its original char
+    let mut lower = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (i, c) in text.char_indices() {
+      for l in c.to_lowercase() {
+        lower.push(l);
+        offsets.resize(lower.len(), i);
       }
     }
+    offsets.push(text.len());
 
-    for (k, v) in &message.metadata {
-      if k.to_lowercase().contains(&self.query) {
-        return true;
-      }
-
-      if v.to_string().to_lowercase().contains(&self.query) {
-        return true;
-      }
-    }
-
-    false
+    lower.match_indices(&self.query)
+      .map(|(start, m)| (offsets[start], offsets[start + m.len()]))
+      .filter(|(start, end)| start < end)
+      .collect()
   }
 
   fn inverted(&self) -> bool {
@@ -112,40 +246,135 @@ impl Filter for FullTextFilter {
 
 pub struct RegexFilter {
   re: Regex,
-  inverted: bool
+  inverted: bool,
+  scope: SearchScope
+}
+
+impl RegexFilter {
+  pub fn with_scope(mut self, scope: SearchScope) -> Self {
+    self.scope = scope;
+    self
+  }
 }
 
 impl Filter for RegexFilter {
   fn new(expr: &str, inverted: bool) -> SimpleResult<Self> {
     Regex::new(&expr)
       .map_err(SimpleError::from)
-      .map(|re| RegexFilter { re, inverted })
+      .map(|re| RegexFilter { re, inverted, scope: SearchScope::All })
   }
 
   fn filter_pass(&self, message: &Message) -> bool {
-    if self.re.find(&message.kind.to_string().to_lowercase()).is_some() {
-      return true;
+    scope_matches(message, self.scope, |s| self.re.find(s).is_some())
+  }
+
+  fn matches(&self, message: &Message) -> Vec<(usize, usize)> {
+    match text_in_scope(self, message, self.scope) {
+      Some(text) => self.re.find_iter(text)
+        .map(|m| (m.start(), m.end()))
+        .filter(|(start, end)| start < end)
+        .collect(),
+      None => Vec::new()
     }
+  }
 
-    if let Some(level) = message.level {
-      if self.re.find(&level.to_string().to_lowercase()).is_some() {
-        return true;
-      }
+  fn inverted(&self) -> bool {
+    self.inverted
+  }
+}
+
+/// How a FieldFilter compares a field's value
+enum FieldOp {
+  Equal(String),
+  NotEqual(String),
+  Matches(Regex)
+}
+
+/// Matches a single field's value, e.g. `status=500`, `status!=200`, or
+/// `path~^/api/`
+///
+/// Fields are looked up in the message's metadata, or by their original name
+/// if a parser mapped them to the timestamp, level, or text. A missing field
+/// only matches `!=`.
+pub struct FieldFilter {
+  key: String,
+  op: FieldOp,
+  inverted: bool
+}
+
+impl FieldFilter {
+  /// the value of the given field as a string, if the message has it
+  fn field_value(message: &Message, key: &str) -> Option<String> {
+    if let Some(value) = message.metadata.get(key) {
+      return Some(nicer_to_string(value));
     }
 
-    if let Some(text) = &message.text {
-      if self.re.find(&text).is_some() {
-        return true;
-      }
+    match message.mapped_fields.get(key)? {
+      MappingField::Timestamp => message.timestamp.map(|t| t.to_rfc3339()),
+      MappingField::Level => message.level
+        .map(|l| l.to_string().to_lowercase()),
+      MappingField::Text => message.text.clone()
     }
+  }
+}
 
-    for (k, v) in &message.metadata {
-      if self.re.find(k).is_some() {
-        return true;
-      }
+impl Filter for FieldFilter {
+  fn new(query: &str, inverted: bool) -> SimpleResult<Self> {
+    lazy_static! {
+      static ref RE: Regex = Regex::new(
+        r"^\s*([^\s=!~]+)\s*(!=|=|~)(.*)$"
+      ).unwrap();
+    }
 
-      if self.re.find(&v.to_string()).is_some() {
-        return true;
+    let caps = RE.captures(query).ok_or_else(|| SimpleError::new(
+      "expected key=value, key!=value, or key~pattern"
+    ))?;
+
+    let value = caps[3].trim();
+    let op = match &caps[2] {
+      "=" => FieldOp::Equal(value.to_string()),
+      "!=" => FieldOp::NotEqual(value.to_string()),
+      _ => FieldOp::Matches(Regex::new(value).map_err(SimpleError::from)?)
+    };
+
+    Ok(FieldFilter { key: caps[1].to_string(), op, inverted })
+  }
+
+  fn filter_pass(&self, message: &Message) -> bool {
+    let value = FieldFilter::field_value(message, &self.key);
+
+    match (&self.op, value) {
+      (FieldOp::Equal(expected), Some(value)) => &value == expected,
+      (FieldOp::NotEqual(expected), Some(value)) => &value != expected,
+      (FieldOp::NotEqual(_), None) => true,
+      (FieldOp::Matches(re), Some(value)) => re.is_match(&value),
+      (_, None) => false
+    }
+  }
+
+  fn inverted(&self) -> bool {
+    self.inverted
+  }
+}
+
+/// Matches messages from exactly one reader source, e.g. a Kubernetes pod
+pub struct SourceFilter {
+  source: String,
+  inverted: bool
+}
+
+impl Filter for SourceFilter {
+  fn new(source: &str, inverted: bool) -> SimpleResult<Self> {
+    Ok(SourceFilter {
+      source: source.to_string(),
+      inverted
+    })
+  }
+
+  fn filter_pass(&self, message: &Message) -> bool {
+    if let Some(meta) = &message.reader_metadata {
+      if let Some(source) = &meta.source {
+        return source == &self.source;
       }
     }
 
@@ -156,3 +385,208 @@ impl Filter for RegexFilter {
     self.inverted
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::{Map, Value};
+  use spectral::prelude::*;
+
+  use crate::parser::{LogLevel, MessageKind, ReaderMetadata};
+
+  fn message() -> Message {
+    let mut metadata = Map::new();
+    metadata.insert(String::from("user"), Value::from("alice"));
+    metadata.insert(String::from("status"), Value::from(500));
+
+    Message {
+      kind: MessageKind::Plain,
+      timestamp: None,
+      level: Some(LogLevel::Info),
+      raw: String::from("info hello world user=alice status=500"),
+      text: Some(String::from("hello world")),
+      metadata,
+      reader_metadata: None,
+      mapped_fields: hashmap! {
+        String::from("severity") => MappingField::Level
+      }
+    }
+  }
+
+  #[test]
+  fn test_scope_all() {
+    let filter = FilterMode::Text.parse("info", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+  }
+
+  #[test]
+  fn test_scope_text() {
+    let mode = FilterMode::Regex;
+
+    let filter = mode.parse("info", false, SearchScope::Text).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+
+    let filter = mode.parse("^hello", false, SearchScope::Text).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+
+    let filter = mode.parse("alice", false, SearchScope::Text).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+  }
+
+  #[test]
+  fn test_scope_metadata() {
+    let mode = FilterMode::Text;
+
+    let filter = mode.parse("ALICE", false, SearchScope::Metadata).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+
+    let filter = mode.parse("hello", false, SearchScope::Metadata).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+  }
+
+  #[test]
+  fn test_field_equal() {
+    let mode = FilterMode::Field;
+
+    let filter = mode.parse("status=500", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+
+    let filter = mode.parse(" user = alice ", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+
+    // exact, not a substring
+    let filter = mode.parse("user=ali", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+
+    // fields mapped to the level are matched by their original name
+    let filter = mode.parse("severity=info", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+  }
+
+  #[test]
+  fn test_field_not_equal() {
+    let mode = FilterMode::Field;
+
+    let filter = mode.parse("status!=500", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+
+    let filter = mode.parse("status!=200", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+  }
+
+  #[test]
+  fn test_field_regex() {
+    let mode = FilterMode::Field;
+
+    let filter = mode.parse(r"status~^5\d\d$", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+
+    let filter = mode.parse("user~^b", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+
+    assert_that!(mode.parse("user~(", false, SearchScope::All).is_err())
+      .is_true();
+  }
+
+  #[test]
+  fn test_field_missing() {
+    let mode = FilterMode::Field;
+
+    let filter = mode.parse("pod=web", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+
+    let filter = mode.parse("pod~.*", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_false();
+
+    let filter = mode.parse("pod!=web", false, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+
+    // inverting a missing field matches
+    let filter = mode.parse("pod=web", true, SearchScope::All).unwrap();
+    assert_that!(filter.filter(&message())).is_true();
+  }
+
+  #[test]
+  fn test_field_invalid() {
+    let mode = FilterMode::Field;
+
+    assert_that!(mode.parse("status", false, SearchScope::All).is_err())
+      .is_true();
+    assert_that!(mode.parse("=500", false, SearchScope::All).is_err())
+      .is_true();
+  }
+
+  #[test]
+  fn test_text_matches() {
+    let mode = FilterMode::Text;
+
+    let filter = mode.parse("O", false, SearchScope::All).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![(4, 5), (7, 8)]);
+
+    // only the text is highlighted
+    let filter = mode.parse("alice", false, SearchScope::All).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![]);
+
+    let filter = mode.parse("hello", false, SearchScope::Metadata).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![]);
+
+    let filter = mode.parse("hello", true, SearchScope::All).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![]);
+  }
+
+  #[test]
+  fn test_text_matches_unicode() {
+    let mut message = message();
+    message.text = Some(String::from("İSTANBUL istanbul"));
+
+    // 'İ' lowercases to two chars, so offsets must map back to the original
+    let mode = FilterMode::Text;
+    let filter = mode.parse("stan", false, SearchScope::All).unwrap();
+    assert_that!(filter.matches(&message)).is_equal_to(vec![(2, 6), (11, 15)]);
+  }
+
+  #[test]
+  fn test_regex_matches() {
+    let mode = FilterMode::Regex;
+
+    let filter = mode.parse(r"\w+", false, SearchScope::Text).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![(0, 5), (6, 11)]);
+
+    // empty matches are skipped
+    let filter = mode.parse("x*", false, SearchScope::All).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![]);
+
+    let filter = mode.parse("world", true, SearchScope::All).unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![]);
+  }
+
+  #[test]
+  fn test_field_matches() {
+    let filter = FilterMode::Field.parse("user=alice", false, SearchScope::All)
+      .unwrap();
+    assert_that!(filter.matches(&message())).is_equal_to(vec![]);
+  }
+
+  #[test]
+  fn test_source() {
+    let filter = SourceFilter::new("web-1/app", false).unwrap();
+
+    // messages without a source never match
+    let mut message = message();
+    assert_that!(filter.filter(&message)).is_false();
+
+    message.reader_metadata = Some(ReaderMetadata {
+      source: Some(String::from("web-1/app")),
+      ..Default::default()
+    });
+    assert_that!(filter.filter(&message)).is_true();
+
+    let inverted = SourceFilter::new("web-1/app", true).unwrap();
+    assert_that!(inverted.filter(&message)).is_false();
+
+    message.reader_metadata.as_mut().unwrap().source = Some("web-10".into());
+    assert_that!(filter.filter(&message)).is_false();
+    assert_that!(inverted.filter(&message)).is_true();
+  }
+}
@@ -0,0 +1,375 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::str::FromStr;
+
+use crossterm::KeyEvent;
+use serde::{Deserialize, Serialize, Serializer};
+use simple_error::SimpleError;
+
+/// An action in the interactive renderer that may be bound to keys
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Action {
+  ForceQuit,
+  Up,
+  Down,
+  Top,
+  Bottom,
+  PageUp,
+  PageDown,
+  HalfPageUp,
+  HalfPageDown,
+
+  Quit,
+  ScrollLeft,
+  ScrollRight,
+  Filter,
+  Search,
+  PopFilter,
+  Reveal,
+  Source,
+  Detail,
+  Group,
+  Follow,
+  Pause,
+  Stats,
+  Copy,
+  CopyScreen,
+  CopyField,
+  CopyRaw,
+  Help,
+
+  CycleMode,
+  ToggleRegex,
+  CycleScope,
+  Invert,
+  Next,
+  Previous
+}
+
+/// each action's name in config files and its default keys
+///
+/// the first binding for a key wins, so global actions should come first
+const DEFAULTS: &[(Action, &str, &[&str])] = &[
+  (Action::ForceQuit, "force-quit", &["ctrl-q"]),
+  (Action::Up, "up", &["up", "k"]),
+  (Action::Down, "down", &["down", "j"]),
+  (Action::Top, "top", &["home"]),
+  (Action::Bottom, "bottom", &["end", "G"]),
+  (Action::PageUp, "page-up", &["page-up"]),
+  (Action::PageDown, "page-down", &["page-down"]),
+  (Action::HalfPageUp, "half-page-up", &["ctrl-u"]),
+  (Action::HalfPageDown, "half-page-down", &["ctrl-d"]),
+
+  (Action::Quit, "quit", &["q"]),
+  (Action::ScrollLeft, "scroll-left", &["left"]),
+  (Action::ScrollRight, "scroll-right", &["right"]),
+  (Action::Filter, "filter", &["f", "|"]),
+  (Action::Search, "search", &["/", "ctrl-f"]),
+  (Action::PopFilter, "pop-filter", &["p"]),
+  (Action::Reveal, "reveal", &["r"]),
+  (Action::Source, "source", &["s"]),
+  (Action::Detail, "detail", &["x"]),
+  (Action::Group, "group", &["g"]),
+  (Action::Follow, "follow", &["F"]),
+  (Action::Pause, "pause", &["space"]),
+  (Action::Stats, "stats", &["S"]),
+  (Action::Copy, "copy", &["c", "ctrl-c"]),
+  (Action::CopyScreen, "copy-screen", &["C"]),
+  (Action::CopyField, "copy-field", &["y"]),
+  (Action::CopyRaw, "copy-raw", &["Y"]),
+  (Action::Help, "help", &["?"]),
+
+  (Action::CycleMode, "cycle-mode", &["ctrl-r"]),
+  (Action::ToggleRegex, "toggle-regex", &["ctrl-l"]),
+  (Action::CycleScope, "cycle-scope", &["ctrl-t"]),
+  (Action::Invert, "invert", &["ctrl-e"]),
+  (Action::Next, "next", &["ctrl-n"]),
+  (Action::Previous, "previous", &["ctrl-p"])
+];
+
+impl FromStr for Action {
+  type Err = SimpleError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    DEFAULTS.iter()
+      .find(|(_, name, _)| *name == s)
+      .map(|(action, _, _)| *action)
+      .ok_or_else(|| SimpleError::new(format!("invalid action: {}", s)))
+  }
+}
+
+/// parses a key like `q`, `ctrl-f`, `alt-x`, `page-up`, or `f1`
+pub fn parse_key(s: &str) -> Result<KeyEvent, SimpleError> {
+  let mut chars = s.chars();
+  if let (Some(c), None) = (chars.next(), chars.next()) {
+    return Ok(KeyEvent::Char(c));
+  }
+
+  let lower = s.to_lowercase();
+  let modified = |prefix: &str| -> Option<char> {
+    if !lower.starts_with(prefix) {
+      return None;
+    }
+
+    let mut chars = lower[prefix.len()..].chars();
+    match (chars.next(), chars.next()) {
+      (Some(c), None) => Some(c),
+      _ => None
+    }
+  };
+
+  if let Some(c) = modified("ctrl-") {
+    return Ok(KeyEvent::Ctrl(c));
+  } else if let Some(c) = modified("alt-") {
+    return Ok(KeyEvent::Alt(c));
+  }
+
+  Ok(match lower.as_str() {
+    "up" => KeyEvent::Up,
+    "down" => KeyEvent::Down,
+    "left" => KeyEvent::Left,
+    "right" => KeyEvent::Right,
+    "home" => KeyEvent::Home,
+    "end" => KeyEvent::End,
+    "page-up" | "pageup" | "pgup" => KeyEvent::PageUp,
+    "page-down" | "pagedown" | "pgdn" => KeyEvent::PageDown,
+    "backspace" => KeyEvent::Backspace,
+    "delete" | "del" => KeyEvent::Delete,
+    "insert" | "ins" => KeyEvent::Insert,
+    "esc" | "escape" => KeyEvent::Esc,
+    "enter" | "return" => KeyEvent::Char('\n'),
+    "tab" => KeyEvent::Char('\t'),
+    "space" => KeyEvent::Char(' '),
+    s => match s.trim_start_matches('f').parse::<u8>() {
+      Ok(n) if s.starts_with('f') && (1..=12).contains(&n) => KeyEvent::F(n),
+      _ => return Err(SimpleError::new(format!("invalid key: {}", s)))
+    }
+  })
+}
+
+/// formats a key as it would be written in a config file
+pub fn format_key(key: &KeyEvent) -> String {
+  match key {
+    KeyEvent::Char('\n') => String::from("enter"),
+    KeyEvent::Char('\t') => String::from("tab"),
+    KeyEvent::Char(' ') => String::from("space"),
+    KeyEvent::Char(c) => c.to_string(),
+    KeyEvent::Ctrl(c) => format!("ctrl-{}", c),
+    KeyEvent::Alt(c) => format!("alt-{}", c),
+    KeyEvent::F(n) => format!("f{}", n),
+    KeyEvent::Up => String::from("up"),
+    KeyEvent::Down => String::from("down"),
+    KeyEvent::Left => String::from("left"),
+    KeyEvent::Right => String::from("right"),
+    KeyEvent::Home => String::from("home"),
+    KeyEvent::End => String::from("end"),
+    KeyEvent::PageUp => String::from("page-up"),
+    KeyEvent::PageDown => String::from("page-down"),
+    KeyEvent::Backspace => String::from("backspace"),
+    KeyEvent::Delete => String::from("delete"),
+    KeyEvent::Insert => String::from("insert"),
+    KeyEvent::Esc => String::from("esc"),
+    key => format!("{:?}", key).to_lowercase()
+  }
+}
+
+/// A YAML value that may be either a single key or a list of keys
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+  One(String),
+  Many(Vec<String>)
+}
+
+/// Keys bound to each interactive action
+///
+/// Loaded from a YAML file mapping action names to one or more keys, e.g.
+/// `down: [j, down]`; unlisted actions keep their default keys.
+pub struct KeyBindings {
+  /// the path these bindings were loaded from, if any
+  pub path: Option<String>,
+
+  /// the number of actions remapped by the file
+  pub remapped: usize,
+
+  bindings: Vec<(Action, Vec<KeyEvent>)>
+}
+
+impl KeyBindings {
+  /// the action bound to the given key, if any
+  pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+    self.bindings.iter()
+      .find(|(_, keys)| keys.contains(key))
+      .map(|(action, _)| *action)
+  }
+
+  /// the keys bound to the given action
+  pub fn keys(&self, action: Action) -> &[KeyEvent] {
+    self.bindings.iter()
+      .find(|(a, _)| *a == action)
+      .map(|(_, keys)| keys.as_slice())
+      .unwrap_or(&[])
+  }
+
+  /// replaces the keys bound to some actions; the new keys are removed from
+  /// any other actions' defaults
+  fn remap(&mut self, mut remapped: HashMap<Action, Vec<KeyEvent>>) {
+    self.remapped = remapped.len();
+
+    for (action, keys) in self.bindings.iter_mut() {
+      if !remapped.contains_key(action) {
+        keys.retain(|key| !remapped.values().any(|k| k.contains(key)));
+      }
+    }
+
+    for (action, keys) in self.bindings.iter_mut() {
+      if let Some(new_keys) = remapped.remove(action) {
+        *keys = new_keys;
+      }
+    }
+  }
+}
+
+impl Default for KeyBindings {
+  fn default() -> Self {
+    let bindings = DEFAULTS.iter()
+      .map(|(action, _, keys)| {
+        (*action, keys.iter().map(|k| parse_key(k).unwrap()).collect())
+      })
+      .collect();
+
+    KeyBindings { path: None, remapped: 0, bindings }
+  }
+}
+
+impl fmt::Debug for KeyBindings {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_map()
+      .entries(self.bindings.iter().map(|(action, keys)| {
+        let keys: Vec<_> = keys.iter().map(format_key).collect();
+        (action, keys)
+      }))
+      .finish()
+  }
+}
+
+impl Serialize for KeyBindings {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.path.as_deref().unwrap_or("default"))
+  }
+}
+
+impl FromStr for KeyBindings {
+  type Err = SimpleError;
+
+  fn from_str(path: &str) -> Result<Self, Self::Err> {
+    if path == "default" {
+      return Ok(KeyBindings::default());
+    }
+
+    let expanded_path = shellexpand::full(path).map_err(SimpleError::from)?;
+    let file = File::open(expanded_path.to_string()).map_err(SimpleError::from)?;
+    let reader = BufReader::new(file);
+
+    let raw: HashMap<String, KeyList> = serde_yaml::from_reader(reader)
+      .map_err(|e| SimpleError::new(
+        format!("error loading keybindings {}: {:?}", path, e)
+      ))?;
+
+    let mut remapped = HashMap::new();
+    for (name, keys) in raw {
+      let action = name.parse::<Action>()?;
+      let keys = match keys {
+        KeyList::One(key) => vec![key],
+        KeyList::Many(keys) => keys
+      };
+
+      let keys = keys.iter()
+        .map(|k| parse_key(k))
+        .collect::<Result<Vec<_>, _>>()?;
+
+      remapped.insert(action, keys);
+    }
+
+    let mut bindings = KeyBindings {
+      path: Some(path.to_string()),
+      ..KeyBindings::default()
+    };
+    bindings.remap(remapped);
+
+    Ok(bindings)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env;
+  use std::fs;
+  use std::process;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_parse_key() {
+    assert_that!(parse_key("q")).is_ok_containing(KeyEvent::Char('q'));
+    assert_that!(parse_key("C")).is_ok_containing(KeyEvent::Char('C'));
+    assert_that!(parse_key("ctrl-F")).is_ok_containing(KeyEvent::Ctrl('f'));
+    assert_that!(parse_key("alt-x")).is_ok_containing(KeyEvent::Alt('x'));
+    assert_that!(parse_key("PageUp")).is_ok_containing(KeyEvent::PageUp);
+    assert_that!(parse_key("enter")).is_ok_containing(KeyEvent::Char('\n'));
+    assert_that!(parse_key("f5")).is_ok_containing(KeyEvent::F(5));
+    assert_that!(parse_key("f13")).is_err();
+    assert_that!(parse_key("ctrl-")).is_err();
+    assert_that!(parse_key("foo")).is_err();
+  }
+
+  #[test]
+  fn test_format_key() {
+    for (_, _, keys) in DEFAULTS {
+      for key in keys.iter() {
+        let parsed = parse_key(key).unwrap();
+        assert_that!(format_key(&parsed)).is_equal_to(key.to_string());
+      }
+    }
+  }
+
+  #[test]
+  fn test_defaults() {
+    let keys = KeyBindings::default();
+
+    assert_that!(keys.action(&KeyEvent::Char('q'))).is_equal_to(Some(Action::Quit));
+    assert_that!(keys.action(&KeyEvent::Ctrl('f')))
+      .is_equal_to(Some(Action::Search));
+    assert_that!(keys.action(&KeyEvent::Char('z'))).is_none();
+    assert_that!(keys.keys(Action::Filter).len()).is_equal_to(2);
+  }
+
+  #[test]
+  fn test_load() {
+    let path = env::temp_dir().join(
+      format!("woodchipper-keys-test-{}.yaml", process::id())
+    );
+    fs::write(&path, "quit: x\ndown: [j, down]\n").unwrap();
+
+    let keys: KeyBindings = path.to_str().unwrap().parse().unwrap();
+    assert_that!(keys.remapped).is_equal_to(2);
+    assert_that!(keys.action(&KeyEvent::Char('x'))).is_equal_to(Some(Action::Quit));
+    assert_that!(keys.action(&KeyEvent::Char('j'))).is_equal_to(Some(Action::Down));
+    assert_that!(keys.action(&KeyEvent::Down)).is_equal_to(Some(Action::Down));
+    assert_that!(keys.action(&KeyEvent::Char('q'))).is_none();
+
+    // `x` is no longer bound to its default action
+    assert_that!(keys.keys(Action::Detail).len()).is_equal_to(0);
+
+    fs::write(&path, "frobnicate: x\n").unwrap();
+    assert_that!(path.to_str().unwrap().parse::<KeyBindings>()).is_err();
+
+    fs::remove_file(&path).ok();
+  }
+}
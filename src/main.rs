@@ -20,7 +20,7 @@ extern crate subprocess;
 use std::error::Error;
 use std::process;
 use std::sync::Arc;
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, sync_channel};
 use std::time::Duration;
 
 use structopt::StructOpt;
@@ -28,16 +28,39 @@ use structopt::StructOpt;
 mod config;
 mod clip;
 mod filter;
+mod keys;
 mod style;
 mod reader;
 mod parser;
 mod classifier;
 mod renderer;
 
-use config::Config;
+use config::{Config, ReaderType};
 
 fn main() -> Result<(), Box<dyn Error>> {
-  let config = Arc::new(Config::from_args());
+  let mut config = Config::from_args();
+  if let Some(path) = &config.write_config {
+    config.write_yaml(path)?;
+    eprintln!("wrote configuration to {}", path);
+
+    return Ok(());
+  }
+
+  // files are loaded while parsing flags, so if we got this far they're valid
+  if config.validate {
+    for line in config.describe_files() {
+      println!("{}", line);
+    }
+
+    println!("configuration is valid");
+    return Ok(());
+  }
+
+  config.apply_style_flags();
+
+  let config = Arc::new(config);
+  renderer::glyphs::set_ascii(config.ascii);
+  renderer::set_timestamp_format(&config);
 
   let renderer_impl = config.renderer.get_renderer(Arc::clone(&config));
   let reader_impl = config.reader.get_reader(Arc::clone(&config));
@@ -53,45 +76,99 @@ fn main() -> Result<(), Box<dyn Error>> {
     process::exit(1);
   }
 
-  let (entry_tx, entry_rx) = channel();
+  let (entry_tx, entry_rx) = sync_channel(config.channel_capacity);
   let renderer = renderer_impl(Arc::clone(&config), entry_rx);
 
+  // optional stages between the reader and renderer, in pipeline order
+  let mut stages: Vec<reader::Stage> = Vec::new();
+
+  // containers are followed by separate threads, so kubernetes messages
+  // interleave by arrival unless reordered; this comes first so later stages
+  // like grep context and replay see messages in timestamp order
+  let following_kubernetes =
+    config.reader.resolve(&config) == ReaderType::Kubernetes;
+  if config.ordered || config.buffer_ms.is_some() || following_kubernetes {
+    stages.push(Box::new(reader::read_ordered));
+  }
+
+  if config.expect.is_some() {
+    // before the summary so its warnings count towards --fail-on-level
+    stages.push(Box::new(reader::read_expect));
+  }
+
+  let level_counts = reader::summary::LevelCounts::default();
+  if config.fail_on_level.is_some() {
+    // count first so nothing is missed due to --history or filtering
+    let counts = Arc::clone(&level_counts);
+    stages.push(Box::new(move |config, rx, tx| {
+      reader::read_summary(config, rx, tx, counts)
+    }));
+  }
+
+  if config.history.is_some() {
+    stages.push(Box::new(reader::read_history));
+  }
+
+  if config.grep.is_some() || config.exclude.is_some() {
+    // after history, which should see the unfiltered input
+    stages.push(Box::new(reader::read_grep));
+  }
+
+  if config.quiet_internal {
+    // after history so its note about skipped messages is also dropped
+    stages.push(Box::new(reader::read_quiet));
+  }
+
+  // replay may be waiting between messages when the renderer quits
+  let (replay_exit_tx, replay_exit_rx) = channel();
+  if config.replay_speed.is_some() {
+    stages.push(Box::new(move |config, rx, tx| {
+      reader::read_replay(config, rx, tx, replay_exit_rx)
+    }));
+  }
+
+  if config.drop_on_full {
+    stages.push(Box::new(reader::read_lossy));
+  }
+
+  // connect the stages, working backwards from the renderer
+  let mut reader_tx = entry_tx;
+  for stage in stages.into_iter().rev() {
+    let (stage_tx, stage_rx) = sync_channel(config.channel_capacity);
+    stage(Arc::clone(&config), stage_rx, reader_tx);
+    reader_tx = stage_tx;
+  }
+
   // kick off the reader thread and hope it goes on to do great things
   // due to blocking IO limitations we can't ever expect to actually get a
   // result out of it, and will have to let the OS handle cleanup for us
   let (exit_req_tx, exit_req_rx) = channel();
   let (exit_resp_tx, exit_resp_rx) = channel();
 
-  if config.ordered || config.buffer_ms.is_some() {
-    // if --ordered or --buffer-ms, wrap the reader in read_ordered
-    let (ord_tx, ord_rx) = channel();
-
-    reader_impl(
-      Arc::clone(&config),
-      ord_tx,
-      exit_req_rx, exit_resp_tx
-    );
-
-    reader::read_ordered(
-      Arc::clone(&config),
-      ord_rx,
-      entry_tx
-    );
-  } else {
-    reader_impl(
-      Arc::clone(&config),
-      entry_tx,
-      exit_req_rx, exit_resp_tx
-    );
-  }
+  reader_impl(
+    Arc::clone(&config),
+    reader_tx,
+    exit_req_rx, exit_resp_tx
+  );
 
   renderer.join().expect("renderer thread did not exit cleanly");
-  
+  replay_exit_tx.send(()).ok();
+
   // attempt to tell the reader to quit (though it'll probably be ignored)
   exit_req_tx.send(()).ok();
 
   // and wait at most 1s for an exit confirmation
   exit_resp_rx.recv_timeout(Duration::from_millis(1000)).ok();
 
+  if let Some(level) = config.fail_on_level {
+    let counts = level_counts.lock().unwrap();
+    let (matched, summary) = reader::summary::summarize(&counts, level);
+    eprintln!("{}", summary);
+
+    if matched > 0 {
+      process::exit(1);
+    }
+  }
+
   Ok(())
 }
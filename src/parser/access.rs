@@ -0,0 +1,164 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::error::Error;
+use std::sync::Arc;
+
+use chrono::prelude::*;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use super::types::{LogLevel, Message, MessageKind, ReaderMetadata};
+
+/// maps an HTTP status code to a LogLevel
+fn status_level(status: u16) -> LogLevel {
+  match status {
+    500..=599 => LogLevel::Error,
+    400..=499 => LogLevel::Warning,
+    _ => LogLevel::Info
+  }
+}
+
+/// unescapes a quoted field, which nginx and Apache escape as e.g. `\"`
+fn unquote(s: &str) -> String {
+  s.replace(r#"\""#, "\"").replace(r"\\", r"\")
+}
+
+/// parses nginx/Apache access logs in the common or combined log format, e.g.
+/// `1.2.3.4 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 200 2326 "-" "-"`
+///
+/// the request line is used as the text, and the level is derived from the
+/// response status
+pub fn parse_access(
+  _config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  lazy_static! {
+    static ref RE: Regex = Regex::new(concat!(
+      r#"^(\S+) \S+ \S+ \[([^\]]+)\] "((?:[^"\\]|\\.)*)" (\d{3}) (\d+|-)"#,
+      r#"(?: "((?:[^"\\]|\\.)*)" "((?:[^"\\]|\\.)*)")?"#
+    )).unwrap();
+  }
+
+  let caps = match RE.captures(line) {
+    Some(caps) => caps,
+    None => return Ok(None)
+  };
+
+  let timestamp = DateTime::parse_from_str(&caps[2], "%d/%b/%Y:%H:%M:%S %z");
+  let timestamp = match timestamp {
+    Ok(timestamp) => timestamp.with_timezone(&Utc),
+    Err(_) => return Ok(None)
+  };
+
+  let status: u16 = caps[4].parse()?;
+  let request = unquote(&caps[3]);
+
+  let mut metadata = Map::new();
+  metadata.insert(String::from("remote_addr"), Value::from(&caps[1]));
+
+  // malformed requests may be logged as just `-`
+  let mut parts = request.split_whitespace();
+  if let (Some(method), Some(path)) = (parts.next(), parts.next()) {
+    metadata.insert(String::from("method"), Value::from(method));
+    metadata.insert(String::from("path"), Value::from(path));
+  }
+
+  metadata.insert(String::from("status"), Value::from(status));
+
+  // a `-` means no body was sent
+  let bytes = caps[5].parse::<u64>().unwrap_or(0);
+  metadata.insert(String::from("bytes"), Value::from(bytes));
+
+  let quoted = [(6, "referer"), (7, "user_agent")];
+  for (group, name) in quoted.iter() {
+    if let Some(value) = caps.get(*group).map(|m| m.as_str()) {
+      if value != "-" {
+        metadata.insert(name.to_string(), Value::from(unquote(value)));
+      }
+    }
+  }
+
+  Ok(Some(Message {
+    kind: MessageKind::Access,
+    timestamp: Some(timestamp),
+    level: Some(status_level(status)),
+    raw: line.to_string(),
+    text: Some(request).filter(|r| !r.is_empty()),
+    metadata,
+    reader_metadata: meta,
+    mapped_fields: hashmap!{}
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_access(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_not_access() {
+    assert_that!(parse("hello world")).is_ok_containing(Value::Null);
+  }
+
+  #[test]
+  fn test_combined() {
+    let line = r#"1.2.3.4 - - [10/Oct/2000:13:55:36 -0700] "GET /x HTTP/1.1" 200 2326 "http://example.com/" "curl/7""#;
+    let parsed = parse(line).unwrap();
+
+    assert_that!(parsed["kind"]).is_equal_to(json!("access"));
+    assert_that!(parsed["raw"]).is_equal_to(json!(line));
+    assert_that!(parsed["timestamp"]).is_equal_to(json!("2000-10-10T20:55:36Z"));
+    assert_that!(parsed["level"]).is_equal_to(json!("info"));
+    assert_that!(parsed["text"]).is_equal_to(json!("GET /x HTTP/1.1"));
+    assert_that!(parsed["metadata"]).is_equal_to(json!({
+      "remote_addr": "1.2.3.4",
+      "method": "GET",
+      "path": "/x",
+      "status": 200,
+      "bytes": 2326,
+      "referer": "http://example.com/",
+      "user_agent": "curl/7"
+    }));
+  }
+
+  #[test]
+  fn test_common_dash_bytes() {
+    let line = r#"1.2.3.4 - bob [10/Oct/2000:13:55:36 +0000] "POST /y HTTP/1.0" 503 -"#;
+    let parsed = parse(line).unwrap();
+
+    assert_that!(parsed["level"]).is_equal_to(json!("error"));
+    assert_that!(parsed["metadata"]).is_equal_to(json!({
+      "remote_addr": "1.2.3.4",
+      "method": "POST",
+      "path": "/y",
+      "status": 503,
+      "bytes": 0
+    }));
+  }
+
+  #[test]
+  fn test_malformed_request() {
+    let line = r#"1.2.3.4 - - [10/Oct/2000:13:55:36 +0000] "-" 400 0 "-" "-""#;
+    let parsed = parse(line).unwrap();
+
+    assert_that!(parsed["level"]).is_equal_to(json!("warning"));
+    assert_that!(parsed["text"]).is_equal_to(json!("-"));
+    assert_that!(parsed["metadata"]).is_equal_to(json!({
+      "remote_addr": "1.2.3.4",
+      "status": 400,
+      "bytes": 0
+    }));
+  }
+}
@@ -0,0 +1,222 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::error::Error;
+use std::mem;
+use std::sync::Arc;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use super::logrus::typed_value;
+use super::types::{LogLevel, Message, MessageKind, ReaderMetadata};
+
+/// number of pipe-delimited fields before the extension, including the
+/// leading `CEF:0`
+const HEADER_FIELDS: usize = 7;
+
+/// splits the header on unescaped pipes, returning its fields (with `\|` and
+/// `\\` unescaped) and the remaining extension, or None if the header is
+/// incomplete
+fn split_header(s: &str) -> Option<(Vec<String>, &str)> {
+  let mut fields = Vec::with_capacity(HEADER_FIELDS);
+  let mut field = String::new();
+
+  let mut chars = s.char_indices();
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some((_, c @ '|')) | Some((_, c @ '\\')) => field.push(c),
+        Some((_, c)) => {
+          field.push('\\');
+          field.push(c);
+        },
+        None => field.push('\\')
+      },
+      '|' => {
+        fields.push(mem::take(&mut field));
+        if fields.len() == HEADER_FIELDS {
+          return Some((fields, &s[i + 1..]));
+        }
+      },
+      c => field.push(c)
+    }
+  }
+
+  None
+}
+
+/// unescapes an extension value, e.g. `a\=b`
+fn unescape_extension(s: &str) -> String {
+  let mut ret = String::with_capacity(s.len());
+
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      ret.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some('n') | Some('r') => ret.push('\n'),
+      Some(c @ '=') | Some(c @ '\\') => ret.push(c),
+      Some(c) => {
+        ret.push('\\');
+        ret.push(c);
+      },
+      None => ret.push('\\')
+    }
+  }
+
+  ret
+}
+
+/// parses the extension's `key=value` pairs
+///
+/// unlike logfmt, values are unquoted and may contain spaces, so each value
+/// runs until the next ` key=`
+fn parse_extension(s: &str) -> Map<String, Value> {
+  lazy_static! {
+    static ref KEY: Regex = Regex::new(r"(?:^|\s)([\w.\[\]-]+)=").unwrap();
+  }
+
+  let keys: Vec<_> = KEY.captures_iter(s)
+    .map(|caps| {
+      let (whole, key) = (caps.get(0).unwrap(), caps.get(1).unwrap());
+      (whole.start(), whole.end(), key.as_str())
+    })
+    .collect();
+
+  let mut ret = Map::new();
+  for (i, (_, value_start, key)) in keys.iter().enumerate() {
+    let value_end = keys.get(i + 1).map(|k| k.0).unwrap_or_else(|| s.len());
+    let value = unescape_extension(s[*value_start..value_end].trim());
+
+    ret.insert(key.to_string(), typed_value(value));
+  }
+
+  ret
+}
+
+/// maps a severity, either 0-10 or a name like `High`, to a LogLevel
+fn parse_severity(s: &str) -> Option<LogLevel> {
+  match s.to_lowercase().as_str() {
+    "low" => Some(LogLevel::Info),
+    "medium" => Some(LogLevel::Warning),
+    "high" => Some(LogLevel::Error),
+    "very-high" => Some(LogLevel::Critical),
+    s => match s.parse::<u8>().ok()? {
+      0..=3 => Some(LogLevel::Info),
+      4..=6 => Some(LogLevel::Warning),
+      7..=8 => Some(LogLevel::Error),
+      9..=10 => Some(LogLevel::Critical),
+      _ => None
+    }
+  }
+}
+
+/// parses ArcSight Common Event Format lines, e.g.
+/// `CEF:0|Vendor|Product|1.0|100|port scan|5|src=1.2.3.4 dst=5.6.7.8`
+///
+/// the event name is used as the text, and the vendor, product, version, and
+/// signature ID are added to the message's fields alongside the extension
+pub fn parse_cef(
+  _config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  if !line.starts_with("CEF:") {
+    return Ok(None);
+  }
+
+  let (header, extension) = match split_header(line) {
+    Some(parts) => parts,
+    None => return Ok(None)
+  };
+
+  let mut metadata = Map::new();
+  let names = ["vendor", "product", "version", "signatureId"];
+  for (name, value) in names.iter().zip(&header[1..5]) {
+    metadata.insert(name.to_string(), Value::from(value.as_str()));
+  }
+
+  metadata.extend(parse_extension(extension));
+
+  let text = header[5].trim();
+
+  Ok(Some(Message {
+    kind: MessageKind::Cef,
+    timestamp: None,
+    level: parse_severity(header[6].trim()),
+    raw: line.to_string(),
+    text: Some(text.to_string()).filter(|t| !t.is_empty()),
+    metadata,
+    reader_metadata: meta,
+    mapped_fields: hashmap!{}
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_cef(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_not_cef() {
+    assert_that!(parse("hello world")).is_ok_containing(Value::Null);
+    assert_that!(parse("CEF:0|Vendor|Product|1.0"))
+      .is_ok_containing(Value::Null);
+  }
+
+  #[test]
+  fn test_cef() {
+    let line = "CEF:0|Acme|Firewall|1.0|100|port scan|5|src=1.2.3.4 spt=22 msg=many ports hit";
+    let parsed = parse(line).unwrap();
+
+    assert_that!(parsed["kind"]).is_equal_to(json!("cef"));
+    assert_that!(parsed["raw"]).is_equal_to(json!(line));
+    assert_that!(parsed["level"]).is_equal_to(json!("warning"));
+    assert_that!(parsed["text"]).is_equal_to(json!("port scan"));
+    assert_that!(parsed["metadata"]).is_equal_to(json!({
+      "vendor": "Acme",
+      "product": "Firewall",
+      "version": "1.0",
+      "signatureId": "100",
+      "src": "1.2.3.4",
+      "spt": 22,
+      "msg": "many ports hit"
+    }));
+  }
+
+  #[test]
+  fn test_cef_escapes() {
+    let line = r"CEF:0|Acme|Fire\|wall|1.0|100|a \\ b|High|act=x\=y path=C:\\tmp";
+    let parsed = parse(line).unwrap();
+
+    assert_that!(parsed["level"]).is_equal_to(json!("error"));
+    assert_that!(parsed["text"]).is_equal_to(json!(r"a \ b"));
+    assert_that!(parsed["metadata"]["product"]).is_equal_to(json!("Fire|wall"));
+    assert_that!(parsed["metadata"]["act"]).is_equal_to(json!("x=y"));
+    assert_that!(parsed["metadata"]["path"]).is_equal_to(json!(r"C:\tmp"));
+  }
+
+  #[test]
+  fn test_severity() {
+    assert_that!(parse_severity("0")).is_equal_to(Some(LogLevel::Info));
+    assert_that!(parse_severity("7")).is_equal_to(Some(LogLevel::Error));
+    assert_that!(parse_severity("10")).is_equal_to(Some(LogLevel::Critical));
+    assert_that!(parse_severity("11")).is_none();
+    assert_that!(parse_severity("Very-High"))
+      .is_equal_to(Some(LogLevel::Critical));
+  }
+}
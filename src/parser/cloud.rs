@@ -0,0 +1,207 @@
+// (C) Copyright 2019 Hewlett Packard Enterprise Development LP
+
+use std::error::Error;
+use std::sync::Arc;
+
+use serde_json::{self, Value, Map};
+
+use crate::config::Config;
+use super::json::{get_timestamp, parse_document};
+use super::types::{
+  LogLevel, MappingField, Message, MessageKind, ReaderMetadata
+};
+
+/// envelope fields that only describe log routing and are dropped entirely
+static ENVELOPE_FIELDS: &[&str] = &[
+  "insertId", "logName", "receiveTimestamp", "resource"
+];
+
+/// converts a cloud log envelope into a message, un-nesting the payload
+fn parse_envelope(
+  config: &Config,
+  line: &str, mut envelope: Map<String, Value>, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  let payload = match (envelope.remove("jsonPayload"), envelope.remove("textPayload")) {
+    (Some(Value::Object(payload)), _) => payload,
+    (_, Some(Value::String(text))) => {
+      let mut payload = Map::new();
+      payload.insert(String::from("message"), Value::String(text));
+      payload
+    },
+    _ => return Ok(None)
+  };
+
+  let mut message = match parse_document(
+    config, line, MessageKind::Cloud, payload, meta
+  )? {
+    Some(message) => message,
+    None => return Ok(None)
+  };
+
+  // fields in the payload take priority, but fall back to the envelope
+  if message.timestamp.is_none() {
+    if let Some((key, timestamp)) = get_timestamp(&envelope) {
+      let key = String::from(key);
+      envelope.remove(&key);
+      message.mapped_fields.insert(key, MappingField::Timestamp);
+      message.timestamp = Some(timestamp);
+    }
+  }
+
+  // GCP severities are mapped like any other level field, e.g. DEFAULT is info
+  // and ALERT is critical; a level in the payload wins, in which case the
+  // severity is kept below as a plain field
+  if message.level.is_none() {
+    if let Some(severity) = envelope.remove("severity") {
+      message.level = severity.as_str()
+        .and_then(|s| LogLevel::parse_with_aliases(config, s));
+      message.mapped_fields.insert(
+        String::from("severity"), MappingField::Level
+      );
+    }
+  }
+
+  // keep anything else useful (labels, trace ids, etc) from the envelope
+  for (key, value) in envelope {
+    if ENVELOPE_FIELDS.contains(&key.as_str()) {
+      continue;
+    }
+
+    if message.mapped_fields.contains_key(&key) {
+      continue;
+    }
+
+    message.metadata.entry(key).or_insert(value);
+  }
+
+  Ok(Some(message))
+}
+
+/// parses GCP-style structured cloud logs, e.g.
+/// `{"severity": "ERROR", "timestamp": "...", "jsonPayload": {...}}`
+pub fn parse_cloud(
+  config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  // skip anything that doesn't at least vaguely look like an envelope
+  if !line.starts_with('{') || !line.ends_with('}') {
+    return Ok(None);
+  }
+
+  if !line.contains("Payload\"") {
+    return Ok(None);
+  }
+
+  match serde_json::from_str(line) {
+    Ok(envelope) => parse_envelope(&config, line, envelope, meta),
+    Err(_) => Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_cloud(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_not_cloud() {
+    assert_that!(parse(r#"{"msg": "hello"}"#)).is_ok_containing(Value::Null);
+    assert_that!(parse(r#"{"jsonPayload": 1}"#)).is_ok_containing(Value::Null);
+  }
+
+  #[test]
+  fn test_json_payload() {
+    let line = r#"{"severity":"WARNING","timestamp":"2019-10-01T20:40:49Z","insertId":"abc","jsonPayload":{"msg":"hello world","user":"alice"},"labels":{"a":"b"}}"#;
+
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "cloud",
+      "raw": line,
+      "timestamp": "2019-10-01T20:40:49Z",
+      "level": "warning",
+      "text": "hello world",
+      "metadata": {
+        "user": "alice",
+        "labels": {"a": "b"}
+      },
+      "mapped_fields": {
+        "msg": "text",
+        "timestamp": "timestamp",
+        "severity": "level"
+      }
+    }));
+  }
+
+  #[test]
+  fn test_text_payload() {
+    let line = r#"{"severity":"CRITICAL","textPayload":"oh no"}"#;
+
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "cloud",
+      "raw": line,
+      "level": "critical",
+      "text": "oh no",
+      "mapped_fields": {
+        "message": "text",
+        "severity": "level"
+      }
+    }));
+  }
+
+  #[test]
+  fn test_severity() {
+    let severities = &[
+      ("DEFAULT", LogLevel::Info),
+      ("DEBUG", LogLevel::Debug),
+      ("INFO", LogLevel::Info),
+      ("NOTICE", LogLevel::Notice),
+      ("WARNING", LogLevel::Warning),
+      ("ERROR", LogLevel::Error),
+      ("CRITICAL", LogLevel::Critical),
+      ("ALERT", LogLevel::Critical),
+      ("EMERGENCY", LogLevel::Fatal)
+    ];
+
+    for (severity, level) in severities {
+      let line = format!(r#"{{"severity":"{}","textPayload":"hi"}}"#, severity);
+      let expected = level.to_string().to_lowercase();
+      assert_that!(parse(&line).unwrap()["level"].as_str())
+        .is_equal_to(Some(expected.as_str()));
+
+      // the envelope agrees with level fields elsewhere
+      assert_that!(severity.parse::<LogLevel>()).is_equal_to(Ok(*level));
+    }
+
+    assert_that!(LogLevel::Trace < LogLevel::Debug).is_true();
+    assert_that!(LogLevel::Critical < LogLevel::Fatal).is_true();
+  }
+
+  #[test]
+  fn test_payload_level_wins() {
+    let line = r#"{"severity":"DEFAULT","jsonPayload":{"level":"debug","msg":"hi"}}"#;
+
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "cloud",
+      "raw": line,
+      "level": "debug",
+      "text": "hi",
+      "metadata": {
+        "severity": "DEFAULT"
+      },
+      "mapped_fields": {
+        "level": "level",
+        "msg": "text"
+      }
+    }));
+  }
+}
@@ -0,0 +1,199 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::error::Error;
+use std::sync::Arc;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use super::json::parse_rfc3339;
+use super::logrus::logrus_to_document;
+use super::util::nicer_to_string;
+use super::types::{
+  LogLevel, MappingField, Message, MessageKind, ReaderMetadata
+};
+
+/// determines the level of a router line from its `at` and `status` fields,
+/// along with the field it came from
+fn router_level(doc: &Map<String, Value>) -> Option<(LogLevel, &'static str)> {
+  if let Some(at) = doc.get("at").and_then(|v| v.as_str()) {
+    if at == "error" {
+      return Some((LogLevel::Error, "at"));
+    }
+  }
+
+  let level = match doc.get("status").and_then(|v| v.as_i64()) {
+    Some(status) if status >= 500 => LogLevel::Error,
+    Some(status) if status >= 400 => LogLevel::Warning,
+    Some(_) => LogLevel::Info,
+    None => return None
+  };
+
+  Some((level, "status"))
+}
+
+/// summarizes a router line, e.g. `GET /foo 200`, or the error description
+fn router_text(doc: &Map<String, Value>) -> Option<String> {
+  if let Some(desc) = doc.get("desc").and_then(|v| v.as_str()) {
+    return Some(desc.to_string());
+  }
+
+  let field = |key: &str| doc.get(key).map(nicer_to_string);
+
+  match (field("method"), field("path"), field("status")) {
+    (Some(method), Some(path), Some(status)) => {
+      Some(format!("{} {} {}", method, path, status))
+    },
+    _ => None
+  }
+}
+
+/// parses Heroku logplex output, e.g.
+/// `2019-10-01T00:00:00.000000+00:00 app[web.1]: hello world`
+///
+/// the `source[process]` tag is used as the message source, and Heroku's own
+/// logfmt lines (e.g. from the router) are parsed into fields
+pub fn parse_heroku(
+  _config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  lazy_static! {
+    static ref RE: Regex = Regex::new(
+      r"^(\d{4}-\d{2}-\d{2}T\S+) (\w+)\[([\w.\-]+)\]: ?(.*)$"
+    ).unwrap();
+  }
+
+  let caps = match RE.captures(line) {
+    Some(caps) => caps,
+    None => return Ok(None)
+  };
+
+  let timestamp = match parse_rfc3339(&caps[1]) {
+    Some(timestamp) => timestamp,
+    None => return Ok(None)
+  };
+
+  let source = &caps[2];
+  let process = &caps[3];
+  let rest = caps[4].trim();
+
+  // keep the reader's source if it has one, e.g. from kubernetes
+  let mut meta = meta.unwrap_or_default();
+  if meta.source.is_none() {
+    meta.source = Some(format!("{}[{}]", source, process));
+  }
+
+  let mut message = Message {
+    kind: MessageKind::Heroku,
+    timestamp: Some(timestamp),
+    level: None,
+    raw: line.to_string(),
+    text: Some(rest.to_string()).filter(|t| !t.is_empty()),
+    metadata: Map::new(),
+    reader_metadata: Some(meta),
+    mapped_fields: hashmap!{}
+  };
+
+  // only heroku's own lines are reliably logfmt, app output may be anything
+  if source == "heroku" {
+    if let Ok(doc) = logrus_to_document(rest) {
+      if !doc.is_empty() {
+        // the status is still worth showing as a field, so only `at` is
+        // consumed, and only if it supplied the level
+        if let Some((level, field)) = router_level(&doc) {
+          message.level = Some(level);
+          if field == "at" {
+            message.mapped_fields.insert(String::from("at"), MappingField::Level);
+          }
+        }
+
+        if let Some(text) = router_text(&doc) {
+          message.text = Some(text);
+        }
+
+        message.metadata = doc.into_iter()
+          .filter(|(k, _)| !message.mapped_fields.contains_key(k))
+          .collect();
+      }
+    }
+  }
+
+  Ok(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_heroku(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_not_heroku() {
+    assert_that!(parse("hello world")).is_ok_containing(Value::Null);
+    assert_that!(parse("2019-10-01 app[web.1]: hi"))
+      .is_ok_containing(Value::Null);
+  }
+
+  #[test]
+  fn test_app() {
+    let line = "2019-10-01T20:40:49.000000+00:00 app[web.1]: hello world";
+
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "heroku",
+      "raw": line,
+      "timestamp": "2019-10-01T20:40:49Z",
+      "text": "hello world",
+      "reader_metadata": {
+        "timestamp": null,
+        "source": "app[web.1]"
+      }
+    }));
+  }
+
+  #[test]
+  fn test_router() {
+    let line = r#"2019-10-01T20:40:49.000000+00:00 heroku[router]: at=info method=GET path="/foo" host=example.com status=503 bytes=1"#;
+
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "heroku",
+      "raw": line,
+      "timestamp": "2019-10-01T20:40:49Z",
+      "level": "error",
+      "text": "GET /foo 503",
+      "metadata": {
+        "at": "info",
+        "method": "GET",
+        "path": "/foo",
+        "host": "example.com",
+        "status": 503,
+        "bytes": 1
+      },
+      "reader_metadata": {
+        "timestamp": null,
+        "source": "heroku[router]"
+      }
+    }));
+  }
+
+  #[test]
+  fn test_router_error() {
+    let line = r#"2019-10-01T20:40:49+00:00 heroku[router]: at=error code=H12 desc="Request timeout" method=GET path="/" status=503"#;
+
+    let parsed = parse(line).unwrap();
+    assert_that!(parsed["level"]).is_equal_to(json!("error"));
+    assert_that!(parsed["text"]).is_equal_to(json!("Request timeout"));
+    assert_that!(parsed["mapped_fields"]).is_equal_to(json!({ "at": "level" }));
+    assert_that!(parsed["metadata"]["at"]).is_equal_to(Value::Null);
+  }
+}
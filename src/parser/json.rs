@@ -15,7 +15,7 @@ use super::types::{
 use super::util::normalize_datetime;
 
 static TIMESTAMP_FIELDS: &[&str] = &["timestamp", "@timestamp", "time", "ts"];
-static LEVEL_FIELDS: &[&str] = &["level"];
+static LEVEL_FIELDS: &[&str] = &["level", "severity"];
 static TEXT_FIELDS: &[&str] = &["text", "msg", "message"];
 
 pub fn get_value<'a, 'b>(
@@ -31,11 +31,28 @@ pub fn get_value<'a, 'b>(
   None
 }
 
+/// maps a numeric level, as used by bunyan and pino, e.g. `"level": 30`
+fn numeric_level(level: f64) -> LogLevel {
+  if level < 20.0 {
+    LogLevel::Trace
+  } else if level < 30.0 {
+    LogLevel::Debug
+  } else if level < 40.0 {
+    LogLevel::Info
+  } else if level < 50.0 {
+    LogLevel::Warning
+  } else if level < 60.0 {
+    LogLevel::Error
+  } else {
+    LogLevel::Fatal
+  }
+}
+
 /// determines if the date string is a simple RFC-2822 datetime, and if so,
 /// parses it
-/// we use dtparse to parse more free-form dates, but its parser is surprisingly
-/// expensive. as most structured logs will use some form of iso8601, we can try
-/// to use chrono's built in and much cheaper parser to save some cycles
+/// there's intentionally no free-form fallback (e.g. dtparse): such parsers are
+/// surprisingly expensive, and as most structured logs use some form of iso8601
+/// chrono's built in parsers keep this hot path cheap
 pub fn parse_rfc2822(s: &str) -> Option<DateTime<Utc>> {
   lazy_static! {
     static ref RE: Regex = Regex::new(
@@ -55,9 +72,9 @@ pub fn parse_rfc2822(s: &str) -> Option<DateTime<Utc>> {
 
 /// determines if the date string is a simple RFC-3339 datetime, and if so,
 /// parses it
-/// we use dtparse to parse more free-form dates, but its parser is surprisingly
-/// expensive. as most structured logs will use some form of iso8601, we can try
-/// to use chrono's built in and much cheaper parser to save some cycles
+/// there's intentionally no free-form fallback (e.g. dtparse): such parsers are
+/// surprisingly expensive, and as most structured logs use some form of iso8601
+/// chrono's built in parsers keep this hot path cheap
 pub fn parse_rfc3339(s: &str) -> Option<DateTime<Utc>> {
   lazy_static! {
     static ref RE: Regex = Regex::new(
@@ -75,14 +92,33 @@ pub fn parse_rfc3339(s: &str) -> Option<DateTime<Utc>> {
   }
 }
 
+/// converts a Unix epoch timestamp, e.g. `"time": 1577836800`, into a datetime
+///
+/// values below 10^11 (i.e. before the year 5138 as seconds) are assumed to be
+/// seconds, and larger values milliseconds
+fn parse_epoch(epoch: f64) -> Option<DateTime<Utc>> {
+  let millis = if epoch.abs() < 1e11 { epoch * 1000.0 } else { epoch };
+  if !millis.is_finite() || millis.abs() > 9e18 {
+    return None;
+  }
+
+  let millis = millis.round() as i64;
+  Utc.timestamp_opt(
+    millis.div_euclid(1000),
+    (millis.rem_euclid(1000) * 1_000_000) as u32
+  ).single()
+}
+
 /// Extract the timestamp from any supported field in the message, returning
 /// both the field and the parsed NaiveDateTime
 pub fn get_timestamp(msg: &Map<String, Value>) -> Option<(&str, DateTime<Utc>)> {
   if let Some((k, v)) = get_value(&msg, TIMESTAMP_FIELDS) {
-    let v_str = if let Some(v) = v.as_str() {
-      v
-    } else {
-      return None;
+    let v_str = match v {
+      Value::String(v) => v,
+      Value::Number(n) => {
+        return n.as_f64().and_then(parse_epoch).map(|dt| (k, dt));
+      },
+      _ => return None
     };
 
     parse_rfc3339(v_str)
@@ -94,6 +130,7 @@ pub fn get_timestamp(msg: &Map<String, Value>) -> Option<(&str, DateTime<Utc>)>
 }
 
 pub fn parse_document(
+  config: &Config,
   line: &str,
   kind: MessageKind,
   doc: Map<String, Value>,
@@ -109,7 +146,12 @@ pub fn parse_document(
   };
 
   let level = if let Some((key, value)) = get_value(&doc, LEVEL_FIELDS) {
-    if let Some(level) = value.as_str().and_then(|s| s.parse::<LogLevel>().ok()) {
+    let level = match value {
+      Value::Number(n) => n.as_f64().map(numeric_level),
+      value => value.as_str().and_then(|s| LogLevel::parse_with_aliases(config, s))
+    };
+
+    if let Some(level) = level {
       mapped_fields.insert(String::from(key), MappingField::Level);
       Some(level)
     } else {
@@ -137,7 +179,7 @@ pub fn parse_document(
   };
 
   // clone remaining fields into the message metadata
-  let metadata: HashMap<String, Value> = doc.iter()
+  let metadata: Map<String, Value> = doc.iter()
     .filter(|(k, _v)| !mapped_fields.contains_key(k.as_str()))
     .map(|(k, v)| (k.to_string(), v.to_owned()))
     .collect();
@@ -152,16 +194,107 @@ pub fn parse_document(
   Ok(Some(message))
 }
 
-pub fn parse_json(
-  _config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
-) -> Result<Option<Message>, Box<dyn Error>> {
+/// parses a line as a JSON object, if it is one
+pub fn parse_object(line: &str) -> Option<Map<String, Value>> {
   // skip anything that doesn't at least vaguely look like json
   if !line.starts_with('{') || !line.ends_with('}') {
-    return Ok(None);
+    return None;
+  }
+
+  serde_json::from_str(line).ok()
+}
+
+pub fn parse_json(
+  config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  match parse_object(line) {
+    Some(message) => parse_document(&config, line, MessageKind::Json, message, meta),
+    None => Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_json(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_numeric_level() {
+    assert_that!(parse(r#"{"level":50,"msg":"x"}"#)).is_ok_containing(json!({
+      "kind": "json",
+      "raw": r#"{"level":50,"msg":"x"}"#,
+      "level": "error",
+      "text": "x",
+      "mapped_fields": {
+        "level": "level",
+        "msg": "text"
+      }
+    }));
+
+    let level = |line: &str| parse(line).unwrap()["level"].clone();
+    assert_that!(level(r#"{"level":10}"#)).is_equal_to(json!("trace"));
+    assert_that!(level(r#"{"level":30}"#)).is_equal_to(json!("info"));
+    assert_that!(level(r#"{"level":60}"#)).is_equal_to(json!("fatal"));
+    assert_that!(level(r#"{"level":"warn"}"#)).is_equal_to(json!("warning"));
+  }
+
+  #[test]
+  fn test_severity() {
+    let line = r#"{"severity":"WARNING","message":"x"}"#;
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "json",
+      "raw": line,
+      "level": "warning",
+      "text": "x",
+      "mapped_fields": {
+        "severity": "level",
+        "message": "text"
+      }
+    }));
+
+    let level = |line: &str| parse(line).unwrap()["level"].clone();
+    assert_that!(level(r#"{"severity":"DEFAULT"}"#)).is_equal_to(json!("info"));
+    assert_that!(level(r#"{"severity":"NOTICE"}"#)).is_equal_to(json!("notice"));
+    assert_that!(level(r#"{"severity":"ALERT"}"#)).is_equal_to(json!("critical"));
+    assert_that!(level(r#"{"severity":"EMERGENCY"}"#)).is_equal_to(json!("fatal"));
+  }
+
+  #[test]
+  fn test_epoch_timestamp() {
+    assert_that!(parse(r#"{"time":1577836800,"msg":"x"}"#)).is_ok_containing(json!({
+      "kind": "json",
+      "raw": r#"{"time":1577836800,"msg":"x"}"#,
+      "timestamp": "2020-01-01T00:00:00Z",
+      "text": "x",
+      "mapped_fields": {
+        "time": "timestamp",
+        "msg": "text"
+      }
+    }));
+
+    let timestamp = |line: &str| parse(line).unwrap()["timestamp"].clone();
+    assert_that!(timestamp(r#"{"ts":1577836800123}"#))
+      .is_equal_to(json!("2020-01-01T00:00:00.123Z"));
+    assert_that!(timestamp(r#"{"ts":1577836800.5}"#))
+      .is_equal_to(json!("2020-01-01T00:00:00.500Z"));
   }
 
-  match serde_json::from_str(line) {
-    Ok(message) => parse_document(line, MessageKind::Json, message, meta),
-    Err(_) => Ok(None)
+  #[test]
+  fn test_raw() {
+    // the exact line is kept, including whitespace and key order
+    let line = r#"{ "msg": "x",  "level":"info" }"#;
+    assert_that!(parse(line).unwrap()["raw"]).is_equal_to(json!(line));
   }
 }
@@ -1,12 +1,11 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
-use std::collections::HashMap;
 use std::error::Error;
 use std::sync::Arc;
 
 use chrono::prelude::*;
 use regex::Regex;
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 use crate::config::Config;
 use super::types::{LogLevel, Message, MessageKind, ReaderMetadata};
@@ -63,7 +62,7 @@ pub fn parse_klog(
 
     let text = caps.get(5).unwrap().as_str();
 
-    let mut metadata = HashMap::new();
+    let mut metadata = Map::new();
 
     let maybe_thread_id = caps.get(3)
       .map(|c| c.as_str())
@@ -93,3 +92,37 @@ pub fn parse_klog(
 
   Ok(None)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> Option<Message> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    parse_klog(config, line, None).unwrap()
+  }
+
+  #[test]
+  fn test_klog_timestamp() {
+    let message = parse(
+      "I0703 17:19:11.688460       1 main.go:42] hello world"
+    ).unwrap();
+
+    let expected = format!("{}-07-03T17:19:11.688460Z", Utc::now().year())
+      .parse::<DateTime<Utc>>()
+      .unwrap();
+
+    assert_that!(message.timestamp).is_equal_to(Some(expected));
+    assert_that!(message.level).is_equal_to(Some(LogLevel::Info));
+    assert_that!(message.text).is_equal_to(Some("hello world".to_string()));
+  }
+
+  #[test]
+  fn test_klog_raw() {
+    let line = "E0703 17:19:11.688460       1 main.go:42] hello  world ";
+    assert_that!(parse(line).unwrap().raw).is_equal_to(line.to_string());
+  }
+}
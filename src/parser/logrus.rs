@@ -4,7 +4,7 @@ use std::error::Error;
 use std::sync::Arc;
 
 use pest::Parser;
-use serde_json::{self, Value, Map};
+use serde_json::{self, Number, Value, Map};
 use simple_error::SimpleError;
 
 use crate::config::Config;
@@ -15,6 +15,63 @@ use super::json::parse_document;
 #[grammar = "parser/logrus.pest"]
 struct LogrusParser;
 
+/// parses a float as a JSON number, if finite; NaN and infinities are left as
+/// strings since JSON can't represent them
+///
+/// values that don't format back to the same text, e.g. `version=1.10`, are
+/// also left as strings so nothing is lost
+fn parse_float(s: &str) -> Option<Number> {
+  let float = s.parse::<f64>().ok()?;
+  if format!("{:?}", float) != s && format!("{:e}", float) != s {
+    return None;
+  }
+
+  Number::from_f64(float)
+}
+
+/// unescapes the content of a quoted value, e.g. `hello \"world\"`
+///
+/// unknown escapes are left as-is
+fn unescape_logrus(s: &str) -> String {
+  let mut ret = String::with_capacity(s.len());
+
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      ret.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some('n') => ret.push('\n'),
+      Some('t') => ret.push('\t'),
+      Some(c @ '"') | Some(c @ '\'') | Some(c @ '\\') => ret.push(c),
+      Some(c) => {
+        ret.push('\\');
+        ret.push(c);
+      },
+      None => ret.push('\\')
+    }
+  }
+
+  ret
+}
+
+/// converts a value into a bool or number where possible, otherwise a string
+pub fn typed_value(s: String) -> Value {
+  if s == "true" {
+    Value::Bool(true)
+  } else if s == "false" {
+    Value::Bool(false)
+  } else if let Ok(int) = s.parse::<i64>() {
+    Value::Number(int.into())
+  } else if let Some(float) = parse_float(&s) {
+    Value::Number(float)
+  } else {
+    Value::String(s)
+  }
+}
+
 /// Converts logrus-style plaintext into a JSON document
 ///
 /// Logrus plaintext output is of the form:
@@ -39,18 +96,13 @@ pub fn logrus_to_document(
     for inner in pair.into_inner() {
       match inner.as_rule() {
         Rule::key => key = Some(inner.as_str().to_string()),
-        Rule::string | Rule::bare_string | Rule::object => {
-          let s = inner.as_str();
-
-          value = if s == "true" {
-            Some(Value::Bool(true))
-          } else if s == "false" {
-            Some(Value::Bool(false))
-          } else if let Ok(int) = s.parse::<i64>() {
-            Some(Value::Number(int.into()))
-          } else {
-            Some(Value::String(inner.as_str().to_string()))
+        Rule::string | Rule::single_quoted | Rule::bare_string | Rule::object => {
+          let s = match inner.as_rule() {
+            Rule::string | Rule::single_quoted => unescape_logrus(inner.as_str()),
+            _ => inner.as_str().to_string()
           };
+
+          value = Some(typed_value(s));
         },
         Rule::EOI => (),
         _ => unreachable!()
@@ -66,14 +118,14 @@ pub fn logrus_to_document(
 }
 
 pub fn parse_logrus(
-  _config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+  config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
 ) -> Result<Option<Message>, Box<dyn Error>> {
   match logrus_to_document(line) {
     Ok(doc) => {
       if doc.is_empty() {
         Ok(None)
       } else {
-        parse_document(line, MessageKind::Logrus, doc, meta)
+        parse_document(&config, line, MessageKind::Logrus, doc, meta)
       }
     },
     Err(_) => Ok(None)
@@ -89,6 +141,9 @@ mod tests {
   use spectral::prelude::*;
   use structopt::StructOpt;
 
+  use crate::config::LevelAliases;
+  use crate::parser::LogLevel;
+
   fn parse(line: &str) -> SimpleResult<Value> {
     let doc = logrus_to_document(line)
       .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
@@ -128,18 +183,42 @@ mod tests {
       "foo": "bar"
     }));
 
-    // TODO: single-quoted strings aren't supported
     assert_that!(parse("foo='bar'")).is_ok_containing(json!({
-      "foo": "'bar'"
+      "foo": "bar"
+    }));
+
+    assert_that!(parse("foo='hello world' bar=\"it's\"")).is_ok_containing(json!({
+      "foo": "hello world",
+      "bar": "it's"
+    }));
+
+    // apostrophes within bare strings aren't quotes
+    assert_that!(parse("foo=it's")).is_ok_containing(json!({
+      "foo": "it's"
     }));
 
     assert_that!(parse("foo=1")).is_ok_containing(json!({
       "foo": 1
     }));
 
-    // TODO: floating-point numbers are treated as bare strings
-    assert_that!(parse("foo=1.5")).is_ok_containing(json!({
-      "foo": "1.5"
+    assert_that!(parse("foo=1.5 bar=-2.0 baz=1e3")).is_ok_containing(json!({
+      "foo": 1.5,
+      "bar": -2.0,
+      "baz": 1000.0
+    }));
+
+    // only values that survive the round trip are converted
+    let parsed = parse("version=1.10 a=1.50 b=1.0e3 c=.5");
+    assert_that!(parsed).is_ok_containing(json!({
+      "version": "1.10",
+      "a": "1.50",
+      "b": "1.0e3",
+      "c": ".5"
+    }));
+
+    assert_that!(parse("foo=NaN bar=inf")).is_ok_containing(json!({
+      "foo": "NaN",
+      "bar": "inf"
     }));
 
     assert_that!(parse("foo=&{bar}")).is_ok_containing(json!({
@@ -154,11 +233,58 @@ mod tests {
       "foo": "hello 'world'"
     }));
 
-    // TODO: need to unescape to make escaped strings reasonable
     assert_that!(parse(r#"foo="hello \"world\"""#)).is_ok_containing(json!({
-      "foo": "hello \\\"world\\\""
+      "foo": "hello \"world\""
+    }));
+
+    assert_that!(parse(r#"foo="trailing\\" bar="\n""#)).is_ok_containing(json!({
+      "foo": "trailing\\",
+      "bar": "\n"
     }));
 
+    // bare strings are left as-is
+    assert_that!(parse(r#"foo=a\tb"#)).is_ok_containing(json!({
+      "foo": "a\\tb"
+    }));
+
+    assert_that!(unescape_logrus(r#"a\"b\x\"#)).is_equal_to(r#"a"b\x\"#.to_string());
+  }
+
+  #[test]
+  fn test_level_aliases() {
+    let mut config = Config::from_iter_safe(vec![""]).unwrap();
+    config.level_aliases = Some(LevelAliases {
+      path: String::from("aliases.yaml"),
+      aliases: hashmap!{ String::from("chatty") => LogLevel::Debug }
+    });
+
+    let parsed = parse_logrus(Arc::new(config), "level=CHATTY msg=hi", None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))
+      .and_then(|m| serde_json::to_value(m).map_err(SimpleError::from));
+
+    assert_that!(parsed).is_ok_containing(json!({
+      "kind": "logrus",
+      "raw": "level=CHATTY msg=hi",
+      "level": "debug",
+      "text": "hi",
+      "mapped_fields": {
+        "level": "level",
+        "msg": "text"
+      }
+    }));
+
+    // unknown levels are still left unset without an alias
+    assert_that!(parse_message("level=chatty msg=hi")).is_ok_containing(json!({
+      "kind": "logrus",
+      "raw": "level=chatty msg=hi",
+      "text": "hi",
+      "metadata": {
+        "level": "chatty"
+      },
+      "mapped_fields": {
+        "msg": "text"
+      }
+    }));
   }
 
   #[test]
@@ -169,8 +295,7 @@ mod tests {
 
     assert_that!(parse(r#"foo="hello "world"""#)).is_err();
 
-    // TODO: single-quoted strings aren't supported
-    //assert_that!(parse(r#"foo='bar"#)).is_err();
+    assert_that!(parse(r#"foo='bar"#)).is_err();
   }
 
   #[test]
@@ -228,4 +353,10 @@ mod tests {
       }
     }))
   }
+
+  #[test]
+  fn test_raw() {
+    let line = r#"level=info  msg="hello  world" foo=bar "#;
+    assert_that!(parse_message(line).unwrap()["raw"]).is_equal_to(json!(line));
+  }
 }
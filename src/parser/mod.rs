@@ -1,35 +1,81 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
+mod access;
+mod cef;
+mod cloud;
+mod heroku;
 mod json;
 mod klog;
 mod logrus;
 mod plain;
 mod regex;
+mod syslog;
 mod types;
 pub mod util;
+mod winevent;
 
 use std::error::Error;
 use std::sync::Arc;
 
 use crate::config::Config;
-pub use types::{LogLevel, Message, MessageKind, ReaderMetadata, Parser};
-
-static PARSERS: &[Parser] = &[
-  json::parse_json,
-  logrus::parse_logrus,
-  klog::parse_klog,
-  regex::parse_regex,
-  plain::parse_plain
+pub use types::{
+  LogLevel, MappingField, Message, MessageKind, ReaderMetadata, Parser
+};
+pub use json::parse_object;
+
+/// all available parsers by name, in the default order they're tried
+static PARSERS: &[(&str, Parser)] = &[
+  ("cloud", cloud::parse_cloud),
+  ("winevent", winevent::parse_winevent),
+  ("json", json::parse_json),
+  ("syslog", syslog::parse_syslog3164),
+  ("cef", cef::parse_cef),
+  ("heroku", heroku::parse_heroku),
+  ("logrus", logrus::parse_logrus),
+  ("klog", klog::parse_klog),
+  ("regex", regex::parse_regex),
+  ("access", access::parse_access),
+  ("plain", plain::parse_plain)
 ];
 
+/// names of all parsers, in their default order
+pub fn parser_names() -> Vec<&'static str> {
+  PARSERS.iter().map(|(name, _)| *name).collect()
+}
+
+/// looks up a parser by name; `logfmt` is accepted as an alias for `logrus`
+pub fn get_parser(name: &str) -> Option<Parser> {
+  let name = match name {
+    "logfmt" => "logrus",
+    name => name
+  };
+
+  PARSERS.iter().find(|(n, _)| *n == name).map(|(_, parser)| *parser)
+}
+
+/// raises the message level to the minimum requested by the reader, if any
+fn apply_min_level(mut message: Message) -> Message {
+  let min_level = message.reader_metadata.as_ref().and_then(|m| m.min_level);
+
+  if let Some(min_level) = min_level {
+    message.level = match message.level {
+      Some(level) if level >= min_level && level <= LogLevel::Fatal => Some(level),
+      _ => Some(min_level)
+    };
+  }
+
+  message
+}
+
 pub fn parse(
   config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
 ) -> Result<Option<Message>, Box<dyn Error>> {
-  for parser_fn in PARSERS {
+  for parser_fn in &config.parsers.parsers {
     let result = parser_fn(Arc::clone(&config), line, meta.clone());
 
     match result {
       Ok(None) => continue,
+      Ok(Some(message)) => return Ok(Some(apply_min_level(message))),
       _ => return result
     };
   }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use chrono::prelude::*;
 use regex::RegexSet;
+use serde_json::Map;
 
 use crate::config::Config;
 use super::types::{LogLevel, Message, MessageKind, ReaderMetadata};
@@ -14,10 +15,22 @@ fn get_log_level(line: &str) -> Option<LogLevel> {
   lazy_static! {
     static ref REGEXES: RegexSet = RegexSet::new(&[
       r"(?i)\bfatal\b", // FATAL, fatal
+      // like "trace" below, "critical" and "notice" are common in prose, so
+      // unless upper case they only count first and followed by a colon, in
+      // brackets, or as `level=...`
+      concat!(
+        r"\bCRIT(?:ICAL)?\b|(?i)^\s*crit(?:ical)?:",
+        r"|[\[<(]crit(?:ical)?[\]>)]|\blevel=crit(?:ical)?\b"
+      ),
       r"(?i)\berr(?:or)?\b", // ERR, ERROR, err, error
       r"(?i)\bwarn(?:ing)?\b", // WARN, WARNING, warn, warning
+      r"\bNOTICE\b|(?i)^\s*notice:|[\[<(]notice[\]>)]|\blevel=notice\b",
       r"(?i)\binfo\b", // INFO, info
       r"(?i)\b(?:debug|dbg)\b", // DBG, DEBUG, dbg, debug
+      // "trace" is common in prose (e.g. "stack trace"), so unless it's upper
+      // case it only counts where a level would be: first, in brackets, or
+      // as `level=trace`
+      r"\bTRACE\b|(?i)^\s*trace\b|[\[<(]trace[\]>)]|\blevel=trace\b",
     ]).unwrap();
   }
 
@@ -28,10 +41,13 @@ fn get_log_level(line: &str) -> Option<LogLevel> {
   for index in REGEXES.matches(line).iter() {
     return match index {
       0 => Some(LogLevel::Fatal),
-      1 => Some(LogLevel::Error),
-      2 => Some(LogLevel::Warning),
-      3 => Some(LogLevel::Info),
-      4 => Some(LogLevel::Debug),
+      1 => Some(LogLevel::Critical),
+      2 => Some(LogLevel::Error),
+      3 => Some(LogLevel::Warning),
+      4 => Some(LogLevel::Notice),
+      5 => Some(LogLevel::Info),
+      6 => Some(LogLevel::Debug),
+      7 => Some(LogLevel::Trace),
       _ => continue
     };
   }
@@ -58,8 +74,66 @@ pub fn parse_plain(
     level: get_log_level(line),
     raw: line.to_string(),
     text: Some(String::from(line)),
-    metadata: HashMap::new(),
+    metadata: Map::new(),
     reader_metadata: meta,
     mapped_fields: HashMap::new()
   }))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  #[test]
+  fn test_plain_raw() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = "  WARN\tdisk almost full  ";
+    let message = parse_plain(config, line, None).unwrap().unwrap();
+
+    assert_that!(message.raw).is_equal_to(line.to_string());
+    assert_that!(message.text).is_equal_to(Some(line.to_string()));
+    assert_that!(message.level).is_equal_to(Some(LogLevel::Warning));
+  }
+
+  #[test]
+  fn test_trace() {
+    assert_that!(get_log_level("TRACE entering loop"))
+      .is_equal_to(Some(LogLevel::Trace));
+    assert_that!(get_log_level("trace: entering loop"))
+      .is_equal_to(Some(LogLevel::Trace));
+    assert_that!(get_log_level("12:00:00 [trace] entering loop"))
+      .is_equal_to(Some(LogLevel::Trace));
+    assert_that!(get_log_level("ts=1 level=trace msg=hi"))
+      .is_equal_to(Some(LogLevel::Trace));
+
+    assert_that!(get_log_level("printing the stack trace")).is_none();
+    assert_that!(get_log_level("an error with stack trace"))
+      .is_equal_to(Some(LogLevel::Error));
+  }
+
+  #[test]
+  fn test_critical_notice() {
+    assert_that!(get_log_level("CRITICAL disk full"))
+      .is_equal_to(Some(LogLevel::Critical));
+    assert_that!(get_log_level("crit: disk full"))
+      .is_equal_to(Some(LogLevel::Critical));
+    assert_that!(get_log_level("<critical> disk full"))
+      .is_equal_to(Some(LogLevel::Critical));
+    assert_that!(get_log_level("NOTICE reloading config"))
+      .is_equal_to(Some(LogLevel::Notice));
+    assert_that!(get_log_level("12:00:00 [notice] reloading config"))
+      .is_equal_to(Some(LogLevel::Notice));
+    assert_that!(get_log_level("ts=1 level=notice msg=hi"))
+      .is_equal_to(Some(LogLevel::Notice));
+
+    assert_that!(get_log_level("entering critical section")).is_none();
+    assert_that!(get_log_level("Critical section entered")).is_none();
+    assert_that!(get_log_level("notice that the cache is cold")).is_none();
+    assert_that!(get_log_level("Notice the cache is cold")).is_none();
+    assert_that!(get_log_level("info: entering critical section"))
+      .is_equal_to(Some(LogLevel::Info));
+  }
+}
@@ -5,8 +5,9 @@ use std::error::Error;
 use std::iter::FromIterator;
 use std::sync::Arc;
 
+use chrono::Duration;
 use chrono::prelude::*;
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 use crate::config::{Config, RegexMapping};
 use super::types::{LogLevel, Message, MessageKind, ReaderMetadata};
@@ -29,12 +30,12 @@ fn parse_rfc3339(s: &str) -> Option<DateTime<Utc>> {
 }
 
 fn parse_format(
-  s: &str, fmt: &str, prepend: &Option<String>
+  s: &str, fmt: &str, prepend: &Option<String>, now: DateTime<Utc>
 ) -> Option<DateTime<Utc>> {
   let datetime = if let Some(prepend) = prepend {
     format!(
       "{} {}",
-      Utc::now().format(prepend),
+      now.format(prepend),
       s
     )
   } else {
@@ -44,17 +45,41 @@ fn parse_format(
   Utc.datetime_from_str(&datetime, fmt).ok()
 }
 
+/// parses a datetime using `datetime_prepend`, retrying with last year if the
+/// result is more than a day in the future, e.g. for December's logs read in
+/// January
+fn parse_format_rollover(
+  s: &str, fmt: &str, prepend: &Option<String>, now: DateTime<Utc>
+) -> Option<DateTime<Utc>> {
+  let datetime = parse_format(s, fmt, prepend, now)?;
+  if datetime <= now + Duration::days(1) {
+    return Some(datetime);
+  }
+
+  // Feb 29th has no equivalent last year
+  let last_year = now.with_year(now.year() - 1)
+    .or_else(|| (now - Duration::days(1)).with_year(now.year() - 1))?;
+
+  parse_format(s, fmt, prepend, last_year)
+}
+
 fn parse_datetime(
-  fmt: &str, datetime: &str, prepend: &Option<String>
+  mapping: &RegexMapping, fmt: &str, datetime: &str, now: DateTime<Utc>
 ) -> Option<DateTime<Utc>> {
+  let prepend = &mapping.datetime_prepend;
+
   match fmt {
     "rfc2822" => parse_rfc2822(datetime),
     "rfc3339" => parse_rfc3339(datetime),
-    _ => parse_format(datetime, fmt, prepend)
+    _ if mapping.datetime_rollover && prepend.is_some() => {
+      parse_format_rollover(datetime, fmt, prepend, now)
+    },
+    _ => parse_format(datetime, fmt, prepend, now)
   }
 }
 
 fn parse_mapping(
+  config: &Config,
   line: &str, mapping: &RegexMapping, meta: &Option<ReaderMetadata>
 ) -> Result<Option<Message>, Box<dyn Error>> {
   let caps = match mapping.pattern.captures(line) {
@@ -70,7 +95,7 @@ fn parse_mapping(
     if let Some(format) = &mapping.datetime {
       group_names.remove("datetime");
 
-      parse_datetime(&format, datetime.as_str(), &mapping.datetime_prepend)
+      parse_datetime(mapping, &format, datetime.as_str(), Utc::now())
     } else {
       None
     }
@@ -89,16 +114,13 @@ fn parse_mapping(
   let level = if let Some(level) = caps.name("level") {
     group_names.remove("level");
 
-    match level.as_str().parse::<LogLevel>() {
-      Ok(l) => Some(l),
-      Err(_) => None
-    }
+    LogLevel::parse_with_aliases(config, level.as_str())
   } else {
     None
   };
 
   // collect all other capture groups into the metadata
-  let mut metadata = HashMap::new();
+  let mut metadata = Map::new();
   for name in group_names {
     if let Some(mat) = caps.name(&name) {
       metadata.insert(
@@ -127,7 +149,7 @@ pub fn parse_regex(
 ) -> Result<Option<Message>, Box<dyn Error>> {
   if let Some(regexes) = &config.regexes {
     for mapping in &regexes.mappings {
-      match parse_mapping(line, mapping, &meta) {
+      match parse_mapping(&config, line, mapping, &meta) {
         Ok(Some(message)) => return Ok(Some(message)),
         Ok(None) => continue,
         Err(e) => return Err(e)
@@ -145,19 +167,22 @@ mod tests {
   use regex::Regex;
   use serde_json::json;
   use simple_error::{SimpleResult, SimpleError};
+  use structopt::StructOpt;
 
   fn mapping(pattern: &str, datetime: &str) -> RegexMapping {
     RegexMapping {
       pattern: Regex::new(pattern).unwrap(),
       datetime: Some(String::from(datetime)),
-      datetime_prepend: None
+      datetime_prepend: None,
+      datetime_rollover: false
     }
   }
 
   fn parse_to_value(
     line: &str, mapping: &RegexMapping, meta: &Option<ReaderMetadata>
   ) -> SimpleResult<Value> {
-    let parsed = parse_mapping(line, mapping, meta)
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let parsed = parse_mapping(&config, line, mapping, meta)
       .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
 
     serde_json::to_value(parsed).map_err(SimpleError::from)
@@ -253,7 +278,8 @@ mod tests {
         r"(?P<text>.+)"
       )).unwrap(),
       datetime: Some(String::from("%Y %m%d %H:%M:%S%.f")),
-      datetime_prepend: Some(String::from("%Y"))
+      datetime_prepend: Some(String::from("%Y")),
+      datetime_rollover: false
     };
 
     let value = parse_to_value(
@@ -289,7 +315,8 @@ mod tests {
         r"(?P<text>.+)"
       )).unwrap(),
       datetime: Some(String::from("%Y %m%d %H:%M:%S%.f")),
-      datetime_prepend: Some(String::from("%Y"))
+      datetime_prepend: Some(String::from("%Y")),
+      datetime_rollover: false
     };
 
     let value = parse_to_value(
@@ -301,6 +328,34 @@ mod tests {
     assert_that!(value).is_ok_containing(json!(null));
   }
 
+  #[test]
+  fn test_rollover() {
+    let mut mapping = RegexMapping {
+      pattern: Regex::new(r"^(?P<datetime>.+)$").unwrap(),
+      datetime: Some(String::from("%Y %b %d %H:%M:%S")),
+      datetime_prepend: Some(String::from("%Y")),
+      datetime_rollover: false
+    };
+
+    let date = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let now = date("2020-01-01T12:00:00Z");
+    let parse = |mapping: &RegexMapping, s: &str| {
+      parse_datetime(mapping, "%Y %b %d %H:%M:%S", s, now)
+    };
+
+    // off by default, so December lands in the future
+    assert_that!(parse(&mapping, "Dec 31 23:00:00"))
+      .is_equal_to(Some(date("2020-12-31T23:00:00Z")));
+
+    mapping.datetime_rollover = true;
+    assert_that!(parse(&mapping, "Dec 31 23:00:00"))
+      .is_equal_to(Some(date("2019-12-31T23:00:00Z")));
+
+    // slightly in the future is fine, e.g. due to clock skew
+    assert_that!(parse(&mapping, "Jan 02 00:00:00"))
+      .is_equal_to(Some(date("2020-01-02T00:00:00Z")));
+  }
+
   #[test]
   fn test_full_docs_example() {
     let value = parse_to_value(
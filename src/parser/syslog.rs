@@ -0,0 +1,171 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::error::Error;
+use std::sync::Arc;
+
+use chrono::Duration;
+use chrono::prelude::*;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use super::types::{LogLevel, Message, MessageKind, ReaderMetadata};
+
+/// maps the severity part of a syslog priority to a LogLevel
+fn severity_level(priority: u8) -> LogLevel {
+  match priority % 8 {
+    0 => LogLevel::Fatal,
+    1 | 2 => LogLevel::Critical,
+    3 => LogLevel::Error,
+    4 => LogLevel::Warning,
+    5 => LogLevel::Notice,
+    6 => LogLevel::Info,
+    _ => LogLevel::Debug
+  }
+}
+
+/// parses a timestamp like `Oct 11 22:14:15`, which has no year
+///
+/// the most recent year in which the timestamp is valid and no more than a
+/// day in the future is assumed, e.g. the previous year when reading
+/// December's logs in January, or the last leap year for `Feb 29`
+fn parse_timestamp(s: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+  let parse = |year: i32| {
+    let datetime = format!("{} {}", year, s);
+    NaiveDateTime::parse_from_str(&datetime, "%Y %b %e %H:%M:%S")
+      .ok()
+      .map(|dt| Utc.from_utc_datetime(&dt))
+  };
+
+  // leap years can be up to 8 years apart, e.g. 1896 and 1904
+  (0..=8)
+    .filter_map(|years_ago| parse(now.year() - years_ago))
+    .find(|timestamp| *timestamp <= now + Duration::days(1))
+}
+
+/// parses BSD-style (RFC 3164) syslog lines, e.g.
+/// `<13>Oct 11 22:14:15 host app[1234]: message`
+///
+/// the host and the `app[pid]` tag are added to the message's fields as
+/// `host`, `process`, and `pid`
+pub fn parse_syslog3164(
+  _config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  lazy_static! {
+    static ref RE: Regex = Regex::new(concat!(
+      r"^<(\d{1,3})>([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) ",
+      r"(\S+) ([^\s\[:]+)(?:\[(\d+)\])?: ?(.*)$"
+    )).unwrap();
+  }
+
+  let caps = match RE.captures(line) {
+    Some(caps) => caps,
+    None => return Ok(None)
+  };
+
+  let priority: u8 = match caps[1].parse() {
+    Ok(priority) if priority <= 191 => priority,
+    _ => return Ok(None)
+  };
+
+  let timestamp = match parse_timestamp(&caps[2], Utc::now()) {
+    Some(timestamp) => timestamp,
+    None => return Ok(None)
+  };
+
+  let mut metadata = Map::new();
+  metadata.insert(String::from("host"), Value::from(&caps[3]));
+  metadata.insert(String::from("process"), Value::from(&caps[4]));
+  if let Some(pid) = caps.get(5).and_then(|pid| pid.as_str().parse::<i64>().ok()) {
+    metadata.insert(String::from("pid"), Value::from(pid));
+  }
+
+  let text = caps[6].trim();
+
+  Ok(Some(Message {
+    kind: MessageKind::Syslog,
+    timestamp: Some(timestamp),
+    level: Some(severity_level(priority)),
+    raw: line.to_string(),
+    text: Some(text.to_string()).filter(|t| !t.is_empty()),
+    metadata,
+    reader_metadata: meta,
+    mapped_fields: hashmap!{}
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_syslog3164(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_not_syslog() {
+    assert_that!(parse("hello world")).is_ok_containing(Value::Null);
+    assert_that!(parse("<999>Oct 11 22:14:15 host app: hi"))
+      .is_ok_containing(Value::Null);
+  }
+
+  #[test]
+  fn test_syslog() {
+    let line = "<13>Oct 11 22:14:15 host1 app[1234]: hello world";
+    let parsed = parse(line).unwrap();
+
+    assert_that!(parsed["kind"]).is_equal_to(json!("syslog"));
+    assert_that!(parsed["raw"]).is_equal_to(json!(line));
+    assert_that!(parsed["level"]).is_equal_to(json!("notice"));
+    assert_that!(parsed["text"]).is_equal_to(json!("hello world"));
+    assert_that!(parsed["metadata"]).is_equal_to(json!({
+      "host": "host1",
+      "process": "app",
+      "pid": 1234
+    }));
+  }
+
+  #[test]
+  fn test_syslog_no_pid() {
+    let parsed = parse("<11>Oct  1 02:03:04 router sshd: oops").unwrap();
+
+    assert_that!(parsed["level"]).is_equal_to(json!("error"));
+    assert_that!(parsed["metadata"]).is_equal_to(json!({
+      "host": "router",
+      "process": "sshd"
+    }));
+  }
+
+  #[test]
+  fn test_timestamp_year() {
+    let date = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let now = date("2020-01-02T00:00:00Z");
+
+    assert_that!(parse_timestamp("Jan  1 12:00:00", now))
+      .is_equal_to(Some(date("2020-01-01T12:00:00Z")));
+    assert_that!(parse_timestamp("Dec 31 12:00:00", now))
+      .is_equal_to(Some(date("2019-12-31T12:00:00Z")));
+  }
+
+  #[test]
+  fn test_timestamp_leap_day() {
+    let date = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let parse = |now: &str| parse_timestamp("Feb 29 12:00:00", date(now));
+    let leap_day = Some(date("2020-02-29T12:00:00Z"));
+
+    assert_that!(parse("2020-03-01T00:00:00Z")).is_equal_to(leap_day);
+    assert_that!(parse("2021-06-01T00:00:00Z")).is_equal_to(leap_day);
+    assert_that!(parse("2024-01-15T00:00:00Z")).is_equal_to(leap_day);
+    let now = date("2021-06-01T00:00:00Z");
+    assert_that!(parse_timestamp("Feb 30 12:00:00", now)).is_none();
+  }
+}
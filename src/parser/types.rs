@@ -10,7 +10,7 @@ use std::sync::Arc;
 use chrono::DateTime;
 use chrono::offset::Utc;
 use serde::{Serialize, Deserialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 use crate::config::Config;
 
@@ -29,6 +29,12 @@ pub enum MessageKind {
   Logrus,
   Klog,
   Regex,
+  Cloud,
+  Heroku,
+  WinEvent,
+  Syslog,
+  Cef,
+  Access,
   Internal
 }
 
@@ -41,10 +47,13 @@ impl fmt::Display for MessageKind {
 #[derive(Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Debug, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
+  Trace,
   Debug,
   Info,
+  Notice,
   Warning,
   Error,
+  Critical,
   Fatal,
   Plain,
   Int
@@ -61,16 +70,31 @@ impl FromStr for LogLevel {
 
   fn from_str(s: &str) -> Result<LogLevel, ()> {
     match s.to_lowercase().as_str() {
+      "trace" | "trc" | "t" => Ok(LogLevel::Trace),
       "debug" | "dbg" | "d" => Ok(LogLevel::Debug),
-      "info" | "i" => Ok(LogLevel::Info),
+      "info" | "i" | "default" => Ok(LogLevel::Info),
+      "notice" | "n" => Ok(LogLevel::Notice),
       "warning" | "warn" | "w" => Ok(LogLevel::Warning),
       "error" | "err" | "e" => Ok(LogLevel::Error),
-      "fatal" | "panic" | "f" | "p" => Ok(LogLevel::Fatal),
+      "critical" | "crit" | "c" | "alert" => Ok(LogLevel::Critical),
+      "fatal" | "panic" | "f" | "p" | "emergency" | "emerg" => {
+        Ok(LogLevel::Fatal)
+      },
       _ => Err(())
     }
   }
 }
 
+impl LogLevel {
+  /// parses a level name, falling back to any aliases from `--level-aliases`
+  pub fn parse_with_aliases(config: &Config, s: &str) -> Option<LogLevel> {
+    match s.parse::<LogLevel>() {
+      Ok(level) => Some(level),
+      Err(()) => config.level_aliases.as_ref().and_then(|a| a.get(s))
+    }
+  }
+}
+
 /// Target fields in Message into which parsers may map input fields
 /// Classifiers may use this to help determine the logging system
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
@@ -81,13 +105,32 @@ pub enum MappingField {
   Text
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ReaderMetadata {
   // an external timestamp, may be overridden by message content
   pub timestamp: Option<DateTime<Utc>>,
 
   // message source if following multiple inputs
-  pub source: Option<String>
+  pub source: Option<String>,
+
+  /// a minimum level to assign if the parsed level is lower or missing, e.g.
+  /// for lines read from a subprocess' stderr
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min_level: Option<LogLevel>,
+
+  /// the 1-based number of the record within its input, if read from stdin
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub line_no: Option<usize>
+}
+
+impl ReaderMetadata {
+  /// metadata for the given record of a plain input, e.g. stdin
+  pub fn line(line_no: usize) -> ReaderMetadata {
+    ReaderMetadata {
+      line_no: Some(line_no),
+      ..Default::default()
+    }
+  }
 }
 
 fn is_empty<K: Hash + Eq, V>(map: &HashMap<K, V>) -> bool {
@@ -114,9 +157,9 @@ pub struct Message {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub text: Option<String>,
 
-  /// Additional fields e.g. in json messages
-  #[serde(skip_serializing_if = "is_empty")]
-  pub metadata: HashMap<String, Value>,
+  /// Additional fields e.g. in json messages, in their original order
+  #[serde(skip_serializing_if = "Map::is_empty")]
+  pub metadata: Map<String, Value>,
 
   /// Metadata from readers (filename, k8s pod, external timestamp, etc)
   #[serde(skip_serializing_if = "Option::is_none")]
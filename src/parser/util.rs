@@ -1,6 +1,7 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use chrono::prelude::*;
+use serde_json::Value;
 
 /// Convert a datetime to UTC if an offset is available
 pub fn normalize_datetime(
@@ -15,3 +16,34 @@ pub fn normalize_datetime(
   // if we can't convert, just assume utc
   Utc.from_utc_datetime(datetime)
 }
+
+/// to_string with a special case for actual strings
+/// by default serde stringifies them json style i.e. quotes them
+/// this unpacks them to avoid printing undesired quotes
+pub fn nicer_to_string(value: &Value) -> String {
+  if let Some(val) = value.as_str() {
+    String::from(val)
+  } else {
+    value.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_nicer_to_string() {
+    assert_that!(nicer_to_string(&json!("foo"))).is_equal_to("foo".to_string());
+    assert_that!(nicer_to_string(&json!("")))
+      .is_equal_to(String::new());
+    assert_that!(nicer_to_string(&json!(42))).is_equal_to("42".to_string());
+    assert_that!(nicer_to_string(&json!(null)))
+      .is_equal_to("null".to_string());
+    assert_that!(nicer_to_string(&json!({"a": "b"})))
+      .is_equal_to(r#"{"a":"b"}"#.to_string());
+  }
+}
@@ -0,0 +1,208 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use chrono::prelude::*;
+use regex::Regex;
+use serde_json::{self, Map, Value};
+
+use crate::config::Config;
+use super::json::parse_rfc3339;
+use super::types::{
+  LogLevel, MappingField, Message, MessageKind, ReaderMetadata
+};
+
+/// parses a .NET `/Date(1577836800000)/` timestamp, as produced by Windows
+/// PowerShell's `ConvertTo-Json`; any trailing offset is ignored since the
+/// milliseconds are always relative to the UTC epoch
+fn parse_dotnet_date(s: &str) -> Option<DateTime<Utc>> {
+  lazy_static! {
+    static ref RE: Regex = Regex::new(
+      r"^/Date\((-?\d+)(?:[+-]\d{4})?\)/$"
+    ).unwrap();
+  }
+
+  let millis: i64 = RE.captures(s)?[1].parse().ok()?;
+
+  Utc.timestamp_opt(
+    millis.div_euclid(1000),
+    (millis.rem_euclid(1000) * 1_000_000) as u32
+  ).single()
+}
+
+/// extracts `TimeCreated`, which may be a plain string or (in older
+/// PowerShell versions) an object whose `value` holds the date
+fn get_time_created(event: &Map<String, Value>) -> Option<DateTime<Utc>> {
+  let value = match event.get("TimeCreated")? {
+    Value::Object(obj) => obj.get("value")?,
+    value => value
+  };
+
+  let s = value.as_str()?;
+  parse_dotnet_date(s).or_else(|| parse_rfc3339(s))
+}
+
+/// maps a `LevelDisplayName`, e.g. "Information", to a LogLevel
+fn parse_level_name(config: &Config, name: &str) -> Option<LogLevel> {
+  match name.to_lowercase().as_str() {
+    "information" => Some(LogLevel::Info),
+    "verbose" => Some(LogLevel::Debug),
+    _ => LogLevel::parse_with_aliases(config, name)
+  }
+}
+
+/// maps a numeric event `Level` to a LogLevel, used if `LevelDisplayName` is
+/// missing (e.g. for events from uninstalled providers)
+fn parse_level_number(level: i64) -> Option<LogLevel> {
+  match level {
+    0 | 4 => Some(LogLevel::Info),
+    1 => Some(LogLevel::Critical),
+    2 => Some(LogLevel::Error),
+    3 => Some(LogLevel::Warning),
+    5 => Some(LogLevel::Debug),
+    _ => None
+  }
+}
+
+/// converts a Windows event into a message
+fn parse_event(
+  config: &Config,
+  line: &str, event: Map<String, Value>, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  let mut mapped_fields = HashMap::new();
+
+  let timestamp = get_time_created(&event);
+  if timestamp.is_some() {
+    mapped_fields.insert(String::from("TimeCreated"), MappingField::Timestamp);
+  }
+
+  let level_name = event.get("LevelDisplayName")
+    .and_then(|v| v.as_str())
+    .and_then(|s| parse_level_name(config, s));
+  let level = match level_name {
+    Some(level) => {
+      mapped_fields.insert(String::from("LevelDisplayName"), MappingField::Level);
+      Some(level)
+    },
+    None => event.get("Level").and_then(|v| v.as_i64()).and_then(parse_level_number)
+  };
+
+  // the numeric level is redundant once a level has been found
+  if level.is_some() {
+    mapped_fields.insert(String::from("Level"), MappingField::Level);
+  }
+
+  let text = event.get("Message")
+    .and_then(|v| v.as_str())
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty());
+  if event.contains_key("Message") {
+    mapped_fields.insert(String::from("Message"), MappingField::Text);
+  }
+
+  // keep the reader's source if it has one, e.g. from kubernetes
+  let mut meta = meta.unwrap_or_default();
+  if meta.source.is_none() {
+    meta.source = event.get("ProviderName")
+      .and_then(|v| v.as_str())
+      .map(String::from);
+  }
+
+  // PowerShell serializes every property of the event, most of them null
+  let metadata: Map<String, Value> = event.into_iter()
+    .filter(|(k, v)| {
+      !mapped_fields.contains_key(k) && k != "ProviderName" && !v.is_null()
+    })
+    .collect();
+
+  Ok(Some(Message {
+    kind: MessageKind::WinEvent,
+    raw: line.to_string(),
+    reader_metadata: Some(meta),
+    timestamp, level, text, metadata, mapped_fields
+  }))
+}
+
+/// parses Windows events exported as JSON, one per line, e.g. from
+/// `Get-WinEvent ... | ForEach-Object { $_ | ConvertTo-Json -Compress }`
+///
+/// the `ProviderName` is used as the message source
+pub fn parse_winevent(
+  config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
+) -> Result<Option<Message>, Box<dyn Error>> {
+  // skip anything that doesn't at least vaguely look like an event
+  if !line.starts_with('{') || !line.ends_with('}') {
+    return Ok(None);
+  }
+
+  if !line.contains("\"ProviderName\"") || !line.contains("\"TimeCreated\"") {
+    return Ok(None);
+  }
+
+  match serde_json::from_str(line) {
+    Ok(event) => parse_event(&config, line, event, meta),
+    Err(_) => Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+  use simple_error::{SimpleResult, SimpleError};
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn parse(line: &str) -> SimpleResult<Value> {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let parsed = parse_winevent(config, line, None)
+      .map_err(|e| SimpleError::new(format!("{:?}", e)))?;
+
+    serde_json::to_value(parsed).map_err(SimpleError::from)
+  }
+
+  #[test]
+  fn test_not_winevent() {
+    assert_that!(parse(r#"{"msg": "hello"}"#)).is_ok_containing(Value::Null);
+  }
+
+  #[test]
+  fn test_winevent() {
+    let line = r#"{"Id":7036,"Level":4,"ProviderName":"Service Control Manager","TimeCreated":"\/Date(1577836800123)\/","LevelDisplayName":"Information","Message":"The Print Spooler service entered the running state.","TaskDisplayName":null,"MachineName":"host1"}"#;
+
+    assert_that!(parse(line)).is_ok_containing(json!({
+      "kind": "winevent",
+      "raw": line,
+      "timestamp": "2020-01-01T00:00:00.123Z",
+      "level": "info",
+      "text": "The Print Spooler service entered the running state.",
+      "metadata": {
+        "Id": 7036,
+        "MachineName": "host1"
+      },
+      "reader_metadata": {
+        "timestamp": null,
+        "source": "Service Control Manager"
+      },
+      "mapped_fields": {
+        "TimeCreated": "timestamp",
+        "LevelDisplayName": "level",
+        "Level": "level",
+        "Message": "text"
+      }
+    }));
+  }
+
+  #[test]
+  fn test_winevent_fallbacks() {
+    // newer PowerShell emits ISO dates, and the level name may be missing
+    let line = r#"{"Level":2,"ProviderName":"app","TimeCreated":"2020-01-01T00:00:00+01:00","LevelDisplayName":null,"Message":"oops"}"#;
+
+    let parsed = parse(line).unwrap();
+    assert_that!(parsed["timestamp"]).is_equal_to(json!("2019-12-31T23:00:00Z"));
+    assert_that!(parsed["level"]).is_equal_to(json!("error"));
+  }
+}
@@ -0,0 +1,135 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use simple_error::SimpleResult;
+
+use crate::config::{Config, ExpectFormat};
+use crate::parser::{Message, MessageKind, parse_object};
+use crate::renderer::LogEntry;
+
+/// max number of characters of a malformed line to include in its warning
+const SNIPPET_LEN: usize = 40;
+
+/// true if the message was parsed from a line in the expected format
+fn is_expected(format: ExpectFormat, message: &Message) -> bool {
+  // blank lines, e.g. at the end of a file, aren't worth a warning
+  if message.raw.trim().is_empty() {
+    return true;
+  }
+
+  match format {
+    ExpectFormat::Json => match message.kind {
+      MessageKind::Json | MessageKind::Cloud | MessageKind::WinEvent => true,
+
+      // other parsers may have claimed a valid object, e.g. via a custom regex
+      _ => parse_object(&message.raw).is_some()
+    }
+  }
+}
+
+fn format_name(format: ExpectFormat) -> &'static str {
+  match format {
+    ExpectFormat::Json => "JSON"
+  }
+}
+
+/// shortens a line for display in a warning
+fn snippet(line: &str) -> String {
+  if line.chars().count() <= SNIPPET_LEN {
+    return line.to_string();
+  }
+
+  let mut snippet: String = line.chars().take(SNIPPET_LEN).collect();
+  snippet.push_str("...");
+  snippet
+}
+
+/// A wrapping reader that warns about input lines that aren't in the format
+/// given by `--expect`
+///
+/// Lines are numbered by the reader if possible, otherwise by the order their
+/// messages arrive; internal messages aren't counted.
+pub fn read_expect(
+  config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_expect".to_string()).spawn(move || {
+    let format = match config.expect {
+      Some(format) => format,
+      None => return Ok(())
+    };
+
+    let mut line_no: usize = 0;
+    for entry in rx {
+      let warning = match &entry.message {
+        Some(m) if m.message.kind != MessageKind::Internal => {
+          // prefer the reader's own count, if any, which also includes lines
+          // that didn't produce a message
+          let meta = m.message.reader_metadata.as_ref();
+          line_no = match meta.and_then(|meta| meta.line_no) {
+            Some(n) => n,
+            None => line_no + 1
+          };
+
+          if is_expected(format, &m.message) {
+            None
+          } else {
+            Some(LogEntry::internal(&config, &format!(
+              "warning: line {} is not valid {}: {}",
+              line_no, format_name(format), snippet(&m.message.raw)
+            )))
+          }
+        },
+        _ => None
+      };
+
+      if let Some(warning) = warning {
+        if tx.send(warning).is_err() {
+          break;
+        }
+      }
+
+      if tx.send(entry).is_err() {
+        break;
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::parser::parse;
+
+  fn expected(line: &str) -> bool {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let message = parse(config, line, None).unwrap().unwrap();
+
+    is_expected(ExpectFormat::Json, &message)
+  }
+
+  #[test]
+  fn test_expect_json() {
+    assert_that!(expected(r#"{"msg": "hello"}"#)).is_true();
+    assert_that!(expected(r#"{"msg": "truncat"#)).is_false();
+    assert_that!(expected("panic: oh no")).is_false();
+    assert_that!(expected("")).is_true();
+  }
+
+  #[test]
+  fn test_snippet() {
+    assert_that!(snippet("short")).is_equal_to("short".to_string());
+    assert_that!(snippet(&"x".repeat(50)))
+      .is_equal_to(format!("{}...", "x".repeat(40)));
+  }
+}
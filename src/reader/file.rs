@@ -0,0 +1,471 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::HashSet;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use flate2::read::MultiGzDecoder;
+use simple_error::{SimpleError, SimpleResult};
+
+use crate::config::Config;
+use crate::parser::ReaderMetadata;
+use crate::renderer::LogEntry;
+use super::gzip::is_gzip;
+use super::records::{follow_records, records, Records};
+use super::tee::Tee;
+
+/// delay between checks for new lines, or for the file to be created
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// identifies the file behind a path, to notice when it's been replaced
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+  use std::os::unix::fs::MetadataExt;
+
+  Some((meta.dev(), meta.ino()))
+}
+
+/// identifies the file behind a path; not supported here, so only truncation
+/// is detected
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<(u64, u64)> {
+  None
+}
+
+/// what to do once the end of the open file has been reached
+#[derive(Debug, PartialEq)]
+enum Change {
+  /// nothing new, keep waiting for lines to be appended
+  None,
+
+  /// the file was truncated or replaced, e.g. by logrotate
+  Rotated,
+
+  /// the path no longer exists, e.g. after a rename but before a new file is
+  /// created
+  Missing
+}
+
+/// compares the open file with whatever is currently at its path
+fn check_change(
+  path: &Path, open: &Metadata, position: u64
+) -> Change {
+  let current = match fs::metadata(path) {
+    Ok(current) => current,
+    Err(_) => return Change::Missing
+  };
+
+  let replaced = match (file_id(open), file_id(&current)) {
+    (Some(open), Some(current)) => open != current,
+    _ => false
+  };
+
+  if replaced || current.len() < position {
+    Change::Rotated
+  } else {
+    Change::None
+  }
+}
+
+/// waits for the file to exist and opens it, returning None if asked to stop
+/// in the meantime
+fn open_file(path: &Path, stop: &AtomicBool) -> Option<File> {
+  loop {
+    if let Ok(file) = File::open(path) {
+      return Some(file);
+    }
+
+    if stop.load(Ordering::Relaxed) {
+      return None;
+    }
+
+    thread::sleep(POLL_INTERVAL);
+  }
+}
+
+/// returns true if the file is named `*.gz` or its content is gzipped
+fn is_gzip_file(path: &Path, file: &mut File) -> io::Result<bool> {
+  if path.extension().map(|e| e == "gz").unwrap_or(false) {
+    return Ok(true);
+  }
+
+  let mut header = [0; 2];
+  let len = file.read(&mut header)?;
+  file.seek(SeekFrom::Start(0))?;
+
+  Ok(is_gzip(&header[..len]))
+}
+
+/// sends each complete line currently in the file, returning false if the
+/// receiver has quit
+fn send_lines<B: BufRead>(
+  config: &Arc<Config>,
+  lines: &mut Records<B>,
+  source: &Option<String>,
+  line_no: &mut usize,
+  tx: &SyncSender<LogEntry>
+) -> SimpleResult<bool> {
+  for line in lines {
+    let line = line.map_err(SimpleError::from)?;
+    *line_no += 1;
+
+    let mut meta = ReaderMetadata::line(*line_no);
+    meta.source = source.clone();
+
+    match LogEntry::message(Arc::clone(config), &line, Some(meta)) {
+      Ok(Some(entry)) => if tx.send(entry).is_err() {
+        return Ok(false);
+      },
+      _ => continue
+    };
+  }
+
+  Ok(true)
+}
+
+/// follows a single file until asked to stop or the receiver quits, or reads
+/// it once if it's gzipped
+fn follow_file(
+  config: &Arc<Config>,
+  path: &Path,
+  source: &Option<String>,
+  tx: &SyncSender<LogEntry>,
+  stop: &AtomicBool
+) -> SimpleResult<()> {
+  if !path.exists() {
+    tx.send(LogEntry::internal(
+      config, &format!("waiting for {} to be created", path.display())
+    )).ok();
+  }
+
+  let mut line_no = 0;
+  'open: loop {
+    let mut file = match open_file(path, stop) {
+      Some(file) => file,
+      None => return Ok(())
+    };
+
+    // compressed archives aren't written to, so there's nothing to follow
+    if is_gzip_file(path, &mut file).map_err(SimpleError::from)? {
+      let mut lines = records(
+        BufReader::new(MultiGzDecoder::new(file)), config.record_separator
+      ).tee(Tee::open(config, tx));
+
+      send_lines(config, &mut lines, source, &mut line_no, tx)?;
+      return Ok(());
+    }
+
+    let open_meta = file.metadata().map_err(SimpleError::from)?;
+    let mut lines = follow_records(
+      BufReader::new(file), config.record_separator
+    ).tee(Tee::open(config, tx));
+
+    loop {
+      if !send_lines(config, &mut lines, source, &mut line_no, tx)? {
+        return Ok(());
+      }
+
+      if stop.load(Ordering::Relaxed) {
+        return Ok(());
+      }
+
+      // at the end of input the buffer is empty, so this is the read offset
+      // (stream_position() needs a newer rust)
+      let position = lines.get_mut()
+        .seek(SeekFrom::Current(0))
+        .map_err(SimpleError::from)?;
+
+      match check_change(path, &open_meta, position) {
+        Change::None | Change::Missing => thread::sleep(POLL_INTERVAL),
+        Change::Rotated => {
+          // catch any lines written to the old file before it was replaced,
+          // including a last line that was never terminated
+          lines.stop_following();
+          if !send_lines(config, &mut lines, source, &mut line_no, tx)? {
+            return Ok(());
+          }
+
+          tx.send(LogEntry::internal(
+            config, &format!("{} was rotated, reopening", path.display())
+          )).ok();
+
+          continue 'open;
+        }
+      }
+    }
+  }
+}
+
+/// labels each path by its file name, or the full path if the names aren't
+/// unique
+fn sources(paths: &[PathBuf]) -> Vec<String> {
+  let names: Vec<String> = paths.iter()
+    .map(|p| match p.file_name() {
+      Some(name) => name.to_string_lossy().to_string(),
+      None => p.display().to_string()
+    })
+    .collect();
+
+  let unique: HashSet<&String> = names.iter().collect();
+  if unique.len() == names.len() {
+    names
+  } else {
+    paths.iter().map(|p| p.display().to_string()).collect()
+  }
+}
+
+/// reads the files given by `--file` and any positional arguments, then
+/// follows them like `tail -F`
+///
+/// if a file is truncated or replaced (e.g. by logrotate), it's reopened and
+/// read from the start; line numbers continue to count up across rotations.
+/// when following more than one file, each line's source is its file name.
+///
+/// gzipped files are read once rather than followed; once all files have
+/// stopped, the end of input is reached
+pub fn read_file(
+  config: Arc<Config>,
+  tx: SyncSender<LogEntry>,
+  exit_req_rx: Receiver<()>,
+  exit_resp_tx: Sender<()>
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_file".to_string()).spawn(move || {
+    let paths: Vec<PathBuf> = config.file.iter()
+      .chain(config.app.iter())
+      .map(PathBuf::from)
+      .collect();
+
+    if paths.is_empty() {
+      tx.send(LogEntry::internal(
+        &config,
+        "error: no file given, e.g. woodchipper -i file app.log"
+      )).ok();
+      tx.send(LogEntry::eof()).ok();
+
+      return Ok(());
+    }
+
+    let labels: Vec<Option<String>> = if paths.len() > 1 {
+      sources(&paths).into_iter().map(Some).collect()
+    } else {
+      vec![None]
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (done_tx, done_rx) = channel();
+
+    for (path, source) in paths.into_iter().zip(labels) {
+      let config = Arc::clone(&config);
+      let tx = tx.clone();
+      let stop = Arc::clone(&stop);
+      let done_tx = done_tx.clone();
+
+      thread::Builder::new().name("read_file_follower".to_string()).spawn(move || {
+        if let Err(e) = follow_file(&config, &path, &source, &tx, &stop) {
+          tx.send(LogEntry::internal(
+            &config, &format!("error: reading {}: {}", path.display(), e)
+          )).ok();
+        }
+
+        done_tx.send(()).ok();
+      }).unwrap();
+    }
+
+    drop(done_tx);
+
+    // followers only stop on their own if they fail or the renderer quits
+    loop {
+      if exit_req_rx.try_recv().is_ok() {
+        stop.store(true, Ordering::Relaxed);
+        exit_resp_tx.send(()).ok();
+        break;
+      }
+
+      match done_rx.recv_timeout(POLL_INTERVAL) {
+        Err(RecvTimeoutError::Disconnected) => {
+          tx.send(LogEntry::eof()).ok();
+          break;
+        },
+        _ => continue
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env;
+  use std::io::Write;
+  use std::iter;
+  use std::process;
+  use std::sync::mpsc::sync_channel;
+
+  use flate2::Compression;
+  use flate2::write::GzEncoder;
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::parser::MessageKind;
+
+  #[test]
+  fn test_check_change() {
+    let path = env::temp_dir().join(
+      format!("woodchipper-file-test-{}.log", process::id())
+    );
+
+    let mut file = File::create(&path).unwrap();
+    file.write_all(b"hello\nworld\n").unwrap();
+    let meta = file.metadata().unwrap();
+
+    assert_that!(check_change(&path, &meta, 12)).is_equal_to(Change::None);
+
+    // truncated
+    let file = File::create(&path).unwrap();
+    assert_that!(check_change(&path, &meta, 12)).is_equal_to(Change::Rotated);
+    drop(file);
+
+    // replaced
+    let rotated = path.with_extension("log.1");
+    fs::rename(&path, &rotated).unwrap();
+    assert_that!(check_change(&path, &meta, 0)).is_equal_to(Change::Missing);
+
+    File::create(&path).unwrap();
+    if cfg!(unix) {
+      assert_that!(check_change(&path, &meta, 0)).is_equal_to(Change::Rotated);
+    }
+
+    fs::remove_file(&path).ok();
+    fs::remove_file(&rotated).ok();
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn test_rotate_partial() {
+    let path = env::temp_dir().join(
+      format!("woodchipper-rotate-test-{}.log", process::id())
+    );
+    let rotated = path.with_extension("log.1");
+    fs::write(&path, "first\nlast").unwrap();
+
+    let config = Arc::new(Config::from_iter_safe(vec![
+      "", "--file", path.to_str().unwrap()
+    ]).unwrap());
+
+    let (tx, rx) = sync_channel(10);
+    let (exit_req_tx, exit_req_rx) = channel();
+    let (exit_resp_tx, _exit_resp_rx) = channel();
+    read_file(config, tx, exit_req_rx, exit_resp_tx);
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).ok();
+    let mut texts = iter::from_fn(recv)
+      .filter_map(|entry| entry.message)
+      .filter(|m| m.message.kind != MessageKind::Internal)
+      .filter_map(|m| m.message.text);
+    assert_that!(texts.next()).is_equal_to(Some("first".to_string()));
+
+    // the unterminated last line is kept when the file is replaced
+    fs::rename(&path, &rotated).unwrap();
+    fs::write(&path, "second\n").unwrap();
+    assert_that!(texts.next()).is_equal_to(Some("last".to_string()));
+    assert_that!(texts.next()).is_equal_to(Some("second".to_string()));
+
+    exit_req_tx.send(()).ok();
+    fs::remove_file(&path).ok();
+    fs::remove_file(&rotated).ok();
+  }
+
+  #[test]
+  fn test_read_gzip() {
+    let path = env::temp_dir().join(
+      format!("woodchipper-gzip-test-{}.log.gz", process::id())
+    );
+
+    let mut encoder = GzEncoder::new(
+      File::create(&path).unwrap(), Compression::default()
+    );
+    encoder.write_all(b"hello\nworld\n").unwrap();
+    encoder.finish().unwrap();
+
+    let config = Arc::new(Config::from_iter_safe(vec![
+      "", "--file", path.to_str().unwrap()
+    ]).unwrap());
+
+    let (tx, rx) = sync_channel(10);
+    let (_exit_req_tx, exit_req_rx) = channel();
+    let (exit_resp_tx, _exit_resp_rx) = channel();
+    read_file(config, tx, exit_req_rx, exit_resp_tx);
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).ok();
+    let texts: Vec<String> = iter::from_fn(recv)
+      .take_while(|entry| entry.eof.is_none())
+      .map(|entry| entry.message.unwrap().message.text.unwrap())
+      .collect();
+
+    assert_that!(texts)
+      .is_equal_to(vec!["hello".to_string(), "world".to_string()]);
+
+    fs::remove_file(&path).ok();
+  }
+
+  #[test]
+  fn test_sources() {
+    let paths = |p: &[&str]| p.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+    assert_that!(sources(&paths(&["/var/log/a.log", "b.log"])))
+      .is_equal_to(vec!["a.log".to_string(), "b.log".to_string()]);
+    assert_that!(sources(&paths(&["x/app.log", "y/app.log"])))
+      .is_equal_to(vec!["x/app.log".to_string(), "y/app.log".to_string()]);
+  }
+
+  #[test]
+  fn test_read_files() {
+    let dir = env::temp_dir();
+    let a = dir.join(format!("woodchipper-a-{}.log", process::id()));
+    let b = dir.join(format!("woodchipper-b-{}.log", process::id()));
+    fs::write(&a, "hello from a\n").unwrap();
+    fs::write(&b, "hello from b\n").unwrap();
+
+    let config = Arc::new(Config::from_iter_safe(vec![
+      "", "-i", "file", a.to_str().unwrap(), b.to_str().unwrap()
+    ]).unwrap());
+
+    let (tx, rx) = sync_channel(10);
+    let (exit_req_tx, exit_req_rx) = channel();
+    let (exit_resp_tx, exit_resp_rx) = channel();
+    read_file(config, tx, exit_req_rx, exit_resp_tx);
+
+    let mut received = Vec::new();
+    for _ in 0..2 {
+      let entry = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+      let message = entry.message.unwrap().message;
+      received.push((
+        message.reader_metadata.and_then(|m| m.source).unwrap(),
+        message.text.unwrap()
+      ));
+    }
+
+    let name = |p: &PathBuf| p.file_name().unwrap().to_string_lossy().to_string();
+
+    received.sort();
+    assert_that!(received).is_equal_to(vec![
+      (name(&a), "hello from a".to_string()),
+      (name(&b), "hello from b".to_string())
+    ]);
+
+    exit_req_tx.send(()).unwrap();
+    assert_that!(exit_resp_rx.recv_timeout(Duration::from_secs(5))).is_ok();
+
+    fs::remove_file(&a).ok();
+    fs::remove_file(&b).ok();
+  }
+}
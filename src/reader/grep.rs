@@ -0,0 +1,197 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use simple_error::SimpleResult;
+
+use crate::config::Config;
+use crate::filter::{Filter, RegexFilter};
+use crate::parser::MessageKind;
+use crate::renderer::LogEntry;
+
+/// Decides which messages to pass through per `--grep` and `--exclude`,
+/// including any surrounding context
+struct Matcher {
+  filters: Vec<RegexFilter>,
+
+  before: usize,
+  after: usize,
+
+  /// recent non-matching messages, to show if a match follows
+  buffer: VecDeque<LogEntry>,
+
+  /// number of messages still to show after the last match
+  remaining: usize
+}
+
+impl Matcher {
+  fn new(config: &Config) -> SimpleResult<Self> {
+    let mut filters = Vec::new();
+    if let Some(grep) = &config.grep {
+      filters.push(RegexFilter::new(grep.as_str(), false)?);
+    }
+
+    if let Some(exclude) = &config.exclude {
+      filters.push(RegexFilter::new(exclude.as_str(), true)?);
+    }
+
+    let filters = filters.into_iter()
+      .map(|f| f.with_scope(config.search_scope))
+      .collect();
+
+    let before = config.before.or(config.around).unwrap_or(0);
+
+    Ok(Matcher {
+      filters,
+      before,
+      after: config.after.or(config.around).unwrap_or(0),
+      buffer: VecDeque::with_capacity(before + 1),
+      remaining: 0
+    })
+  }
+
+  /// returns the entries to pass through after receiving `entry`, in order
+  fn push(&mut self, entry: LogEntry) -> Vec<LogEntry> {
+    let message = match &entry.message {
+      Some(m) => &m.message,
+      None => return vec![entry]
+    };
+
+    // woodchipper's own messages aren't filtered or counted as context
+    if message.kind == MessageKind::Internal {
+      return vec![entry];
+    }
+
+    if self.filters.iter().all(|f| f.filter(message)) {
+      self.remaining = self.after;
+
+      let mut entries: Vec<LogEntry> = self.buffer.drain(..).collect();
+      entries.push(entry);
+      return entries;
+    }
+
+    if self.remaining > 0 {
+      self.remaining -= 1;
+      return vec![entry];
+    }
+
+    if self.before > 0 {
+      if self.buffer.len() == self.before {
+        self.buffer.pop_front();
+      }
+
+      self.buffer.push_back(entry);
+    }
+
+    Vec::new()
+  }
+}
+
+/// A wrapping reader that drops messages not matching `--grep` or matching
+/// `--exclude`, except for context requested with `--after`, `--before`, or
+/// `--around`
+pub fn read_grep(
+  config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_grep".to_string()).spawn(move || {
+    let mut matcher = Matcher::new(&config)?;
+
+    'outer: for entry in rx {
+      for entry in matcher.push(entry) {
+        if tx.send(entry).is_err() {
+          break 'outer;
+        }
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::reader::read_ordered;
+
+  fn grep(args: Vec<&str>, lines: &[&str]) -> Vec<String> {
+    let mut args = args;
+    args.insert(0, "");
+
+    let config = Arc::new(Config::from_iter_safe(args).unwrap());
+    let mut matcher = Matcher::new(&config).unwrap();
+
+    lines.iter()
+      .map(|line| LogEntry::message(Arc::clone(&config), line, None))
+      .flat_map(|entry| matcher.push(entry.unwrap().unwrap()))
+      .filter_map(|entry| entry.message)
+      .map(|m| m.message.raw)
+      .collect()
+  }
+
+  fn strings(s: &[&str]) -> Vec<String> {
+    s.iter().map(|s| s.to_string()).collect()
+  }
+
+  #[test]
+  fn test_grep() {
+    let lines = &["a", "b", "match 1", "c", "d", "e", "match 2", "f"];
+
+    assert_that!(grep(vec!["--grep", "match"], lines))
+      .is_equal_to(strings(&["match 1", "match 2"]));
+
+    assert_that!(grep(vec!["--grep", "match", "-B", "1", "-A", "1"], lines))
+      .is_equal_to(strings(&["b", "match 1", "c", "e", "match 2", "f"]));
+
+    // overlapping context is only shown once
+    assert_that!(grep(vec!["--grep", "match", "--around", "2"], lines))
+      .is_equal_to(strings(lines));
+  }
+
+  #[test]
+  fn test_grep_ordered() {
+    let config = Arc::new(Config::from_iter_safe(vec![
+      "", "--ordered", "--grep", "match", "-B", "1", "-A", "1"
+    ]).unwrap());
+
+    let (reader_tx, ordered_rx) = sync_channel(16);
+    let (ordered_tx, grep_rx) = sync_channel(16);
+    let (grep_tx, rx) = sync_channel(16);
+    read_ordered(Arc::clone(&config), ordered_rx, ordered_tx);
+    read_grep(Arc::clone(&config), grep_rx, grep_tx);
+
+    // arrives out of order, but context is taken from the sorted order
+    for (second, text) in &[(3, "c"), (1, "a"), (2, "match"), (4, "d")] {
+      let line = format!(
+        r#"{{"time":"2020-01-01T00:00:0{}Z","msg":"{}"}}"#, second, text
+      );
+      reader_tx.send(
+        LogEntry::message(Arc::clone(&config), &line, None).unwrap().unwrap()
+      ).unwrap();
+    }
+    reader_tx.send(LogEntry::eof()).unwrap();
+
+    let texts: Vec<String> = rx.iter()
+      .filter_map(|entry| entry.message)
+      .filter(|m| m.message.kind != MessageKind::Internal)
+      .filter_map(|m| m.message.text)
+      .collect();
+    assert_that!(texts).is_equal_to(strings(&["a", "match", "c"]));
+  }
+
+  #[test]
+  fn test_exclude() {
+    assert_that!(grep(vec!["--exclude", "^health"], &["a", "healthz", "b"]))
+      .is_equal_to(strings(&["a", "b"]));
+  }
+}
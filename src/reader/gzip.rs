@@ -0,0 +1,95 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+
+/// the first two bytes of any gzip stream
+const MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// returns true if `header` looks like the start of a gzip stream
+pub fn is_gzip(header: &[u8]) -> bool {
+  header.starts_with(MAGIC)
+}
+
+/// wraps `reader` in a gzip decoder if its content starts with the gzip magic
+/// bytes, otherwise returns it as-is
+///
+/// concatenated gzip streams, e.g. `cat a.gz b.gz`, are decoded in full
+pub fn maybe_gunzip<'a, R: Read + 'a>(
+  mut reader: R
+) -> io::Result<Box<dyn BufRead + 'a>> {
+  // a pipe may return fewer bytes than the magic number in one read, so keep
+  // reading until there are enough to tell or the input ends
+  let mut header = Vec::with_capacity(MAGIC.len());
+  (&mut reader).take(MAGIC.len() as u64).read_to_end(&mut header)?;
+
+  let gzip = is_gzip(&header);
+
+  // put the header back so plain input is unaffected
+  let reader = BufReader::new(Cursor::new(header).chain(reader));
+  if gzip {
+    Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+  } else {
+    Ok(Box::new(reader))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::io::Write;
+
+  use flate2::Compression;
+  use flate2::write::GzEncoder;
+  use spectral::prelude::*;
+
+  fn gzip(content: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content).unwrap();
+    encoder.finish().unwrap()
+  }
+
+  fn read_all(input: &[u8]) -> String {
+    let mut ret = String::new();
+    maybe_gunzip(input).unwrap().read_to_string(&mut ret).unwrap();
+    ret
+  }
+
+  /// a reader returning at most one byte at a time, like a slow pipe
+  struct Trickle<'a>(&'a [u8]);
+
+  impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      if self.0.is_empty() || buf.is_empty() {
+        return Ok(0);
+      }
+
+      buf[0] = self.0[0];
+      self.0 = &self.0[1..];
+      Ok(1)
+    }
+  }
+
+  #[test]
+  fn test_maybe_gunzip_trickle() {
+    let mut ret = String::new();
+    let gzipped = gzip(b"hello\n");
+    maybe_gunzip(Trickle(&gzipped)).unwrap().read_to_string(&mut ret).unwrap();
+    assert_that!(ret).is_equal_to("hello\n".to_string());
+
+    let mut ret = String::new();
+    maybe_gunzip(Trickle(b"h")).unwrap().read_to_string(&mut ret).unwrap();
+    assert_that!(ret).is_equal_to("h".to_string());
+  }
+
+  #[test]
+  fn test_maybe_gunzip() {
+    assert_that!(read_all(&gzip(b"hello\nworld\n")))
+      .is_equal_to("hello\nworld\n".to_string());
+    assert_that!(read_all(b"hello\nworld\n"))
+      .is_equal_to("hello\nworld\n".to_string());
+    assert_that!(read_all(b"")).is_equal_to(String::new());
+  }
+}
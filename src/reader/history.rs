@@ -0,0 +1,412 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Serializer};
+use simple_error::{SimpleError, SimpleResult};
+
+use crate::config::Config;
+use crate::parser::MessageKind;
+use crate::renderer::{LogEntry, MessageEntry};
+
+/// once the reader has gone this long without sending anything, the initial
+/// backlog is assumed to have been read and later messages are live
+const IDLE_MS: u64 = 500;
+
+/// the backlog is never held longer than this, so a busy stream that never
+/// goes idle is still shown
+const MAX_WAIT_MS: u64 = 5000;
+
+/// the most messages held from the backlog, or `--max-entries` if smaller
+const MAX_BACKLOG: usize = 100_000;
+
+/// The amount of history to keep from the initial backlog, per `--history`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryLimit {
+  /// keep only the last N messages
+  Count(usize),
+
+  /// keep only messages within this duration of the newest message
+  Duration(Duration)
+}
+
+impl FromStr for HistoryLimit {
+  type Err = SimpleError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    let unit_index = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(unit_index);
+
+    let value: i64 = value.parse().map_err(|_| SimpleError::new(format!(
+      "invalid history: {}", s
+    )))?;
+
+    let unit_seconds = match unit {
+      "" => return Ok(HistoryLimit::Count(value as usize)),
+      "s" => 1,
+      "m" => 60,
+      "h" => 3600,
+      "d" => 86400,
+      _ => return Err(SimpleError::new(format!(
+        "invalid history unit '{}', expected one of: s, m, h, d", unit
+      )))
+    };
+
+    // chrono panics on durations over i64::MAX milliseconds
+    value.checked_mul(unit_seconds)
+      .filter(|seconds| *seconds <= i64::MAX / 1000)
+      .map(|seconds| HistoryLimit::Duration(Duration::seconds(seconds)))
+      .ok_or_else(|| SimpleError::new(format!("history is too long: {}", s)))
+  }
+}
+
+impl Serialize for HistoryLimit {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      HistoryLimit::Count(count) => serializer.serialize_u64(*count as u64),
+      HistoryLimit::Duration(duration) => {
+        // use the largest unit that can represent the duration exactly
+        let seconds = duration.num_seconds();
+        let formatted = if seconds % 86400 == 0 {
+          format!("{}d", seconds / 86400)
+        } else if seconds % 3600 == 0 {
+          format!("{}h", seconds / 3600)
+        } else if seconds % 60 == 0 {
+          format!("{}m", seconds / 60)
+        } else {
+          format!("{}s", seconds)
+        };
+
+        serializer.serialize_str(&formatted)
+      }
+    }
+  }
+}
+
+fn entry_timestamp(entry: &MessageEntry) -> Option<DateTime<Utc>> {
+  entry.message.timestamp.or_else(|| {
+    entry.message.reader_metadata.as_ref().and_then(|m| m.timestamp)
+  })
+}
+
+/// Messages held from the initial backlog, trimmed to the limit as they
+/// arrive
+struct Backlog {
+  limit: HistoryLimit,
+
+  /// the most entries held, whatever the limit
+  cap: usize,
+
+  entries: VecDeque<MessageEntry>,
+  newest: Option<DateTime<Utc>>,
+
+  /// the number of entries dropped so far
+  skipped: usize
+}
+
+impl Backlog {
+  fn new(limit: HistoryLimit, cap: usize) -> Self {
+    Backlog {
+      limit,
+      cap,
+      entries: VecDeque::new(),
+      newest: None,
+      skipped: 0
+    }
+  }
+
+  /// the oldest timestamp kept by a duration limit, if known
+  fn cutoff(&self) -> Option<DateTime<Utc>> {
+    match (self.limit, self.newest) {
+      (HistoryLimit::Duration(duration), Some(newest)) => Some(newest - duration),
+      _ => None
+    }
+  }
+
+  fn pop_front(&mut self) {
+    if self.entries.pop_front().is_some() {
+      self.skipped += 1;
+    }
+  }
+
+  fn push(&mut self, entry: MessageEntry) {
+    if let Some(timestamp) = entry_timestamp(&entry) {
+      self.newest = Some(self.newest.map_or(timestamp, |n| n.max(timestamp)));
+    }
+
+    self.entries.push_back(entry);
+
+    let max_len = match self.limit {
+      HistoryLimit::Count(count) => min(count, self.cap),
+      HistoryLimit::Duration(_) => self.cap
+    };
+
+    while self.entries.len() > max_len {
+      self.pop_front();
+    }
+
+    // entries mostly arrive in order, so expired ones are at the front; any
+    // stragglers are caught by `finish()`
+    if let Some(cutoff) = self.cutoff() {
+      while let Some(timestamp) = self.entries.front().and_then(entry_timestamp) {
+        if timestamp >= cutoff {
+          break;
+        }
+
+        self.pop_front();
+      }
+    }
+  }
+
+  /// applies the limit to all remaining entries, returning them along with the
+  /// total number of entries dropped
+  ///
+  /// with a duration limit, entries without a timestamp are kept
+  fn finish(mut self) -> (VecDeque<MessageEntry>, usize) {
+    if let Some(cutoff) = self.cutoff() {
+      let before = self.entries.len();
+      self.entries.retain(|e| match entry_timestamp(e) {
+        Some(timestamp) => timestamp >= cutoff,
+        None => true
+      });
+
+      self.skipped += before - self.entries.len();
+    }
+
+    (self.entries, self.skipped)
+  }
+}
+
+/// A wrapping reader that discards all but the most recent messages from the
+/// reader's initial backlog, per `--history`
+///
+/// Messages are held until the reader goes briefly idle, reaches the end of
+/// input, or `MAX_WAIT_MS` passes, at which point the kept messages are sent
+/// and everything after is passed through untouched. Internal messages are
+/// never held.
+pub fn read_history(
+  config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_history".to_string()).spawn(move || {
+    let limit = match config.history {
+      Some(limit) => limit,
+      None => bail!("read_history requires --history")
+    };
+
+    // there's no point holding more than the renderer will keep
+    let cap = config.max_entries.map_or(MAX_BACKLOG, |m| min(m, MAX_BACKLOG));
+    let mut backlog = Backlog::new(limit, cap);
+    let mut eof = None;
+
+    // wait as long as necessary for the reader to start, but then stop
+    // buffering as soon as it catches up
+    let mut next = rx.recv().ok();
+    let started = Instant::now();
+    let max_wait = StdDuration::from_millis(MAX_WAIT_MS);
+    while let Some(entry) = next {
+      if entry.eof.is_some() {
+        eof = Some(entry);
+        break;
+      }
+
+      if let Some(message) = entry.message {
+        if message.message.kind == MessageKind::Internal {
+          tx.send(LogEntry { message: Some(message), eof: None }).ok();
+        } else {
+          backlog.push(message);
+        }
+      }
+
+      // a busy stream may never go idle, so give up waiting eventually
+      let timeout = match max_wait.checked_sub(started.elapsed()) {
+        Some(remaining) => min(remaining, StdDuration::from_millis(IDLE_MS)),
+        None => break
+      };
+
+      next = rx.recv_timeout(timeout).ok();
+    }
+
+    let (backlog, skipped) = backlog.finish();
+    if skipped > 0 {
+      tx.send(LogEntry::internal(&config, &format!(
+        "note: skipped {} older messages (--history)", skipped
+      ))).ok();
+    }
+
+    for message in backlog {
+      if tx.send(LogEntry { message: Some(message), eof: None }).is_err() {
+        return Ok(());
+      }
+    }
+
+    if let Some(eof) = eof {
+      tx.send(eof).ok();
+    }
+
+    for entry in rx {
+      if tx.send(entry).is_err() {
+        break;
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn config(history: &str) -> Arc<Config> {
+    Arc::new(Config::from_iter_safe(vec!["", "--history", history]).unwrap())
+  }
+
+  /// a message at the given minute past midnight
+  fn entry(config: &Arc<Config>, minute: u32) -> MessageEntry {
+    let line = format!(
+      r#"{{"time":"2020-01-01T00:{:02}:00Z","msg":"at {}"}}"#, minute, minute
+    );
+
+    LogEntry::message(Arc::clone(config), &line, None)
+      .unwrap().unwrap()
+      .message.unwrap()
+  }
+
+  fn texts<'a, I>(entries: I) -> Vec<String>
+  where
+    I: IntoIterator<Item = &'a MessageEntry>
+  {
+    entries.into_iter()
+      .map(|e| e.message.text.clone().unwrap())
+      .collect()
+  }
+
+  /// runs the stage over some messages followed by eof
+  fn run(config: Arc<Config>, minutes: &[u32]) -> Vec<String> {
+    let (in_tx, in_rx) = sync_channel(minutes.len() + 1);
+    for minute in minutes {
+      in_tx.send(LogEntry {
+        message: Some(entry(&config, *minute)),
+        eof: None
+      }).unwrap();
+    }
+    in_tx.send(LogEntry::eof()).unwrap();
+
+    let (out_tx, out_rx) = sync_channel(minutes.len() + 2);
+    read_history(config, in_rx, out_tx);
+
+    out_rx.iter()
+      .take_while(|entry| entry.eof.is_none())
+      .filter_map(|entry| entry.message)
+      .map(|message| message.message.text.unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_backlog_count() {
+    let config = config("3");
+    let mut backlog = Backlog::new(HistoryLimit::Count(3), MAX_BACKLOG);
+    for minute in 0..10 {
+      backlog.push(entry(&config, minute));
+      assert_that!(backlog.entries.len()).is_less_than_or_equal_to(3);
+    }
+
+    let (entries, skipped) = backlog.finish();
+    assert_that!(texts(&entries)).is_equal_to(vec![
+      String::from("at 7"), String::from("at 8"), String::from("at 9")
+    ]);
+    assert_that!(skipped).is_equal_to(7);
+  }
+
+  #[test]
+  fn test_backlog_duration() {
+    let config = config("5m");
+    let mut backlog = Backlog::new(
+      HistoryLimit::Duration(Duration::minutes(5)), MAX_BACKLOG
+    );
+
+    // expired entries are dropped as newer ones arrive, not just at the end
+    for minute in 0..30 {
+      backlog.push(entry(&config, minute));
+      assert_that!(backlog.entries.len()).is_less_than_or_equal_to(6);
+    }
+
+    // a late arrival older than the window is caught at the end
+    backlog.push(entry(&config, 1));
+
+    let (entries, skipped) = backlog.finish();
+    assert_that!(texts(&entries)).is_equal_to(vec![
+      String::from("at 24"), String::from("at 25"), String::from("at 26"),
+      String::from("at 27"), String::from("at 28"), String::from("at 29")
+    ]);
+    assert_that!(skipped).is_equal_to(25);
+  }
+
+  #[test]
+  fn test_backlog_cap() {
+    let config = config("1d");
+    let mut backlog = Backlog::new(HistoryLimit::Duration(Duration::days(1)), 4);
+    for minute in 0..10 {
+      backlog.push(entry(&config, minute));
+    }
+
+    let (entries, skipped) = backlog.finish();
+    assert_that!(entries.len()).is_equal_to(4);
+    assert_that!(skipped).is_equal_to(6);
+  }
+
+  #[test]
+  fn test_read_history_count() {
+    assert_that!(run(config("2"), &[0, 1, 2, 3])).is_equal_to(vec![
+      String::from("note: skipped 2 older messages (--history)"),
+      String::from("at 2"),
+      String::from("at 3")
+    ]);
+  }
+
+  #[test]
+  fn test_read_history_duration() {
+    assert_that!(run(config("10m"), &[0, 5, 12, 20])).is_equal_to(vec![
+      String::from("note: skipped 2 older messages (--history)"),
+      String::from("at 12"),
+      String::from("at 20")
+    ]);
+  }
+
+  #[test]
+  fn test_parse_history() {
+    assert_that!("500".parse::<HistoryLimit>())
+      .is_ok_containing(HistoryLimit::Count(500));
+    assert_that!("30m".parse::<HistoryLimit>())
+      .is_ok_containing(HistoryLimit::Duration(Duration::minutes(30)));
+    assert_that!("2d".parse::<HistoryLimit>())
+      .is_ok_containing(HistoryLimit::Duration(Duration::days(2)));
+
+    assert_that!("".parse::<HistoryLimit>()).is_err();
+    assert_that!("1w".parse::<HistoryLimit>()).is_err();
+    assert_that!("h".parse::<HistoryLimit>()).is_err();
+
+    // out of range durations are errors rather than panics
+    assert_that!("99999999999999d".parse::<HistoryLimit>()).is_err();
+    assert_that!("9223372036854775807s".parse::<HistoryLimit>()).is_err();
+    assert_that!("9223372036854775s".parse::<HistoryLimit>())
+      .is_ok_containing(HistoryLimit::Duration(
+        Duration::seconds(9_223_372_036_854_775)
+      ));
+  }
+}
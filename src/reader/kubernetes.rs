@@ -1,35 +1,265 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader};
-use std::sync::Arc;
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::mpsc::{channel, Sender, SyncSender, Receiver};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use chrono::prelude::*;
 use rand::prelude::*;
-use reqwest::{Client, StatusCode};
+use reqwest::{Certificate, Client, RequestBuilder, StatusCode};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
 use serde::Deserialize;
+use serde_json::Value;
 use simple_error::{SimpleError, SimpleResult};
 use subprocess::{Popen, PopenConfig, Redirection, Exec};
 
-use crate::config::Config;
+use crate::config::{Config, RecordSeparator};
 use crate::renderer::LogEntry;
-use crate::parser::ReaderMetadata;
+use crate::parser::{parse, LogLevel, Message, ReaderMetadata};
 use crate::parser::util::normalize_datetime;
+use super::records::records;
+use super::tee::Tee;
+
+/// the number of consecutive failed pod list requests before giving up
+const MAX_WATCH_FAILURES: usize = 5;
+
+/// how long the api server should keep a pod watch open before it's restarted;
+/// this must be shorter than the client's read timeout
+const WATCH_TIMEOUT_SECS: u64 = 25;
+
+/// minimum interval between attempts to restart a dead kubectl proxy
+const PROXY_RESPAWN_SECS: u64 = 5;
+
+/// a container log followed for at least this long is considered healthy,
+/// resetting its retry count when the stream ends
+const HEALTHY_FOLLOW_SECS: u64 = 30;
+
+/// returns how long to wait before the next attempt to follow a container's
+/// log, given the number of consecutive failed attempts so far, or None to
+/// give up; the first attempt is always made, then up to `max_retries` more
+fn next_attempt(
+  retries: u32, max_retries: u32, backoff: Duration
+) -> Option<Duration> {
+  if retries > max_retries {
+    None
+  } else if retries > 0 {
+    Some(backoff)
+  } else {
+    Some(Duration::from_millis(0))
+  }
+}
+
+/// the current kubectl proxy port, which changes if the proxy is restarted
+///
+/// requests should load this fresh rather than holding on to an old port
+type SharedPort = Arc<AtomicU16>;
+
+/// where a pod's service account credentials are mounted
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// how often a service account token is re-read; kubernetes rotates bound
+/// tokens in place well before they expire
+const TOKEN_REFRESH_SECS: u64 = 60;
+
+/// a service account token, re-read from its file periodically
+struct ServiceAccountToken {
+  path: PathBuf,
+
+  /// the authorization header and when the token was last read
+  cached: Mutex<(HeaderValue, Instant)>
+}
+
+impl ServiceAccountToken {
+  fn read_header(path: &Path) -> SimpleResult<HeaderValue> {
+    let token = fs::read_to_string(path).map_err(|e| {
+      SimpleError::new(format!(
+        "could not read service account token {}: {}", path.display(), e
+      ))
+    })?;
+
+    let mut header = HeaderValue::from_str(&format!("Bearer {}", token.trim()))
+      .map_err(SimpleError::from)?;
+    header.set_sensitive(true);
+
+    Ok(header)
+  }
+
+  fn open(path: &Path) -> SimpleResult<Self> {
+    let header = ServiceAccountToken::read_header(path)?;
+
+    Ok(ServiceAccountToken {
+      path: path.to_path_buf(),
+      cached: Mutex::new((header, Instant::now()))
+    })
+  }
+
+  /// returns the authorization header, re-reading the token if it's stale;
+  /// if it can't be re-read, the last token is used
+  fn header(&self) -> HeaderValue {
+    let mut cached = self.cached.lock().unwrap();
+    if cached.1.elapsed() >= Duration::from_secs(TOKEN_REFRESH_SECS) {
+      if let Ok(header) = ServiceAccountToken::read_header(&self.path) {
+        cached.0 = header;
+      }
+
+      cached.1 = Instant::now();
+    }
+
+    cached.0.clone()
+  }
+}
+
+/// where api requests are sent
+#[derive(Clone)]
+enum Endpoint {
+  /// a local kubectl proxy, which handles authentication
+  Proxy(SharedPort),
+
+  /// the api server itself, e.g. `https://10.0.0.1:443`, and the token used
+  /// to authenticate with it
+  Direct(String, Arc<ServiceAccountToken>)
+}
+
+/// a kubernetes api client, connecting either through a kubectl proxy or,
+/// when running in a pod, directly using the pod's service account
+#[derive(Clone)]
+pub struct KubernetesClient {
+  client: Client,
+  endpoint: Endpoint
+}
+
+impl KubernetesClient {
+  /// creates a client that sends requests through a kubectl proxy
+  fn proxy(port: SharedPort) -> Self {
+    KubernetesClient {
+      client: Client::new(),
+      endpoint: Endpoint::Proxy(port)
+    }
+  }
+
+  /// creates a client from the pod's service account token and CA, and the
+  /// `KUBERNETES_SERVICE_HOST` and `KUBERNETES_SERVICE_PORT` variables
+  pub fn in_cluster() -> SimpleResult<Self> {
+    let host = env::var("KUBERNETES_SERVICE_HOST").map_err(|_| {
+      SimpleError::new("KUBERNETES_SERVICE_HOST is not set")
+    })?;
+    let port = env::var("KUBERNETES_SERVICE_PORT")
+      .unwrap_or_else(|_| String::from("443"));
+
+    let dir = Path::new(SERVICE_ACCOUNT_DIR);
+    KubernetesClient::from_service_account(
+      &host, &port, &dir.join("token"), &dir.join("ca.crt")
+    )
+  }
+
+  fn from_service_account(
+    host: &str, port: &str, token_path: &Path, ca_path: &Path
+  ) -> SimpleResult<Self> {
+    let token = ServiceAccountToken::open(token_path)?;
+
+    let ca = fs::read(ca_path).map_err(|e| {
+      SimpleError::new(format!(
+        "could not read service account CA {}: {}", ca_path.display(), e
+      ))
+    })?;
+
+    let ca = Certificate::from_pem(&ca).map_err(SimpleError::from)?;
+    let client = Client::builder()
+      .add_root_certificate(ca)
+      .build()
+      .map_err(SimpleError::from)?;
+
+    // ipv6 addresses need brackets
+    let host = if host.contains(':') {
+      format!("[{}]", host)
+    } else {
+      host.to_string()
+    };
+
+    Ok(KubernetesClient {
+      client,
+      endpoint: Endpoint::Direct(
+        format!("https://{}:{}", host, port), Arc::new(token)
+      )
+    })
+  }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+  /// points a proxy client at a restarted proxy's new port
+  fn set_proxy_port(&self, new_port: u16) {
+    if let Endpoint::Proxy(port) = &self.endpoint {
+      port.store(new_port, Ordering::SeqCst);
+    }
+  }
+
+  /// returns the full url of an api path, e.g. `/api/v1/namespaces`
+  fn url(&self, path: &str) -> String {
+    match &self.endpoint {
+      // the proxy may have been restarted on a different port, so always load
+      // the current one
+      Endpoint::Proxy(port) => format!(
+        "http://localhost:{}{}", port.load(Ordering::SeqCst), path
+      ),
+      Endpoint::Direct(base, _) => format!("{}{}", base, path)
+    }
+  }
+
+  fn get(&self, path: &str) -> RequestBuilder {
+    let request = self.client.get(&self.url(path));
+
+    match &self.endpoint {
+      Endpoint::Proxy(_) => request,
+      Endpoint::Direct(_, token) => {
+        request.header(AUTHORIZATION, token.header())
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
 struct Container {
+  namespace: String,
   pod: String,
   container: String,
-  siblings: usize
+  siblings: usize,
+
+  /// the pod's labels, which don't affect the container's identity
+  labels: HashMap<String, String>
 }
 
 impl Container {
-  pub fn new(pod: String, container: String, siblings: usize) -> Self {
-    Container { pod, container, siblings }
+  pub fn new(
+    namespace: String, pod: String, container: String, siblings: usize
+  ) -> Self {
+    Container { namespace, pod, container, siblings, labels: HashMap::new() }
+  }
+}
+
+impl PartialEq for Container {
+  fn eq(&self, other: &Container) -> bool {
+    self.namespace == other.namespace
+      && self.pod == other.pod
+      && self.container == other.container
+      && self.siblings == other.siblings
+  }
+}
+
+impl Eq for Container {}
+
+impl Hash for Container {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.namespace.hash(state);
+    self.pod.hash(state);
+    self.container.hash(state);
+    self.siblings.hash(state);
   }
 }
 
@@ -47,7 +277,7 @@ impl fmt::Display for Container {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum PodEvent {
   Added(Container),
   Removed(Container)
@@ -57,6 +287,8 @@ enum PodEvent {
 struct KubernetesMetadata {
   name: String,
   namespace: String,
+
+  #[serde(default)]
   labels: HashMap<String, String>
 }
 
@@ -116,7 +348,8 @@ struct KubernetesContainerStatus {
 struct KubernetesPodStatus {
   phase: KubernetesPodPhase,
 
-  #[serde(rename = "containerStatuses")]
+  // unset until the pod is scheduled
+  #[serde(rename = "containerStatuses", default)]
   container_statuses: Vec<KubernetesContainerStatus>
 }
 
@@ -127,21 +360,70 @@ struct KubernetesPod {
   status: KubernetesPodStatus
 }
 
+#[derive(Debug, Default, Deserialize)]
+struct KubernetesListMetadata {
+  #[serde(rename = "resourceVersion")]
+  resource_version: Option<String>
+}
+
 #[derive(Debug, Deserialize)]
 struct KubernetesListObject {
+  #[serde(default)]
+  metadata: KubernetesListMetadata,
+
   items: Vec<KubernetesPod>
 }
 
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+enum KubernetesWatchEventType {
+  Added,
+  Modified,
+  Deleted,
+  Bookmark,
+  Error
+}
+
+/// one line of a watch stream
+///
+/// the object is a pod, except for errors, where it's a status
+#[derive(Debug, Deserialize)]
+struct KubernetesWatchEvent {
+  #[serde(rename = "type")]
+  kind: KubernetesWatchEventType,
+
+  object: Value
+}
+
+#[derive(Debug, Deserialize)]
+struct KubernetesNamespaceMetadata {
+  name: String
+}
+
+#[derive(Debug, Deserialize)]
+struct KubernetesNamespace {
+  metadata: KubernetesNamespaceMetadata
+}
+
+#[derive(Debug, Deserialize)]
+struct KubernetesNamespaceList {
+  items: Vec<KubernetesNamespace>
+}
+
 fn get_containers(pod: &KubernetesPod) -> Vec<Container> {
   let mut ret = Vec::new();
 
   let pod_name = pod.metadata.name.clone();
   let siblings = pod.spec.containers.len();
   for container in &pod.spec.containers {
-    ret.push(Container::new(
-      pod_name.clone(), container.name.clone(),
-      siblings
-    ));
+    ret.push(Container {
+      labels: pod.metadata.labels.clone(),
+      ..Container::new(
+        pod.metadata.namespace.clone(),
+        pod_name.clone(), container.name.clone(),
+        siblings
+      )
+    });
   }
 
   ret
@@ -183,17 +465,54 @@ fn pod_matches<T: AsRef<str>>(pod: &KubernetesPod, args: &[T]) -> bool {
   false
 }
 
+fn list_pods(
+  client: &KubernetesClient,
+  namespace: &str,
+  query: &[(String, String)]
+) -> SimpleResult<KubernetesListObject> {
+  let mut response = client
+    .get(&format!("/api/v1/namespaces/{}/pods", namespace))
+    .query(query)
+    .send().map_err(SimpleError::from)?;
+
+  if !response.status().is_success() {
+    return Err(SimpleError::new("failed to list pods in namespace"))
+  }
+
+  response.json().map_err(SimpleError::from)
+}
+
+/// lists the names of all namespaces matching the given label selector
+fn list_namespaces(
+  client: &KubernetesClient, selector: &str
+) -> SimpleResult<Vec<String>> {
+  let mut response = client
+    .get("/api/v1/namespaces")
+    .query(&[("labelSelector", selector)])
+    .send().map_err(SimpleError::from)?;
+
+  if !response.status().is_success() {
+    return Err(SimpleError::new("failed to list namespaces"))
+  }
+
+  let list: KubernetesNamespaceList = response.json()
+    .map_err(SimpleError::from)?;
+
+  Ok(list.items.into_iter().map(|ns| ns.metadata.name).collect())
+}
+
 fn wrap_watch(
   config: Arc<Config>,
-  namespace: String, port: u16,
-  log_tx: Sender<LogEntry>,
+  namespace: String, client: KubernetesClient,
+  log_tx: SyncSender<LogEntry>,
   event_tx: Sender<PodEvent>,
+  stop: Arc<AtomicBool>
 ) -> SimpleResult<()> {
   let use_selector = is_selector(&config.app);
   let query = if use_selector {
     let selector = &config.app[0];
 
-    log_tx.send(LogEntry::internal(&format!(
+    log_tx.send(LogEntry::internal(&config, &format!(
       "watching pods matching {} in namespace {}",
       &selector, &namespace
     ))).ok();
@@ -201,6 +520,7 @@ fn wrap_watch(
     vec![("labelSelector".to_string(), selector.clone())]
   } else if config.app.is_empty() {
     log_tx.send(LogEntry::internal(
+      &config,
       &format!("watching namespace {}", &namespace)
     )).ok();
 
@@ -211,7 +531,7 @@ fn wrap_watch(
       .collect::<Vec<String>>()
       .join(", ");
 
-    log_tx.send(LogEntry::internal(&format!(
+    log_tx.send(LogEntry::internal(&config, &format!(
       "watching pods in namespace {} containing: {}",
        &namespace, names
     ))).ok();
@@ -221,24 +541,36 @@ fn wrap_watch(
 
   let mut current_containers: HashSet<Container> = HashSet::new();
 
-  // unfortunately watch is prone to timeouts, especially if behind a proxy
-  // so we'll have to poll instead :(
-  let client = Client::new();
+  // watch is prone to timeouts, especially if behind a proxy, so fall back to
+  // polling if it fails
+  let mut polling = config.kubernetes.poll;
+  let mut failures = 0;
   loop {
-    let mut response = client
-      .get(&format!(
-        "http://localhost:{port}/api/v1/namespaces/{namespace}/pods",
-        port = port, namespace = namespace
-      ))
-      .query(&query)
-      .send().map_err(SimpleError::from)?;
-    
-    if !response.status().is_success() {
-      return Err(SimpleError::new("failed to list pods in namespace"))
+    // the namespace may have stopped matching --namespace-selector
+    if stop.load(Ordering::SeqCst) {
+      return Ok(());
     }
 
-    let pod_list: KubernetesListObject = response.json()
-      .map_err(SimpleError::from)?;
+    let result = list_pods(&client, &namespace, &query);
+    let pod_list = match result {
+      Ok(pod_list) => {
+        failures = 0;
+        pod_list
+      },
+      Err(e) => {
+        failures += 1;
+        if failures >= MAX_WATCH_FAILURES {
+          return Err(e);
+        }
+
+        log_tx.send(LogEntry::internal_level(&config, LogLevel::Warning, &format!(
+          "error listing pods, will retry: {}", e
+        ))).ok();
+
+        thread::sleep(Duration::from_secs(config.kubernetes.poll_interval));
+        continue;
+      }
+    };
 
     let new_containers: HashSet<Container> = pod_list.items.iter()
       .filter(|pod| use_selector || pod_matches(pod, &config.app))
@@ -262,21 +594,144 @@ fn wrap_watch(
 
     current_containers = new_containers;
 
+    let resource_version = pod_list.metadata.resource_version;
+    if let (false, Some(resource_version)) = (polling, resource_version) {
+      let started = Instant::now();
+      let request = client
+        .get(&format!("/api/v1/namespaces/{}/pods", namespace))
+        .query(&query);
+
+      let result = watch_pods(
+        &config, request, &resource_version,
+        &mut current_containers, &event_tx, &stop
+      );
+
+      match result {
+        // the watch timed out or expired, so re-list and watch again, unless
+        // it ended suspiciously quickly
+        Ok(()) if started.elapsed() >= Duration::from_secs(1) => continue,
+        Ok(()) => (),
+        Err(e) => {
+          polling = true;
+
+          log_tx.send(LogEntry::internal_level(&config, LogLevel::Warning, &format!(
+            "error watching pods in namespace {}, falling back to polling: {}",
+            namespace, e
+          ))).ok();
+        }
+      }
+    }
+
     thread::sleep(Duration::from_secs(config.kubernetes.poll_interval));
   }
 }
 
+/// applies a watch event to the set of followed containers, returning the
+/// resulting pod events, or `None` if the watch has ended and pods should be
+/// re-listed, e.g. because the resource version expired
+fn apply_watch_event<T: AsRef<str>>(
+  event: KubernetesWatchEvent,
+  use_selector: bool, args: &[T],
+  current: &mut HashSet<Container>
+) -> SimpleResult<Option<Vec<PodEvent>>> {
+  let deleted = match event.kind {
+    KubernetesWatchEventType::Added => false,
+    KubernetesWatchEventType::Modified => false,
+    KubernetesWatchEventType::Deleted => true,
+    KubernetesWatchEventType::Bookmark => return Ok(Some(vec![])),
+    KubernetesWatchEventType::Error => return Ok(None)
+  };
+
+  let pod: KubernetesPod = serde_json::from_value(event.object)
+    .map_err(SimpleError::from)?;
+  if !use_selector && !pod_matches(&pod, args) {
+    return Ok(Some(vec![]));
+  }
+
+  let mut events = Vec::new();
+  for container in get_containers(&pod) {
+    if deleted {
+      if current.remove(&container) {
+        events.push(PodEvent::Removed(container));
+      }
+    } else if current.insert(container.clone()) {
+      events.push(PodEvent::Added(container));
+    }
+  }
+
+  Ok(Some(events))
+}
+
+/// streams pod changes since `resource_version`, updating `current` and
+/// sending events until the server ends the watch
+///
+/// `request` is a pod list request, e.g. with a label selector
+fn watch_pods(
+  config: &Config,
+  request: RequestBuilder,
+  resource_version: &str,
+  current: &mut HashSet<Container>,
+  event_tx: &Sender<PodEvent>,
+  stop: &AtomicBool
+) -> SimpleResult<()> {
+  let timeout = WATCH_TIMEOUT_SECS.to_string();
+  let response = request
+    .query(&[
+      ("watch", "true"),
+      ("resourceVersion", resource_version),
+      ("timeoutSeconds", &timeout)
+    ])
+    .send().map_err(SimpleError::from)?;
+
+  if !response.status().is_success() {
+    return Err(SimpleError::new(format!(
+      "failed to watch pods: {}", response.status().as_u16()
+    )));
+  }
+
+  let use_selector = is_selector(&config.app);
+  for line in BufReader::new(response).lines() {
+    // the namespace may have stopped matching --namespace-selector
+    if stop.load(Ordering::SeqCst) {
+      return Ok(());
+    }
+
+    let line = line.map_err(SimpleError::from)?;
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let event: KubernetesWatchEvent = serde_json::from_str(&line)
+      .map_err(SimpleError::from)?;
+
+    match apply_watch_event(event, use_selector, &config.app, current)? {
+      Some(events) => for event in events {
+        event_tx.send(event).map_err(SimpleError::from)?;
+      },
+      None => return Ok(())
+    }
+  }
+
+  Ok(())
+}
+
 fn watch_events(
   config: Arc<Config>,
-  namespace: String, port: u16,
-  log_tx: Sender<LogEntry>,
-  event_tx: Sender<PodEvent>
+  namespace: String, client: KubernetesClient,
+  log_tx: SyncSender<LogEntry>,
+  event_tx: Sender<PodEvent>,
+  stop: Arc<AtomicBool>
 ) -> JoinHandle<SimpleResult<()>> {
   thread::spawn(move || {
-    match wrap_watch(config, namespace, port, log_tx.clone(), event_tx) {
+    let watch_config = Arc::clone(&config);
+    let result = wrap_watch(
+      watch_config, namespace, client, log_tx.clone(), event_tx, stop
+    );
+
+    match result {
       Ok(()) => (),
       Err(e) => {
-        log_tx.send(LogEntry::internal(&format!(
+        log_tx.send(LogEntry::internal_level(&config, LogLevel::Error, &format!(
           "watch ended with error: {:?}", e
         ))).ok();
 
@@ -296,14 +751,14 @@ fn watch_events(
 /// If the container no longer exists, returns `Ok(None)`, otherwise returns
 /// `Ok(Some(status))`
 fn get_container_status(
-  namespace: &str, port: u16,
+  client: &KubernetesClient,
+  namespace: &str,
   container: &Container
 ) -> SimpleResult<Option<KubernetesContainerStatus>> {
-  let client = Client::new();
   let mut response = client
     .get(&format!(
-      "http://localhost:{port}/api/v1/namespaces/{namespace}/pods/{pod}",
-      port = port, namespace = namespace, pod = &container.pod
+      "/api/v1/namespaces/{namespace}/pods/{pod}",
+      namespace = namespace, pod = &container.pod
     ))
     .send()
     .map_err(SimpleError::from)?;
@@ -326,22 +781,24 @@ fn get_container_status(
 }
 
 fn should_stop_following(
-  namespace: &str, port: u16,
+  config: &Config,
+  client: &KubernetesClient,
+  namespace: &str,
   container: &Container,
-  tx: Sender<LogEntry>
+  tx: SyncSender<LogEntry>
 ) -> bool {
-  match get_container_status(&namespace, port, &container) {
+  match get_container_status(client, &namespace, &container) {
     Ok(Some(status)) => {
       if status.state.running.is_some() {
         // log ran out, but the container is still running
         // either it restarted already or there was a network issue
-        tx.send(LogEntry::internal(&format!(
+        tx.send(LogEntry::internal_level(config, LogLevel::Warning, &format!(
           "container log was interrupted: {}", container
         ))).ok();
 
         false
       } else if let Some(terminated) = status.state.terminated {
-        tx.send(LogEntry::internal(&format!(
+        tx.send(LogEntry::internal(config, &format!(
           "container {} terminated with code {}",
           container, terminated.exit_code)
         )).ok();
@@ -352,18 +809,20 @@ fn should_stop_following(
       }
     },
     Ok(None) => {
-      tx.send(LogEntry::internal(&format!(
+      tx.send(LogEntry::internal(config, &format!(
         "container {} has been removed", container)
       )).ok();
 
       true
     },
     Err(e) => {
-      tx.send(LogEntry::internal(&format!(
+      // this may be temporary, e.g. if the proxy is being restarted, so leave
+      // it to the caller's retry limit
+      tx.send(LogEntry::internal_level(config, LogLevel::Error, &format!(
         "error watching container {}: {}", container, e.to_string()
       ))).ok();
 
-      true
+      false
     }
   }
 }
@@ -386,55 +845,112 @@ fn parse_line<'a>(
   Ok((dt_utc, rest))
 }
 
+/// copies the pod labels named by `--label-fields` into a message's metadata,
+/// leaving any fields the message already has; should be called before the
+/// message is classified
+fn add_label_fields(
+  config: &Config, message: &mut Message, container: &Container
+) {
+  for field in &config.kubernetes.label_fields {
+    let value = match container.labels.get(field) {
+      Some(value) => value,
+      None => continue
+    };
+
+    if !message.metadata.contains_key(field) {
+      message.metadata.insert(field.clone(), Value::String(value.clone()));
+    }
+  }
+}
+
+/// builds the query for a container's log: either followed, or with
+/// `--previous`, a one-off snapshot of its previous instance, optionally
+/// limited per `--since` and `--tail`
+fn log_query(
+  config: &Config, container: &Container
+) -> Vec<(&'static str, String)> {
+  let mode = if config.kubernetes.previous { "previous" } else { "follow" };
+
+  let mut query = vec![
+    (mode, String::from("true")),
+    ("container", container.container.clone()),
+    ("timestamps", String::from("true"))
+  ];
+
+  if let Some(since) = config.kubernetes.since {
+    query.push(("sinceSeconds", since.to_string()));
+  }
+
+  if let Some(tail) = config.kubernetes.tail {
+    query.push(("tailLines", tail.to_string()));
+  }
+
+  query
+}
+
 fn follow_log(
   config: Arc<Config>,
-  namespace: String, port: u16,
+  namespace: String, client: KubernetesClient,
   container: Container,
-  tx: Sender<LogEntry>
+  tx: SyncSender<LogEntry>,
+  stop: Arc<AtomicBool>
 ) {
   thread::spawn(move || {
-    let client = Client::new();
-
     // a count of retry attempts
     // this value may be reset if the log successfully runs for long enough
     let mut retries = 0;
+    let max_retries = config.kubernetes.max_retries;
+    let backoff = Duration::from_millis(config.kubernetes.retry_backoff_ms);
+    let previous = config.kubernetes.previous;
 
     // TODO: save last timestamp
     // if the log is interrupted, we can avoid duplicating messages
     // TODO: should query latest pod status to see if it's terminating
 
     loop {
-      if retries > 2 {
-        tx.send(LogEntry::internal(
-          &format!("giving up watching container due to errors: {}", container)
-        )).ok();
-
+      // the pod was removed or its namespace stopped matching
+      if stop.load(Ordering::SeqCst) {
         break;
-      } else if retries > 0 {
-        // if this is the 2nd (or nth) try, wait a bit
-        // maybe the pod wasn't ready?
-        thread::sleep(Duration::from_millis(5000));
       }
 
-      // check to make sure the container still exists
-      if should_stop_following(&namespace, port, &container, tx.clone()) {
+      // if this is the 2nd (or nth) try, wait a bit
+      // maybe the pod wasn't ready?
+      match next_attempt(retries, max_retries, backoff) {
+        Some(delay) => thread::sleep(delay),
+        None => {
+          tx.send(LogEntry::internal_level(
+            &config, LogLevel::Error,
+            &format!("giving up watching container due to errors: {}", container)
+          )).ok();
+
+          break;
+        }
+      }
+
+      // check to make sure the container still exists; previous logs are
+      // still available if it's since terminated
+      if !previous && should_stop_following(
+        &config, &client, &namespace, &container, tx.clone()
+      ) {
         break;
       }
 
-      tx.send(LogEntry::internal(&format!(
-        "started watching container: {}", container
+      let note = if previous {
+        "showing previous logs for container"
+      } else {
+        "started watching container"
+      };
+      tx.send(LogEntry::internal(&config, &format!(
+        "{}: {}", note, container
       ))).ok();
 
-      let query = vec![
-        ("follow", "true"),
-        ("container", &container.container),
-        ("timestamps", "true")
-      ];
+      let query = log_query(&config, &container);
 
       let maybe_response = client
         .get(&format!(
-          "http://localhost:{port}/api/v1/namespaces/{namespace}/pods/{pod}/log",
-          port = port, namespace = namespace, pod = &container.pod
+          "/api/v1/namespaces/{namespace}/pods/{pod}/log",
+          namespace = namespace,
+          pod = &container.pod
         ))
         .query(&query)
         .send();
@@ -442,7 +958,8 @@ fn follow_log(
       let response = match maybe_response {
         Ok(response) => response,
         Err(e) => {
-          tx.send(LogEntry::internal(
+          tx.send(LogEntry::internal_level(
+            &config, LogLevel::Error,
             &format!("error watching container {}: {:?}", container, e)
           )).ok();
 
@@ -451,9 +968,21 @@ fn follow_log(
         }
       };
 
+      // a container that never restarted has no previous logs, which isn't
+      // worth retrying
+      if previous && response.status() == StatusCode::BAD_REQUEST {
+        tx.send(LogEntry::internal_level(
+          &config, LogLevel::Warning,
+          &format!("no previous logs for container: {}", container)
+        )).ok();
+
+        break;
+      }
+
       // todo: could try to parse out the error message field but lazy
       if !response.status().is_success() {
-        tx.send(LogEntry::internal(
+        tx.send(LogEntry::internal_level(
+          &config, LogLevel::Error,
           &format!("error watching container {}", container)
         )).ok();
 
@@ -461,43 +990,57 @@ fn follow_log(
         continue;
       }
 
-      let reader = BufReader::new(response);
-      for (i, line) in reader.lines().enumerate() {
+      let following_since = Instant::now();
+      // the api always separates lines with newlines
+      let lines = records(BufReader::new(response), RecordSeparator::Lf)
+        .tee(Tee::open(&config, &tx));
+
+      for line in lines {
+        if stop.load(Ordering::SeqCst) {
+          break;
+        }
+
         // skip bad lines
         let line = match line {
           Ok(line) => line,
           Err(_) => continue
         };
 
-        let mut timestamp = None;
-        let parsed = match parse_line(&line) {
-          Ok((ts, line)) => {
-            timestamp = Some(ts);
-
-            line
-          },
-          Err(_) => &line
+        // always set a timestamp so read_ordered can sort by event time; if
+        // the kubelet's is missing, the time received is the next best thing
+        let (timestamp, parsed) = match parse_line(&line) {
+          Ok((ts, line)) => (ts, line),
+          Err(_) => (Utc::now(), line.as_str())
         };
 
         let meta = ReaderMetadata {
-          timestamp,
-          source: Some(container.to_string())
+          timestamp: Some(timestamp),
+          source: Some(container.to_string()),
+          ..Default::default()
         };
 
         // TODO: need some special parsing magic
         // need container name available, and we can fill dates using timestamps=true
         // can we pass this info in directly as pre-parsed chunks?
-        match LogEntry::message(Arc::clone(&config), parsed, Some(meta)) {
-          Ok(Some(entry)) => tx.send(entry).ok(),
+        match parse(Arc::clone(&config), parsed, Some(meta)) {
+          Ok(Some(mut message)) => {
+            add_label_fields(&config, &mut message, &container);
+            tx.send(LogEntry::parsed(&config, message)).ok()
+          },
           _ => continue
         };
+      }
 
-        // assume the error state clears as long as we read a couple lines
-        // this allows "Unable to retrieve container logs..." messages to count
-        // toward the retry limit
-        if i > 1 {
-          retries = 0;
-        }
+      // previous logs are a fixed snapshot, so there's nothing to restart
+      if previous {
+        break;
+      }
+
+      // assume the error state has cleared if the log was followed for a
+      // while; short-lived responses (e.g. "Unable to retrieve container
+      // logs...") still count toward the retry limit
+      if following_since.elapsed() >= Duration::from_secs(HEALTHY_FOLLOW_SECS) {
+        retries = 0;
       }
 
       retries += 1;
@@ -507,10 +1050,15 @@ fn follow_log(
       thread::sleep(Duration::from_millis(500));
 
       // decide if we should restart the log
-      if should_stop_following(&namespace, port, &container, tx.clone()) {
+      if should_stop_following(
+        &config, &client, &namespace, &container, tx.clone()
+      ) {
         break;
       }
     }
+
+    // let the container be followed again if it's re-added
+    stop.store(true, Ordering::SeqCst);
   });
 }
 
@@ -525,12 +1073,13 @@ fn spawn_kubectl(config: Arc<Config>) -> SimpleResult<(Popen, u16)> {
     thread_rng().gen_range(1000, 65535)
   };
 
-  let port_arg = &format!("--port={}", port);
-  let args = vec![
-    "kubectl",
-    "proxy",
-    &port_arg
+  let port_arg = format!("--port={}", port);
+  let mut args = vec![
+    String::from("kubectl"),
+    String::from("proxy"),
+    port_arg
   ];
+  args.extend(context_args(&config));
 
   let mut child = Popen::create(&args, PopenConfig {
     stdout: Redirection::Merge,
@@ -549,10 +1098,59 @@ fn spawn_kubectl(config: Arc<Config>) -> SimpleResult<(Popen, u16)> {
   }
 }
 
-fn kubectl_get_namespace() -> SimpleResult<String> {
+/// kubectl arguments selecting the context given by `--context`, if any
+fn context_args(config: &Config) -> Vec<String> {
+  config.kubernetes.context.iter()
+    .map(|context| format!("--context={}", context))
+    .collect()
+}
+
+/// checks that `name` is one of `contexts`, as listed by
+/// `kubectl config get-contexts -o name`
+fn find_context(contexts: &str, name: &str) -> SimpleResult<()> {
+  let contexts: Vec<&str> = contexts.lines()
+    .map(|line| line.trim())
+    .filter(|line| !line.is_empty())
+    .collect();
+
+  if contexts.contains(&name) {
+    Ok(())
+  } else {
+    Err(SimpleError::new(format!(
+      "context not found: {} (available: {})", name, contexts.join(", ")
+    )))
+  }
+}
+
+/// ensures the context given by `--context`, if any, exists, since kubectl
+/// proxy would otherwise fail with a less helpful error
+fn kubectl_check_context(config: &Config) -> SimpleResult<()> {
+  let name = match &config.kubernetes.context {
+    Some(name) => name,
+    None => return Ok(())
+  };
+
+  let data = Exec::cmd("kubectl")
+    .args(&["config", "get-contexts", "-o", "name"])
+    .stdout(Redirection::Pipe)
+    .stderr(Redirection::Pipe)
+    .capture()
+    .map_err(SimpleError::from)?;
+
+  if data.success() {
+    find_context(&data.stdout_str(), name)
+  } else {
+    Err(SimpleError::new(format!(
+      "kubectl error: {}", data.stderr_str()
+    )))
+  }
+}
+
+fn kubectl_get_namespace(config: &Config) -> SimpleResult<String> {
   // kubectl _appears_ to helpfully rewrite the config output to show the
   // current context first... but since that may or may not be intended
-  // behavior, we also pass --minify which removes all but the current context
+  // behavior, we also pass --minify which removes all but the current (or
+  // selected) context
   let data = Exec::cmd("kubectl")
     .args(&[
       "config",
@@ -561,6 +1159,7 @@ fn kubectl_get_namespace() -> SimpleResult<String> {
       "-o",
       "jsonpath={.contexts[0].context.namespace}"
     ])
+    .args(&context_args(config))
     .stdout(Redirection::Pipe)
     .stderr(Redirection::Pipe)
     .capture()
@@ -581,28 +1180,197 @@ fn kubectl_get_namespace() -> SimpleResult<String> {
   }
 }
 
+/// reads the pod's own namespace from its service account
+fn in_cluster_namespace() -> SimpleResult<String> {
+  let path = Path::new(SERVICE_ACCOUNT_DIR).join("namespace");
+  let namespace = fs::read_to_string(&path).map_err(|e| {
+    SimpleError::new(format!(
+      "could not read service account namespace {}: {}", path.display(), e
+    ))
+  })?;
+
+  Ok(namespace.trim().to_string())
+}
+
+/// true if kubectl has a kubeconfig to use, per `$KUBECONFIG` or the default
+/// `~/.kube/config`
+fn kubeconfig_exists() -> bool {
+  if let Some(paths) = env::var_os("KUBECONFIG") {
+    if env::split_paths(&paths).any(|path| path.is_file()) {
+      return true;
+    }
+  }
+
+  Path::new(shellexpand::tilde("~/.kube/config").as_ref()).is_file()
+}
+
+/// re-lists namespaces matching `selector`, starting watches for new
+/// namespaces and stopping watches for namespaces that no longer match
+fn update_namespace_watches(
+  config: &Arc<Config>,
+  selector: &str,
+  client: &KubernetesClient,
+  watches: &mut HashMap<String, Arc<AtomicBool>>,
+  tx: &SyncSender<LogEntry>,
+  event_tx: &Sender<PodEvent>
+) -> SimpleResult<()> {
+  let namespaces: HashSet<String> = list_namespaces(client, selector)?
+    .into_iter()
+    .collect();
+
+  let removed: Vec<String> = watches.keys()
+    .filter(|ns| !namespaces.contains(*ns))
+    .cloned()
+    .collect();
+
+  for namespace in removed {
+    if let Some(stop) = watches.remove(&namespace) {
+      stop.store(true, Ordering::SeqCst);
+    }
+
+    tx.send(LogEntry::internal(config, &format!(
+      "namespace {} no longer matches {}", namespace, selector
+    ))).ok();
+  }
+
+  for namespace in namespaces {
+    if watches.contains_key(&namespace) {
+      continue;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    watch_events(
+      Arc::clone(config), namespace.clone(), client.clone(),
+      tx.clone(), event_tx.clone(), Arc::clone(&stop)
+    );
+
+    watches.insert(namespace, stop);
+  }
+
+  Ok(())
+}
+
+/// Containers being followed, each with a flag that stops its follower when
+/// set; followers also set it themselves once they finish
+type Follows = HashMap<Container, Arc<AtomicBool>>;
+
+/// updates `follows` for a pod event, returning a container to start
+/// following along with its stop flag, if any
+///
+/// containers that are already being followed are skipped, as are those in
+/// namespaces not in `watches` (if given), which may have stopped matching
+/// `--namespace-selector` since the event was sent
+fn apply_pod_event(
+  follows: &mut Follows,
+  watches: Option<&HashMap<String, Arc<AtomicBool>>>,
+  event: PodEvent
+) -> Option<(Container, Arc<AtomicBool>)> {
+  match event {
+    PodEvent::Added(container) => {
+      if let Some(watches) = watches {
+        if !watches.contains_key(&container.namespace) {
+          return None;
+        }
+      }
+
+      if let Some(stop) = follows.get(&container) {
+        if !stop.load(Ordering::SeqCst) {
+          return None;
+        }
+      }
+
+      let stop = Arc::new(AtomicBool::new(false));
+      follows.insert(container.clone(), Arc::clone(&stop));
+      Some((container, stop))
+    },
+    PodEvent::Removed(container) => {
+      if let Some(stop) = follows.remove(&container) {
+        stop.store(true, Ordering::SeqCst);
+      }
+
+      None
+    }
+  }
+}
+
+/// stops following containers in namespaces that are no longer watched
+fn stop_unwatched_follows(
+  follows: &mut Follows, watches: &HashMap<String, Arc<AtomicBool>>
+) {
+  follows.retain(|container, stop| {
+    if watches.contains_key(&container.namespace) {
+      true
+    } else {
+      stop.store(true, Ordering::SeqCst);
+      false
+    }
+  });
+}
+
 pub fn read_kubernetes_selector(
   config: Arc<Config>,
-  tx: Sender<LogEntry>,
+  tx: SyncSender<LogEntry>,
   exit_req_rx: Receiver<()>,
   exit_resp_tx: Sender<()>
 ) -> JoinHandle<SimpleResult<()>> {
   thread::Builder::new().name("read_kubernetes_selector".to_string()).spawn(move || {
+    // without a kubeconfig, kubectl can't help, but if we're running in a pod
+    // its service account can be used instead
+    let in_cluster = config.kubernetes.context.is_none()
+      && !kubeconfig_exists()
+      && env::var_os("KUBERNETES_SERVICE_HOST").is_some();
+
+    if !in_cluster {
+      kubectl_check_context(&config)?;
+    }
+
+    let selector = config.kubernetes.namespace_selector.clone();
     let namespace = if let Some(namespace) = &config.kubernetes.namespace {
-      namespace.clone()
+      Some(namespace.clone())
+    } else if selector.is_some() {
+      None
+    } else if in_cluster {
+      Some(in_cluster_namespace()?)
     } else {
-      kubectl_get_namespace()?
+      Some(kubectl_get_namespace(&config)?)
     };
 
-    let (mut kubectl, port) = spawn_kubectl(Arc::clone(&config))?;
-    tx.send(LogEntry::internal(
-      &format!("started kubernetes api proxy on port {}", port)
-    )).ok();
+    let (mut kubectl, client) = if in_cluster {
+      let client = KubernetesClient::in_cluster()?;
+      tx.send(LogEntry::internal(
+        &config, "no kubeconfig found, using the pod's service account"
+      )).ok();
+
+      (None, client)
+    } else {
+      let (kubectl, initial_port) = spawn_kubectl(Arc::clone(&config))?;
+      tx.send(LogEntry::internal(
+        &config,
+        &format!("started kubernetes api proxy on port {}", initial_port)
+      )).ok();
+
+      let port: SharedPort = Arc::new(AtomicU16::new(initial_port));
+      (Some(kubectl), KubernetesClient::proxy(port))
+    };
 
     let (event_tx, event_rx) = channel();
-    watch_events(
-      Arc::clone(&config), namespace.clone(), port, tx.clone(), event_tx
-    );
+
+    // with --namespace-selector, namespaces are rediscovered every poll
+    // interval, each with its own watch that can be stopped independently
+    let mut namespace_watches: HashMap<String, Arc<AtomicBool>> = HashMap::new();
+    let mut last_discovery: Option<Instant> = None;
+    let mut follows: Follows = HashMap::new();
+    let poll_interval = Duration::from_secs(config.kubernetes.poll_interval);
+
+    if let Some(namespace) = &namespace {
+      watch_events(
+        Arc::clone(&config), namespace.clone(), client.clone(),
+        tx.clone(), event_tx.clone(), Arc::new(AtomicBool::new(false))
+      );
+    }
+
+    let mut proxy_alive = true;
+    let mut last_spawn = Instant::now();
 
     loop {
       thread::sleep(Duration::from_millis(100));
@@ -611,29 +1379,520 @@ pub fn read_kubernetes_selector(
         break;
       }
 
-      for event in event_rx.try_iter() {
-        match event {
-          PodEvent::Added(container) => {
-            follow_log(
-              Arc::clone(&config),
-              namespace.clone(),
-              port,
-              container,
-              tx.clone()
-            );
-          },
-          PodEvent::Removed(_container) => {
-            // TODO: do we care?
+      // supervise the proxy, if any: if it dies, followers will keep retrying
+      // against the shared port until a new proxy is up
+      if let Some(kubectl) = &mut kubectl {
+        if proxy_alive {
+          if let Some(status) = kubectl.poll() {
+            proxy_alive = false;
+
+            tx.send(LogEntry::internal_level(&config, LogLevel::Warning, &format!(
+              "kubernetes api proxy exited unexpectedly ({:?}), restarting",
+              status
+            ))).ok();
+          }
+        }
+
+        let respawn_interval = Duration::from_secs(PROXY_RESPAWN_SECS);
+        if !proxy_alive && last_spawn.elapsed() >= respawn_interval {
+          last_spawn = Instant::now();
+
+          match spawn_kubectl(Arc::clone(&config)) {
+            Ok((child, new_port)) => {
+              *kubectl = child;
+              client.set_proxy_port(new_port);
+              proxy_alive = true;
+
+              tx.send(LogEntry::internal(&config, &format!(
+                "restarted kubernetes api proxy on port {}", new_port
+              ))).ok();
+            },
+            Err(e) => {
+              tx.send(LogEntry::internal_level(&config, LogLevel::Error, &format!(
+                "could not restart kubernetes api proxy: {}", e
+              ))).ok();
+            }
           }
         }
       }
+
+      if let Some(selector) = &selector {
+        let due = match last_discovery {
+          Some(last) => last.elapsed() >= poll_interval,
+          None => true
+        };
+
+        if due && proxy_alive {
+          last_discovery = Some(Instant::now());
+
+          if let Err(e) = update_namespace_watches(
+            &config, selector, &client, &mut namespace_watches, &tx, &event_tx
+          ) {
+            tx.send(LogEntry::internal_level(&config, LogLevel::Error, &format!(
+              "error listing namespaces matching {}: {}", selector, e
+            ))).ok();
+          }
+
+          stop_unwatched_follows(&mut follows, &namespace_watches);
+        }
+      }
+
+      let watches = selector.as_ref().map(|_| &namespace_watches);
+      for event in event_rx.try_iter() {
+        if let Some((container, stop)) = apply_pod_event(
+          &mut follows, watches, event
+        ) {
+          follow_log(
+            Arc::clone(&config),
+            container.namespace.clone(),
+            client.clone(),
+            container,
+            tx.clone(),
+            stop
+          );
+        }
+      }
     }
 
-    kubectl.terminate().ok();
-    kubectl.wait().ok();
+    if let Some(mut kubectl) = kubectl {
+      kubectl.terminate().ok();
+      kubectl.wait().ok();
+    }
 
     exit_resp_tx.send(()).ok();
 
     Ok(())
   }).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::io::Write;
+  use std::net::TcpListener;
+  use std::process;
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  /// a self-signed CA, standing in for a service account's `ca.crt`
+  const CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBgTCCASegAwIBAgIUEiXNkI22dHlglIeBsFP8boj0P2EwCgYIKoZIzj0EAwIw
+FTETMBEGA1UEAwwKa3ViZXJuZXRlczAgFw0yNjEwMTYxNzI5MThaGA8yMTI2MDky
+MjE3MjkxOFowFTETMBEGA1UEAwwKa3ViZXJuZXRlczBZMBMGByqGSM49AgEGCCqG
+SM49AwEHA0IABBrQgzZhcNKYYAcK95DR3ACEgv4ikiS6AyCWmRl0EQ0TpoL2vQ4W
+ybvbl5X8/CzUb8EbFzO1M0mhtSGZtoSQQxSjUzBRMB0GA1UdDgQWBBSSgV0P6ALf
+VRsVsmNCIVYoblEVWTAfBgNVHSMEGDAWgBSSgV0P6ALfVRsVsmNCIVYoblEVWTAP
+BgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIA4zlPkr2NY6lbeLQWVT
+sOaoCb7+7LWp/gAo1IAUPUWiAiEApdTcxqPgbzQNDj4eY7EwAludbWX76YV5EFhv
+CTQCQMo=
+-----END CERTIFICATE-----
+";
+
+  #[test]
+  fn test_next_attempt() {
+    let backoff = Duration::from_millis(5000);
+    let now = Some(Duration::from_millis(0));
+
+    // the first attempt is always made, even with no retries
+    assert_that!(next_attempt(0, 0, backoff)).is_equal_to(now);
+    assert_that!(next_attempt(1, 0, backoff)).is_none();
+
+    assert_that!(next_attempt(0, 3, backoff)).is_equal_to(now);
+    assert_that!(next_attempt(1, 3, backoff)).is_equal_to(Some(backoff));
+    assert_that!(next_attempt(3, 3, backoff)).is_equal_to(Some(backoff));
+    assert_that!(next_attempt(4, 3, backoff)).is_none();
+  }
+
+  /// serves a single request with an empty response of the given status,
+  /// returning the port it listens on
+  fn serve_once(status: &'static str) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+
+      // read up to the blank line ending the request headers
+      BufReader::new(&stream).lines()
+        .take_while(|line| match line {
+          Ok(line) => !line.is_empty(),
+          Err(_) => false
+        })
+        .for_each(drop);
+
+      write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+      ).ok();
+    });
+
+    port
+  }
+
+  #[test]
+  fn test_proxy_restart() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let container = Container::new(
+      String::from("default"), String::from("web-1"), String::from("app"), 1
+    );
+
+    // nothing is listening on a port that was just released
+    let dead_port = TcpListener::bind("127.0.0.1:0").unwrap()
+      .local_addr().unwrap().port();
+    let client = KubernetesClient::proxy(Arc::new(AtomicU16::new(dead_port)));
+    let follower = client.clone();
+
+    // a dead proxy is reported, but isn't a reason to stop following
+    let (tx, rx) = sync_channel(10);
+    assert_that!(should_stop_following(
+      &config, &follower, "default", &container, tx.clone()
+    )).is_false();
+    assert_that!(rx.try_recv().is_ok()).is_true();
+
+    // followers share the restarted proxy's port
+    let new_port = serve_once("404 Not Found");
+    client.set_proxy_port(new_port);
+    assert_that!(follower.url("/api"))
+      .is_equal_to(format!("http://localhost:{}/api", new_port));
+
+    assert_that!(should_stop_following(
+      &config, &follower, "default", &container, tx
+    )).is_true();
+  }
+
+  #[test]
+  fn test_in_cluster_client() {
+    let dir = env::temp_dir().join(
+      format!("woodchipper-k8s-test-{}", process::id())
+    );
+    fs::create_dir_all(&dir).unwrap();
+
+    let token = dir.join("token");
+    let ca = dir.join("ca.crt");
+    fs::write(&token, "abc.def.ghi\n").unwrap();
+    fs::write(&ca, CA).unwrap();
+
+    let client = KubernetesClient::from_service_account(
+      "10.0.0.1", "443", &token, &ca
+    ).unwrap();
+    assert_that!(client.url("/api/v1/namespaces"))
+      .is_equal_to(String::from("https://10.0.0.1:443/api/v1/namespaces"));
+
+    // rotated tokens are picked up once the cached one is stale
+    let token_header = |client: &KubernetesClient| match &client.endpoint {
+      Endpoint::Direct(_, token) => token.header(),
+      Endpoint::Proxy(_) => panic!("expected a direct client")
+    };
+    assert_that!(token_header(&client))
+      .is_equal_to(HeaderValue::from_static("Bearer abc.def.ghi"));
+
+    fs::write(&token, "jkl.mno.pqr\n").unwrap();
+    assert_that!(token_header(&client))
+      .is_equal_to(HeaderValue::from_static("Bearer abc.def.ghi"));
+
+    if let Endpoint::Direct(_, cached) = &client.endpoint {
+      let stale = Instant::now() - Duration::from_secs(TOKEN_REFRESH_SECS);
+      cached.cached.lock().unwrap().1 = stale;
+    }
+    assert_that!(token_header(&client))
+      .is_equal_to(HeaderValue::from_static("Bearer jkl.mno.pqr"));
+
+    let client = KubernetesClient::from_service_account(
+      "fd00::1", "6443", &token, &ca
+    ).unwrap();
+    assert_that!(client.url("/api"))
+      .is_equal_to(String::from("https://[fd00::1]:6443/api"));
+
+    // a bad CA or missing token is an error rather than an insecure client
+    fs::write(&ca, "not a certificate").unwrap();
+    assert_that!(KubernetesClient::from_service_account(
+      "10.0.0.1", "443", &token, &ca
+    ).is_err()).is_true();
+
+    fs::remove_file(&token).ok();
+    assert_that!(KubernetesClient::from_service_account(
+      "10.0.0.1", "443", &token, &ca
+    ).is_err()).is_true();
+
+    fs::remove_dir_all(&dir).ok();
+  }
+
+  #[test]
+  fn test_find_context() {
+    // as listed by `kubectl config get-contexts -o name`
+    let contexts = "kind-dev\nprod-us-east\n\n";
+
+    assert_that!(find_context(contexts, "prod-us-east").is_ok()).is_true();
+    assert_that!(find_context(contexts, "kind-dev").is_ok()).is_true();
+
+    let err = find_context(contexts, "prod").unwrap_err();
+    assert_that!(err.to_string().as_str()).is_equal_to(
+      "context not found: prod (available: kind-dev, prod-us-east)"
+    );
+  }
+
+  #[test]
+  fn test_context_args() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    assert_that!(context_args(&config)).is_equal_to(vec![]);
+
+    let config = Config::from_iter_safe(
+      vec!["", "--context", "prod-us-east"]
+    ).unwrap();
+    assert_that!(context_args(&config))
+      .is_equal_to(vec![String::from("--context=prod-us-east")]);
+  }
+
+  #[test]
+  fn test_log_query() {
+    let container = Container::new(
+      String::from("default"), String::from("web-1"), String::from("app"), 1
+    );
+    let query = |args: Vec<&str>| {
+      let config = Config::from_iter_safe(args).unwrap();
+      log_query(&config, &container).into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+    };
+
+    assert_that!(query(vec![""])).is_equal_to(vec![
+      String::from("follow=true"),
+      String::from("container=app"),
+      String::from("timestamps=true")
+    ]);
+
+    assert_that!(query(vec!["", "--previous"])).is_equal_to(vec![
+      String::from("previous=true"),
+      String::from("container=app"),
+      String::from("timestamps=true")
+    ]);
+
+    assert_that!(query(vec!["", "--since", "1h", "--tail", "100"]))
+      .is_equal_to(vec![
+        String::from("follow=true"),
+        String::from("container=app"),
+        String::from("timestamps=true"),
+        String::from("sinceSeconds=3600"),
+        String::from("tailLines=100")
+      ]);
+  }
+
+  /// a watch event for a pod with two containers
+  fn pod_event(kind: &str, name: &str) -> String {
+    format!(
+      concat!(
+        r#"{{"type":"{}","object":{{"kind":"Pod","apiVersion":"v1","#,
+        r#""metadata":{{"name":"{}","namespace":"default","#,
+        r#""resourceVersion":"1234"}},"#,
+        r#""spec":{{"containers":[{{"name":"app"}},{{"name":"sidecar"}}]}},"#,
+        r#""status":{{"phase":"Pending"}}}}}}"#
+      ),
+      kind, name
+    )
+  }
+
+  fn containers(name: &str) -> Vec<Container> {
+    vec![
+      Container::new(
+        String::from("default"), String::from(name), String::from("app"), 2
+      ),
+      Container::new(
+        String::from("default"), String::from(name), String::from("sidecar"), 2
+      )
+    ]
+  }
+
+  fn apply(
+    line: &str, args: &[&str], current: &mut HashSet<Container>
+  ) -> Option<Vec<PodEvent>> {
+    let event: KubernetesWatchEvent = serde_json::from_str(line).unwrap();
+    apply_watch_event(event, false, args, current).unwrap()
+  }
+
+  #[test]
+  fn test_watch_event() {
+    let event: KubernetesWatchEvent = serde_json::from_str(
+      &pod_event("ADDED", "web-1")
+    ).unwrap();
+    assert_that!(event.kind).is_equal_to(KubernetesWatchEventType::Added);
+
+    // pending pods have no labels or container statuses yet
+    let pod: KubernetesPod = serde_json::from_value(event.object).unwrap();
+    assert_that!(pod.metadata.name.as_str()).is_equal_to("web-1");
+    assert_that!(pod.metadata.labels.is_empty()).is_true();
+    assert_that!(pod.status.container_statuses.is_empty()).is_true();
+
+    let error = r#"{"type":"ERROR","object":{"kind":"Status","apiVersion":"v1","status":"Failure","message":"too old resource version: 1 (1234)","reason":"Expired","code":410}}"#;
+    let event: KubernetesWatchEvent = serde_json::from_str(error).unwrap();
+    assert_that!(event.kind).is_equal_to(KubernetesWatchEventType::Error);
+
+    let bookmark = r#"{"type":"BOOKMARK","object":{"kind":"Pod","metadata":{"resourceVersion":"1300"}}}"#;
+    let event: KubernetesWatchEvent = serde_json::from_str(bookmark).unwrap();
+    assert_that!(event.kind).is_equal_to(KubernetesWatchEventType::Bookmark);
+  }
+
+  #[test]
+  fn test_apply_watch_event() {
+    let mut current = HashSet::new();
+    let web = containers("web-1");
+
+    let events = apply(&pod_event("ADDED", "web-1"), &[], &mut current);
+    assert_that!(events).is_equal_to(Some(vec![
+      PodEvent::Added(web[0].clone()),
+      PodEvent::Added(web[1].clone())
+    ]));
+
+    // already followed
+    let events = apply(&pod_event("MODIFIED", "web-1"), &[], &mut current);
+    assert_that!(events).is_equal_to(Some(vec![]));
+
+    // doesn't match the pod name arguments
+    let events = apply(&pod_event("ADDED", "db-1"), &["web"], &mut current);
+    assert_that!(events).is_equal_to(Some(vec![]));
+
+    let events = apply(&pod_event("DELETED", "web-1"), &[], &mut current);
+    assert_that!(events).is_equal_to(Some(vec![
+      PodEvent::Removed(web[0].clone()),
+      PodEvent::Removed(web[1].clone())
+    ]));
+    assert_that!(current.is_empty()).is_true();
+
+    // expired watches end so pods can be re-listed
+    let error = r#"{"type":"ERROR","object":{"kind":"Status","code":410}}"#;
+    assert_that!(apply(error, &[], &mut current)).is_equal_to(None);
+  }
+
+  #[test]
+  fn test_apply_pod_event() {
+    let mut follows = Follows::new();
+    let web = containers("web-1").remove(0);
+
+    let (container, stop) = apply_pod_event(
+      &mut follows, None, PodEvent::Added(web.clone())
+    ).unwrap();
+    assert_that!(container).is_equal_to(&web);
+
+    // already followed, e.g. the namespace matched again
+    let followed = apply_pod_event(
+      &mut follows, None, PodEvent::Added(web.clone())
+    );
+    assert_that!(followed.is_none()).is_true();
+
+    // removing the pod stops its follower
+    let followed = apply_pod_event(
+      &mut follows, None, PodEvent::Removed(web.clone())
+    );
+    assert_that!(followed.is_none()).is_true();
+    assert_that!(stop.load(Ordering::SeqCst)).is_true();
+    assert_that!(follows.is_empty()).is_true();
+
+    // a follower that finished on its own can be replaced
+    let followed = apply_pod_event(
+      &mut follows, None, PodEvent::Added(web.clone())
+    );
+    followed.unwrap().1.store(true, Ordering::SeqCst);
+    let followed = apply_pod_event(
+      &mut follows, None, PodEvent::Added(web.clone())
+    );
+    assert_that!(followed.is_some()).is_true();
+  }
+
+  #[test]
+  fn test_unwatched_follows() {
+    let mut follows = Follows::new();
+    let mut watches = HashMap::new();
+    let web = containers("web-1").remove(0);
+
+    // the namespace stopped matching before the event was handled
+    let followed = apply_pod_event(
+      &mut follows, Some(&watches), PodEvent::Added(web.clone())
+    );
+    assert_that!(followed.is_none()).is_true();
+
+    watches.insert(String::from("default"), Arc::new(AtomicBool::new(false)));
+    let (_, stop) = apply_pod_event(
+      &mut follows, Some(&watches), PodEvent::Added(web.clone())
+    ).unwrap();
+
+    stop_unwatched_follows(&mut follows, &watches);
+    assert_that!(stop.load(Ordering::SeqCst)).is_false();
+
+    watches.clear();
+    stop_unwatched_follows(&mut follows, &watches);
+    assert_that!(stop.load(Ordering::SeqCst)).is_true();
+    assert_that!(follows.is_empty()).is_true();
+  }
+
+  #[test]
+  fn test_pod_list_version() {
+    let list: KubernetesListObject = serde_json::from_str(
+      r#"{"kind":"PodList","metadata":{"resourceVersion":"1234"},"items":[]}"#
+    ).unwrap();
+
+    assert_that!(list.metadata.resource_version)
+      .is_equal_to(Some(String::from("1234")));
+  }
+
+  #[test]
+  fn test_label_fields() {
+    let config = Config::from_iter_safe(
+      vec!["", "--label-fields", "app,tier,missing"]
+    ).unwrap();
+    assert_that!(config.kubernetes.label_fields).is_equal_to(vec![
+      String::from("app"), String::from("tier"), String::from("missing")
+    ]);
+
+    let event: KubernetesWatchEvent = serde_json::from_str(&pod_event(
+      "ADDED", "web-1"
+    )).unwrap();
+    let mut pod: KubernetesPod = serde_json::from_value(event.object).unwrap();
+    pod.metadata.labels = hashmap! {
+      String::from("app") => String::from("web"),
+      String::from("tier") => String::from("frontend"),
+      String::from("pod-template-hash") => String::from("5d8f9")
+    };
+    let container = &get_containers(&pod)[0];
+
+    let config = Arc::new(config);
+    let line = r#"{"msg":"hello","tier":"backend"}"#;
+    let mut message = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+    add_label_fields(&config, &mut message, container);
+
+    // labels don't overwrite the message's own fields
+    let metadata = &message.metadata;
+    let string = |s: &str| Value::String(String::from(s));
+    assert_that!(metadata.get("app")).is_equal_to(Some(&string("web")));
+    assert_that!(metadata.get("tier")).is_equal_to(Some(&string("backend")));
+    assert_that!(metadata.contains_key("pod-template-hash")).is_false();
+    assert_that!(metadata.contains_key("missing")).is_false();
+
+    // added before classifying, so they're shown
+    let entry = LogEntry::parsed(&config, message).message.unwrap();
+    let shown = entry.chunks.iter()
+      .flat_map(|c| c.children.iter())
+      .any(|c| c.value.as_deref() == Some("app="));
+    assert_that!(shown).is_true();
+
+    // labels are ignored when comparing containers
+    let mut relabeled = container.clone();
+    relabeled.labels.clear();
+    assert_that!(&relabeled).is_equal_to(container);
+  }
+
+  #[test]
+  fn test_proxy_client() {
+    let port: SharedPort = Arc::new(AtomicU16::new(8001));
+    let client = KubernetesClient::proxy(Arc::clone(&port));
+    assert_that!(client.url("/api"))
+      .is_equal_to(String::from("http://localhost:8001/api"));
+
+    // requests follow a restarted proxy
+    client.set_proxy_port(8002);
+    assert_that!(client.url("/api"))
+      .is_equal_to(String::from("http://localhost:8002/api"));
+  }
+}
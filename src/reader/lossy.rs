@@ -0,0 +1,163 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use simple_error::SimpleResult;
+
+use crate::config::Config;
+use crate::renderer::LogEntry;
+
+/// Time to wait before retrying when the downstream channel is full
+const RETRY_MS: u64 = 10;
+
+/// a note reporting how many messages were dropped, resetting the count
+fn dropped_note(config: &Config, dropped: &mut usize) -> LogEntry {
+  let note = LogEntry::internal(config, &format!(
+    "warning: renderer fell behind, dropped {} messages", dropped
+  ));
+
+  *dropped = 0;
+  note
+}
+
+/// A wrapping reader that keeps a slow renderer from holding up the
+/// underlying reader: entries are queued up to `--channel-capacity` and, once
+/// the renderer falls behind, the oldest queued entries are discarded to make
+/// room for new ones.
+///
+/// While the renderer is behind, new entries are only collected every
+/// `RETRY_MS`, so the reader may wait briefly if it fills the channel in the
+/// meantime.
+pub fn read_lossy(
+  config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_lossy".to_string()).spawn(move || {
+    let capacity = config.channel_capacity;
+    let mut queue: VecDeque<LogEntry> = VecDeque::with_capacity(capacity);
+    let mut dropped: usize = 0;
+
+    loop {
+      // only block on the reader if there's nothing left to forward
+      if queue.is_empty() {
+        match rx.recv() {
+          Ok(entry) => queue.push_back(entry),
+          Err(_) => break
+        }
+      }
+
+      queue.extend(rx.try_iter());
+
+      while queue.len() > capacity {
+        queue.pop_front();
+        dropped += 1;
+      }
+
+      while let Some(entry) = queue.pop_front() {
+        // report drops before the eof, after which the renderer stops reading
+        let entry = if entry.eof.is_some() && dropped > 0 {
+          queue.push_front(entry);
+          dropped_note(&config, &mut dropped)
+        } else {
+          entry
+        };
+
+        match tx.try_send(entry) {
+          Ok(()) => (),
+          Err(TrySendError::Full(entry)) => {
+            queue.push_front(entry);
+            break;
+          },
+
+          // assume the renderer has quit
+          Err(TrySendError::Disconnected(_)) => return Ok(())
+        }
+      }
+
+      if queue.is_empty() && dropped > 0 {
+        let count = dropped;
+        if let Err(TrySendError::Full(_)) = tx.try_send(
+          dropped_note(&config, &mut dropped)
+        ) {
+          dropped = count;
+        }
+      }
+
+      if !queue.is_empty() {
+        thread::sleep(Duration::from_millis(RETRY_MS));
+      }
+    }
+
+    // the reader has exited, so flush whatever is left (including the eof)
+    for entry in queue {
+      if tx.send(entry).is_err() {
+        break;
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  /// queues `count` messages and an eof, then runs them through the stage
+  /// into a channel of `out_capacity`, returning the texts received
+  fn run(args: &[&str], count: usize, out_capacity: usize) -> Vec<String> {
+    let args = [&[""], args].concat();
+    let config = Arc::new(Config::from_iter_safe(args).unwrap());
+
+    let (in_tx, in_rx) = sync_channel(count + 1);
+    for i in 0..count {
+      in_tx.send(LogEntry::internal(&config, &format!("message {}", i))).unwrap();
+    }
+    in_tx.send(LogEntry::eof()).unwrap();
+    drop(in_tx);
+
+    let (out_tx, out_rx) = sync_channel(out_capacity);
+    read_lossy(config, in_rx, out_tx);
+
+    let mut texts = Vec::new();
+    for entry in out_rx.iter() {
+      if entry.eof.is_some() {
+        break;
+      }
+
+      texts.extend(entry.message.and_then(|m| m.message.text));
+    }
+
+    texts
+  }
+
+  #[test]
+  fn test_lossy_keeping_up() {
+    let texts = run(&["--channel-capacity", "10"], 5, 10);
+    assert_that!(texts).is_equal_to(
+      (0..5).map(|i| format!("message {}", i)).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_lossy_drops_oldest() {
+    // everything arrives at once, so only the newest messages (and the eof)
+    // fit in the queue, and the drops are reported before the eof
+    let texts = run(&["--channel-capacity", "3"], 10, 1);
+    assert_that!(texts).is_equal_to(vec![
+      String::from("message 8"),
+      String::from("message 9"),
+      String::from("warning: renderer fell behind, dropped 8 messages")
+    ]);
+  }
+}
@@ -1,15 +1,36 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 pub mod types;
+pub mod records;
+pub mod gzip;
 pub mod stdin;
 pub mod stdin_hack;
 pub mod kubernetes;
+pub mod subprocess;
+pub mod file;
 pub mod null;
 pub mod ordered;
+pub mod lossy;
+pub mod tee;
+pub mod history;
+pub mod quiet;
+pub mod replay;
+pub mod summary;
+pub mod expect;
+pub mod grep;
 
-pub use types::Reader;
+pub use types::{Reader, Stage};
 pub use stdin::read_stdin;
 pub use stdin_hack::read_stdin_hack;
 pub use kubernetes::read_kubernetes_selector;
+pub use subprocess::read_subprocess;
+pub use file::read_file;
 pub use null::read_null;
 pub(crate) use ordered::read_ordered;
+pub(crate) use lossy::read_lossy;
+pub(crate) use history::read_history;
+pub(crate) use quiet::read_quiet;
+pub(crate) use replay::read_replay;
+pub(crate) use summary::read_summary;
+pub(crate) use expect::read_expect;
+pub(crate) use grep::read_grep;
@@ -3,7 +3,7 @@
 use std::collections::BinaryHeap;
 use std::cmp::{Ord, Ordering, PartialEq, PartialOrd};
 use std::sync::Arc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, SyncSender};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
@@ -82,14 +82,14 @@ impl Eq for TimestampedEntry {}
 pub fn read_ordered(
   config: Arc<Config>,
   rx: Receiver<LogEntry>,
-  tx: Sender<LogEntry>,
+  tx: SyncSender<LogEntry>,
 ) -> JoinHandle<SimpleResult<()>> {
   thread::Builder::new().name("read_ordered".to_string()).spawn(move || {
     let buffer_duration = Duration::from_millis(
       config.buffer_ms.unwrap_or(DEFAULT_BUFFER_MS)
     );
 
-    tx.send(LogEntry::internal(&format!(
+    tx.send(LogEntry::internal(&config, &format!(
       "note: attempting to reorder messages, buffer: {}ms",
       buffer_duration.as_millis()
     ))).ok();
@@ -116,7 +116,15 @@ pub fn read_ordered(
           }
 
         } else if let Some(_) = unbuffered_entry.eof {
-          // quit and send immediately (buffered messages will be discarded)
+          // the input has ended so there's nothing left to wait for: flush
+          // everything still buffered, in order, before passing on the eof
+          while let Some(entry) = heap.pop() {
+            tx.send(LogEntry {
+              message: Some(entry.entry),
+              eof: None
+            }).ok();
+          }
+
           tx.send(LogEntry::eof()).ok();
           break 'outer;
         }
@@ -143,3 +151,51 @@ pub fn read_ordered(
     Ok(())
   }).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  #[test]
+  fn test_flush_on_eof() {
+    // long enough that nothing is released before the eof
+    let config = Arc::new(
+      Config::from_iter_safe(vec!["", "--buffer-ms", "60000"]).unwrap()
+    );
+
+    let (in_tx, in_rx) = sync_channel(10);
+    let (out_tx, out_rx) = sync_channel(10);
+    read_ordered(Arc::clone(&config), in_rx, out_tx);
+
+    for line in &[
+      r#"{"time":"2020-01-01T00:00:02Z","msg":"b"}"#,
+      r#"{"time":"2020-01-01T00:00:03Z","msg":"c"}"#,
+      r#"{"time":"2020-01-01T00:00:01Z","msg":"a"}"#
+    ] {
+      let entry = LogEntry::message(Arc::clone(&config), line, None);
+      in_tx.send(entry.unwrap().unwrap()).unwrap();
+    }
+    in_tx.send(LogEntry::eof()).unwrap();
+
+    let mut texts = Vec::new();
+    for entry in out_rx.iter() {
+      match entry.message {
+        Some(m) if m.message.kind == MessageKind::Internal => continue,
+        Some(m) => texts.push(m.message.text.unwrap()),
+        None => {
+          texts.push(String::from("eof"));
+          break;
+        }
+      }
+    }
+
+    assert_that!(texts).is_equal_to(vec![
+      "a".to_string(), "b".to_string(), "c".to_string(), "eof".to_string()
+    ]);
+  }
+}
@@ -0,0 +1,72 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use simple_error::SimpleResult;
+
+use crate::config::Config;
+use crate::parser::{LogLevel, MessageKind};
+use crate::renderer::LogEntry;
+
+/// true if the entry is an internal status message that can be dropped
+fn is_quiet(entry: &LogEntry) -> bool {
+  let message = match &entry.message {
+    Some(entry) => &entry.message,
+    None => return false
+  };
+
+  if message.kind != MessageKind::Internal {
+    return false;
+  }
+
+  // internal warnings and errors are kept
+  match message.level {
+    Some(level) => level < LogLevel::Warning,
+    None => true
+  }
+}
+
+/// A wrapping reader that drops woodchipper's own status messages, e.g.
+/// "watching pods", per `--quiet-internal`
+///
+/// Internal errors and warnings are still passed through.
+pub fn read_quiet(
+  _config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_quiet".to_string()).spawn(move || {
+    for entry in rx {
+      if is_quiet(&entry) {
+        continue;
+      }
+
+      if tx.send(entry).is_err() {
+        break;
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  #[test]
+  fn test_is_quiet() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+
+    assert_that!(is_quiet(&LogEntry::internal(&config, "watching pods"))).is_true();
+    assert_that!(is_quiet(&LogEntry::internal(&config, "warning: slow"))).is_false();
+    assert_that!(is_quiet(&LogEntry::internal_level(
+      &config, LogLevel::Error, "watch ended with error"
+    ))).is_false();
+  }
+}
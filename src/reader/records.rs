@@ -0,0 +1,198 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::io::{self, BufRead};
+use std::mem;
+
+use crate::config::RecordSeparator;
+use super::tee::Tee;
+
+/// An iterator over the records of a reader, like `BufRead::lines()` but
+/// split on the given separator
+pub struct Records<B> {
+  reader: B,
+  separator: RecordSeparator,
+
+  /// if set, an unterminated record at the end of input is held back until
+  /// the rest of it arrives, e.g. when tailing a file that's being written
+  follow: bool,
+
+  /// the start of a held-back record
+  pending: Vec<u8>,
+
+  /// if set, each raw record is copied here, separator included
+  tee: Option<Tee>
+}
+
+/// splits `reader` into records per `--record-separator`
+pub fn records<B: BufRead>(reader: B, separator: RecordSeparator) -> Records<B> {
+  Records { reader, separator, follow: false, pending: Vec::new(), tee: None }
+}
+
+/// like `records()`, but for input that may still be growing: iteration ends
+/// at the current end of input, but may be resumed once more is written
+pub fn follow_records<B: BufRead>(
+  reader: B, separator: RecordSeparator
+) -> Records<B> {
+  Records { reader, separator, follow: true, pending: Vec::new(), tee: None }
+}
+
+impl<B: BufRead> Records<B> {
+  pub fn get_mut(&mut self) -> &mut B {
+    &mut self.reader
+  }
+
+  /// stops waiting for more input, e.g. once a followed file is rotated, so
+  /// a held back partial record is returned as the final record
+  pub fn stop_following(&mut self) {
+    self.follow = false;
+  }
+
+  /// copies each raw record to `tee` (per `--tee`) as it's split, before
+  /// separators are trimmed or anything is parsed
+  pub fn tee(mut self, tee: Option<Tee>) -> Self {
+    self.tee = tee;
+    self
+  }
+
+  /// returns true if `buf` ends with a full separator
+  fn is_terminated(&self, buf: &[u8]) -> bool {
+    match self.separator {
+      RecordSeparator::Lf => buf.ends_with(b"\n"),
+      RecordSeparator::Cr => buf.ends_with(b"\r"),
+      RecordSeparator::Crlf => buf.ends_with(b"\r\n"),
+      RecordSeparator::Nul => buf.ends_with(b"\0")
+    }
+  }
+
+  /// reads one record into `buf`, including its separator, returning false at
+  /// the end of input
+  fn read_record(&mut self, buf: &mut Vec<u8>) -> io::Result<bool> {
+    match self.separator {
+      RecordSeparator::Lf => self.reader.read_until(b'\n', buf),
+      RecordSeparator::Cr => self.reader.read_until(b'\r', buf),
+      RecordSeparator::Nul => self.reader.read_until(b'\0', buf),
+      RecordSeparator::Crlf => {
+        // bare newlines are part of the record, so keep reading until one
+        // follows a carriage return
+        loop {
+          if self.reader.read_until(b'\n', buf)? == 0 || buf.ends_with(b"\r\n") {
+            break;
+          }
+
+          if !buf.ends_with(b"\n") {
+            // end of input
+            break;
+          }
+        }
+
+        Ok(buf.len())
+      }
+    }.map(|len| len > 0)
+  }
+}
+
+impl<B: BufRead> Iterator for Records<B> {
+  type Item = io::Result<String>;
+
+  fn next(&mut self) -> Option<io::Result<String>> {
+    let mut buf = mem::take(&mut self.pending);
+    match self.read_record(&mut buf) {
+      Ok(true) => (),
+      Ok(false) if self.follow || buf.is_empty() => {
+        self.pending = buf;
+        return None;
+      },
+      Ok(false) => (),
+      Err(e) => return Some(Err(e))
+    };
+
+    if self.follow && !self.is_terminated(&buf) {
+      self.pending = buf;
+      return None;
+    }
+
+    // stop teeing after a failed write rather than reporting every record
+    if let Some(tee) = &mut self.tee {
+      if !tee.write(&buf) {
+        self.tee = None;
+      }
+    }
+
+    let trim: &[u8] = match self.separator {
+      // also strip the \r of \r\n line endings
+      RecordSeparator::Lf if buf.ends_with(b"\r\n") => b"\r\n",
+      RecordSeparator::Lf => b"\n",
+      RecordSeparator::Cr => b"\r",
+      RecordSeparator::Crlf => b"\r\n",
+      RecordSeparator::Nul => b"\0"
+    };
+
+    if buf.ends_with(trim) {
+      buf.truncate(buf.len() - trim.len());
+    }
+
+    Some(String::from_utf8(buf).map_err(|e| {
+      io::Error::new(io::ErrorKind::InvalidData, e)
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::io::Cursor;
+
+  use spectral::prelude::*;
+
+  fn split(input: &str, separator: &str) -> Vec<String> {
+    records(input.as_bytes(), separator.parse().unwrap())
+      .map(|r| r.unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_lf() {
+    assert_that!(split("a\nb\r\n\nc", "lf"))
+      .is_equal_to(vec!["a".to_string(), "b".to_string(), "".to_string(), "c".to_string()]);
+  }
+
+  #[test]
+  fn test_cr() {
+    assert_that!(split("a\rb\nc\r", "cr"))
+      .is_equal_to(vec!["a".to_string(), "b\nc".to_string()]);
+  }
+
+  #[test]
+  fn test_crlf() {
+    assert_that!(split("a\r\nb\nc\r\nd\n", "crlf"))
+      .is_equal_to(vec!["a".to_string(), "b\nc".to_string(), "d\n".to_string()]);
+  }
+
+  #[test]
+  fn test_follow() {
+    let input = Cursor::new(b"a\nb".to_vec());
+    let mut records = follow_records(input, RecordSeparator::Lf);
+
+    assert_that!(records.next().unwrap().unwrap()).is_equal_to("a".to_string());
+
+    // the partial `b` is held back until its newline arrives
+    assert_that!(records.next()).is_none();
+    records.get_mut().get_mut().extend_from_slice(b"c\n");
+    assert_that!(records.next().unwrap().unwrap()).is_equal_to("bc".to_string());
+    assert_that!(records.next()).is_none();
+
+    // once input is over, a partial record is returned as-is
+    records.get_mut().get_mut().extend_from_slice(b"d");
+    assert_that!(records.next()).is_none();
+    records.stop_following();
+    assert_that!(records.next().unwrap().unwrap()).is_equal_to("d".to_string());
+    assert_that!(records.next()).is_none();
+  }
+
+  #[test]
+  fn test_nul() {
+    assert_that!(split("a\0b\nc\0", "nul"))
+      .is_equal_to(vec!["a".to_string(), "b\nc".to_string()]);
+  }
+}
@@ -0,0 +1,188 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use simple_error::SimpleResult;
+
+use crate::config::Config;
+use crate::parser::MessageKind;
+use crate::renderer::LogEntry;
+
+/// the longest (scaled) delay between two messages, so large gaps in the input
+/// don't stall playback
+const MAX_DELAY_MS: u64 = 10_000;
+
+/// returns the timestamp of a non-internal message, if any
+fn replay_timestamp(entry: &LogEntry) -> Option<DateTime<Utc>> {
+  let message = &entry.message.as_ref()?.message;
+  if message.kind == MessageKind::Internal {
+    return None;
+  }
+
+  message.timestamp
+}
+
+/// returns how long to wait between two timestamps at the given speed
+fn replay_delay(
+  last: DateTime<Utc>, next: DateTime<Utc>, speed: f64
+) -> Option<Duration> {
+  let gap_ms = next.signed_duration_since(last).num_milliseconds();
+  if gap_ms <= 0 {
+    return None;
+  }
+
+  let delay_ms = (gap_ms as f64 / speed) as u64;
+  if delay_ms == 0 {
+    None
+  } else {
+    Some(Duration::from_millis(delay_ms.min(MAX_DELAY_MS)))
+  }
+}
+
+/// A wrapping reader that replays messages in real time per `--replay-speed`,
+/// sleeping between messages according to the gaps between their timestamps
+///
+/// Messages without a timestamp are passed through immediately. Waiting stops
+/// early if anything is sent on `exit_rx`, e.g. once the renderer has quit.
+pub fn read_replay(
+  config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+  exit_rx: Receiver<()>
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_replay".to_string()).spawn(move || {
+    let speed = match config.replay_speed {
+      Some(speed) => speed,
+      None => bail!("read_replay requires --replay-speed")
+    };
+
+    let mut last: Option<DateTime<Utc>> = None;
+    for entry in rx {
+      if let Some(timestamp) = replay_timestamp(&entry) {
+        if let Some(last) = last {
+          if let Some(delay) = replay_delay(last, timestamp, speed) {
+            match exit_rx.recv_timeout(delay) {
+              Err(RecvTimeoutError::Timeout) => (),
+              _ => break
+            }
+          }
+        }
+
+        // out-of-order messages shouldn't rewind the clock
+        last = Some(last.map_or(timestamp, |last| last.max(timestamp)));
+      }
+
+      if tx.send(entry).is_err() {
+        break;
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::{channel, sync_channel};
+  use std::time::Instant;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::reader::read_ordered;
+
+  fn ts(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn test_replay_delay() {
+    let start = ts("2020-01-01T00:00:00Z");
+    let later = ts("2020-01-01T00:00:02Z");
+
+    assert_that!(replay_delay(start, later, 1.0))
+      .is_some().is_equal_to(Duration::from_secs(2));
+    assert_that!(replay_delay(start, later, 4.0))
+      .is_some().is_equal_to(Duration::from_millis(500));
+
+    // backwards and simultaneous messages aren't delayed
+    assert_that!(replay_delay(later, start, 1.0)).is_none();
+    assert_that!(replay_delay(start, start, 1.0)).is_none();
+
+    // long gaps are capped
+    let much_later = ts("2020-01-02T00:00:00Z");
+    assert_that!(replay_delay(start, much_later, 1.0))
+      .is_some().is_equal_to(Duration::from_millis(MAX_DELAY_MS));
+  }
+
+  #[test]
+  fn test_replay_ordered() {
+    let config = Arc::new(Config::from_iter_safe(vec![
+      "", "--ordered", "--replay-speed", "10"
+    ]).unwrap());
+
+    let (reader_tx, ordered_rx) = sync_channel(10);
+    let (ordered_tx, replay_rx) = sync_channel(10);
+    let (replay_tx, rx) = sync_channel(10);
+    let (_exit_tx, exit_rx) = channel();
+    read_ordered(Arc::clone(&config), ordered_rx, ordered_tx);
+    read_replay(Arc::clone(&config), replay_rx, replay_tx, exit_rx);
+
+    // out of order input is sorted before delays are computed
+    for (ms, text) in &[(300, "c"), (0, "a"), (200, "b")] {
+      let line = format!(
+        r#"{{"time":"2020-01-01T00:00:00.{:03}Z","msg":"{}"}}"#, ms, text
+      );
+      let entry = LogEntry::message(Arc::clone(&config), &line, None);
+      reader_tx.send(entry.unwrap().unwrap()).unwrap();
+    }
+    reader_tx.send(LogEntry::eof()).unwrap();
+
+    let start = Instant::now();
+    let texts: Vec<String> = rx.iter()
+      .filter_map(|entry| entry.message)
+      .filter(|m| m.message.kind != MessageKind::Internal)
+      .filter_map(|m| m.message.text)
+      .collect();
+
+    assert_that!(texts).is_equal_to(vec![
+      String::from("a"), String::from("b"), String::from("c")
+    ]);
+    assert_that!(start.elapsed() >= Duration::from_millis(30)).is_true();
+  }
+
+  #[test]
+  fn test_replay_exit() {
+    let config = Arc::new(
+      Config::from_iter_safe(vec!["", "--replay-speed", "1"]).unwrap()
+    );
+    let (in_tx, in_rx) = sync_channel(10);
+    let (out_tx, out_rx) = sync_channel(10);
+    let (exit_tx, exit_rx) = channel();
+
+    for line in &[
+      r#"{"time":"2020-01-01T00:00:00Z","msg":"first"}"#,
+      r#"{"time":"2020-01-01T00:00:10Z","msg":"second"}"#
+    ] {
+      let entry = LogEntry::message(Arc::clone(&config), line, None);
+      in_tx.send(entry.unwrap().unwrap()).unwrap();
+    }
+
+    let handle = read_replay(config, in_rx, out_tx, exit_rx);
+    assert_that!(out_rx.recv_timeout(Duration::from_secs(5))).is_ok();
+
+    // the stage is now waiting 10s for the second message, but stops early
+    let start = Instant::now();
+    exit_tx.send(()).unwrap();
+    handle.join().unwrap().unwrap();
+
+    assert_that!(start.elapsed() < Duration::from_secs(5)).is_true();
+    assert_that!(out_rx.try_recv()).is_err();
+  }
+}
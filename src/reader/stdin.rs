@@ -1,14 +1,18 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
-use std::io::{self, BufRead};
+use std::io;
 use std::sync::Arc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
 use std::thread::{self, JoinHandle};
 
 use simple_error::{SimpleError, SimpleResult};
 
 use crate::config::Config;
+use crate::parser::ReaderMetadata;
 use crate::renderer::LogEntry;
+use super::gzip::maybe_gunzip;
+use super::records::records;
+use super::tee::Tee;
 
 // TODO: if we want to surface errors, it might be best to send it as a message
 // over the tx channel
@@ -21,7 +25,7 @@ use crate::renderer::LogEntry;
 
 pub fn read_stdin(
   config: Arc<Config>,
-  tx: Sender<LogEntry>,
+  tx: SyncSender<LogEntry>,
   _exit_req_rx: Receiver<()>,
   _exit_resp_tx: Sender<()>
 ) -> JoinHandle<SimpleResult<()>> {
@@ -31,11 +35,17 @@ pub fn read_stdin(
 
   thread::Builder::new().name("read_stdin".to_string()).spawn(move || {
     let mut empty = true;
-    for line in io::stdin().lock().lines() {
+    let stdin = io::stdin();
+    let input = maybe_gunzip(stdin.lock()).map_err(SimpleError::from)?;
+    let lines = records(input, config.record_separator)
+      .tee(Tee::open(&config, &tx));
+    for (i, line) in lines.enumerate() {
       let line = line.map_err(SimpleError::from)?;
       empty = false;
 
-      match LogEntry::message(Arc::clone(&config), &line, None) {
+      match LogEntry::message(
+        Arc::clone(&config), &line, Some(ReaderMetadata::line(i + 1))
+      ) {
         Ok(Some(entry)) => match tx.send(entry) {
           Ok(_) => (),
           // assume receiver has quit and stop
@@ -48,6 +58,7 @@ pub fn read_stdin(
 
     if empty {
       tx.send(LogEntry::internal(
+        &config,
         "warning: reached end of input without reading any messages"
       )).ok();
     }
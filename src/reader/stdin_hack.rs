@@ -1,15 +1,18 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::sync::Arc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
 use std::thread::{self, JoinHandle};
 
 use simple_error::{SimpleError, SimpleResult};
 
 use crate::config::Config;
+use crate::parser::ReaderMetadata;
 use crate::renderer::LogEntry;
+use super::gzip::maybe_gunzip;
+use super::records::records;
+use super::tee::Tee;
 
 /// reads the process stdin directly using Evil Hacks to ensure our fd doesn't
 /// get closed when the interactive UI opens /dev/tty
@@ -19,19 +22,24 @@ use crate::renderer::LogEntry;
 /// their application via a subprocess or just fall back to the styled renderer
 pub fn read_stdin_hack(
   config: Arc<Config>,
-  tx: Sender<LogEntry>,
+  tx: SyncSender<LogEntry>,
   _exit_req_rx: Receiver<()>,
   _exit_resp_tx: Sender<()>
 ) -> JoinHandle<SimpleResult<()>> {
   thread::Builder::new().name("read_stdin_hack".to_string()).spawn(move || {
     let file = File::open("/dev/stdin").map_err(SimpleError::from)?;
+    let input = maybe_gunzip(file).map_err(SimpleError::from)?;
 
     let mut empty = true;
-    for line in BufReader::new(file).lines() {
+    let lines = records(input, config.record_separator)
+      .tee(Tee::open(&config, &tx));
+    for (i, line) in lines.enumerate() {
       let line = line.map_err(SimpleError::from)?;
       empty = false;
 
-      match LogEntry::message(Arc::clone(&config), &line, None) {
+      match LogEntry::message(
+        Arc::clone(&config), &line, Some(ReaderMetadata::line(i + 1))
+      ) {
         Ok(Some(entry)) => match tx.send(entry) {
           Ok(_) => (),
           Err(_) => break
@@ -43,6 +51,7 @@ pub fn read_stdin_hack(
 
     if empty {
       tx.send(LogEntry::internal(
+        &config,
         "warning: reached end of input without reading any messages"
       )).ok();
     }
@@ -0,0 +1,249 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use simple_error::{SimpleError, SimpleResult};
+use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
+
+use crate::config::Config;
+use crate::parser::{LogLevel, ReaderMetadata};
+use crate::renderer::LogEntry;
+use super::records::records;
+use super::tee::Tee;
+
+/// reads lines from one of the child's output streams until it closes
+///
+/// lines are parsed with the given minimum level, if any
+fn read_stream(
+  config: Arc<Config>,
+  stream: File,
+  min_level: Option<LogLevel>,
+  tx: SyncSender<LogEntry>
+) -> SimpleResult<()> {
+  let lines = records(BufReader::new(stream), config.record_separator)
+    .tee(Tee::open(&config, &tx));
+
+  for line in lines {
+    let line = match line {
+      Ok(line) => line,
+
+      // skip lines that aren't valid utf-8 rather than giving up on the rest
+      Err(ref e) if e.kind() == io::ErrorKind::InvalidData => continue,
+      Err(e) => return Err(SimpleError::from(e))
+    };
+
+    let meta = min_level.map(|level| ReaderMetadata {
+      min_level: Some(level),
+      ..Default::default()
+    });
+
+    match LogEntry::message(Arc::clone(&config), &line, meta) {
+      Ok(Some(entry)) => match tx.send(entry) {
+        Ok(_) => (),
+        Err(_) => break
+      },
+      _ => continue
+    };
+  }
+
+  Ok(())
+}
+
+fn describe_status(status: ExitStatus) -> String {
+  match status {
+    ExitStatus::Exited(code) => format!("exited with status {}", code),
+    ExitStatus::Signaled(signal) => format!("killed by signal {}", signal),
+    other => format!("exited: {:?}", other)
+  }
+}
+
+/// how long the child's lock is held at a time while waiting for it to exit,
+/// so the exit handler can still get in to kill it
+const WAIT_POLL_MS: u64 = 100;
+
+/// waits for the child to exit without holding its lock the whole time
+fn wait_child(child: &Mutex<Popen>) -> Option<ExitStatus> {
+  loop {
+    let status = match child.lock() {
+      Ok(mut child) => child.wait_timeout(Duration::from_millis(WAIT_POLL_MS)),
+      Err(_) => return None
+    };
+
+    match status {
+      Ok(Some(status)) => return Some(status),
+      Ok(None) => continue,
+      Err(_) => return None
+    }
+  }
+}
+
+/// runs the command given in `app` and reads its stdout and stderr
+///
+/// the streams are read separately so lines from stderr can be assigned a
+/// minimum level via `--stderr-level`
+pub fn read_subprocess(
+  config: Arc<Config>,
+  tx: SyncSender<LogEntry>,
+  exit_req_rx: Receiver<()>,
+  exit_resp_tx: Sender<()>
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_subprocess".to_string()).spawn(move || {
+    if config.app.is_empty() {
+      tx.send(LogEntry::internal(
+        &config,
+        "error: no command given, e.g. woodchipper -i subprocess -- make"
+      )).ok();
+      tx.send(LogEntry::eof()).ok();
+
+      return Ok(());
+    }
+
+    let mut child = match Popen::create(&config.app, PopenConfig {
+      stdin: Redirection::Pipe,
+      stdout: Redirection::Pipe,
+      stderr: Redirection::Pipe,
+
+      ..Default::default()
+    }) {
+      Ok(child) => child,
+      Err(e) => {
+        tx.send(LogEntry::internal(&config, &format!(
+          "error: could not start {}: {}", config.app[0], e
+        ))).ok();
+        tx.send(LogEntry::eof()).ok();
+
+        return Err(SimpleError::from(e));
+      }
+    };
+
+    // close stdin immediately, the child shouldn't compete with the
+    // interactive renderer for terminal input
+    child.stdin.take();
+
+    // if this fails something has gone very wrong with Redirection::Pipe
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    // the child is shared with the exit handler so it can be killed early
+    let child = Arc::new(Mutex::new(child));
+    let exit_child = Arc::clone(&child);
+    thread::Builder::new().name("read_subprocess_exit".to_string()).spawn(move || {
+      if exit_req_rx.recv().is_ok() {
+        if let Ok(mut child) = exit_child.lock() {
+          child.terminate().ok();
+        }
+
+        exit_resp_tx.send(()).ok();
+      }
+    }).unwrap();
+
+    let stderr_config = Arc::clone(&config);
+    let stderr_tx = tx.clone();
+    let stderr_level = config.stderr_level;
+    let stderr_handle = thread::Builder::new()
+      .name("read_subprocess_stderr".to_string())
+      .spawn(move || read_stream(stderr_config, stderr, stderr_level, stderr_tx))
+      .unwrap();
+
+    let result = read_stream(Arc::clone(&config), stdout, None, tx.clone());
+    stderr_handle.join().ok();
+
+    if let Some(status) = wait_child(&child) {
+      tx.send(LogEntry::internal(&config, &format!(
+        "note: {} {}", config.app[0], describe_status(status)
+      ))).ok();
+    }
+
+    // the renderer still needs to know the input is done, even on errors
+    tx.send(LogEntry::eof()).ok();
+
+    result
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::iter;
+  use std::sync::mpsc::{channel, sync_channel};
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn start(script: &str) -> (Receiver<LogEntry>, Sender<()>, Receiver<()>) {
+    let config = Arc::new(Config::from_iter_safe(vec![
+      "", "-i", "subprocess", "--", "sh", "-c", script
+    ]).unwrap());
+
+    let (tx, rx) = sync_channel(10);
+    let (exit_req_tx, exit_req_rx) = channel();
+    let (exit_resp_tx, exit_resp_rx) = channel();
+    read_subprocess(config, tx, exit_req_rx, exit_resp_tx);
+
+    (rx, exit_req_tx, exit_resp_rx)
+  }
+
+  /// collects message texts up to (but not including) eof, if it arrives
+  fn texts(rx: &Receiver<LogEntry>) -> (Vec<String>, bool) {
+    let mut eof = false;
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).ok();
+    let texts = iter::from_fn(recv)
+      .take_while(|entry| {
+        eof = entry.eof.is_some();
+        !eof
+      })
+      .filter_map(|entry| entry.message.and_then(|m| m.message.text))
+      .collect();
+
+    (texts, eof)
+  }
+
+  #[test]
+  fn test_read_subprocess() {
+    let (rx, _exit_req_tx, _exit_resp_rx) = start("echo hello; exit 3");
+    let (texts, eof) = texts(&rx);
+
+    assert_that!(texts).is_equal_to(vec![
+      String::from("hello"),
+      String::from("note: sh exited with status 3")
+    ]);
+    assert_that!(eof).is_true();
+  }
+
+  #[test]
+  fn test_read_subprocess_invalid_utf8() {
+    let (rx, _exit_req_tx, _exit_resp_rx) = start(r"printf 'a\n\377\nb\n'");
+    let (texts, eof) = texts(&rx);
+
+    assert_that!(texts).is_equal_to(vec![
+      String::from("a"),
+      String::from("b"),
+      String::from("note: sh exited with status 0")
+    ]);
+    assert_that!(eof).is_true();
+  }
+
+  #[test]
+  fn test_read_subprocess_exit() {
+    // exec, so terminating the child also closes its stdout
+    let (rx, exit_req_tx, exit_resp_rx) = start("echo started; exec sleep 30");
+
+    // wait until the child is running (and being waited on)
+    let first = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_that!(first.message.and_then(|m| m.message.text))
+      .is_equal_to(Some(String::from("started")));
+    thread::sleep(Duration::from_millis(200));
+
+    exit_req_tx.send(()).unwrap();
+    assert_that!(exit_resp_rx.recv_timeout(Duration::from_secs(5))).is_ok();
+
+    let (_, eof) = texts(&rx);
+    assert_that!(eof).is_true();
+  }
+}
@@ -0,0 +1,159 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use serde_json::{json, Value};
+use simple_error::SimpleResult;
+
+use crate::config::Config;
+use crate::parser::{LogLevel, MessageKind};
+use crate::renderer::LogEntry;
+
+/// count of messages seen at each level, used by `--fail-on-level`
+pub type LevelCounts = Arc<Mutex<BTreeMap<LogLevel, usize>>>;
+
+/// returns the level of a message, if it has a real level
+///
+/// internal notes aren't counted, but internal warnings and errors are, e.g. if
+/// a subprocess couldn't be started
+fn entry_level(entry: &LogEntry) -> Option<LogLevel> {
+  let message = &entry.message.as_ref()?.message;
+  if message.kind == MessageKind::Internal {
+    if let Some(level) = message.level {
+      if level < LogLevel::Warning {
+        return None;
+      }
+    }
+  }
+
+  // plain and internal levels sort above fatal but aren't severities
+  message.level.filter(|level| *level <= LogLevel::Fatal)
+}
+
+/// summarizes the messages seen at or above `threshold`, returning the number
+/// of matching messages and a JSON summary of all counts
+pub fn summarize(
+  counts: &BTreeMap<LogLevel, usize>, threshold: LogLevel
+) -> (usize, Value) {
+  let matched: usize = counts.iter()
+    .filter(|(level, _)| **level >= threshold)
+    .map(|(_, count)| count)
+    .sum();
+
+  let levels: serde_json::Map<String, Value> = counts.iter()
+    .map(|(level, count)| (level.to_string().to_lowercase(), json!(count)))
+    .collect();
+
+  let summary = json!({
+    "fail_on_level": threshold.to_string().to_lowercase(),
+    "matched": matched,
+    "levels": levels
+  });
+
+  (matched, summary)
+}
+
+/// A wrapping reader that counts messages by level into `counts` for
+/// `--fail-on-level`
+pub fn read_summary(
+  _config: Arc<Config>,
+  rx: Receiver<LogEntry>,
+  tx: SyncSender<LogEntry>,
+  counts: LevelCounts
+) -> JoinHandle<SimpleResult<()>> {
+  thread::Builder::new().name("read_summary".to_string()).spawn(move || {
+    for entry in rx {
+      if let Some(level) = entry_level(&entry) {
+        *counts.lock().unwrap().entry(level).or_insert(0) += 1;
+      }
+
+      if tx.send(entry).is_err() {
+        break;
+      }
+    }
+
+    Ok(())
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  fn config() -> Arc<Config> {
+    Arc::new(Config::from_iter_safe(vec![""]).unwrap())
+  }
+
+  fn level(line: &str) -> Option<LogLevel> {
+    let entry = LogEntry::message(config(), line, None).unwrap().unwrap();
+    entry_level(&entry)
+  }
+
+  #[test]
+  fn test_entry_level() {
+    assert_that!(level(r#"{"level":"error","msg":"x"}"#))
+      .is_equal_to(Some(LogLevel::Error));
+    assert_that!(level(r#"{"level":"debug","msg":"x"}"#))
+      .is_equal_to(Some(LogLevel::Debug));
+    assert_that!(level("plain text")).is_none();
+
+    let config = config();
+    let internal = |text: &str| entry_level(&LogEntry::internal(&config, text));
+    assert_that!(internal("note: done")).is_none();
+    assert_that!(internal("warning: slow")).is_equal_to(Some(LogLevel::Warning));
+    assert_that!(internal("error: failed")).is_equal_to(Some(LogLevel::Error));
+    assert_that!(entry_level(&LogEntry::eof())).is_none();
+  }
+
+  #[test]
+  fn test_summarize() {
+    let mut counts = BTreeMap::new();
+    counts.insert(LogLevel::Info, 3);
+    counts.insert(LogLevel::Warning, 2);
+    counts.insert(LogLevel::Error, 1);
+
+    let (matched, summary) = summarize(&counts, LogLevel::Warning);
+    assert_that!(matched).is_equal_to(3);
+    assert_that!(summary).is_equal_to(json!({
+      "fail_on_level": "warning",
+      "matched": 3,
+      "levels": { "info": 3, "warning": 2, "error": 1 }
+    }));
+
+    let (matched, _) = summarize(&counts, LogLevel::Fatal);
+    assert_that!(matched).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_read_summary() {
+    let config = config();
+    let (in_tx, in_rx) = sync_channel(10);
+    let (out_tx, out_rx) = sync_channel(10);
+    let counts = LevelCounts::default();
+
+    let handle = read_summary(
+      Arc::clone(&config), in_rx, out_tx, Arc::clone(&counts)
+    );
+
+    for line in &[r#"{"level":"error","msg":"a"}"#, r#"{"level":"info","msg":"b"}"#] {
+      let entry = LogEntry::message(Arc::clone(&config), line, None);
+      in_tx.send(entry.unwrap().unwrap()).unwrap();
+    }
+    drop(in_tx);
+    handle.join().unwrap().unwrap();
+
+    // everything is forwarded
+    assert_that!(out_rx.iter().count()).is_equal_to(2);
+
+    let (matched, _) = summarize(&counts.lock().unwrap(), LogLevel::Error);
+    assert_that!(matched).is_equal_to(1);
+  }
+}
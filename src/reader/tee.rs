@@ -0,0 +1,150 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::mpsc::SyncSender;
+
+use crate::config::Config;
+use crate::renderer::LogEntry;
+
+/// A file that raw input records are appended to, per `--tee`
+///
+/// Records are copied as they're split from the input, separators and all,
+/// so the file sees every line whether or not a parser claims it. Each reader
+/// opens its own handle in append mode and writes whole records at once, so
+/// records from concurrent readers (e.g. several pods) don't interleave.
+pub struct Tee {
+  config: Arc<Config>,
+  path: String,
+  file: File,
+
+  /// where write errors are reported
+  tx: SyncSender<LogEntry>
+}
+
+impl Tee {
+  /// opens the `--tee` file, if set; if it can't be opened, an error message
+  /// is sent to `tx` and nothing is written
+  pub fn open(config: &Arc<Config>, tx: &SyncSender<LogEntry>) -> Option<Tee> {
+    let path = config.tee.as_ref()?;
+
+    match OpenOptions::new().create(true).append(true).open(path) {
+      Ok(file) => Some(Tee {
+        config: Arc::clone(config),
+        path: path.clone(),
+        file,
+        tx: tx.clone()
+      }),
+      Err(e) => {
+        tx.send(LogEntry::internal(config, &format!(
+          "error: could not open tee file {}: {}", path, e
+        ))).ok();
+
+        None
+      }
+    }
+  }
+
+  /// appends a raw record, returning false (after reporting the error) if it
+  /// couldn't be written
+  pub fn write(&mut self, record: &[u8]) -> bool {
+    match self.file.write_all(record) {
+      Ok(()) => true,
+      Err(e) => {
+        self.tx.send(LogEntry::internal(&self.config, &format!(
+          "error: could not write to tee file {}: {}", self.path, e
+        ))).ok();
+
+        false
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env;
+  use std::fs;
+  use std::io::Cursor;
+  use std::process;
+  use std::sync::mpsc::sync_channel;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::config::RecordSeparator;
+  use crate::reader::records::records;
+
+  /// splits `input` into records with a tee attached, returning the records
+  /// and everything written to the tee file
+  fn tee_records(
+    name: &str, input: &[u8], separator: RecordSeparator
+  ) -> (Vec<String>, Vec<u8>) {
+    let (_config, lines, written) = tee_records_with(name, input, separator, &[]);
+    (lines, written)
+  }
+
+  fn tee_records_with(
+    name: &str, input: &[u8], separator: RecordSeparator, args: &[&str]
+  ) -> (Arc<Config>, Vec<String>, Vec<u8>) {
+    let path = env::temp_dir().join(
+      format!("woodchipper-tee-{}-{}.log", name, process::id())
+    );
+    fs::remove_file(&path).ok();
+
+    let mut config_args = vec!["", "--tee", path.to_str().unwrap()];
+    config_args.extend(args);
+    let config = Arc::new(Config::from_iter_safe(config_args).unwrap());
+
+    let (tx, _rx) = sync_channel(10);
+    let lines: Vec<String> = records(Cursor::new(input.to_vec()), separator)
+      .tee(Tee::open(&config, &tx))
+      .map(Result::unwrap)
+      .collect();
+
+    let written = fs::read(&path).unwrap();
+    fs::remove_file(&path).ok();
+
+    (config, lines, written)
+  }
+
+  #[test]
+  fn test_tee_exact() {
+    // blank lines, whitespace and crlf endings are kept as-is, as is a final
+    // line without a newline
+    let input = b"{\"msg\":\"hello\"}\r\n\n  \nplain text\t\nno newline";
+    let (lines, written) = tee_records("exact", input, RecordSeparator::Lf);
+
+    assert_that!(lines.len()).is_equal_to(5);
+    assert_that!(written).is_equal_to(input.to_vec());
+  }
+
+  #[test]
+  fn test_tee_unclaimed() {
+    // with only the json parser, nothing claims the plain line, but it's
+    // still teed
+    let input = b"{\"msg\":\"hello\"}\nnot json\n";
+    let (config, lines, written) = tee_records_with(
+      "unclaimed", input, RecordSeparator::Lf, &["--parsers", "json"]
+    );
+
+    let parsed = LogEntry::message(config, &lines[1], None).unwrap();
+    assert_that!(parsed.is_none()).is_true();
+
+    assert_that!(written).is_equal_to(input.to_vec());
+  }
+
+  #[test]
+  fn test_tee_nul() {
+    let input = b"one\0two\nlines\0";
+    let (lines, written) = tee_records("nul", input, RecordSeparator::Nul);
+
+    assert_that!(lines).is_equal_to(vec![
+      String::from("one"), String::from("two\nlines")
+    ]);
+    assert_that!(written).is_equal_to(input.to_vec());
+  }
+}
@@ -1,16 +1,24 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::sync::Arc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
 use std::thread::JoinHandle;
 
-use simple_error::SimpleError;
+use simple_error::{SimpleError, SimpleResult};
 
 use crate::config::Config;
 use crate::renderer::LogEntry;
 
 pub type Reader = fn(
-  config: Arc<Config>, tx: Sender<LogEntry>,
+  config: Arc<Config>, tx: SyncSender<LogEntry>,
   exit_req_rx: Receiver<()>, exit_resp_tx: Sender<()>
 ) -> JoinHandle<Result<(), SimpleError>>;
 
+/// An intermediate pipeline stage that sits between a reader and the renderer,
+/// consuming entries from `rx` and forwarding (some of) them to `tx`.
+///
+/// Boxed so stages can capture state, e.g. the level counts kept for
+/// `--fail-on-level`.
+pub type Stage = Box<dyn FnOnce(
+  Arc<Config>, Receiver<LogEntry>, SyncSender<LogEntry>
+) -> JoinHandle<SimpleResult<()>>>;
@@ -1,18 +1,69 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
-use std::collections::VecDeque;
-use std::cmp::max;
-
+use std::borrow::Cow;
+use std::collections::{BTreeSet, VecDeque};
+use std::cmp::{max, min};
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use ansi_term::{ANSIStrings, Style};
+use chrono::{NaiveDate, TimeZone, Utc};
 use textwrap::{Wrapper, NoHyphenation};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+use crate::config::{Config, DisplayTimezone};
 use crate::style::StyleProfile;
 use crate::classifier::{
-  Chunk, ChunkKind, ChunkSlot, ChunkAlignment, ChunkWeight
+  relative_time, strip_common, Chunk, ChunkKind, ChunkSlot, ChunkAlignment,
+  ChunkWeight
 };
-use crate::renderer::MessageEntry;
+use crate::renderer::{LogEntry, MessageEntry};
+use crate::renderer::glyphs::glyphs;
 
 #[cfg(test)] use spectral::prelude::*;
 
+/// width reserved for dates, per `--date-format`
+static DATE_WIDTH: AtomicUsize = AtomicUsize::new(10);
+
+/// width reserved for times, per `--time-format` or `--precision`
+static TIME_WIDTH: AtomicUsize = AtomicUsize::new(8);
+
+/// if set, times are relative to now and so are refreshed on each render
+static RELATIVE_TIME: AtomicBool = AtomicBool::new(false);
+
+/// width reserved for relative times, e.g. `-59m` or `-364d`; only times over
+/// 999 years away are wider
+const RELATIVE_TIME_WIDTH: usize = 5;
+
+/// measures the display width of timestamps in the given format
+fn format_width(timezone: DisplayTimezone, format: &str) -> usize {
+  // late in the year and day, so e.g. `%B` and `%A` give their longest names
+  let sample = NaiveDate::from_ymd_opt(2020, 9, 30)
+    .and_then(|date| date.and_hms_nano_opt(23, 59, 59, 999_999_999))
+    .unwrap();
+
+  display_width(&timezone.format(Utc.from_utc_datetime(&sample), format))
+}
+
+/// configures how timestamps are rendered, measuring the widths reserved for
+/// dates and times by formatting a sample timestamp; should be called once at
+/// startup
+pub fn set_timestamp_format(config: &Config) {
+  RELATIVE_TIME.store(config.relative_time, Ordering::Relaxed);
+
+  let timezone = config.timezone;
+  let date_width = format_width(timezone, &config.date_format);
+  let time_width = if config.relative_time {
+    RELATIVE_TIME_WIDTH
+  } else {
+    format_width(timezone, config.time_format())
+  };
+
+  DATE_WIDTH.store(date_width, Ordering::Relaxed);
+  TIME_WIDTH.store(time_width, Ordering::Relaxed);
+}
+
 #[derive(Debug, Clone)]
 pub struct RenderedChunk {
   /// content of this chunk, potentially styled
@@ -55,7 +106,7 @@ impl RenderedChunk {
   }
 
   pub fn spacer(width: usize, profile: &StyleProfile) -> Self {
-    let mut space = format!("{:w$}", "", w=width);
+    let mut space = " ".repeat(width);
     if profile.is_opaque() {
       space = profile.get_base().paint(space).to_string(); 
     }
@@ -344,17 +395,100 @@ pub fn right_pad_chunk(
 
 pub fn fixed_width(kind: ChunkKind) -> Option<usize> {
   match kind {
-    ChunkKind::Date => Some(10),
-    ChunkKind::Time => Some(8),
-    ChunkKind::Level(_) => Some(7),
+    ChunkKind::Date => Some(DATE_WIDTH.load(Ordering::Relaxed)),
+    ChunkKind::Time => Some(TIME_WIDTH.load(Ordering::Relaxed)),
+    ChunkKind::Level(_) => Some(8),
+    ChunkKind::LevelBadge(_) => Some(1),
     _ => None
   }
 }
 
+/// returns the number of terminal columns needed to display `s`, e.g. 2 for
+/// each CJK character and 0 for combining marks
+pub fn display_width(s: &str) -> usize {
+  UnicodeWidthStr::width(s)
+}
+
+/// a piece of a styled line
+#[derive(Debug)]
+enum StyledPart<'a> {
+  /// an ANSI escape sequence, which takes up no space
+  Escape(&'a str),
+
+  /// a visible character
+  Char(char)
+}
+
+/// splits a line that may contain ANSI escapes into escapes and characters
+fn styled_parts(line: &str) -> Vec<StyledPart<'_>> {
+  let mut parts = Vec::new();
+
+  let mut chars = line.char_indices().peekable();
+  while let Some((start, c)) = chars.next() {
+    if c != '\x1b' || chars.peek().map(|(_, c)| *c) != Some('[') {
+      parts.push(StyledPart::Char(c));
+      continue;
+    }
+
+    // CSI sequences run until a final byte in `@`..`~`
+    chars.next();
+    let mut end = line.len();
+    for (i, c) in chars.by_ref() {
+      if ('@'..='~').contains(&c) {
+        end = i + c.len_utf8();
+        break;
+      }
+    }
+
+    parts.push(StyledPart::Escape(&line[start..end]));
+  }
+
+  parts
+}
+
+/// returns the display width of a line, ignoring any ANSI escapes
+pub fn styled_width(line: &str) -> usize {
+  styled_parts(line).iter()
+    .map(|part| match part {
+      StyledPart::Char(c) => UnicodeWidthChar::width(*c).unwrap_or(0),
+      StyledPart::Escape(_) => 0
+    })
+    .sum()
+}
+
+/// returns the `width` display columns of a line starting at column `offset`,
+/// keeping any ANSI escapes so styles still apply
+///
+/// wide characters that would straddle either edge are dropped
+pub fn slice_columns(line: &str, offset: usize, width: usize) -> String {
+  let mut ret = String::with_capacity(line.len());
+  let mut column = 0;
+
+  for part in styled_parts(line) {
+    match part {
+      StyledPart::Escape(escape) => ret.push_str(escape),
+      StyledPart::Char(c) => {
+        let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+        if column >= offset && column + char_width <= offset + width {
+          ret.push(c);
+        }
+
+        column += char_width;
+      }
+    }
+  }
+
+  ret
+}
+
+/// pads `content` to `width` columns; unlike `format!()`'s padding this
+/// accounts for wide characters
 pub fn align(content: &str, width: usize, alignment: ChunkAlignment) -> String {
+  let padding = " ".repeat(width.saturating_sub(display_width(content)));
+
   match alignment {
-    ChunkAlignment::Left => format!("{:<width$}", content, width=width),
-    ChunkAlignment::Right => format!("{:>width$}", content, width=width)
+    ChunkAlignment::Left => format!("{}{}", content, padding),
+    ChunkAlignment::Right => format!("{}{}", padding, content)
   }
 }
 
@@ -379,6 +513,49 @@ where
   (left, center, right)
 }
 
+/// clips highlighted byte ranges to the line spanning `start..end` of its
+/// chunk, relative to the start of the line
+fn line_highlights(
+  highlights: &[(usize, usize)], start: usize, end: usize
+) -> Vec<(usize, usize)> {
+  highlights.iter()
+    .map(|(s, e)| (max(*s, start), min(*e, end)))
+    .filter(|(s, e)| s < e)
+    .map(|(s, e)| (s - start, e - start))
+    .collect()
+}
+
+/// paints `content`, inverting the given (sorted) byte ranges
+fn paint_highlighted(
+  content: &str, style: Style, highlights: &[(usize, usize)]
+) -> String {
+  if highlights.is_empty() {
+    return style.paint(content).to_string();
+  }
+
+  let emphasized = style.reverse();
+  let mut strings = Vec::new();
+  let mut last = 0;
+  for (start, end) in highlights {
+    if *end <= last {
+      continue;
+    }
+
+    if *start > last {
+      strings.push(style.paint(&content[last..*start]));
+    }
+
+    strings.push(emphasized.paint(&content[max(*start, last)..*end]));
+    last = *end;
+  }
+
+  if last < content.len() {
+    strings.push(style.paint(&content[last..]));
+  }
+
+  ANSIStrings(&strings).to_string()
+}
+
 /// renders a single chunk into one or more RenderedChunk
 /// these chunks are semantically intended to appear on one line, but may be
 /// wrapped later if necessary
@@ -389,7 +566,7 @@ fn styled_render_chunk(
   chunk: &Chunk,
   profile: &StyleProfile, wrap_width: Option<usize>
 ) -> Vec<RenderedChunk> {
-  let chunk_style = profile.get_style(&chunk.kind);
+  let chunk_style = profile.get_chunk_style(chunk);
 
   let mut rendered_chunks = Vec::new();
   
@@ -405,17 +582,42 @@ fn styled_render_chunk(
       None => vec![value.clone()]
     };
 
+    // byte offset of the current wrapped line within `value`
+    let mut offset = 0;
+
     for wrapped_line in wrapped {
+      // wrapping may trim whitespace, so find where this line actually starts
+      let line_start = value[offset..].find(wrapped_line.as_str())
+        .map(|i| offset + i);
+      if let Some(line_start) = line_start {
+        offset = line_start + wrapped_line.len();
+      }
+
       // TODO: decide if we should apply fixed width to all wrapped lines
-      let content = if let Some(fixed_width) = fixed_width(chunk.kind) {
-        align(&wrapped_line, fixed_width, chunk.alignment)
+      let fixed = fixed_width(chunk.kind);
+      let (content, painted) = if let Some(fixed_width) = fixed {
+        let content = align(&wrapped_line, fixed_width, chunk.alignment);
+        let painted = chunk_style.paint(content.as_str()).to_string();
+
+        (content, painted)
       } else {
-        wrapped_line
+        let highlights = match line_start {
+          Some(start) => line_highlights(
+            &chunk.highlights, start, start + wrapped_line.len()
+          ),
+          None => Vec::new()
+        };
+
+        let painted = paint_highlighted(
+          &wrapped_line, *chunk_style, &highlights
+        );
+
+        (wrapped_line, painted)
       };
 
-      let length = content.chars().count();
+      let length = display_width(&content);
       rendered_chunks.push(RenderedChunk {
-        content: chunk_style.paint(content).to_string(),
+        content: painted,
         width: length,
         pad_left: chunk.pad_left,
         pad_right: chunk.pad_right,
@@ -479,47 +681,191 @@ fn prune_level(wrap_width: Option<usize>) -> ChunkWeight {
   ChunkWeight::Low
 }
 
+/// creates a badge chunk showing how many times a message was repeated
+fn repeat_chunk(count: usize) -> Chunk {
+  Chunk {
+    kind: ChunkKind::Repeat,
+    slot: ChunkSlot::Right,
+    value: Some(format!("{}{}", glyphs().repeat, count)),
+    weight: ChunkWeight::High.value(),
+
+    pad_left: true,
+    pad_right: true,
+    alignment: ChunkAlignment::Right,
+    force_break_after: true,
+
+    ..Default::default()
+  }
+}
+
+/// the displayed source of an entry, if any
+pub fn source_label(entry: &MessageEntry) -> Option<&str> {
+  entry.chunks.iter()
+    .find(|chunk| chunk.kind == ChunkKind::Source)
+    .and_then(|chunk| chunk.value.as_ref())
+    .map(String::as_str)
+}
+
+/// strips the prefix and suffix shared by all `labels` (per `source_label`)
+/// from an entry's displayed source, for `--strip-source-common`
+///
+/// the labels seen so far are kept by each renderer, so sources are only
+/// shortened as much as the messages received so far allow
+pub fn strip_source_common<'a>(
+  entry: &'a MessageEntry, labels: &BTreeSet<String>
+) -> Cow<'a, MessageEntry> {
+  let label = match source_label(entry) {
+    Some(label) => label,
+    None => return Cow::Borrowed(entry)
+  };
+
+  let stripped = strip_common(labels, label);
+  if stripped.len() == label.len() {
+    return Cow::Borrowed(entry);
+  }
+
+  let stripped = stripped.to_string();
+  let mut entry = entry.clone();
+  for chunk in entry.chunks.iter_mut() {
+    if chunk.kind == ChunkKind::Source {
+      chunk.value = Some(stripped);
+      break;
+    }
+  }
+
+  Cow::Owned(entry)
+}
+
+/// assigns byte ranges within the message text to the text chunks containing
+/// them, relative to each chunk's value
+///
+/// text chunks may be split by line or around quoted strings, so each chunk's
+/// position is found by searching the text in order
+fn chunk_highlights(
+  text: &str, chunks: &[Chunk], highlights: &[(usize, usize)]
+) -> Vec<Option<Vec<(usize, usize)>>> {
+  let mut offset = 0;
+
+  chunks.iter()
+    .map(|chunk| match (&chunk.value, chunk.kind) {
+      (Some(value), ChunkKind::Text) | (Some(value), ChunkKind::Quoted) => {
+        let start = offset + text[offset..].find(value.as_str())?;
+        offset = start + value.len();
+
+        Some(line_highlights(highlights, start, offset))
+          .filter(|h| !h.is_empty())
+      },
+      _ => None
+    })
+    .collect()
+}
+
 /// renders a MessageEntry into a list of strings wrapped to fit `width`
 pub fn styled_render(
   entry: &MessageEntry, profile: &StyleProfile, wrap_width: Option<usize>
+) -> Vec<String> {
+  styled_render_highlighted(entry, profile, wrap_width, &[])
+}
+
+/// renders a MessageEntry like `styled_render`, additionally inverting the
+/// given byte ranges of the message text, e.g. search matches
+pub fn styled_render_highlighted(
+  entry: &MessageEntry, profile: &StyleProfile, wrap_width: Option<usize>,
+  highlights: &[(usize, usize)]
 ) -> Vec<String> {
   // TODO: if wrapping is disabled, use measure_chunks before splitting
   // into buckets to prune fields based on weight
-  // for now, just skip rendering the right column if wrapping is disabled
   // TODO: allow left and right columns to wrap as well?
   let min_weight = prune_level(wrap_width).value();
 
-  let (left, center, right) = bucketize(entry.chunks.iter());
+  // the repeat count changes as duplicates arrive, so it's added at render
+  // time rather than by a classifier
+  let repeat = match entry.repeat.get() {
+    count if count > 1 => Some(repeat_chunk(count)),
+    _ => None
+  };
+
+  // similarly, relative times change as the message ages
+  let relative_time = if RELATIVE_TIME.load(Ordering::Relaxed) {
+    Some(relative_time(&entry.message, Utc::now()))
+  } else {
+    None
+  };
+
+  let text_highlights = match &entry.message.text {
+    Some(text) if !highlights.is_empty() => {
+      chunk_highlights(text, &entry.chunks, highlights)
+    },
+    _ => Vec::new()
+  };
+
+  // chunks are only copied if they need changes
+  let modified = relative_time.is_some() || !text_highlights.is_empty();
+  let entry_chunks: Vec<Cow<Chunk>> = if modified {
+    entry.chunks.iter()
+      .enumerate()
+      .map(|(i, chunk)| match &relative_time {
+        Some(time) if chunk.kind == ChunkKind::Time => Cow::Owned(Chunk {
+          value: Some(time.clone()),
+          ..chunk.clone()
+        }),
+        _ => match text_highlights.get(i) {
+          Some(Some(highlights)) => Cow::Owned(Chunk {
+            highlights: highlights.clone(),
+            ..chunk.clone()
+          }),
+          _ => Cow::Borrowed(chunk)
+        }
+      })
+      .collect()
+  } else {
+    Vec::new()
+  };
+
+  let (left, center, right) = if modified {
+    bucketize(repeat.iter().chain(entry_chunks.iter().map(Cow::as_ref)))
+  } else {
+    bucketize(repeat.iter().chain(entry.chunks.iter()))
+  };
   let right_is_empty = right.is_empty();
-  let left_rendered = styled_render_region(
+  let mut left_rendered = styled_render_region(
     prune(left, min_weight), profile, None
   );
-  let left_width = largest_chunk(&left_rendered);
+  let mut left_width = largest_chunk(&left_rendered);
+
+  // on really narrow terminals there may be no room left for the message, so
+  // drop the left column entirely and show only the center
+  if let Some(wrap_width) = wrap_width {
+    if left_width + 1 >= wrap_width {
+      left_rendered.clear();
+      left_width = 0;
+    }
+  }
+
   let right_rendered = styled_render_region(
     prune(right, min_weight), profile, None
   );
   let right_width = largest_chunk(&right_rendered);
   
-  let center_width = match wrap_width {
-    Some(wrap_width) => 
-      if right_is_empty || left_width + right_width + 2 > wrap_width {
-        // not enough room for the right side
-        // TODO: this can still overflow and panic for really tiny widths
-        wrap_width - left_width - 1
-      } else {
-        // we can render all 3 columns
-        wrap_width - left_width - right_width - 2
-      },
-    
-    // don't render the right column if wrapping is disabled
-    // TODO: reevaluate this in the future
-    None => 0
-  };
+  let center_width = wrap_width.map(|wrap_width| {
+    if right_is_empty || left_width + right_width + 2 > wrap_width {
+      // not enough room for the right side
+      wrap_width.saturating_sub(left_width + 1)
+    } else {
+      // we can render all 3 columns
+      wrap_width - left_width - right_width - 2
+    }
+  });
 
   let center_rendered = styled_render_region(
-    prune(center, min_weight), profile, Some(center_width)
+    prune(center, min_weight), profile, center_width
   );
 
+  // if wrapping is disabled, the center is as wide as its longest line and the
+  // right column follows it
+  let center_width = center_width
+    .unwrap_or_else(|| largest_chunk(&center_rendered));
+
   let left_spacer = RenderedChunk::spacer(left_width, profile);
   let center_spacer = RenderedChunk::spacer(center_width, profile);
   let right_spacer = RenderedChunk::spacer(right_width, profile);
@@ -553,10 +899,86 @@ pub fn styled_render(
   ret
 }
 
+fn write_entries_int<W, F>(
+  out: &mut W, rx: Receiver<LogEntry>, mut render: F
+) -> io::Result<()>
+where
+  W: Write,
+  F: FnMut(&MessageEntry) -> Vec<String>
+{
+  loop {
+    // flush only once caught up, so bursts of input are written in batches
+    let entry = match rx.try_recv() {
+      Ok(entry) => entry,
+      Err(TryRecvError::Empty) => {
+        out.flush()?;
+
+        match rx.recv() {
+          Ok(entry) => entry,
+          Err(_) => break
+        }
+      },
+      Err(TryRecvError::Disconnected) => break
+    };
+
+    if let Some(message) = &entry.message {
+      for line in render(message) {
+        writeln!(out, "{}", line)?;
+      }
+    }
+
+    if entry.eof.is_some() {
+      break;
+    }
+  }
+
+  out.flush()
+}
+
+/// writes the lines rendered for each entry to `out` until eof, buffering
+/// output and flushing whenever no more entries are waiting
+///
+/// if the output is closed early, e.g. when piped to `head`, this stops
+/// quietly rather than panicking like `println!` would
+/// see also: https://github.com/rust-lang/rust/issues/24821
+pub fn write_entries<W, F>(
+  out: W, rx: Receiver<LogEntry>, render: F
+) -> io::Result<()>
+where
+  W: Write,
+  F: FnMut(&MessageEntry) -> Vec<String>
+{
+  let mut out = BufWriter::new(out);
+
+  match write_entries_int(&mut out, rx, render) {
+    Err(ref e) if is_closed(e) => Ok(()),
+    result => result
+  }
+}
+
+/// true if a write failed because the reader went away
+fn is_closed(error: &io::Error) -> bool {
+  matches!(
+    error.kind(),
+    io::ErrorKind::BrokenPipe | io::ErrorKind::WriteZero
+  )
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  use std::cell::Cell;
+  use std::sync::Arc;
+
+  use structopt::StructOpt;
+
+  use ansi_term::{Color, Style};
+
+  use crate::classifier::{classify, ValueKind};
+  use crate::config::Config;
+  use crate::parser::parse;
+
   fn spacers(count: usize) -> Vec<RenderedChunk> {
     let normal = StyleProfile::default_normal();
 
@@ -633,7 +1055,7 @@ mod tests {
           ..Default::default()
         },
         Chunk {
-          kind: ChunkKind::FieldValue,
+          kind: ChunkKind::FieldValue(ValueKind::String),
           slot: ChunkSlot::Left,
           pad_right: true,
           weight: 0,
@@ -780,4 +1202,255 @@ mod tests {
     assert_that!(measure_chunks(&get_message(&normal))).is_equal_to(29);
     assert_that!(measure_chunks(&get_message(&selected))).is_equal_to(29);
   }
+
+  #[test]
+  fn test_styled_render_narrow() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"time":"2020-01-01T00:00:00Z","msg":"hello world"}"#;
+    let message = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+    let chunks = classify(&config, &message);
+    let entry = MessageEntry { message, chunks, repeat: Cell::new(1) };
+
+    let normal = StyleProfile::default_normal();
+    for width in 0..12 {
+      let rendered = styled_render(&entry, &normal, Some(width));
+      assert_that!(rendered.is_empty()).is_false();
+    }
+
+    // the date column doesn't fit, so only the wrapped text is shown
+    let rendered = styled_render(&entry, &normal, Some(5)).join("\n");
+    assert_that!(rendered).contains("hell");
+    assert_that!(rendered.contains("2020")).is_false();
+  }
+
+  #[test]
+  fn test_styled_render_repeat() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"time":"2020-01-01T00:00:00Z","msg":"hello world"}"#;
+    let message = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+    let chunks = classify(&config, &message);
+    let entry = MessageEntry { message, chunks, repeat: Cell::new(1) };
+
+    let normal = StyleProfile::default_normal();
+    let badge = format!("{}3", glyphs().repeat);
+
+    let rendered = styled_render(&entry, &normal, Some(80)).join("\n");
+    assert_that!(rendered.contains(&badge)).is_false();
+
+    entry.repeat.set(3);
+    let rendered = styled_render(&entry, &normal, Some(80)).join("\n");
+    assert_that!(rendered).contains(badge.as_str());
+  }
+
+  #[test]
+  fn test_strip_source_common() {
+    use crate::parser::ReaderMetadata;
+
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let entry = |source: &str| {
+      let mut message = parse(Arc::clone(&config), "hello", None)
+        .unwrap().unwrap();
+      message.reader_metadata = Some(ReaderMetadata {
+        source: Some(source.to_string()),
+        ..Default::default()
+      });
+
+      let chunks = classify(&config, &message);
+      MessageEntry { message, chunks, repeat: Cell::new(1) }
+    };
+
+    let first = entry("default/web-1/nginx");
+    let second = entry("default/web-2/nginx");
+
+    // the classifier leaves sources alone, regardless of what arrived first
+    assert_that!(source_label(&first)).is_equal_to(Some("default/web-1/nginx"));
+
+    let mut labels = BTreeSet::new();
+    labels.insert(String::from("default/web-1/nginx"));
+    let stripped = strip_source_common(&first, &labels);
+    assert_that!(source_label(&stripped)).is_equal_to(Some("default/web-1/nginx"));
+
+    // once another source arrives, both are shortened at render time
+    labels.insert(String::from("default/web-2/nginx"));
+    let stripped = strip_source_common(&first, &labels);
+    assert_that!(source_label(&stripped)).is_equal_to(Some("web-1"));
+    let stripped = strip_source_common(&second, &labels);
+    assert_that!(source_label(&stripped)).is_equal_to(Some("web-2"));
+  }
+
+  #[test]
+  fn test_paint_highlighted() {
+    let style = Style::new().fg(Color::Red);
+    let plain = style.paint("hello world").to_string();
+    assert_that!(paint_highlighted("hello world", style, &[]))
+      .is_equal_to(plain);
+
+    let expected = ANSIStrings(&[
+      style.paint("hello "),
+      style.reverse().paint("wor"),
+      style.paint("ld")
+    ]).to_string();
+    assert_that!(paint_highlighted("hello world", style, &[(6, 9)]))
+      .is_equal_to(expected);
+
+    assert_that!(line_highlights(&[(2, 4), (6, 12)], 5, 10))
+      .is_equal_to(vec![(1, 5)]);
+  }
+
+  #[test]
+  fn test_styled_render_highlighted() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"msg":"first line\nsay \"hello\" world"}"#;
+    let message = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+    let chunks = classify(&config, &message);
+    let entry = MessageEntry { message, chunks, repeat: Cell::new(1) };
+
+    // "line" in the first text chunk and "hello" within the quoted chunk
+    let highlights = vec![(6, 10), (16, 21)];
+    let offsets = chunk_highlights(
+      entry.message.text.as_ref().unwrap(), &entry.chunks, &highlights
+    );
+    let highlighted: Vec<_> = offsets.into_iter().flatten().collect();
+    assert_that!(highlighted).is_equal_to(vec![vec![(6, 10)], vec![(1, 6)]]);
+
+    let normal = StyleProfile::default_normal();
+    let reversed = normal.get_base().reverse().paint("line").to_string();
+    let plain = styled_render(&entry, &normal, Some(80)).join("\n");
+    assert_that!(plain.contains(&reversed)).is_false();
+
+    let rendered = styled_render_highlighted(
+      &entry, &normal, Some(80), &highlights
+    ).join("\n");
+    assert_that!(rendered.contains(&reversed)).is_true();
+  }
+
+  #[test]
+  fn test_format_width() {
+    let utc = DisplayTimezone::Utc;
+    assert_that!(format_width(utc, "%Y-%m-%d")).is_equal_to(10);
+    assert_that!(format_width(utc, "%H:%M:%S")).is_equal_to(8);
+    assert_that!(format_width(utc, "%H:%M:%S%.3f")).is_equal_to(12);
+    assert_that!(format_width(utc, "%H:%M:%S%.6f")).is_equal_to(15);
+    assert_that!(format_width(utc, "%H:%M:%S%.9f")).is_equal_to(18);
+    assert_that!(format_width(utc, "%A %B %e")).is_equal_to(22);
+    assert_that!(format_width(utc, "")).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_display_width() {
+    assert_that!(display_width("hello")).is_equal_to(5);
+    assert_that!(display_width("日本語")).is_equal_to(6);
+    assert_that!(display_width("🇯🇵")).is_equal_to(2);
+    assert_that!(display_width("e\u{301}")).is_equal_to(1);
+  }
+
+  #[test]
+  fn test_styled_width() {
+    let styled = Style::new().fg(Color::Red).paint("日本 ok").to_string();
+    assert_that!(styled_width(&styled)).is_equal_to(7);
+    assert_that!(styled_width("plain")).is_equal_to(5);
+  }
+
+  #[test]
+  fn test_slice_columns() {
+    assert_that!(slice_columns("hello world", 0, 5))
+      .is_equal_to(String::from("hello"));
+    assert_that!(slice_columns("hello world", 6, 20))
+      .is_equal_to(String::from("world"));
+    assert_that!(slice_columns("hello", 10, 5)).is_equal_to(String::new());
+
+    // escapes are kept even if their text is scrolled out of view
+    let red = Style::new().fg(Color::Red);
+    let styled = format!("{} {}", red.paint("abc"), red.paint("def"));
+    assert_that!(slice_columns(&styled, 2, 3)).is_equal_to(format!(
+      "{} {}", red.paint("c"), red.paint("d")
+    ));
+
+    // wide characters on the edge are dropped rather than split
+    assert_that!(slice_columns("日本語", 1, 4)).is_equal_to(String::from("本"));
+  }
+
+  #[test]
+  fn test_align_wide() {
+    assert_that!(align("日本語", 8, ChunkAlignment::Left))
+      .is_equal_to("日本語  ".to_string());
+    assert_that!(align("🇯🇵", 4, ChunkAlignment::Right))
+      .is_equal_to("  🇯🇵".to_string());
+    assert_that!(align("日本語", 4, ChunkAlignment::Left))
+      .is_equal_to("日本語".to_string());
+  }
+
+  #[test]
+  fn test_render_wide() {
+    let normal = StyleProfile::default_normal();
+    let chunk = get_text_chunk("日本語 🇯🇵");
+    let rendered = styled_render_chunk(&chunk, &normal, None);
+    assert_that!(rendered[0].width).is_equal_to(9);
+  }
+
+  /// a writer whose reader has gone away after accepting `remaining` bytes
+  struct ClosedWriter {
+    remaining: usize
+  }
+
+  impl Write for ClosedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      if self.remaining == 0 {
+        return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+      }
+
+      let len = min(buf.len(), self.remaining);
+      self.remaining -= len;
+      Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  fn entries(texts: &[&str]) -> Receiver<LogEntry> {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    for text in texts {
+      tx.send(LogEntry {
+        message: Some(MessageEntry::internal(&config, text)),
+        eof: None
+      }).unwrap();
+    }
+    tx.send(LogEntry::eof()).unwrap();
+
+    rx
+  }
+
+  fn text(entry: &MessageEntry) -> Vec<String> {
+    vec![entry.message.text.clone().unwrap()]
+  }
+
+  #[test]
+  fn test_write_entries() {
+    let mut out = Vec::new();
+    let result = write_entries(&mut out, entries(&["a", "b", "c"]), text);
+
+    assert_that!(result).is_ok();
+    assert_that!(String::from_utf8(out).unwrap())
+      .is_equal_to(String::from("a\nb\nc\n"));
+  }
+
+  #[test]
+  fn test_write_entries_closed() {
+    // e.g. `woodchipper -r plain | head -c 10`
+    let lines = vec!["hello world"; 10000];
+    let out = ClosedWriter { remaining: 10 };
+
+    let mut rendered = 0;
+    let result = write_entries(out, entries(&lines), |entry| {
+      rendered += 1;
+      text(entry)
+    });
+
+    // stops early without reporting an error
+    assert_that!(result).is_ok();
+    assert_that!(rendered).is_less_than(10000);
+  }
 }
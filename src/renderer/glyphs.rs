@@ -0,0 +1,76 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// if set, only ASCII glyphs are used, per `--ascii`
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Decorative characters used by the renderers
+///
+/// New non-ASCII glyphs should be added here, with an ASCII equivalent, so
+/// `--ascii` covers them.
+pub struct Glyphs {
+  /// prefix for the repeat count badge, e.g. `×3`
+  pub repeat: &'static str,
+
+  /// horizontal rule, e.g. for group headers
+  pub rule: char,
+
+  /// marks elided text, e.g. with `--value-max-width`
+  pub ellipsis: &'static str,
+
+  /// gutter mark for unparsed messages, per `--mark-unparsed`
+  pub unparsed: &'static str
+}
+
+static UNICODE_GLYPHS: Glyphs = Glyphs {
+  repeat: "×",
+  rule: '─',
+  ellipsis: "…",
+  unparsed: "·"
+};
+
+static ASCII_GLYPHS: Glyphs = Glyphs {
+  repeat: "x",
+  rule: '-',
+  ellipsis: "...",
+  unparsed: "."
+};
+
+/// selects ASCII-only glyphs; should be called once at startup
+pub fn set_ascii(ascii: bool) {
+  ASCII.store(ascii, Ordering::Relaxed);
+}
+
+fn select(ascii: bool) -> &'static Glyphs {
+  if ascii {
+    &ASCII_GLYPHS
+  } else {
+    &UNICODE_GLYPHS
+  }
+}
+
+/// returns the glyphs to use for rendering
+pub fn glyphs() -> &'static Glyphs {
+  select(ASCII.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  fn all(glyphs: &Glyphs) -> String {
+    format!(
+      "{}{}{}{}", glyphs.repeat, glyphs.rule, glyphs.ellipsis, glyphs.unparsed
+    )
+  }
+
+  #[test]
+  fn test_select() {
+    assert_that!(all(select(true)).is_ascii()).is_true();
+    assert_that!(all(select(true))).is_equal_to(String::from("x-...."));
+    assert_that!(all(select(false))).is_equal_to(String::from("×─…·"));
+  }
+}
@@ -5,17 +5,27 @@ use std::rc::Rc;
 
 use crossterm::{Terminal, TerminalCursor, KeyEvent};
 
+use crate::keys::Action;
+
 use super::{RcState, InputAction};
+use super::help;
 use super::log;
 use super::status_bar;
 use super::search_bar;
+use super::stats;
 use super::filter_bar;
+use super::source_bar;
+use super::jump_bar;
+use super::field_bar;
 
 #[derive(Copy, Clone)]
 pub enum BarType {
   Status,
   Filter,
-  Search
+  Search,
+  Source,
+  Jump,
+  Field
 }
 
 #[derive(Clone)]
@@ -34,25 +44,55 @@ impl BarState {
 pub fn render(
   state: RcState, terminal: &Terminal, cursor: &TerminalCursor
 ) -> Result<RcState, Box<dyn Error>> {
+  let state = stats::render(state, terminal, cursor)?;
+  let state = help::render(state, terminal, cursor)?;
+
   let renderer = match state.bar.active {
     BarType::Status => status_bar::render,
     BarType::Filter => filter_bar::render,
-    BarType::Search => search_bar::render
+    BarType::Search => search_bar::render,
+    BarType::Source => source_bar::render,
+    BarType::Jump => jump_bar::render,
+    BarType::Field => field_bar::render
   };
 
   renderer(state, terminal, cursor)
 }
 
 /// handles global input (e.g. ctrl-q, scrolling)
+///
+/// plain character keys (e.g. `j`, `k`) only apply in the status bar, so they
+/// can still be typed into the filter and search bars
 fn input_global(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
-  state = match key {
-    KeyEvent::Ctrl('q') => return (state, InputAction::Exit),
-    KeyEvent::Up => log::actions::move_selection(state, 1),
-    KeyEvent::Down => log::actions::move_selection(state, -1),
-    KeyEvent::Home => log::actions::move_selection_to_top(state),
-    KeyEvent::End => log::actions::clear_selection(state),
-    KeyEvent::PageUp => log::actions::move_selection_page_up(state),
-    KeyEvent::PageDown => log::actions::move_selection_page_down(state),
+  let typing = !matches!(state.bar.active, BarType::Status);
+  if typing && matches!(key, KeyEvent::Char(_)) {
+    return (state, InputAction::Unhandled);
+  }
+
+  // the filter and search bars recall previous input with up and down, and
+  // the field bar uses them to pick a field
+  let own_arrows = matches!(
+    state.bar.active,
+    BarType::Filter | BarType::Search | BarType::Field
+  );
+  if own_arrows && matches!(key, KeyEvent::Up | KeyEvent::Down) {
+    return (state, InputAction::Unhandled);
+  }
+
+  state = match state.config.keybindings.action(key) {
+    Some(Action::ForceQuit) => return (state, InputAction::Exit),
+    Some(Action::Up) => log::actions::move_selection(state, 1),
+    Some(Action::Down) => log::actions::move_selection(state, -1),
+    Some(Action::Top) => log::actions::move_selection_to_top(state),
+    Some(Action::Bottom) => log::actions::move_selection_to_bottom(state),
+    Some(Action::PageUp) => log::actions::move_selection_page_up(state),
+    Some(Action::PageDown) => log::actions::move_selection_page_down(state),
+    Some(Action::HalfPageUp) => {
+      log::actions::move_selection_half_page_up(state)
+    },
+    Some(Action::HalfPageDown) => {
+      log::actions::move_selection_half_page_down(state)
+    },
     _ => return (state, InputAction::Unhandled)
   };
 
@@ -60,6 +100,11 @@ fn input_global(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
 }
 
 pub fn input(state: RcState, key: KeyEvent) -> (RcState, InputAction) {
+  // any key dismisses the help overlay
+  if state.show_help {
+    return (help::actions::close(state), InputAction::Rerender);
+  }
+
   let (state, action) = input_global(state, &key);
   if action != InputAction::Unhandled {
     return (state, action);
@@ -68,7 +113,10 @@ pub fn input(state: RcState, key: KeyEvent) -> (RcState, InputAction) {
   let handler = match state.bar.active {
     BarType::Status => status_bar::input,
     BarType::Filter => filter_bar::input,
-    BarType::Search => search_bar::input
+    BarType::Search => search_bar::input,
+    BarType::Source => source_bar::input,
+    BarType::Jump => jump_bar::input,
+    BarType::Field => field_bar::input
   };
 
   handler(state, &key)
@@ -84,3 +132,43 @@ pub mod actions {
     state
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env;
+  use std::fs;
+  use std::process;
+  use std::sync::Arc;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::config::Config;
+  use crate::renderer::interactive::state::RenderState;
+
+  #[test]
+  fn test_remapped_quit() {
+    let path = env::temp_dir().join(
+      format!("woodchipper-bar-test-{}.yaml", process::id())
+    );
+    fs::write(&path, "quit: x\ndown: j\n").unwrap();
+
+    let config = Config::from_iter_safe(
+      vec!["", "--keybindings", path.to_str().unwrap()]
+    ).unwrap();
+    fs::remove_file(&path).ok();
+
+    let state = Rc::new(RenderState::new(Arc::new(config)));
+
+    let (state, action) = input(state, KeyEvent::Char('q'));
+    assert_that!(action).is_equal_to(InputAction::Unhandled);
+
+    let (state, action) = input(state, KeyEvent::Char('j'));
+    assert_that!(action).is_equal_to(InputAction::Rerender);
+
+    let (_, action) = input(state, KeyEvent::Char('x'));
+    assert_that!(action).is_equal_to(InputAction::Exit);
+  }
+}
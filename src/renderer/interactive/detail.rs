@@ -0,0 +1,140 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::cmp::min;
+use std::error::Error;
+use std::rc::Rc;
+
+use crossterm::{Terminal, TerminalCursor, ClearType};
+
+use super::state::RcState;
+use super::state::actions as state_actions;
+
+/// number of bytes shown on each row of the hex dump
+const BYTES_PER_ROW: usize = 16;
+
+/// formats bytes as a classic hex + ASCII dump, one row per 16 bytes, e.g.
+/// `00000000  68 65 6c 6c 6f 0a                                 |hello.|`
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+  bytes.chunks(BYTES_PER_ROW).enumerate().map(|(row, chunk)| {
+    let mut hex = String::new();
+    for i in 0..BYTES_PER_ROW {
+      // extra gap between the two groups of 8
+      if i == BYTES_PER_ROW / 2 {
+        hex.push(' ');
+      }
+
+      match chunk.get(i) {
+        Some(b) => hex.push_str(&format!("{:02x} ", b)),
+        None => hex.push_str("   ")
+      }
+    }
+
+    let ascii: String = chunk.iter()
+      .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' })
+      .collect();
+
+    format!("{:08x}  {} |{}|", row * BYTES_PER_ROW, hex, ascii)
+  }).collect()
+}
+
+/// true if the hex dump is enabled and there's a selected message to show
+pub fn is_open(state: &RcState) -> bool {
+  state.detail && state.log.selection.is_some()
+}
+
+/// returns the raw bytes of the currently selected message, if any
+fn selected_raw(state: &RcState) -> Option<Vec<u8>> {
+  let selection = state.log.selection?;
+  let filtered_entries = state.filtered_entries.borrow();
+  let entry = filtered_entries.get(selection.rel_index)?.entry.upgrade()?;
+
+  Some(entry.message.raw.as_bytes().to_vec())
+}
+
+/// draws the hex dump of the selected message over the bottom half of the log
+pub fn render(
+  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+) -> Result<RcState, Box<dyn Error>> {
+  let bytes = match selected_raw(&state) {
+    Some(bytes) => bytes,
+    None => return Ok(state)
+  };
+
+  let selected = state.config.style.selected.get_base();
+  let normal = state.config.style.normal.get_base();
+
+  let lines = hex_dump(&bytes);
+  let max_rows = (state.height / 2).saturating_sub(1);
+  let rows = min(lines.len() as u16, max_rows);
+  if state.height < rows + 2 {
+    return Ok(state);
+  }
+
+  let top = state.height - 2 - rows;
+
+  let mut header = format!(" raw: {} bytes", bytes.len());
+  if lines.len() > rows as usize {
+    header.push_str(&format!(
+      " ({} rows not shown)", lines.len() - rows as usize
+    ));
+  }
+  header.push_str(" | x: close");
+
+  cursor.goto(0, top)?;
+  terminal.clear(ClearType::CurrentLine)?;
+  terminal.write(selected.paint(format!(
+    "{:width$}", header, width = state.width as usize
+  )))?;
+
+  for (i, line) in lines.iter().take(rows as usize).enumerate() {
+    cursor.goto(0, top + 1 + i as u16)?;
+    terminal.clear(ClearType::CurrentLine)?;
+    terminal.write(normal.paint(format!(" {}", line)))?;
+  }
+
+  Ok(state)
+}
+
+pub mod actions {
+  use super::*;
+
+  /// shows or hides the hex dump of the selected message
+  pub fn toggle(mut state: RcState) -> RcState {
+    if is_open(&state) {
+      return close(state);
+    }
+
+    if state.log.selection.is_none() {
+      return state_actions::internal(state, "no message is selected");
+    }
+
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.detail = true;
+
+    state
+  }
+
+  pub fn close(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.detail = false;
+
+    state
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_hex_dump() {
+    assert_that!(hex_dump(b"")).is_empty();
+
+    assert_that!(hex_dump(b"hi\x1b[0m\tthere, world!\n")).is_equal_to(vec![
+      "00000000  68 69 1b 5b 30 6d 09 74  68 65 72 65 2c 20 77 6f  |hi.[0m.there, wo|".to_string(),
+      "00000010  72 6c 64 21 0a                                    |rld!.|".to_string()
+    ]);
+  }
+}
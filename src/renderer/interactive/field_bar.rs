@@ -0,0 +1,272 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::cmp::min;
+use std::error::Error;
+use std::rc::Rc;
+
+use crossterm::{Terminal, TerminalCursor, KeyEvent, ClearType};
+
+use crate::clip::{clip, clipboard_enabled};
+use crate::keys::Action;
+use crate::parser::Message;
+use crate::parser::util::nicer_to_string;
+use crate::renderer::common::slice_columns;
+
+use super::state::RcState;
+use super::state::actions as state_actions;
+use super::bar::{self, BarType};
+use super::InputAction;
+
+/// maximum number of fields to show at once
+const MAX_FIELDS: u16 = 10;
+
+#[derive(Clone)]
+pub struct FieldBarState {
+  /// the index of the highlighted field
+  index: usize
+}
+
+impl FieldBarState {
+  pub fn new() -> Self {
+    FieldBarState {
+      index: 0
+    }
+  }
+}
+
+/// lists a message's fields as key / value pairs, including its source, if
+/// any; string values are unquoted
+fn fields(message: &Message) -> Vec<(String, String)> {
+  let mut fields: Vec<(String, String)> = message.metadata.iter()
+    .map(|(key, value)| {
+      let value = nicer_to_string(value);
+
+      (key.clone(), value)
+    })
+    .collect();
+
+  let source = message.reader_metadata.as_ref().and_then(|m| m.source.as_ref());
+  if let Some(source) = source {
+    if !message.metadata.contains_key("source") {
+      fields.push((String::from("source"), source.clone()));
+    }
+  }
+
+  fields
+}
+
+/// returns the selected message's fields, if a message is selected
+fn selected_fields(state: &RcState) -> Option<Vec<(String, String)>> {
+  let selection = state.log.selection?;
+  let entry = state.filtered_entries.borrow()[selection.rel_index].entry
+    .upgrade()?;
+
+  Some(fields(&entry.message))
+}
+
+pub fn render(
+  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+) -> Result<RcState, Box<dyn Error>> {
+  let selected = state.config.style.selected.get_base();
+  let normal = state.config.style.normal.get_base();
+
+  // draw the field list over the bottom of the log
+  let fields = selected_fields(&state).unwrap_or_default();
+  let rows = min(
+    min(fields.len() as u16, MAX_FIELDS),
+    state.height.saturating_sub(2)
+  );
+
+  for (i, (key, value)) in fields.iter().take(rows as usize).enumerate() {
+    let style = if i == state.field.index { selected } else { normal };
+
+    // number the first 9 fields for quick selection
+    let number = if i < 9 { (i + 1).to_string() } else { String::from(" ") };
+    let line = format!(" {} {}={}", number, key, value);
+
+    cursor.goto(0, state.height - 2 - i as u16)?;
+    terminal.clear(ClearType::CurrentLine)?;
+    let line = slice_columns(&line, 0, state.width as usize);
+    terminal.write(style.paint(line))?;
+  }
+
+  cursor.goto(0, state.height - 1)?;
+  terminal.clear(ClearType::CurrentLine)?;
+  terminal.write(selected.paint(" ".repeat(state.width as usize)))?;
+
+  let right = format!("| {} fields (tab, 1-9, enter)", fields.len());
+  if let Some(col) = state.width.checked_sub(right.len() as u16) {
+    cursor.goto(col, state.height - 1)?;
+    terminal.write(selected.paint(right))?;
+  }
+
+  let key = fields.get(state.field.index).map_or("", |(key, _)| key.as_str());
+
+  cursor.goto(0, state.height - 1)?;
+  terminal.write(selected.paint(format!("copy field > {}", key)))?;
+
+  Ok(state)
+}
+
+pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
+  state = match key {
+    KeyEvent::Esc => actions::close(state),
+    KeyEvent::Char('\n') => actions::submit(state),
+    KeyEvent::Char('\t') | KeyEvent::Down => actions::step(state, true),
+    KeyEvent::Up => actions::step(state, false),
+    KeyEvent::Char(c @ '1'..='9') => {
+      let index = *c as usize - '1' as usize;
+      actions::set_index(state, index)
+    },
+    _ => match state.config.keybindings.action(key) {
+      Some(Action::Next) => actions::step(state, true),
+      Some(Action::Previous) => actions::step(state, false),
+      _ => return (state, InputAction::Unhandled)
+    }
+  };
+
+  (state, InputAction::Rerender)
+}
+
+pub mod actions {
+  use super::*;
+
+  /// opens the field picker for the selected message, or copies its text
+  /// directly if it has no fields
+  pub fn open(state: RcState) -> RcState {
+    if !clipboard_enabled() {
+      return state;
+    }
+
+    let fields = match selected_fields(&state) {
+      Some(fields) => fields,
+      None => return state_actions::internal(state, "no message is selected")
+    };
+
+    if !fields.is_empty() {
+      let state = set_index(state, 0);
+      return bar::actions::set_active(state, BarType::Field);
+    }
+
+    let text = {
+      let selection = state.log.selection.unwrap();
+      let entry = &state.filtered_entries.borrow()[selection.rel_index];
+      entry.entry.upgrade().and_then(|e| e.message.text.clone())
+    };
+
+    match text {
+      Some(text) => copy(state, "message text", text),
+      None => state_actions::internal(state, "message has nothing to copy")
+    }
+  }
+
+  pub fn close(state: RcState) -> RcState {
+    bar::actions::set_active(state, BarType::Status)
+  }
+
+  /// highlights the field at the given index, if it exists
+  pub fn set_index(mut state: RcState, index: usize) -> RcState {
+    let len = selected_fields(&state).map_or(0, |f| f.len());
+    if index < len {
+      Rc::make_mut(&mut state).field.index = index;
+    }
+
+    state
+  }
+
+  /// highlights the next or previous field, wrapping around the list
+  pub fn step(state: RcState, forward: bool) -> RcState {
+    let len = selected_fields(&state).map_or(0, |f| f.len());
+    if len == 0 {
+      return state;
+    }
+
+    let index = if forward {
+      (state.field.index + 1) % len
+    } else {
+      (state.field.index + len - 1) % len
+    };
+
+    set_index(state, index)
+  }
+
+  /// copies the highlighted field's value
+  pub fn submit(state: RcState) -> RcState {
+    let field = selected_fields(&state)
+      .and_then(|f| f.into_iter().nth(state.field.index));
+    let state = close(state);
+
+    match field {
+      Some((key, value)) => copy(state, &format!("field {}", key), value),
+      None => state_actions::internal(state, "no field is selected")
+    }
+  }
+
+  fn copy(state: RcState, name: &str, text: String) -> RcState {
+    match clip(text) {
+      Ok(()) => state_actions::internal(
+        state, &format!("copied {} to clipboard", name)
+      ),
+      Err(e) => state_actions::internal(
+        state, &format!("error writing to clipboard: {:?}", e)
+      )
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::config::Config;
+  use crate::parser::{parse, ReaderMetadata};
+
+  fn message(line: &str) -> Message {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    parse(config, line, None).unwrap().unwrap()
+  }
+
+  fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+  }
+
+  #[test]
+  fn test_fields() {
+    let message = message(
+      r#"{"msg":"hello","request_id":"abc-123","status":500,"ok":false}"#
+    );
+
+    assert_that!(fields(&message)).is_equal_to(pairs(&[
+      ("request_id", "abc-123"),
+      ("status", "500"),
+      ("ok", "false")
+    ]));
+  }
+
+  #[test]
+  fn test_fields_source() {
+    let mut message = message(r#"{"msg":"hello","pod":"web-1"}"#);
+    message.reader_metadata = Some(ReaderMetadata {
+      source: Some(String::from("web-1/app")),
+      ..Default::default()
+    });
+
+    assert_that!(fields(&message)).is_equal_to(pairs(&[
+      ("pod", "web-1"),
+      ("source", "web-1/app")
+    ]));
+  }
+
+  #[test]
+  fn test_fields_empty() {
+    let message = message("just some text");
+
+    assert_that!(fields(&message)).is_equal_to(vec![]);
+    assert_that!(message.text).is_equal_to(Some(String::from("just some text")));
+  }
+}
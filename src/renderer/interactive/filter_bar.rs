@@ -5,7 +5,8 @@ use std::rc::Rc;
 
 use crossterm::{Terminal, TerminalCursor, KeyEvent, ClearType};
 
-use crate::filter::FilterMode;
+use crate::filter::{FilterMode, SearchScope};
+use crate::keys::Action;
 use crate::style::{StyleProfileKind, styler_base, styler_error};
 
 use super::state::{self, RcState};
@@ -17,16 +18,18 @@ use super::InputAction;
 #[derive(Clone)]
 pub struct FilterBarState {
   mode: FilterMode,
+  scope: SearchScope,
   text: TextBuffer,
   inverted: bool
 }
 
 impl FilterBarState {
-  pub fn new() -> Self {
+  pub fn new(scope: SearchScope) -> Self {
     let styler = styler_base(StyleProfileKind::Selected);
 
     FilterBarState {
       mode: FilterMode::Regex,
+      scope,
       text: TextBuffer::new().with_styler(Some(styler)),
       inverted: false
     }
@@ -38,16 +41,18 @@ fn format_right(state: &RcState) -> String {
     let inv = if state.filter.inverted { "y" } else { "n" };
 
     format!(
-      "| m: {} (C-r), i: {} (C-e)",
+      "| m: {} (C-r/l), s: {} (C-t), i: {} (C-e)",
       state.filter.mode.name(),
+      state.filter.scope.name(),
       inv
     )
   } else {
     let inv = if state.filter.inverted { "yes" } else { "no" };
 
     format!(
-      "| mode: {} (C-r), invert: {} (C-e)",
+      "| mode: {} (C-r, C-l), scope: {} (C-t), invert: {} (C-e)",
       state.filter.mode.name(),
+      state.filter.scope.name(),
       inv
     )
   }
@@ -111,7 +116,9 @@ pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
       a
     },
     TextInputAction::Submit(a, input) => {
-      match state.filter.mode.parse(&input, state.filter.inverted) {
+      match state.filter.mode.parse(
+        &input, state.filter.inverted, state.filter.scope
+      ) {
         Ok(filter) => {
           state = actions::clear_input(state);
           state = bar::actions::set_active(state, BarType::Status);
@@ -134,15 +141,29 @@ pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
   };
 
   let final_action = match input_action {
-    InputAction::Unhandled => match key {
-      KeyEvent::Ctrl('r') => {
+    InputAction::Unhandled => match state.config.keybindings.action(key) {
+      Some(Action::CycleMode) => {
         state = actions::next_mode(state);
         state = actions::update_highlight(state);
         state = actions::update_style(state);
 
         InputAction::Rerender
       },
-      KeyEvent::Ctrl('e') => {
+      Some(Action::ToggleRegex) => {
+        state = actions::toggle_regex(state);
+        state = actions::update_highlight(state);
+        state = actions::update_style(state);
+
+        InputAction::Rerender
+      },
+      Some(Action::CycleScope) => {
+        state = actions::next_scope(state);
+        state = actions::update_highlight(state);
+        state = actions::update_style(state);
+
+        InputAction::Rerender
+      },
+      Some(Action::Invert) => {
         state = actions::toggle_inverted(state);
         state = actions::update_highlight(state);
         state = actions::update_style(state);
@@ -165,7 +186,9 @@ pub mod actions {
 
     let new_filter = if input.is_empty() {
       None
-    } else if let Ok(parsed) = state.filter.mode.parse(&input, state.filter.inverted) {
+    } else if let Ok(parsed) = state.filter.mode.parse(
+      &input, state.filter.inverted, state.filter.scope
+    ) {
       Some(Rc::new(parsed))
     } else {
       None
@@ -180,7 +203,9 @@ pub mod actions {
     let input = &state_mut.filter.text.input;
     let mode = &state_mut.filter.mode;
 
-    let styler = if input.is_empty() || mode.parse(input, state_mut.filter.inverted).is_ok() {
+    let styler = if input.is_empty() || mode.parse(
+      input, state_mut.filter.inverted, state_mut.filter.scope
+    ).is_ok() {
       styler_base(StyleProfileKind::Selected)
     } else {
       styler_error(StyleProfileKind::Selected)
@@ -209,6 +234,20 @@ pub mod actions {
     state
   }
 
+  pub fn toggle_regex(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.filter.mode = state_mut.filter.mode.toggle_regex();
+
+    state
+  }
+
+  pub fn next_scope(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.filter.scope = state_mut.filter.scope.next();
+
+    state
+  }
+
   pub fn toggle_inverted(mut state: RcState) -> RcState {
     let state_mut = Rc::make_mut(&mut state);
     state_mut.filter.inverted = !state_mut.filter.inverted;
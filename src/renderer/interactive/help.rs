@@ -0,0 +1,206 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::cmp::min;
+use std::error::Error;
+use std::rc::Rc;
+
+use crossterm::{Terminal, TerminalCursor};
+
+use crate::keys::{format_key, Action, KeyBindings};
+use crate::renderer::common::{display_width, slice_columns};
+use super::state::RcState;
+
+/// keys shown for a help entry
+enum Keys {
+  /// keys bound to these actions, per `--keybindings`
+  Bound(&'static [Action]),
+
+  /// keys that can't be remapped
+  Fixed(&'static str)
+}
+
+/// all keybindings, grouped by the bar they apply to
+///
+/// this should be kept in sync with each bar's `input()`
+const KEYBINDINGS: &[(&str, &[(Keys, &str)])] = &[
+  ("log", &[
+    (Keys::Bound(&[Action::Up, Action::Down]), "move the selection"),
+    (Keys::Bound(&[Action::PageUp, Action::PageDown]), "scroll one screenful"),
+    (
+      Keys::Bound(&[Action::HalfPageUp, Action::HalfPageDown]),
+      "scroll half a screenful"
+    ),
+    (
+      Keys::Bound(&[Action::Top, Action::Bottom]),
+      "move to the first or last message"
+    ),
+    (
+      Keys::Bound(&[Action::ScrollLeft, Action::ScrollRight]),
+      "scroll horizontally"
+    ),
+    (Keys::Fixed("0-9"), "jump to a percentage of the way through"),
+    (Keys::Bound(&[Action::Filter]), "add a filter"),
+    (Keys::Bound(&[Action::PopFilter]), "pop the last filter"),
+    (Keys::Bound(&[Action::Source]), "filter by source"),
+    (Keys::Bound(&[Action::Search]), "search"),
+    (Keys::Bound(&[Action::Reveal]), "reveal messages around the selection"),
+    (Keys::Bound(&[Action::Group]), "toggle grouping by source"),
+    (Keys::Bound(&[Action::Follow]), "toggle following new messages"),
+    (Keys::Bound(&[Action::Pause]), "pause or resume incoming messages"),
+    (Keys::Bound(&[Action::Stats]), "toggle message counts by level"),
+    (Keys::Bound(&[Action::Detail]), "show the selected message's raw bytes"),
+    (Keys::Bound(&[Action::Copy]), "copy the selected message"),
+    (Keys::Bound(&[Action::CopyScreen]), "copy the current screen"),
+    (
+      Keys::Bound(&[Action::CopyField]),
+      "copy one field of the selected message"
+    ),
+    (
+      Keys::Bound(&[Action::CopyRaw]),
+      "copy the selected message's original line"
+    ),
+    (Keys::Fixed("esc"), "close, clear the selection, or quit"),
+    (Keys::Bound(&[Action::Quit, Action::ForceQuit]), "quit"),
+    (Keys::Bound(&[Action::Help]), "show this help")
+  ]),
+  ("filter, search", &[
+    (Keys::Fixed("enter"), "add the filter, or find the next match"),
+    (Keys::Fixed("up, down"), "recall previous input"),
+    (Keys::Bound(&[Action::CycleMode]), "cycle the match mode"),
+    (
+      Keys::Bound(&[Action::ToggleRegex]),
+      "toggle regex and plain text matching"
+    ),
+    (Keys::Bound(&[Action::CycleScope]), "cycle the fields to match"),
+    (Keys::Bound(&[Action::Invert]), "invert the filter"),
+    (Keys::Bound(&[Action::Next]), "next match"),
+    (Keys::Bound(&[Action::Previous]), "previous match"),
+    (Keys::Fixed("esc"), "cancel")
+  ]),
+  ("copy field", &[
+    (Keys::Fixed("tab, up, down"), "highlight the next or previous field"),
+    (Keys::Fixed("1-9"), "highlight a numbered field"),
+    (Keys::Fixed("enter"), "copy the highlighted field's value"),
+    (Keys::Fixed("esc"), "cancel")
+  ])
+];
+
+/// formats the keys for a help entry, e.g. `f, |`
+fn format_keys(bindings: &KeyBindings, keys: &Keys) -> String {
+  match keys {
+    Keys::Bound(actions) => {
+      let keys: Vec<_> = actions.iter()
+        .flat_map(|action| bindings.keys(*action))
+        .map(format_key)
+        .collect();
+
+      if keys.is_empty() {
+        String::from("(unbound)")
+      } else {
+        keys.join(", ")
+      }
+    },
+    Keys::Fixed(keys) => keys.to_string()
+  }
+}
+
+/// formats the keybindings as lines of text, one section per bar
+fn help_lines(bindings: &KeyBindings) -> Vec<String> {
+  let sections: Vec<(&str, Vec<(String, &str)>)> = KEYBINDINGS.iter()
+    .map(|(bar, entries)| {
+      let entries = entries.iter()
+        .map(|(keys, description)| (format_keys(bindings, keys), *description))
+        .collect();
+
+      (*bar, entries)
+    })
+    .collect();
+
+  let key_width = sections.iter()
+    .flat_map(|(_, entries)| entries.iter())
+    .map(|(keys, _)| display_width(keys))
+    .max()
+    .unwrap_or(0);
+
+  let mut lines = vec![String::from("keybindings (press any key to close)")];
+  for (bar, entries) in sections {
+    lines.push(String::new());
+    lines.push(format!("{}:", bar));
+
+    for (keys, description) in entries {
+      lines.push(format!(
+        "  {:width$}  {}", keys, description, width = key_width
+      ));
+    }
+  }
+
+  lines
+}
+
+/// draws the help overlay, if open, centered over the log
+pub fn render(
+  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+) -> Result<RcState, Box<dyn Error>> {
+  if !state.show_help {
+    return Ok(state);
+  }
+
+  let style = state.config.style.selected.get_base();
+
+  let lines = help_lines(&state.config.keybindings);
+  let inner_width = lines.iter().map(|l| display_width(l)).max().unwrap_or(0);
+  let box_width = min(inner_width + 2, state.width as usize);
+
+  // leave the bar uncovered
+  let log_height = state.height.saturating_sub(1) as usize;
+  let rows = min(lines.len(), log_height);
+  let top = (log_height - rows) / 2;
+  let left = (state.width as usize - box_width) / 2;
+
+  for (i, line) in lines.iter().take(rows).enumerate() {
+    let padded = format!(" {:width$} ", line, width = inner_width);
+
+    cursor.goto(left as u16, (top + i) as u16)?;
+    terminal.write(style.paint(slice_columns(&padded, 0, box_width)))?;
+  }
+
+  Ok(state)
+}
+
+pub mod actions {
+  use super::*;
+
+  pub fn open(mut state: RcState) -> RcState {
+    Rc::make_mut(&mut state).show_help = true;
+
+    state
+  }
+
+  pub fn close(mut state: RcState) -> RcState {
+    Rc::make_mut(&mut state).show_help = false;
+
+    state
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_help_lines() {
+    let lines = help_lines(&KeyBindings::default());
+
+    assert_that!(lines).contains(String::from("filter, search:"));
+    assert_that!(lines.iter().any(|l| l.trim_start().starts_with("? ")))
+      .is_true();
+    assert_that!(lines.iter().any(|l| l.contains("f, |  ")))
+      .is_true();
+
+    // descriptions line up in one column
+    let column = lines[3].find("move").unwrap();
+    assert_that!(lines[4].find("scroll")).is_equal_to(Some(column));
+  }
+}
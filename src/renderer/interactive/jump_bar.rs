@@ -0,0 +1,201 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::error::Error;
+use std::rc::Rc;
+
+use crossterm::{Terminal, TerminalCursor, KeyEvent, ClearType};
+
+use crate::style::{StyleProfileKind, styler_base, styler_error};
+
+use super::state::RcState;
+use super::state::actions as state_actions;
+use super::bar::{self, BarType};
+use super::log;
+use super::text::{self, TextBuffer, TextInputAction};
+use super::InputAction;
+
+#[derive(Clone)]
+pub struct JumpBarState {
+  text: TextBuffer
+}
+
+impl JumpBarState {
+  pub fn new() -> Self {
+    let styler = styler_base(StyleProfileKind::Selected);
+
+    JumpBarState {
+      text: TextBuffer::new().with_styler(Some(styler))
+    }
+  }
+}
+
+/// parses a percentage like `50` or `12.5%`, if it's within 0-100
+fn parse_percent(input: &str) -> Option<f64> {
+  input.trim().trim_end_matches('%').parse::<f64>()
+    .ok()
+    .filter(|p| *p >= 0.0 && *p <= 100.0)
+}
+
+/// returns the index at the given percentage through a list of `len` entries
+fn percent_index(percent: f64, len: usize) -> Option<usize> {
+  if len == 0 {
+    return None;
+  }
+
+  Some(((len - 1) as f64 * percent / 100.0).round() as usize)
+}
+
+pub fn render(
+  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+) -> Result<RcState, Box<dyn Error>> {
+  let selected = state.config.style.selected.get_base();
+
+  cursor.goto(0, state.height - 1)?;
+  terminal.clear(ClearType::CurrentLine)?;
+  terminal.write(selected.paint(" ".repeat(state.width as usize)))?;
+
+  let right = format!(
+    "| % of {} messages (enter)", state.filtered_entries.borrow().len()
+  );
+  if let Some(col) = state.width.checked_sub(right.len() as u16) {
+    cursor.goto(col, state.height - 1)?;
+    terminal.write(selected.paint(right))?;
+  }
+
+  cursor.goto(0, state.height - 1)?;
+  terminal.write(selected.paint("jump > "))?;
+  text::render(
+    Rc::clone(&state), &state.jump.text,
+    terminal, cursor,
+    7, state.height - 1
+  )?;
+
+  Ok(state)
+}
+
+/// handles text component input in a pseudo-action
+///
+/// it doesn't /quite/ conform to the 'RcState in, RcState out' pattern so it
+/// isn't explicitly an action
+fn handle_text_input(
+  mut state: RcState, key: &KeyEvent
+) -> (RcState, TextInputAction) {
+  let state_mut = Rc::make_mut(&mut state);
+
+  let text_state = state_mut.jump.text.clone();
+  let (text_state, action) = text::input(text_state, key);
+  state_mut.jump.text = text_state;
+
+  (state, action)
+}
+
+pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
+  // as in less, `%` ends the input, e.g. `50%`
+  if let KeyEvent::Char('%') = key {
+    return (actions::submit(state), InputAction::Rerender);
+  }
+
+  let (new_state, action) = handle_text_input(state, key);
+  state = new_state;
+
+  let input_action = match action {
+    TextInputAction::Action(a) => a,
+    TextInputAction::Exit(a) => {
+      state = actions::close(state);
+      a
+    },
+    TextInputAction::Submit(a, _) => {
+      state = actions::submit(state);
+      a
+    },
+    TextInputAction::Update(a) => {
+      state = actions::update_style(state);
+      a
+    }
+  };
+
+  (state, input_action)
+}
+
+pub mod actions {
+  use super::*;
+
+  /// opens the jump bar, starting with the given digit
+  pub fn open(mut state: RcState, digit: char) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.jump.text = text::actions::push_input(
+      text::actions::clear_input(state_mut.jump.text.clone()),
+      digit
+    );
+
+    let state = update_style(state);
+    bar::actions::set_active(state, BarType::Jump)
+  }
+
+  pub fn close(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.jump.text = text::actions::clear_input(
+      state_mut.jump.text.clone()
+    );
+
+    bar::actions::set_active(state, BarType::Status)
+  }
+
+  /// highlights the input in red if it isn't a valid percentage
+  pub fn update_style(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+
+    let input = &state_mut.jump.text.input;
+    let styler = if input.is_empty() || parse_percent(input).is_some() {
+      styler_base(StyleProfileKind::Selected)
+    } else {
+      styler_error(StyleProfileKind::Selected)
+    };
+
+    state_mut.jump.text.styler = Some(styler);
+
+    state
+  }
+
+  /// moves the selection to the entered percentage of the filtered messages
+  pub fn submit(state: RcState) -> RcState {
+    let percent = parse_percent(&state.jump.text.input);
+    let state = close(state);
+
+    let percent = match percent {
+      Some(percent) => percent,
+      None => return state_actions::internal(
+        state, "invalid percentage, expected e.g. 50%"
+      )
+    };
+
+    let len = state.filtered_entries.borrow().len();
+    match percent_index(percent, len) {
+      Some(index) => log::actions::move_selection_to_index(state, index),
+      None => state_actions::internal(state, "no messages to jump to")
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_parse_percent() {
+    assert_that!(parse_percent("50")).is_equal_to(Some(50.0));
+    assert_that!(parse_percent("12.5%")).is_equal_to(Some(12.5));
+    assert_that!(parse_percent("101")).is_none();
+    assert_that!(parse_percent("abc")).is_none();
+  }
+
+  #[test]
+  fn test_percent_index() {
+    assert_that!(percent_index(50.0, 0)).is_none();
+    assert_that!(percent_index(0.0, 10)).is_equal_to(Some(0));
+    assert_that!(percent_index(50.0, 11)).is_equal_to(Some(5));
+    assert_that!(percent_index(100.0, 10)).is_equal_to(Some(9));
+  }
+}
@@ -1,29 +1,165 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::{min, max};
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::rc::Rc;
 
-use crossterm::{Terminal, TerminalCursor, ClearType};
+use crossterm::{Terminal, TerminalCursor, ClearType, MouseButton, MouseEvent};
 
+use crate::classifier::ChunkKind;
+use crate::config::{GroupBy, RenderOrder};
 use crate::renderer::types::*;
 use crate::renderer::common::*;
+use crate::renderer::glyphs::glyphs;
 use crate::style::StyleProfile;
-use crate::renderer::interactive::state::{RenderState, RcState};
+use crate::renderer::interactive::InputAction;
+use crate::renderer::interactive::detail;
+use crate::renderer::interactive::state::{RenderState, RcState, group_key};
+
+/// number of entries the selection moves per mouse wheel step
+const WHEEL_STEP: isize = 3;
+
+/// number of columns scrolled horizontally per keypress
+pub const SCROLL_STEP: isize = 8;
 
 /// renders a message without displaying and returns its height
 /// this is mildly expensive and should be called sparingly
-fn measure_entry(state: RcState, abs_index: usize) -> usize {
-  let entry = &state.entries.borrow()[abs_index];
-  styled_render(
-    &entry,
+fn measure_entry(state: RcState, rel_index: usize) -> usize {
+  let entry = match state.filtered_entries.borrow()[rel_index].entry.upgrade() {
+    Some(entry) => entry,
+    None => return 0
+  };
+  let header = group_header(&state, rel_index).map_or(0, |_| 1);
+
+  header + styled_render(
+    &display_entry(&state, &entry),
     &state.config.style.normal,
-    Some(state.width as usize)
+    wrap_width(&state)
   ).len()
 }
 
+/// the width messages are wrapped to; once scrolled horizontally, messages are
+/// left unwrapped so their long lines can be scrolled through
+fn wrap_width(state: &RenderState) -> Option<usize> {
+  if state.log.scroll_x > 0 {
+    None
+  } else {
+    Some(state.width as usize)
+  }
+}
+
+/// shortens an entry's source as needed for `--strip-source-common`
+fn display_entry<'a>(
+  state: &RenderState, entry: &'a MessageEntry
+) -> Cow<'a, MessageEntry> {
+  strip_source_common(entry, &state.source_labels.borrow())
+}
+
+/// if true, the latest messages are shown at the top of the screen rather than
+/// the bottom
+fn newest_first(state: &RenderState) -> bool {
+  state.config.order == RenderOrder::NewestFirst
+}
+
+/// returns the (relative) index `count` entries visually above `index`, if any
+fn index_above(state: &RenderState, index: usize, count: usize) -> Option<usize> {
+  if newest_first(state) {
+    Some(index + count).filter(|i| *i < state.filtered_entries.borrow().len())
+  } else {
+    index.checked_sub(count)
+  }
+}
+
+/// returns the (relative) index `count` entries visually below `index`, if any
+fn index_below(state: &RenderState, index: usize, count: usize) -> Option<usize> {
+  if newest_first(state) {
+    index.checked_sub(count)
+  } else {
+    Some(index + count).filter(|i| *i < state.filtered_entries.borrow().len())
+  }
+}
+
+/// the number of entries to move up to get from `from` to `to`; negative if
+/// `to` is below `from`
+fn steps_up(state: &RenderState, from: usize, to: usize) -> isize {
+  if newest_first(state) {
+    to as isize - from as isize
+  } else {
+    from as isize - to as isize
+  }
+}
+
+/// maps a relative index to its new value once the entries at the (sorted)
+/// `removed` indices are removed, or None if it was removed itself
+pub fn shift_index(index: usize, removed: &[usize]) -> Option<usize> {
+  match removed.binary_search(&index) {
+    Ok(_) => None,
+    Err(count) => Some(index - count)
+  }
+}
+
+/// if the entry at `rel_index` is visually the first of its source group,
+/// returns a header line to display above it
+fn group_header(state: &RenderState, rel_index: usize) -> Option<String> {
+  // context is shown interleaved regardless of grouping
+  if state.group_by != GroupBy::Source || state.context.is_some() {
+    return None;
+  }
+
+  let filtered_entries = state.filtered_entries.borrow();
+  let key = group_key(&filtered_entries[rel_index]);
+  if let Some(above) = index_above(state, rel_index, 1) {
+    if group_key(&filtered_entries[above]) == key {
+      return None;
+    }
+  }
+
+  let rule = glyphs().rule;
+  let mut header = format!(
+    "{}{} {} ", rule, rule, key.as_ref().map_or("(no source)", String::as_str)
+  );
+  let len = display_width(&header);
+  for _ in len..state.width as usize {
+    header.push(rule);
+  }
+
+  let style = state.config.style.normal.get_style(&ChunkKind::Context);
+  Some(style.paint(header).to_string())
+}
+
+/// renders the entry at `rel_index`, including its group header, if any
+fn render_lines(
+  state: &RenderState, rel_index: usize, selected: bool
+) -> Vec<String> {
+  // dropped entries are pruned before each render, so this shouldn't happen,
+  // but there's nothing to render if it does
+  let entry = match state.filtered_entries.borrow()[rel_index].entry.upgrade() {
+    Some(entry) => entry,
+    None => return Vec::new()
+  };
+  // invert the matched text itself, in addition to highlighting the message
+  let highlights = match &state.highlight_filter {
+    Some(filter) => filter.matches(&entry.message),
+    None => Vec::new()
+  };
+
+  let mut lines = styled_render_highlighted(
+    &display_entry(state, &entry),
+    profile_for_message(state, &entry, selected),
+    wrap_width(state),
+    &highlights
+  );
+
+  if let Some(header) = group_header(state, rel_index) {
+    lines.insert(0, header);
+  }
+
+  lines
+}
+
 fn profile_for_message<'a>(
   state: &'a RenderState, message: &MessageEntry, selected: bool
 ) -> &'a  StyleProfile {
@@ -85,6 +221,9 @@ pub struct LogState {
   anchors: Rc<RefCell<BTreeMap<usize, Anchor>>>,
 
   pub selection: Option<Selection>,
+
+  /// the number of columns scrolled to the right
+  pub scroll_x: usize
 }
 
 impl LogState {
@@ -93,18 +232,91 @@ impl LogState {
       range_min: 0,
       range_max: 0,
       anchors: Rc::new(RefCell::new(BTreeMap::new())),
-      selection: None
+      selection: None,
+      scroll_x: 0
     }
   }
 }
 
+/// measures the widest unwrapped line of the entries in the rendered range
+fn max_line_width(state: &RenderState) -> usize {
+  let entries = state.filtered_entries.borrow();
+  let last = match entries.len().checked_sub(1) {
+    Some(last) => min(last, state.log.range_max),
+    None => return 0
+  };
+
+  (state.log.range_min..=last)
+    .filter_map(|i| entries[i].entry.upgrade())
+    .flat_map(|entry| styled_render(
+      &display_entry(state, &entry), &state.config.style.normal, None
+    ))
+    .map(|line| styled_width(&line))
+    .max()
+    .unwrap_or(0)
+}
+
+/// applies the horizontal scroll offset to a rendered line
+fn scroll_line(state: &RenderState, line: String) -> String {
+  if state.log.scroll_x == 0 {
+    line
+  } else {
+    slice_columns(&line, state.log.scroll_x, state.width as usize)
+  }
+}
+
+/// returns the indices of anchored entries with at least one row within the
+/// log area (rows `0..end_y`), ordered from top to bottom
+fn visible_anchors(anchors: &BTreeMap<usize, Anchor>, end_y: u16) -> Vec<usize> {
+  let mut visible: Vec<(isize, usize)> = anchors.iter()
+    .filter_map(|(index, anchor)| {
+      let top = end_y as isize - anchor.offset as isize;
+      let bottom = top + anchor.height.unwrap_or(1) as isize;
+
+      if bottom > 0 && top < end_y as isize {
+        Some((top, *index))
+      } else {
+        None
+      }
+    })
+    .collect();
+
+  visible.sort();
+  visible.into_iter().map(|(_, index)| index).collect()
+}
+
+/// returns the index of the anchored entry displayed at `row`, if any
+fn anchor_at(
+  anchors: &BTreeMap<usize, Anchor>, end_y: u16, row: u16
+) -> Option<usize> {
+  if row >= end_y {
+    return None;
+  }
+
+  anchors.iter()
+    .find(|(_, anchor)| {
+      let top = end_y as isize - anchor.offset as isize;
+      let bottom = top + anchor.height.unwrap_or(1) as isize;
+
+      row as isize >= top && (row as isize) < bottom
+    })
+    .map(|(index, _)| *index)
+}
+
+/// returns the (relative) indices of entries displayed as of the last render,
+/// fully or partially, in the order they appear on screen
+pub fn visible_indices(state: &RenderState) -> Vec<usize> {
+  let len = state.filtered_entries.borrow().len();
+
+  visible_anchors(&state.log.anchors.borrow(), state.height.saturating_sub(1))
+    .into_iter()
+    .filter(|i| *i < len)
+    .collect()
+}
+
 fn render_int(
   state_mut: &mut RenderState, terminal: &Terminal, cursor: &TerminalCursor
 ) -> Result<(), Box<dyn Error>> {
-  // TODO: handle weak refs better, we're just blindly unwrapping right now
-  // (at the moment they should never dealloc but eventually some max log size
-  // should be implemented)
-
   // design note re: clearing: we want to reduce (as much as possible) the delay
   // between line clearing and writing content back to the screen
   // in some cases, the screen may flicker if content isn't written before the
@@ -123,6 +335,7 @@ fn render_int(
     return Ok(());
   }
 
+  let newest_first = newest_first(state_mut);
   let start_selected: bool;
   let start_index: usize;
   let mut start_y: u16;
@@ -137,6 +350,12 @@ fn render_int(
       None => end_y
     } as u16;
     start_height = selection.anchor.height;
+  } else if newest_first {
+    // pin the latest message to the top and render older messages below it
+    start_selected = false;
+    start_index = filtered_entries.len() - 1;
+    start_y = 0;
+    start_height = None;
   } else {
     start_selected = false;
     start_index = filtered_entries.len() - 1;
@@ -149,12 +368,7 @@ fn render_int(
 
   // render the anchored entry first so we can decide if start_y is still
   // valid
-  let start_entry = &filtered_entries[start_index].entry.upgrade().unwrap();
-  let start_lines = styled_render(
-    start_entry,
-    profile_for_message(&state_mut, start_entry, start_selected),
-    Some(state_mut.width as usize)
-  );
+  let start_lines = render_lines(state_mut, start_index, start_selected);
 
   // if the message height has changed (e.g. due to a resize),
   // update the position to keep it anchored
@@ -207,7 +421,7 @@ fn render_int(
   for line in start_lines {
     cursor.goto(0, y_pos as u16)?;
     terminal.clear(ClearType::CurrentLine)?;
-    terminal.write(line)?;
+    terminal.write(scroll_line(state_mut, line))?;
 
     y_pos += 1;
     if y_pos >= end_y {
@@ -215,17 +429,26 @@ fn render_int(
     }
   }
 
+  // entries visually below and above the anchored entry, nearest first
+  let (below, above): (Box<dyn Iterator<Item = usize>>, Box<dyn Iterator<Item = usize>>) = if newest_first {
+    (Box::new((0..start_index).rev()), Box::new(start_index + 1..filtered_entries.len()))
+  } else {
+    (Box::new(start_index + 1..filtered_entries.len()), Box::new((0..start_index).rev()))
+  };
+
+  let has_above = if newest_first {
+    start_index + 1 < filtered_entries.len()
+  } else {
+    start_index > 0
+  };
+
   // now render as many entries below it as possible
   if y_pos < end_y {
-    'outer_down: for i in {start_index + 1 .. filtered_entries.len()} {
-      let entry = &filtered_entries[i].entry.upgrade().unwrap();
-      let lines = styled_render(
-        entry,
-        profile_for_message(&state_mut, entry, false),
-        Some(state_mut.width as usize)
-      );
-
-      state_mut.log.range_max = i;
+    'outer_down: for i in below {
+      let lines = render_lines(state_mut, i, false);
+
+      state_mut.log.range_min = min(state_mut.log.range_min, i);
+      state_mut.log.range_max = max(state_mut.log.range_max, i);
       anchors.insert(i, Anchor {
         offset: end_y - y_pos,
         height: Some(lines.len() as u16)
@@ -234,7 +457,7 @@ fn render_int(
       for line in lines {
         cursor.goto(0, y_pos)?;
         terminal.clear(ClearType::CurrentLine)?;
-        terminal.write(line)?;
+        terminal.write(scroll_line(state_mut, line))?;
 
         y_pos += 1;
         if y_pos >= end_y {
@@ -251,18 +474,14 @@ fn render_int(
   }
 
   // now reset y_pos and render upward
-  if start_y > 0 && start_index > 0 {
+  if start_y > 0 && has_above {
     y_pos = start_y - 1;
 
-    'outer_up: for i in {0..start_index}.rev() {
-      let entry = &filtered_entries[i].entry.upgrade().unwrap();
-      let lines = styled_render(
-        entry,
-        profile_for_message(&state_mut, entry, false),
-        Some(state_mut.width as usize)
-      );
-      
-      state_mut.log.range_min = i;
+    'outer_up: for i in above {
+      let lines = render_lines(state_mut, i, false);
+
+      state_mut.log.range_min = min(state_mut.log.range_min, i);
+      state_mut.log.range_max = max(state_mut.log.range_max, i);
 
       // y here is only used for anchoring purposes
       // if the message happens to extend off-screen, we want to scroll to
@@ -274,10 +493,10 @@ fn render_int(
         height: Some(lines.len() as u16)
       });
 
-      for line in lines.iter().rev() {
+      for line in lines.into_iter().rev() {
         cursor.goto(0, y_pos as u16)?;
         terminal.clear(ClearType::CurrentLine)?;
-        terminal.write(line)?;
+        terminal.write(scroll_line(state_mut, line))?;
 
         if y_pos == 0 {
           // we've reached the top
@@ -308,6 +527,8 @@ fn render_int(
 pub fn render(
   mut state: RcState, terminal: &Terminal, cursor: &TerminalCursor
 ) -> Result<RcState, Box<dyn Error>> {
+  state = actions::prune_dropped(state);
+
   // ugly dancing around the borrow checker
   // the &mut needs to be dropped so we can return the new state
   {
@@ -318,20 +539,68 @@ pub fn render(
   Ok(state)
 }
 
+/// handles mouse input: the wheel moves the selection and clicking a message
+/// selects it
+pub fn mouse_input(state: RcState, event: &MouseEvent) -> (RcState, InputAction) {
+  match event {
+    MouseEvent::Press(MouseButton::WheelUp, _, _) => {
+      (actions::move_selection(state, WHEEL_STEP), InputAction::Rerender)
+    },
+    MouseEvent::Press(MouseButton::WheelDown, _, _) => {
+      (actions::move_selection(state, -WHEEL_STEP), InputAction::Rerender)
+    },
+    MouseEvent::Press(MouseButton::Left, _, y) if !detail::is_open(&state) => {
+      // mouse coordinates are 1-based
+      let len = state.filtered_entries.borrow().len();
+      let index = anchor_at(
+        &state.log.anchors.borrow(),
+        state.height.saturating_sub(1),
+        y.saturating_sub(1)
+      ).filter(|i| *i < len);
+
+      match index {
+        Some(index) => {
+          (actions::move_selection_to_index(state, index), InputAction::Rerender)
+        },
+        None => (state, InputAction::Unhandled)
+      }
+    },
+    _ => (state, InputAction::Unhandled)
+  }
+}
+
 pub mod actions {
   use super::*;
 
   /// Moves the current selection by some number of entries
   ///
-  /// Positive amounts move the selection up, i.e. toward earlier messages,
-  /// while negative amounts move the down, toward the latest message
+  /// Positive amounts move the selection up, i.e. toward earlier messages (or
+  /// later messages with `--order newest-first`), while negative amounts move
+  /// the selection down
+  ///
+  /// Following new messages stops when a message is selected, and resumes once
+  /// the selection moves past the latest message.
   pub fn move_selection(state: RcState, amount: isize) -> RcState {
+    let mut state = move_selection_by(state, amount);
+
+    let follow = state.log.selection.is_none();
+    if state.follow != follow {
+      Rc::make_mut(&mut state).follow = follow;
+    }
+
+    state
+  }
+
+  fn move_selection_by(state: RcState, amount: isize) -> RcState {
     if amount == 0 {
       return state;
     }
 
     let filtered_entries = &state.filtered_entries.borrow();
 
+    // with newest-first ordering, moving up means moving toward later entries
+    let direction = if newest_first(&state) { -1 } else { 1 };
+
     // make a hard clone of state to play around in, otherwise we'll have to
     // jump through hoops to return later
     let mut state = (*state).clone();
@@ -342,7 +611,7 @@ pub mod actions {
     }
 
     let desired_index = if let Some(current) = &state.log.selection {
-      let new = max(current.rel_index as isize - amount, 0) as usize;
+      let new = max(current.rel_index as isize - amount * direction, 0) as usize;
       if new >= filtered_entries.len() {
         state.log.selection = None;
 
@@ -351,24 +620,30 @@ pub mod actions {
 
       new
     } else {
-      // no selection means we're already anchored at the bottom, so moving
-      // further down is a no-op
-      if amount < 0 {
+      // no selection means we're already anchored to the latest message, so
+      // moving further toward it is a no-op
+      if amount * direction < 0 {
         state.log.selection = None;
         return Rc::new(state);
       }
 
-      max(filtered_entries.len() as isize - amount, 0) as usize
+      max(filtered_entries.len() as isize - amount * direction, 0) as usize
     };
 
-    if desired_index < state.log.range_min {
-      // selected message is off-screen and early/above
+    let (above, below) = if newest_first(&state) {
+      (desired_index > state.log.range_max, desired_index < state.log.range_min)
+    } else {
+      (desired_index < state.log.range_min, desired_index > state.log.range_max)
+    };
+
+    if above {
+      // selected message is off-screen and above
       state.log.selection = Some(Selection {
         rel_index: desired_index,
         anchor: Anchor { offset: state.height - 1, height: None }
       });
-    } else if desired_index > state.log.range_max {
-      // selected message is off-screen and later/below
+    } else if below {
+      // selected message is off-screen and below
       // anchor to the height; this is definitely incorrect, but we don't want
       // to render the message here just to determine how many lines it spans
       // the renderer will adjust the selection if (when) it notices that it's
@@ -378,8 +653,11 @@ pub mod actions {
         anchor: Anchor { offset: 0, height: None }
       });
     } else {
-      // selected message is already on-screen
-      let anchor = state.log.anchors.borrow()[&desired_index];
+      // selected message is already on-screen; its anchor may be missing if
+      // entries were removed since the last render, in which case the
+      // renderer will adjust it as above
+      let anchor = state.log.anchors.borrow().get(&desired_index).copied()
+        .unwrap_or(Anchor { offset: 0, height: None });
       let anchor_height = anchor.height.unwrap_or(0);
 
       // if the anchor is partially off-screen (i.e. too high up), nudge in the
@@ -412,15 +690,22 @@ pub mod actions {
   /// Note that index is relative i.e. filtered entries (if any)
   pub fn move_selection_to_index(state: RcState, index: usize) -> RcState {
     let amount = if let Some(selection) = state.log.selection {
-      selection.rel_index as isize - index as isize
+      steps_up(&state, selection.rel_index, index)
     } else {
-      (state.filtered_entries.borrow().len() - index) as isize
+      // as if selecting from one past the latest message
+      steps_up(&state, state.filtered_entries.borrow().len(), index)
     };
 
     move_selection(state, amount)
   }
 
+  /// Moves the selection to the message at the top of the screen, i.e. the
+  /// first message (or the latest with `--order newest-first`)
   pub fn move_selection_to_top(state: RcState) -> RcState {
+    if newest_first(&state) {
+      return clear_selection(state);
+    }
+
     // note that the index given no selection deliberately +1 from the true last
     // index, as 1 selection up from empty will select the last message
     let index = state.log.selection
@@ -429,108 +714,548 @@ pub mod actions {
     move_selection(state, index as isize)
   }
 
+  /// Moves the selection to the message at the bottom of the screen, i.e. the
+  /// latest message (or the first with `--order newest-first`)
+  pub fn move_selection_to_bottom(state: RcState) -> RcState {
+    if newest_first(&state) {
+      move_selection_to_index(state, 0)
+    } else {
+      clear_selection(state)
+    }
+  }
+
+  /// Counts the number of entries from `index` in the given direction that
+  /// can fit on one page
+  pub(super) fn count_page(state: &RcState, index: usize, up: bool) -> usize {
+    // we can't move further than this
+    count_rows(state, index, up, state.height as isize - 2)
+  }
+
+  /// Counts the number of entries from `index` in the given direction that
+  /// fit within `max_height` rows
+  fn count_rows(
+    state: &RcState, index: usize, up: bool, max_height: isize
+  ) -> usize {
+    let mut running_height = 0;
+    let mut running_count = 0;
+
+    loop {
+      let next = if up {
+        index_above(state, index, running_count + 1)
+      } else {
+        index_below(state, index, running_count + 1)
+      };
+
+      let next = match next {
+        Some(next) => next,
+        None => break
+      };
+
+      let next_height = measure_entry(Rc::clone(state), next) as isize;
+      if running_height as isize + next_height > max_height {
+        break;
+      }
+
+      running_height += next_height;
+      running_count += 1;
+    }
+
+    // always move at least one entry, even if it's taller than the screen
+    max(running_count, 1)
+  }
+
   pub fn move_selection_page_up(state: RcState) -> RcState {
+    let top = if newest_first(&state) {
+      state.log.range_max
+    } else {
+      state.log.range_min
+    };
+
     if let Some(selection) = state.log.selection {
-      if selection.rel_index == 0 {
+      if index_above(&state, selection.rel_index, 1).is_none() {
         // no-op
         return state;
       }
 
-      if selection.rel_index == state.log.range_min {
+      if selection.rel_index == top {
         // move up a page, keeping at least one line of this old selection
         // visible to give the user some context
-
-        // we can't move further than this
-        let max_height = state.height as isize - 2;
-        let mut running_height = 0;
-        let mut running_count = 0;
-
-        loop {
-          let next_height = measure_entry(
-            Rc::clone(&state), selection.rel_index - running_count - 1
-          ) as isize;
-          if running_height as isize + next_height > max_height {
-            break;
-          }
-
-          running_height += next_height;
-          running_count += 1;
-
-          // avoid subtraction overflows...
-          if running_count >= selection.rel_index {
-            break;
-          }
-        }
-
-        move_selection(Rc::clone(&state), running_count as isize)
+        let count = count_page(&state, selection.rel_index, true);
+        move_selection(state, count as isize)
       } else {
         // move to the top of the current page
-        move_selection(
-          Rc::clone(&state),
-          selection.rel_index as isize - state.log.range_min as isize
-        )
+        let amount = steps_up(&state, selection.rel_index, top);
+        move_selection(state, amount)
       }
+    } else if newest_first(&state) {
+      // already at the top
+      state
     } else {
       // start the selection at the top of the current page
-      move_selection(
-        Rc::clone(&state),
-        state.filtered_entries.borrow().len() as isize - state.log.range_min as isize
-      )
+      let amount = state.filtered_entries.borrow().len() as isize - top as isize;
+      move_selection(state, amount)
     }
   }
 
   pub fn move_selection_page_down(state: RcState) -> RcState {
-    if let Some(selection) = state.log.selection {
-      let sel_index = selection.rel_index;
-      let filtered_len = state.filtered_entries.borrow().len();
+    let bottom = if newest_first(&state) {
+      state.log.range_min
+    } else {
+      state.log.range_max
+    };
 
-      if sel_index == filtered_len - 1 {
-        // clear the selection
+    if let Some(selection) = state.log.selection {
+      if index_below(&state, selection.rel_index, 1).is_none() {
+        // clear the selection (or no-op if newest-first)
         return move_selection(state, -1);
       }
 
-      if selection.rel_index == state.log.range_max {
+      if selection.rel_index == bottom {
         // move down a page, keeping at least one line of this old selection
         // visible to give the user some context
-
-        // we can't move further than this
-        let max_height = state.height as isize - 2;
-        let mut running_height = 0;
-        let mut running_count = 0;
-
-        loop {
-          let next_height = measure_entry(
-            Rc::clone(&state), sel_index + running_count + 1
-          ) as isize;
-          if running_height as isize + next_height > max_height {
-            break;
-          }
-
-          running_height += next_height;
-          running_count += 1;
-
-          if sel_index + running_count + 1 >= filtered_len {
-            break;
-          }
-        }
-
-        move_selection(Rc::clone(&state), -(running_count as isize))
+        let count = count_page(&state, selection.rel_index, false);
+        move_selection(state, -(count as isize))
       } else {
         // move to the bottom of the current page
-        move_selection(
-          Rc::clone(&state),
-          sel_index as isize - state.log.range_max as isize
-        )
+        let amount = steps_up(&state, selection.rel_index, bottom);
+        move_selection(state, amount)
       }
+    } else if newest_first(&state) {
+      // start the selection at the bottom of the current page
+      let amount = state.filtered_entries.borrow().len() as isize - bottom as isize;
+      move_selection(state, -amount)
     } else {
       // no-op
       state
     }
   }
 
+  /// Counts the entries in about half a screen from the selection, or from
+  /// the latest message if nothing is selected
+  fn count_half_page(state: &RcState, up: bool) -> usize {
+    let index = match state.log.selection {
+      Some(selection) => selection.rel_index,
+      None => state.filtered_entries.borrow().len()
+    };
+
+    count_rows(state, index, up, (state.height as isize - 1) / 2)
+  }
+
+  /// Moves the selection up by half a screen, like vim's ctrl-u
+  pub fn move_selection_half_page_up(state: RcState) -> RcState {
+    let count = count_half_page(&state, true);
+    move_selection(state, count as isize)
+  }
+
+  /// Moves the selection down by half a screen, like vim's ctrl-d
+  pub fn move_selection_half_page_down(state: RcState) -> RcState {
+    let count = count_half_page(&state, false);
+    move_selection(state, -(count as isize))
+  }
+
+  /// Selects the given (relative) index, roughly centering it on the screen
+  ///
+  /// Unlike `move_selection_to_index`, this doesn't depend on the previous
+  /// render and so is safe to use after `filtered_entries` is replaced.
+  pub fn select_index(mut state: RcState, index: usize) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.follow = false;
+    state_mut.log.selection = Some(Selection {
+      rel_index: index,
+      anchor: Anchor { offset: state_mut.height / 2, height: None }
+    });
+
+    state
+  }
+
+  /// Scrolls the log horizontally by some number of columns, positive to the
+  /// right, without scrolling past the end of the widest visible line
+  pub fn scroll_horizontal(mut state: RcState, amount: isize) -> RcState {
+    let limit = max_line_width(&state).saturating_sub(state.width as usize);
+    let scroll_x = max(state.log.scroll_x as isize + amount, 0) as usize;
+
+    Rc::make_mut(&mut state).log.scroll_x = min(scroll_x, limit);
+
+    state
+  }
+
+  /// Keeps the selection and the rendered range pointing at the same entries
+  /// after the entries at the (sorted) `removed` indices were removed from
+  /// `filtered_entries`, e.g. when old entries are evicted
+  ///
+  /// If the selected entry itself was removed, the selection is cleared.
+  pub fn remove_indices(mut state: RcState, removed: &[usize]) -> RcState {
+    if removed.is_empty() {
+      return state;
+    }
+
+    let shift = |index: usize| match removed.binary_search(&index) {
+      Ok(count) | Err(count) => index - count
+    };
+
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.log.range_min = shift(state_mut.log.range_min);
+    state_mut.log.range_max = shift(state_mut.log.range_max);
+
+    {
+      let mut anchors = state_mut.log.anchors.borrow_mut();
+      let shifted: BTreeMap<usize, Anchor> = anchors.iter()
+        .filter_map(|(i, a)| shift_index(*i, removed).map(|i| (i, *a)))
+        .collect();
+
+      *anchors = shifted;
+    }
+
+    if let Some(selection) = state_mut.log.selection {
+      match shift_index(selection.rel_index, removed) {
+        Some(rel_index) => {
+          state_mut.log.selection = Some(Selection { rel_index, ..selection });
+        },
+        None => return clear_selection(state)
+      }
+    }
+
+    state
+  }
+
+  /// Removes filtered entries whose messages have since been freed
+  pub fn prune_dropped(state: RcState) -> RcState {
+    let removed: Vec<usize> = state.filtered_entries.borrow().iter()
+      .enumerate()
+      .filter(|(_, e)| e.entry.strong_count() == 0)
+      .map(|(i, _)| i)
+      .collect();
+
+    if removed.is_empty() {
+      return state;
+    }
+
+    state.filtered_entries.borrow_mut()
+      .retain(|e| e.entry.strong_count() > 0);
+    state.filtered_changed();
+
+    remove_indices(state, &removed)
+  }
+
   pub fn clear_selection(mut state: RcState) -> RcState {
-    Rc::make_mut(&mut state).log.selection = None;
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.log.selection = None;
+    state_mut.follow = true;
 
     state
   }
+
+  /// Toggles following new messages; pausing selects the latest message so
+  /// the view stays put as more arrive
+  pub fn toggle_follow(mut state: RcState) -> RcState {
+    if !state.follow {
+      return clear_selection(state);
+    }
+
+    let len = state.filtered_entries.borrow().len();
+    if len == 0 {
+      Rc::make_mut(&mut state).follow = false;
+      state
+    } else {
+      move_selection_to_index(state, len - 1)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::config::Config;
+  use crate::renderer::interactive::state::actions as state_actions;
+
+  fn anchor(offset: u16, height: u16) -> Anchor {
+    Anchor { offset, height: Some(height) }
+  }
+
+  #[test]
+  fn test_visible_anchors_partial_top() {
+    // 10 rows (end_y = 10); entry 3 starts 2 rows above the screen
+    let anchors: BTreeMap<usize, Anchor> = btreemap! {
+      3 => anchor(12, 4),
+      4 => anchor(8, 3),
+      5 => anchor(5, 5)
+    };
+
+    assert_that!(visible_anchors(&anchors, 10)).is_equal_to(vec![3, 4, 5]);
+
+    // an entry entirely above the screen is not visible
+    let anchors: BTreeMap<usize, Anchor> = btreemap! {
+      2 => anchor(14, 2),
+      3 => anchor(12, 2),
+      4 => anchor(10, 10)
+    };
+
+    assert_that!(visible_anchors(&anchors, 10)).is_equal_to(vec![4]);
+  }
+
+  #[test]
+  fn test_anchor_at() {
+    let anchors: BTreeMap<usize, Anchor> = btreemap! {
+      3 => anchor(12, 4),
+      4 => anchor(8, 3),
+      5 => anchor(5, 5)
+    };
+
+    // entry 3 spans rows -2..2, entry 4 rows 2..5, entry 5 rows 5..10
+    assert_that!(anchor_at(&anchors, 10, 0)).is_equal_to(Some(3));
+    assert_that!(anchor_at(&anchors, 10, 2)).is_equal_to(Some(4));
+    assert_that!(anchor_at(&anchors, 10, 4)).is_equal_to(Some(4));
+    assert_that!(anchor_at(&anchors, 10, 9)).is_equal_to(Some(5));
+
+    // the bar is below the log area
+    assert_that!(anchor_at(&anchors, 10, 10)).is_none();
+  }
+
+  #[test]
+  fn test_visible_anchors_selection() {
+    // selection (entry 5) anchored mid-screen, with a tall entry 6 running
+    // off the bottom
+    let anchors: BTreeMap<usize, Anchor> = btreemap! {
+      4 => anchor(7, 2),
+      5 => anchor(5, 2),
+      6 => anchor(3, 8)
+    };
+
+    assert_that!(visible_anchors(&anchors, 10)).is_equal_to(vec![4, 5, 6]);
+
+    // with newest-first ordering, later entries are rendered above
+    let anchors: BTreeMap<usize, Anchor> = btreemap! {
+      4 => anchor(3, 2),
+      5 => anchor(5, 2),
+      6 => anchor(7, 2)
+    };
+
+    assert_that!(visible_anchors(&anchors, 10)).is_equal_to(vec![6, 5, 4]);
+  }
+
+  /// a 10 row log (plus the bar) of 20 single-line messages
+  fn test_state() -> RcState {
+    test_state_with(&[], |i| format!("message {}", i))
+  }
+
+  /// like `test_state()`, but with extra arguments and message texts
+  fn test_state_with<F>(args: &[&str], text: F) -> RcState
+  where
+    F: Fn(usize) -> String
+  {
+    let mut config_args = vec![""];
+    config_args.extend(args);
+    let config = Arc::new(Config::from_iter_safe(config_args).unwrap());
+    let mut state = Rc::new(RenderState::new(Arc::clone(&config)));
+    {
+      let state_mut = Rc::make_mut(&mut state);
+      state_mut.width = 80;
+      state_mut.height = 11;
+
+      // as if nothing has been rendered yet
+      state_mut.log.range_min = 20;
+      state_mut.log.range_max = 20;
+    }
+
+    for i in 0..20 {
+      let entry = MessageEntry::internal(&config, &text(i));
+      state = state_actions::add_entry(state, entry);
+    }
+
+    state
+  }
+
+  fn selected(state: &RcState) -> Option<usize> {
+    state.log.selection.map(|s| s.rel_index)
+  }
+
+  #[test]
+  fn test_move_selection_half_page_up() {
+    let state = test_state();
+
+    // from the latest message, 5 rows up
+    let state = actions::move_selection_half_page_up(state);
+    assert_that!(selected(&state)).is_equal_to(Some(15));
+
+    let state = actions::move_selection_half_page_up(state);
+    assert_that!(selected(&state)).is_equal_to(Some(10));
+
+    // stops at the first message
+    let state = actions::move_selection_to_index(state, 2);
+    let state = actions::move_selection_half_page_up(state);
+    assert_that!(selected(&state)).is_equal_to(Some(0));
+  }
+
+  #[test]
+  fn test_move_selection_half_page_down() {
+    let state = test_state();
+
+    // already following the latest message
+    let state = actions::move_selection_half_page_down(state);
+    assert_that!(selected(&state)).is_none();
+
+    let state = actions::move_selection_to_index(state, 3);
+    let state = actions::move_selection_half_page_down(state);
+    assert_that!(selected(&state)).is_equal_to(Some(8));
+
+    // stops at the latest message, then clears the selection
+    let state = actions::move_selection_to_index(state, 17);
+    let state = actions::move_selection_half_page_down(state);
+    assert_that!(selected(&state)).is_equal_to(Some(19));
+
+    let state = actions::move_selection_half_page_down(state);
+    assert_that!(selected(&state)).is_none();
+  }
+
+  #[test]
+  fn test_index_above_below() {
+    let state = test_state();
+    assert_that!(index_above(&state, 5, 2)).is_equal_to(Some(3));
+    assert_that!(index_above(&state, 0, 1)).is_none();
+    assert_that!(index_below(&state, 5, 2)).is_equal_to(Some(7));
+    assert_that!(index_below(&state, 18, 1)).is_equal_to(Some(19));
+    assert_that!(index_below(&state, 19, 1)).is_none();
+    assert_that!(steps_up(&state, 5, 2)).is_equal_to(3);
+    assert_that!(steps_up(&state, 2, 5)).is_equal_to(-3);
+
+    // with newest-first, later entries are visually above
+    let state = test_state_with(
+      &["--order", "newest-first"], |i| format!("message {}", i)
+    );
+    assert_that!(index_above(&state, 5, 2)).is_equal_to(Some(7));
+    assert_that!(index_above(&state, 19, 1)).is_none();
+    assert_that!(index_below(&state, 5, 2)).is_equal_to(Some(3));
+    assert_that!(index_below(&state, 0, 1)).is_none();
+    assert_that!(steps_up(&state, 5, 2)).is_equal_to(-3);
+    assert_that!(steps_up(&state, 2, 5)).is_equal_to(3);
+  }
+
+  #[test]
+  fn test_count_page() {
+    // 9 rows fit between the top of the screen and the old selection
+    let state = test_state();
+    assert_that!(actions::count_page(&state, 19, true)).is_equal_to(9);
+    assert_that!(actions::count_page(&state, 10, false)).is_equal_to(9);
+
+    // fewer at the edges, but always at least one
+    assert_that!(actions::count_page(&state, 3, true)).is_equal_to(3);
+    assert_that!(actions::count_page(&state, 16, false)).is_equal_to(3);
+    assert_that!(actions::count_page(&state, 0, true)).is_equal_to(1);
+    assert_that!(actions::count_page(&state, 19, false)).is_equal_to(1);
+  }
+
+  #[test]
+  fn test_count_page_wrapped() {
+    // every other message wraps onto 3 rows
+    let state = test_state_with(&[], |i| if i % 2 == 0 {
+      "x".repeat(120)
+    } else {
+      format!("message {}", i)
+    });
+    assert_that!(measure_entry(Rc::clone(&state), 18)).is_equal_to(3);
+
+    // above 19, 3 + 1 + 3 + 1 rows fit, but another 3 don't
+    assert_that!(actions::count_page(&state, 19, true)).is_equal_to(4);
+
+    // below 0, 1 + 3 + 1 + 3 + 1 rows fill the page exactly
+    assert_that!(actions::count_page(&state, 0, false)).is_equal_to(5);
+
+    // an entry taller than the screen is still one step
+    let state = test_state_with(&[], |_| "x".repeat(1000));
+    assert_that!(actions::count_page(&state, 19, true)).is_equal_to(1);
+  }
+
+  #[test]
+  fn test_move_selection_page() {
+    let mut state = test_state();
+    {
+      // as if the last 10 entries are on screen
+      let state_mut = Rc::make_mut(&mut state);
+      state_mut.log.range_min = 10;
+      state_mut.log.range_max = 19;
+    }
+
+    // starts at the top of the page, then moves up a full page
+    let state = actions::move_selection_page_up(state);
+    assert_that!(selected(&state)).is_equal_to(Some(10));
+
+    let state = actions::move_selection_page_up(state);
+    assert_that!(selected(&state)).is_equal_to(Some(1));
+
+    // nothing above the first entry
+    let state = actions::move_selection_to_index(state, 0);
+    let state = actions::move_selection_page_up(state);
+    assert_that!(selected(&state)).is_equal_to(Some(0));
+
+    // down to the bottom of the page, then past the latest entry
+    let state = actions::move_selection_to_index(state, 12);
+    let state = actions::move_selection_page_down(state);
+    assert_that!(selected(&state)).is_equal_to(Some(19));
+
+    let state = actions::move_selection_page_down(state);
+    assert_that!(selected(&state)).is_none();
+  }
+
+  #[test]
+  fn test_dropped_entries() {
+    let state = actions::select_index(test_state(), 10);
+
+    // free messages 0 and 5 out from under their filtered entries
+    state.entries.borrow_mut().remove(5);
+    state.entries.borrow_mut().remove(0);
+
+    assert_that!(render_lines(&state, 5, false)).is_equal_to(vec![]);
+    assert_that!(measure_entry(Rc::clone(&state), 0)).is_equal_to(0);
+    assert_that!(render_lines(&state, 10, true).len()).is_equal_to(1);
+
+    // measuring a page skips the dropped entries rather than panicking
+    actions::move_selection_page_up(Rc::clone(&state));
+
+    let state = actions::prune_dropped(state);
+    assert_that!(state.filtered_entries.borrow().len()).is_equal_to(18);
+    assert_that!(selected(&state)).is_equal_to(Some(8));
+
+    let text = state.filtered_entries.borrow()[8].entry.upgrade()
+      .and_then(|e| e.message.text.clone());
+    assert_that!(text).is_equal_to(Some(String::from("message 10")));
+  }
+
+  #[test]
+  fn test_scroll_horizontal() {
+    let mut state = test_state();
+    let config = Arc::clone(&state.config);
+    let entry = MessageEntry::internal(&config, &"x".repeat(200));
+    state = state_actions::add_entry(state, entry);
+    {
+      // as if the last 10 entries are on screen
+      let state_mut = Rc::make_mut(&mut state);
+      state_mut.log.range_min = 11;
+      state_mut.log.range_max = 20;
+    }
+
+    // wrapped to the terminal until scrolled
+    assert_that!(render_lines(&state, 20, false).len()).is_greater_than(1);
+
+    let state = actions::scroll_horizontal(state, SCROLL_STEP);
+    assert_that!(state.log.scroll_x).is_equal_to(SCROLL_STEP as usize);
+    assert_that!(render_lines(&state, 20, false).len()).is_equal_to(1);
+    assert_that!(measure_entry(Rc::clone(&state), 20)).is_equal_to(1);
+
+    // stops once the end of the longest line is on screen
+    let width = styled_width(&render_lines(&state, 20, false)[0]);
+    assert_that!(width).is_greater_than(200);
+
+    let state = actions::scroll_horizontal(state, 1000);
+    assert_that!(state.log.scroll_x).is_equal_to(width - 80);
+
+    let state = actions::scroll_horizontal(state, -1000);
+    assert_that!(state.log.scroll_x).is_equal_to(0);
+    assert_that!(render_lines(&state, 20, false).len()).is_greater_than(1);
+  }
 }
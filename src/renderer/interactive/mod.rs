@@ -1,5 +1,6 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
+use std::cmp::min;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::mpsc::Receiver;
@@ -18,16 +19,26 @@ pub mod bar;
 pub mod status_bar;
 pub mod filter_bar;
 pub mod search_bar;
+pub mod source_bar;
+pub mod jump_bar;
+pub mod field_bar;
+pub mod rate;
+pub mod detail;
+pub mod help;
+pub mod stats;
 
 pub use state::RenderState;
 pub use state::RcState;
 
 lazy_static! {
   /// The interval between full redraws even if no inputs occur
+  ///
+  /// This is independent of `--refresh-hz`, which sets how often the loop
+  /// checks for new messages and input
   static ref REFRESH_INTERVAL: Duration = Duration::from_millis(500);
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum InputAction {
   /// The application should exit
   Exit,
@@ -41,6 +52,8 @@ pub enum InputAction {
 
 pub fn interactive_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> JoinHandle<()> {
   thread::Builder::new().name("interactive".to_string()).spawn(move || {
+    let sleep_duration = Duration::from_secs_f32(1.0 / config.refresh_hz);
+    let mouse = !config.no_mouse;
     let mut rs = Rc::new(RenderState::new(config));
 
     let screen = Screen::default();
@@ -58,9 +71,16 @@ pub fn interactive_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> Join
 
     let input = TerminalInput::from_output(&alt.screen.stdout);
 
+    // terminals without mouse support ignore this and just never send any
+    // mouse events, so failures aren't fatal
+    if mouse {
+      input.enable_mouse_mode().ok();
+    }
+
     let mut stdin = input.read_async();
 
     let mut last_render: Option<Instant> = None;
+    let mut last_bar_render: Option<Instant> = None;
     let (mut last_width, mut last_height) = (0, 0);
     'outer: loop {
       // whether or not we should redraw at the end of this iter
@@ -68,7 +88,7 @@ pub fn interactive_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> Join
 
       for entry in rx.try_iter() {
         if let Some(message) = entry.message {
-          rs = state::actions::add_entry(rs.clone(), message);
+          rs = state::actions::receive(rs.clone(), message);
           dirty = true;
         }
 
@@ -80,15 +100,17 @@ pub fn interactive_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> Join
 
       // handle as many input events as we can
       while let Some(event) = stdin.next() {
-        if let InputEvent::Keyboard(key) = event {
-          let (new_state, action) = bar::input(rs.clone(), key);
-          rs = new_state;
-
-          match action {
-            InputAction::Exit => break 'outer,
-            InputAction::Rerender => dirty = true,
-            InputAction::Unhandled => ()
-          };
+        let (new_state, action) = match event {
+          InputEvent::Keyboard(key) => bar::input(rs.clone(), key),
+          InputEvent::Mouse(mouse) => log::mouse_input(rs.clone(), &mouse),
+          _ => continue
+        };
+        rs = new_state;
+
+        match action {
+          InputAction::Exit => break 'outer,
+          InputAction::Rerender => dirty = true,
+          InputAction::Unhandled => ()
         };
       }
 
@@ -97,7 +119,10 @@ pub fn interactive_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> Join
       let resized = (width != last_width) || (height != last_height);
       if resized {
         let rs_mut = Rc::make_mut(&mut rs);
-        rs_mut.width = width;
+        rs_mut.width = match rs_mut.config.max_width {
+          Some(max_width) => min(width as usize, max_width) as u16,
+          None => width
+        };
         rs_mut.height = height;
       }
 
@@ -109,25 +134,46 @@ pub fn interactive_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> Join
       // resizes won't be very smooth, but it will clean itself up
       // note that we also want to reduce unnecessary redraws as they can
       // clear a user's terminal selection
+      // relative times also need a periodic redraw to stay current
       let force_refresh = if let Some(last_render) = last_render {
-        resized && (last_render.elapsed() >= *REFRESH_INTERVAL)
+        (resized || rs.config.relative_time)
+          && (last_render.elapsed() >= *REFRESH_INTERVAL)
       } else {
         // first render
         true
       };
 
+      // the bar shows a live message rate, so redraw it periodically even if
+      // nothing else has changed
+      let refresh_bar = match last_bar_render {
+        Some(last_bar_render) => last_bar_render.elapsed() >= *REFRESH_INTERVAL,
+        None => true
+      };
+
       if dirty || force_refresh {
         // TODO actually render
         rs = log::render(rs.clone(), &terminal, &cursor).unwrap();
+        if detail::is_open(&rs) {
+          rs = detail::render(rs.clone(), &terminal, &cursor).unwrap();
+        }
+
         rs = bar::render(rs.clone(), &terminal, &cursor).unwrap();
 
         last_render = Some(Instant::now());
+        last_bar_render = last_render;
+      } else if refresh_bar && !rs.eof {
+        rs = bar::render(rs.clone(), &terminal, &cursor).unwrap();
+        last_bar_render = Some(Instant::now());
       }
 
-      thread::sleep(Duration::from_millis(25));
+      thread::sleep(sleep_duration);
     }
 
-    // attempt to un-hide the cursor on the way out
+    // attempt to un-hide the cursor on the way out, and release the mouse so
+    // the terminal's own selection works again
     cursor.show().ok();
+    if mouse {
+      input.disable_mouse_mode().ok();
+    }
   }).unwrap()
 }
@@ -0,0 +1,99 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// number of complete seconds averaged over
+const WINDOW_SECS: usize = 60;
+
+/// Counts incoming messages per second over a rolling window
+#[derive(Debug)]
+pub struct RateMeter {
+  start: Instant,
+
+  /// seconds since `start` of the last bucket in `counts`
+  second: u64,
+
+  /// per-second message counts, oldest first; the last is still in progress
+  counts: VecDeque<usize>
+}
+
+impl RateMeter {
+  pub fn new(start: Instant) -> Self {
+    let mut counts = VecDeque::with_capacity(WINDOW_SECS + 1);
+    counts.push_back(0);
+
+    RateMeter { start, second: 0, counts }
+  }
+
+  /// starts new buckets for any seconds that have passed
+  fn advance(&mut self, now: Instant) {
+    let second = now.duration_since(self.start).as_secs();
+
+    // no need to push more empty buckets than fit in the window
+    let elapsed = (second - self.second) as usize;
+    for _ in 0..elapsed.min(WINDOW_SECS + 1) {
+      self.counts.push_back(0);
+    }
+
+    while self.counts.len() > WINDOW_SECS + 1 {
+      self.counts.pop_front();
+    }
+
+    self.second = second;
+  }
+
+  /// counts one message received at `now`
+  pub fn record(&mut self, now: Instant) {
+    self.advance(now);
+
+    if let Some(count) = self.counts.back_mut() {
+      *count += 1;
+    }
+  }
+
+  /// returns the count for the last complete second and the average over the
+  /// window, or None if no second has completed yet
+  pub fn rate(&mut self, now: Instant) -> Option<(usize, f64)> {
+    self.advance(now);
+
+    let complete = self.counts.len() - 1;
+    if complete == 0 {
+      return None;
+    }
+
+    let last = self.counts[complete - 1];
+    let sum: usize = self.counts.iter().take(complete).sum();
+
+    Some((last, sum as f64 / complete as f64))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_rate() {
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+
+    let mut meter = RateMeter::new(start);
+    meter.record(at(100));
+    meter.record(at(200));
+    assert_that!(meter.rate(at(500))).is_none();
+
+    meter.record(at(1500));
+    assert_that!(meter.rate(at(2100))).is_equal_to(Some((1, 1.5)));
+
+    // idle seconds count towards the average
+    assert_that!(meter.rate(at(4000))).is_equal_to(Some((0, 0.75)));
+
+    // old seconds fall out of the window
+    assert_that!(meter.rate(at(120_000))).is_equal_to(Some((0, 0.0)));
+  }
+}
@@ -5,10 +5,11 @@ use std::rc::Rc;
 
 use crossterm::{Terminal, TerminalCursor, KeyEvent, ClearType};
 
-use crate::filter::{Filter, FilterMode};
+use crate::filter::{Filter, FilterMode, SearchScope};
+use crate::keys::Action;
 use crate::style::{StyleProfileKind, styler_base, styler_error};
 
-use super::state::RcState;
+use super::state::{FilteredEntry, RcState};
 use super::state::actions as state_actions;
 use super::bar::{self, BarType};
 use super::status_bar;
@@ -16,50 +17,106 @@ use super::log;
 use super::text::{self, TextBuffer, TextInputAction};
 use super::InputAction;
 
+/// entries matching the search filter, cached between renders
+#[derive(Clone)]
+struct MatchCache {
+  /// the `filtered_generation` when the matches were counted
+  generation: usize,
+
+  /// relative indices of matching entries, in order
+  indices: Rc<Vec<usize>>
+}
+
 #[derive(Clone)]
 pub struct SearchBarState {
   mode: FilterMode,
+  scope: SearchScope,
   text: TextBuffer,
   inverted: bool,
-  filter: Option<Rc<Box<dyn Filter>>>
+  filter: Option<Rc<Box<dyn Filter>>>,
+  matches: Option<MatchCache>
 }
 
 impl SearchBarState {
-  pub fn new() -> Self {
+  pub fn new(scope: SearchScope) -> Self {
     let styler = styler_base(StyleProfileKind::Selected);
 
     SearchBarState {
       mode: FilterMode::Regex,
+      scope,
       text: TextBuffer::new().with_styler(Some(styler)),
       inverted: false,
-      filter: None
+      filter: None,
+      matches: None
     }
   }
 }
 
+/// finds the relative indices of all filtered entries matching `filter`
+fn matching_indices(
+  entries: &[FilteredEntry], filter: &dyn Filter
+) -> Vec<usize> {
+  entries.iter()
+    .enumerate()
+    .filter(|(_, e)| match e.entry.upgrade() {
+      Some(entry) => filter.filter(&entry.message),
+      None => false
+    })
+    .map(|(i, _)| i)
+    .collect()
+}
+
+/// formats the selection's position among matches, e.g. `3 / 17`, or `- / 17`
+/// if the selection isn't a match
+fn format_position(indices: &[usize], selection: Option<usize>) -> String {
+  let position = selection.and_then(|s| indices.binary_search(&s).ok());
+
+  match position {
+    Some(position) => format!("{} / {}", position + 1, indices.len()),
+    None => format!("- / {}", indices.len())
+  }
+}
+
 fn format_right(state: &RcState) -> String {
-  if state.width < 80 {
+  let position = match &state.search.matches {
+    Some(matches) => format!(
+      "| {} ",
+      format_position(
+        &matches.indices,
+        state.log.selection.map(|s| s.rel_index)
+      )
+    ),
+    None => String::new()
+  };
+
+  let options = if state.width < 80 {
     let inv = if state.search.inverted { "y" } else { "n" };
 
     format!(
-      "| m: {} (C-r), i: {} (C-e)",
+      "| m: {} (C-r/l), s: {} (C-t), i: {} (C-e)",
       state.search.mode.name(),
+      state.search.scope.name(),
       inv
     )
   } else {
     let inv = if state.search.inverted { "yes" } else { "no" };
 
     format!(
-      "| mode: {} (C-r), invert: {} (C-e)",
+      "| mode: {} (C-r, C-l), scope: {} (C-t), invert: {} (C-e)",
       state.search.mode.name(),
+      state.search.scope.name(),
       inv
     )
-  }
+  };
+
+  position + &options
 }
 
 pub fn render(
-  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+  mut state: RcState, terminal: &Terminal, cursor: &TerminalCursor
 ) -> Result<RcState, Box<dyn Error>> {
+  state = actions::update_matches(state);
+
   cursor.goto(0, state.height - 1)?;
   terminal.clear(ClearType::CurrentLine)?;
 
@@ -139,18 +196,18 @@ pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
   };
 
   let final_action = match input_action {
-    InputAction::Unhandled => match key {
-      KeyEvent::Ctrl('p') => {
+    InputAction::Unhandled => match state.config.keybindings.action(key) {
+      Some(Action::Previous) => {
         state = actions::prev_match(state);
 
         InputAction::Rerender
       },
-      KeyEvent::Ctrl('n') => {
+      Some(Action::Next) => {
         state = actions::next_match(state, false);
 
         InputAction::Rerender
       },
-      KeyEvent::Ctrl('r') => {
+      Some(Action::CycleMode) => {
         state = actions::next_mode(state);
         state = actions::update_filter(state);
         state = actions::next_match(state, true);
@@ -159,7 +216,25 @@ pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
 
         InputAction::Rerender
       },
-      KeyEvent::Ctrl('e') => {
+      Some(Action::ToggleRegex) => {
+        state = actions::toggle_regex(state);
+        state = actions::update_filter(state);
+        state = actions::next_match(state, true);
+        state = actions::update_highlight(state);
+        state = actions::update_style(state);
+
+        InputAction::Rerender
+      },
+      Some(Action::CycleScope) => {
+        state = actions::next_scope(state);
+        state = actions::update_filter(state);
+        state = actions::next_match(state, true);
+        state = actions::update_highlight(state);
+        state = actions::update_style(state);
+
+        InputAction::Rerender
+      },
+      Some(Action::Invert) => {
         state = actions::toggle_inverted(state);
         state = actions::update_filter(state);
         state = actions::next_match(state, true);
@@ -185,7 +260,9 @@ pub mod actions {
 
     let new_filter = if input.is_empty() {
       None
-    } else if let Ok(parsed) = state.search.mode.parse(&input, state.search.inverted) {
+    } else if let Ok(parsed) = state.search.mode.parse(
+      &input, state.search.inverted, state.search.scope
+    ) {
       Some(Rc::new(parsed))
     } else {
       None
@@ -193,6 +270,32 @@ pub mod actions {
 
     let state_mut = Rc::make_mut(&mut state);
     state_mut.search.filter = new_filter;
+    state_mut.search.matches = None;
+
+    state
+  }
+
+  /// Counts entries matching the search filter, if the filter or the filtered
+  /// entries have changed since they were last counted
+  pub fn update_matches(mut state: RcState) -> RcState {
+    let generation = state.filtered_generation.get();
+    let stale = match &state.search.matches {
+      Some(matches) => matches.generation != generation,
+      None => state.search.filter.is_some()
+    };
+
+    if !stale {
+      return state;
+    }
+
+    let matches = state.search.filter.as_ref().map(|filter| MatchCache {
+      generation,
+      indices: Rc::new(matching_indices(
+        &state.filtered_entries.borrow(), filter.as_ref().as_ref()
+      ))
+    });
+
+    Rc::make_mut(&mut state).search.matches = matches;
 
     state
   }
@@ -219,7 +322,9 @@ pub mod actions {
     let input = &state_mut.search.text.input;
     let mode = &state_mut.search.mode;
 
-    let styler = if input.is_empty() || mode.parse(input, state_mut.search.inverted).is_ok() {
+    let styler = if input.is_empty() || mode.parse(
+      input, state_mut.search.inverted, state_mut.search.scope
+    ).is_ok() {
       styler_base(StyleProfileKind::Selected)
     } else {
       styler_error(StyleProfileKind::Selected)
@@ -336,6 +441,20 @@ pub mod actions {
     state
   }
 
+  pub fn toggle_regex(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.search.mode = state_mut.search.mode.toggle_regex();
+
+    state
+  }
+
+  pub fn next_scope(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.search.scope = state_mut.search.scope.next();
+
+    state
+  }
+
   pub fn toggle_inverted(mut state: RcState) -> RcState {
     let state_mut = Rc::make_mut(&mut state);
     state_mut.search.inverted = !state_mut.search.inverted;
@@ -343,3 +462,117 @@ pub mod actions {
     state
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use std::sync::Arc;
+
+  use crate::config::Config;
+  use crate::parser::ReaderMetadata;
+  use crate::renderer::interactive::state::RenderState;
+  use crate::renderer::types::MessageEntry;
+
+  fn count(texts: &[&str], query: &str) -> Vec<usize> {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let entries: Vec<Rc<MessageEntry>> = texts.iter()
+      .map(|text| Rc::new(MessageEntry::internal(&config, text)))
+      .collect();
+    let filtered: Vec<FilteredEntry> = entries.iter().enumerate()
+      .map(|(index, e)| FilteredEntry { index, entry: Rc::downgrade(e) })
+      .collect();
+
+    let filter = FilterMode::Text.parse(query, false, SearchScope::Text)
+      .unwrap();
+
+    matching_indices(&filtered, filter.as_ref())
+  }
+
+  #[test]
+  fn test_matching_indices() {
+    let texts = &["foo", "bar", "foo bar", "baz", "bar"];
+
+    assert_that!(count(texts, "qux")).is_equal_to(vec![]);
+    assert_that!(count(texts, "baz")).is_equal_to(vec![3]);
+    assert_that!(count(texts, "bar")).is_equal_to(vec![1, 2, 4]);
+    assert_that!(count(&[], "bar")).is_equal_to(vec![]);
+  }
+
+  /// a state searching for "foo" among messages from the given sources
+  fn search_state(args: &[&str], messages: &[(&str, &str)]) -> RcState {
+    let mut config_args = vec![""];
+    config_args.extend(args);
+    let config = Arc::new(Config::from_iter_safe(config_args).unwrap());
+
+    let mut state = Rc::new(RenderState::new(Arc::clone(&config)));
+    for (source, text) in messages {
+      state = add_message(state, source, text);
+    }
+
+    let filter = FilterMode::Text.parse("foo", false, SearchScope::Text)
+      .unwrap();
+    Rc::make_mut(&mut state).search.filter = Some(Rc::new(filter));
+
+    actions::update_matches(state)
+  }
+
+  fn add_message(state: RcState, source: &str, text: &str) -> RcState {
+    let mut entry = MessageEntry::internal(&state.config, text);
+    entry.message.reader_metadata = Some(ReaderMetadata {
+      source: Some(source.to_string()),
+      ..Default::default()
+    });
+
+    state_actions::add_entry(state, entry)
+  }
+
+  fn match_indices(state: &RcState) -> Vec<usize> {
+    state.search.matches.as_ref()
+      .map(|m| m.indices.as_ref().clone())
+      .unwrap_or_default()
+  }
+
+  #[test]
+  fn test_update_matches_grouped() {
+    let state = search_state(&[], &[("b", "foo"), ("a", "bar"), ("b", "foo")]);
+    assert_that!(match_indices(&state)).is_equal_to(vec![0, 2]);
+
+    // same entries, new order
+    let state = state_actions::toggle_group_by(state);
+    let state = actions::update_matches(state);
+    assert_that!(match_indices(&state)).is_equal_to(vec![1, 2]);
+  }
+
+  #[test]
+  fn test_update_matches_evicted() {
+    let state = search_state(
+      &["--max-entries", "3"], &[("a", "foo"), ("a", "bar"), ("a", "foo")]
+    );
+    assert_that!(match_indices(&state)).is_equal_to(vec![0, 2]);
+
+    // the oldest entry is evicted, so there are still 3
+    let state = add_message(state, "a", "bar");
+    assert_that!(state.filtered_entries.borrow().len()).is_equal_to(3);
+
+    let state = actions::update_matches(state);
+    assert_that!(match_indices(&state)).is_equal_to(vec![1]);
+  }
+
+  #[test]
+  fn test_format_position() {
+    assert_that!(format_position(&[], None).as_str()).is_equal_to("- / 0");
+    assert_that!(format_position(&[3], Some(3)).as_str()).is_equal_to("1 / 1");
+    assert_that!(format_position(&[1, 2, 4], Some(4)).as_str())
+      .is_equal_to("3 / 3");
+
+    // the selection isn't a match
+    assert_that!(format_position(&[1, 2, 4], Some(3)).as_str())
+      .is_equal_to("- / 3");
+    assert_that!(format_position(&[1, 2, 4], None).as_str())
+      .is_equal_to("- / 3");
+  }
+}
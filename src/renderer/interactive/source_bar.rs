@@ -0,0 +1,328 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::cmp::min;
+use std::error::Error;
+use std::rc::Rc;
+
+use crossterm::{Terminal, TerminalCursor, KeyEvent, ClearType};
+
+use crate::filter::{Filter, SourceFilter};
+use crate::keys::Action;
+use crate::style::{StyleProfileKind, styler_base};
+
+use super::state::RcState;
+use super::state::actions as state_actions;
+use super::bar::{self, BarType};
+use super::text::{self, TextBuffer, TextInputAction};
+use super::InputAction;
+
+/// maximum number of candidate sources to show at once
+const MAX_CANDIDATES: u16 = 10;
+
+#[derive(Clone)]
+pub struct SourceBarState {
+  text: TextBuffer,
+
+  /// the index of the highlighted candidate
+  index: usize
+}
+
+impl SourceBarState {
+  pub fn new() -> Self {
+    let styler = styler_base(StyleProfileKind::Selected);
+
+    SourceBarState {
+      text: TextBuffer::new().with_styler(Some(styler)),
+      index: 0
+    }
+  }
+}
+
+/// fuzzy-matches the query against a candidate, returning a score if all
+/// query characters appear in order; lower scores are tighter matches
+fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+  let candidate = candidate.to_lowercase();
+  let mut chars = candidate.char_indices();
+
+  let mut first = None;
+  let mut last = 0;
+  for q in query.to_lowercase().chars() {
+    match chars.find(|(_, c)| *c == q) {
+      Some((i, _)) => {
+        first = first.or(Some(i));
+        last = i;
+      },
+      None => return None
+    }
+  }
+
+  Some(last - first.unwrap_or(0))
+}
+
+/// returns all known sources matching the current input, best matches first
+fn candidates(state: &RcState) -> Vec<String> {
+  let query = &state.source.text.input;
+
+  let sources = state.sources.borrow();
+  let mut scored: Vec<(usize, &String)> = sources.iter()
+    .filter_map(|s| fuzzy_score(query, s).map(|score| (score, s)))
+    .collect();
+
+  // sources are already sorted, and sort_by_key is stable
+  scored.sort_by_key(|(score, _)| *score);
+  scored.into_iter().map(|(_, s)| s.clone()).collect()
+}
+
+pub fn render(
+  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+) -> Result<RcState, Box<dyn Error>> {
+  let selected = state.config.style.selected.get_base();
+  let normal = state.config.style.normal.get_base();
+
+  // draw the candidate list over the bottom of the log
+  let candidates = candidates(&state);
+  let rows = min(
+    min(candidates.len() as u16, MAX_CANDIDATES),
+    state.height.saturating_sub(2)
+  );
+
+  for (i, candidate) in candidates.iter().take(rows as usize).enumerate() {
+    let style = if i == state.source.index { selected } else { normal };
+
+    cursor.goto(0, state.height - 2 - i as u16)?;
+    terminal.clear(ClearType::CurrentLine)?;
+    terminal.write(style.paint(format!(" {}", candidate)))?;
+  }
+
+  cursor.goto(0, state.height - 1)?;
+  terminal.clear(ClearType::CurrentLine)?;
+  terminal.write(selected.paint(" ".repeat(state.width as usize)))?;
+
+  let right = format!("| {} sources (C-n, C-p)", candidates.len());
+  if let Some(col) = state.width.checked_sub(right.len() as u16) {
+    cursor.goto(col, state.height - 1)?;
+    terminal.write(selected.paint(right))?;
+  }
+
+  cursor.goto(0, state.height - 1)?;
+  terminal.write(selected.paint("source > "))?;
+  text::render(
+    Rc::clone(&state), &state.source.text,
+    terminal, cursor,
+    9, state.height - 1
+  )?;
+
+  Ok(state)
+}
+
+/// handles text component input in a pseudo-action
+///
+/// it doesn't /quite/ conform to the 'RcState in, RcState out' pattern so it
+/// isn't explicitly an action
+fn handle_text_input(
+  mut state: RcState, key: &KeyEvent
+) -> (RcState, TextInputAction) {
+  let state_mut = Rc::make_mut(&mut state);
+
+  let text_state = state_mut.source.text.clone();
+  let (text_state, action) = text::input(text_state, key);
+  state_mut.source.text = text_state;
+
+  (state, action)
+}
+
+pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
+  let (new_state, action) = handle_text_input(state, key);
+  state = new_state;
+
+  let input_action = match action {
+    TextInputAction::Action(a) => a,
+    TextInputAction::Exit(a) => {
+      state = actions::close(state);
+      a
+    },
+    TextInputAction::Submit(a, _) => {
+      state = actions::submit(state);
+      a
+    },
+    TextInputAction::Update(a) => {
+      state = actions::set_index(state, 0);
+      a
+    }
+  };
+
+  let final_action = match input_action {
+    InputAction::Unhandled => match state.config.keybindings.action(key) {
+      Some(Action::Next) => {
+        let index = state.source.index + 1;
+        state = actions::set_index(state, index);
+
+        InputAction::Rerender
+      },
+      Some(Action::Previous) => {
+        let index = state.source.index.saturating_sub(1);
+        state = actions::set_index(state, index);
+
+        InputAction::Rerender
+      },
+      _ => InputAction::Unhandled
+    },
+    _ => input_action
+  };
+
+  (state, final_action)
+}
+
+pub mod actions {
+  use super::*;
+
+  pub fn open(state: RcState) -> RcState {
+    if state.sources.borrow().is_empty() {
+      return state_actions::internal(state, "no message sources to pick from");
+    }
+
+    let state = set_index(state, 0);
+    bar::actions::set_active(state, BarType::Source)
+  }
+
+  pub fn close(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.source.text = text::actions::clear_input(
+      state_mut.source.text.clone()
+    );
+
+    bar::actions::set_active(state, BarType::Status)
+  }
+
+  /// highlights the candidate at the given index, clamped to the list
+  pub fn set_index(mut state: RcState, index: usize) -> RcState {
+    let len = candidates(&state).len();
+
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.source.index = min(index, len.saturating_sub(1));
+
+    state
+  }
+
+  /// adds a filter for the highlighted source
+  pub fn submit(state: RcState) -> RcState {
+    let candidate = candidates(&state).into_iter().nth(state.source.index);
+    let state = close(state);
+
+    match candidate {
+      Some(source) => match SourceFilter::new(&source, false) {
+        Ok(filter) => state_actions::add_filter(state, Box::new(filter)),
+        Err(e) => state_actions::internal(
+          state, &format!("invalid filter: {:?}", e)
+        )
+      },
+      None => state_actions::internal(state, "no matching source")
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::config::Config;
+  use crate::parser::{parse, ReaderMetadata};
+  use crate::renderer::interactive::state::RenderState;
+
+  fn test_state(sources: &[&str]) -> RcState {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let state = Rc::new(RenderState::new(config));
+    state.sources.borrow_mut()
+      .extend(sources.iter().map(|s| s.to_string()));
+
+    state
+  }
+
+  fn with_query(mut state: RcState, query: &str) -> RcState {
+    Rc::make_mut(&mut state).source.text.input = query.to_string();
+    state
+  }
+
+  #[test]
+  fn test_fuzzy_score() {
+    assert_that!(fuzzy_score("", "web-1")).is_equal_to(Some(0));
+    assert_that!(fuzzy_score("web", "web-1")).is_equal_to(Some(2));
+    assert_that!(fuzzy_score("WB1", "web-1")).is_equal_to(Some(4));
+    assert_that!(fuzzy_score("1w", "web-1")).is_none();
+    assert_that!(fuzzy_score("webx", "web-1")).is_none();
+  }
+
+  #[test]
+  fn test_candidates() {
+    let state = test_state(&["db-0/postgres", "web-1/app", "web-2/app"]);
+    assert_that!(candidates(&state)).has_length(3);
+
+    // tighter matches sort first, ties keep the sorted order
+    let state = with_query(state, "wp");
+    assert_that!(candidates(&state)).is_equal_to(vec![
+      String::from("web-1/app"),
+      String::from("web-2/app")
+    ]);
+
+    let state = with_query(state, "dbpg");
+    assert_that!(candidates(&state)).is_equal_to(vec![
+      String::from("db-0/postgres")
+    ]);
+
+    let state = with_query(state, "nope");
+    assert_that!(candidates(&state)).is_empty();
+  }
+
+  #[test]
+  fn test_set_index() {
+    let state = test_state(&["a", "b", "c"]);
+
+    let state = actions::set_index(state, 1);
+    assert_that!(state.source.index).is_equal_to(1);
+
+    let state = actions::set_index(state, 10);
+    assert_that!(state.source.index).is_equal_to(2);
+
+    let state = actions::set_index(with_query(state, "nope"), 1);
+    assert_that!(state.source.index).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_open_empty() {
+    let state = actions::open(test_state(&[]));
+    assert_that!(matches!(state.bar.active, BarType::Source)).is_false();
+
+    let state = actions::open(test_state(&["a"]));
+    assert_that!(matches!(state.bar.active, BarType::Source)).is_true();
+  }
+
+  #[test]
+  fn test_submit() {
+    let state = test_state(&["web-1/app", "web-2/app"]);
+    let state = actions::open(with_query(state, "web"));
+    let state = actions::set_index(state, 1);
+
+    let state = actions::submit(state);
+    assert_that!(matches!(state.bar.active, BarType::Status)).is_true();
+    assert_that!(state.source.text.input.as_str()).is_equal_to("");
+
+    let filters = state.filters.borrow();
+    assert_that!(filters.len()).is_equal_to(1);
+
+    let config = Arc::clone(&state.config);
+    let mut message = parse(config, "hello", None).unwrap().unwrap();
+    message.reader_metadata = Some(ReaderMetadata {
+      source: Some(String::from("web-2/app")),
+      ..Default::default()
+    });
+    assert_that!(filters[0].filter(&message)).is_true();
+
+    message.reader_metadata.as_mut().unwrap().source = Some("web-1/app".into());
+    assert_that!(filters[0].filter(&message)).is_false();
+  }
+}
@@ -1,23 +1,49 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, VecDeque};
+use std::cmp::{min, Ordering};
+use std::mem;
 use std::rc::{Rc, Weak};
 use std::sync::Arc;
+use std::time::Instant;
 
-use crate::config::Config;
+use crate::config::{Config, GroupBy};
 use crate::filter::Filter;
+use crate::parser::MessageKind;
+use crate::renderer::common::source_label;
 use crate::renderer::types::*;
 
-use super::log::LogState;
+use super::log::{self, LogState, Selection};
 use super::bar::BarState;
 use super::filter_bar::FilterBarState;
 use super::search_bar::SearchBarState;
+use super::source_bar::SourceBarState;
+use super::jump_bar::JumpBarState;
+use super::field_bar::FieldBarState;
+use super::rate::RateMeter;
+
+/// the most messages held while paused, if `--max-entries` isn't set
+const MAX_PAUSED_ENTRIES: usize = 100_000;
 
 pub struct FilteredEntry {
+  /// the entry's position in the input, i.e. its index in `entries` plus the
+  /// number of entries evicted before it
   pub index: usize,
   pub entry: Weak<MessageEntry>,
 }
 
+/// state saved while temporarily showing the unfiltered entries around a
+/// filtered message
+#[derive(Clone)]
+pub struct ContextState {
+  /// the filtered entries to restore when the context view is closed
+  pub filtered_entries: Rc<RefCell<Vec<FilteredEntry>>>,
+
+  /// the selection to restore when the context view is closed
+  pub selection: Option<Selection>
+}
+
 /// shared state between all components
 /// this struct is semi-immutable: each action should return a new clone, but
 /// certain fields (entries and filtered_entries) are shared as cloning would
@@ -37,6 +63,16 @@ pub struct RenderState {
   /// RenderState instances for performance.
   pub entries: Rc<RefCell<Vec<Rc<MessageEntry>>>>,
 
+  /// The set of all reader sources (e.g. pods) seen so far
+  pub sources: Rc<RefCell<BTreeSet<String>>>,
+
+  /// The set of displayed sources seen so far, if `--strip-source-common` is
+  /// set
+  pub source_labels: Rc<RefCell<BTreeSet<String>>>,
+
+  /// Counts of incoming messages per second, for the status bar
+  pub rate: Rc<RefCell<RateMeter>>,
+
   /// A list of filters used to generated `filtered_entries` from `entries`
   pub filters: Rc<RefCell<Vec<Box<dyn Filter>>>>,
 
@@ -45,19 +81,57 @@ pub struct RenderState {
   /// This list contains the subset of entries requested by the user
   pub filtered_entries: Rc<RefCell<Vec<FilteredEntry>>>,
 
+  /// Bumped whenever `filtered_entries` is rebuilt or modified, so anything
+  /// derived from it (e.g. search matches) knows when to refresh
+  pub filtered_generation: Rc<Cell<usize>>,
+
   /// A cached temporary filter representing the user's current filter input,
   /// if it exists and is valid.
   ///
   /// in a refcell because we can't clone all filter types :/
   pub highlight_filter: Option<Rc<Box<dyn Filter>>>,
 
+  /// If set, `filtered_entries` is temporarily showing unfiltered context
+  /// around a message and the real filtered list is stashed here
+  pub context: Option<ContextState>,
+
+  /// If true, a hex dump of the selected message's raw bytes is shown
+  pub detail: bool,
+
+  /// If true, the view stays on the latest message as new messages arrive;
+  /// selecting a message stops following
+  pub follow: bool,
+
+  /// If true, incoming messages are held in `paused_entries` rather than
+  /// added to the log
+  pub paused: bool,
+
+  /// Messages received while paused, in arrival order; only the latest
+  /// `--max-entries` (or `MAX_PAUSED_ENTRIES`) are kept
+  pub paused_entries: Rc<RefCell<VecDeque<MessageEntry>>>,
+
+  /// If true, the keybinding help overlay is shown
+  pub show_help: bool,
+
+  /// If true, a panel of message counts per level is shown
+  pub show_stats: bool,
+
+  /// The current grouping of `filtered_entries`, initially `--group-by`
+  pub group_by: GroupBy,
+
   /// If true, input EoF has been reached
   pub eof: bool,
 
+  /// The number of old entries dropped from `entries` due to `--max-entries`
+  pub evicted: usize,
+
   pub log: LogState,
   pub bar: BarState,
   pub filter: FilterBarState,
-  pub search: SearchBarState
+  pub search: SearchBarState,
+  pub source: SourceBarState,
+  pub jump: JumpBarState,
+  pub field: FieldBarState
 }
 
 /// A RenderState wrapped in a Cow for perf reasons
@@ -68,6 +142,9 @@ pub type RcState = Rc<RenderState>;
 // one of the refcell fields
 impl RenderState {
   pub fn new(config: Arc<Config>) -> Self {
+    let scope = config.search_scope;
+    let group_by = config.group_by;
+
     RenderState {
       config,
 
@@ -75,19 +152,47 @@ impl RenderState {
       height: 0,
 
       entries: Rc::new(RefCell::new(Vec::new())),
+      sources: Rc::new(RefCell::new(BTreeSet::new())),
+      source_labels: Rc::new(RefCell::new(BTreeSet::new())),
+      rate: Rc::new(RefCell::new(RateMeter::new(Instant::now()))),
       filters: Rc::new(RefCell::new(Vec::new())),
       filtered_entries: Rc::new(RefCell::new(Vec::new())),
+      filtered_generation: Rc::new(Cell::new(0)),
 
       highlight_filter: None,
 
+      context: None,
+
+      detail: false,
+
+      follow: true,
+
+      paused: false,
+      paused_entries: Rc::new(RefCell::new(VecDeque::new())),
+
+      show_help: false,
+
+      show_stats: false,
+
+      group_by,
+
       eof: false,
+      evicted: 0,
 
       log: LogState::new(),
       bar: BarState::new(),
-      filter: FilterBarState::new(),
-      search: SearchBarState::new()
+      filter: FilterBarState::new(scope),
+      search: SearchBarState::new(scope),
+      source: SourceBarState::new(),
+      jump: JumpBarState::new(),
+      field: FieldBarState::new()
     }
   }
+
+  /// marks `filtered_entries` as changed; see `filtered_generation`
+  pub fn filtered_changed(&self) {
+    self.filtered_generation.set(self.filtered_generation.get() + 1);
+  }
 }
 
 pub fn filter_pass(state: RcState, entry: &MessageEntry) -> bool {
@@ -105,56 +210,118 @@ pub fn filter_pass(state: RcState, entry: &MessageEntry) -> bool {
   true
 }
 
+/// returns the source an entry is grouped under with `--group-by source`
+pub fn group_key(entry: &FilteredEntry) -> Option<String> {
+  entry.entry.upgrade().and_then(|e| {
+    e.message.reader_metadata.as_ref().and_then(|m| m.source.clone())
+  })
+}
+
+/// builds the list of entries passing all current filters, grouped if needed
+fn filter_entries(state: &RcState) -> Vec<FilteredEntry> {
+  let mut filtered: Vec<FilteredEntry> = state.entries.borrow().iter()
+    .enumerate()
+    .filter(|(_, e)| filter_pass(Rc::clone(state), e))
+    .map(|(i, e)| FilteredEntry {
+      index: state.evicted + i,
+      entry: Rc::downgrade(e)
+    })
+    .collect();
+
+  if state.group_by == GroupBy::Source {
+    // the sort is stable, so each group stays in arrival order
+    filtered.sort_by_cached_key(group_key);
+  }
+
+  filtered
+}
+
+/// returns the index at which a new entry should be inserted into (sorted)
+/// `filtered_entries` to keep it at the end of its group
+fn group_position(filtered_entries: &[FilteredEntry], key: Option<&str>) -> usize {
+  // never equal, so this finds the first entry in a later group
+  let result = filtered_entries.binary_search_by(|e| {
+    let entry = e.entry.upgrade();
+    let source = entry.as_ref()
+      .and_then(|e| e.message.reader_metadata.as_ref())
+      .and_then(|m| m.source.as_deref());
+
+    if source <= key {
+      Ordering::Less
+    } else {
+      Ordering::Greater
+    }
+  });
+
+  match result {
+    Ok(i) | Err(i) => i
+  }
+}
+
+/// removes entries evicted from `entries` from a filtered list, returning
+/// their former positions
+///
+/// lists that are `sorted` by index only need to drop a prefix
+fn remove_evicted(
+  filtered_entries: &RefCell<Vec<FilteredEntry>>, evicted: usize, sorted: bool
+) -> Vec<usize> {
+  let mut filtered_entries = filtered_entries.borrow_mut();
+
+  if sorted {
+    let count = filtered_entries.iter()
+      .take_while(|e| e.index < evicted)
+      .count();
+
+    filtered_entries.drain(..count);
+    return (0..count).collect();
+  }
+
+  let removed = filtered_entries.iter()
+    .enumerate()
+    .filter(|(_, e)| e.index < evicted)
+    .map(|(i, _)| i)
+    .collect();
+
+  filtered_entries.retain(|e| e.index >= evicted);
+  removed
+}
+
 pub mod actions {
   use super::*;
 
   pub fn add_filter(mut state: RcState, filter: Box<dyn Filter>) -> RcState {
+    state = close_context(state);
+
+    // a new filter can only narrow the current set, so there's no need to
+    // check every entry again
+    state.filtered_entries.borrow_mut().retain(|e| match e.entry.upgrade() {
+      Some(entry) => filter.filter(&entry.message),
+      None => false
+    });
+    state.filtered_changed();
+
     let state_mut = Rc::make_mut(&mut state);
     state_mut.filters.borrow_mut().push(filter);
 
     // TODO: figure out how to keep the selection while adjusting filters
     state_mut.log.selection = None;
-
-    *state.filtered_entries.borrow_mut() = state.entries.borrow().iter()
-      .enumerate()
-      .filter(|(_, e)| filter_pass(Rc::clone(&state), e))
-      .map(|(i, e)| FilteredEntry {
-        index: i,
-        entry: Rc::downgrade(e)
-      })
-      .collect();
+    state_mut.follow = true;
 
     state
   }
 
   pub fn pop_filter(mut state: RcState) -> RcState {
+    state = close_context(state);
+
     let state_mut = Rc::make_mut(&mut state);
     state_mut.log.selection = None;
+    state_mut.follow = true;
 
     state.filters.borrow_mut().pop();
 
-    let new_filtered = if state.filters.borrow().is_empty() {
-      state.entries.borrow().iter()
-        .enumerate()
-        .filter(|(_, e)| filter_pass(Rc::clone(&state), e))
-        .map(|(i, e)| FilteredEntry {
-          index: i,
-          entry: Rc::downgrade(e)
-        })
-        .collect()
-
-    } else {
-      state.entries.borrow().iter()
-        .enumerate()
-        .filter(|(_, e)| filter_pass(Rc::clone(&state), e))
-        .map(|(i, e)| FilteredEntry {
-          index: i,
-          entry: Rc::downgrade(e)
-        })
-        .collect()
-    };
-
+    let new_filtered = filter_entries(&state);
     *state.filtered_entries.borrow_mut() = new_filtered;
+    state.filtered_changed();
 
     state
   }
@@ -174,27 +341,274 @@ pub mod actions {
     state
   }
 
-  pub fn add_entry(state: RcState, entry: MessageEntry) -> RcState {
+  pub fn add_entry(mut state: RcState, entry: MessageEntry) -> RcState {
+    state.rate.borrow_mut().record(Instant::now());
+
+    if let Some(meta) = &entry.message.reader_metadata {
+      if let Some(source) = &meta.source {
+        if !state.sources.borrow().contains(source) {
+          state.sources.borrow_mut().insert(source.clone());
+        }
+      }
+    }
+
+    if state.config.strip_source_common {
+      if let Some(label) = source_label(&entry) {
+        if !state.source_labels.borrow().contains(label) {
+          state.source_labels.borrow_mut().insert(label.to_string());
+        }
+      }
+    }
+
+    // where the entry was inserted into the filtered list, if anywhere
+    let mut inserted = None;
+
     {
       // this mut borrow needs to be dropped so we can return state
       let mut entries = state.entries.borrow_mut();
 
+      // bump the existing entry in place; it's already filtered (or not)
+      // correctly since the filters match on the same message
+      if state.config.dedup {
+        if let Some(last) = entries.last() {
+          if last.is_repeat(&entry) {
+            last.repeat.set(last.repeat.get() + 1);
+            return Rc::clone(&state);
+          }
+        }
+      }
+
+      // if the context view is open, keep the real filtered list up to date
+      let filtered_entries = match &state.context {
+        Some(context) => &context.filtered_entries,
+        None => &state.filtered_entries
+      };
+
       if filter_pass(Rc::clone(&state), &entry) {
         entries.push(Rc::new(entry));
-        state.filtered_entries.borrow_mut().push(FilteredEntry {
-          index: entries.len() - 1,
+
+        let filtered_entry = FilteredEntry {
+          index: state.evicted + entries.len() - 1,
           entry: Rc::downgrade(&entries[entries.len() - 1]),
-        });
+        };
+
+        let mut filtered_entries = filtered_entries.borrow_mut();
+        let position = if state.group_by == GroupBy::Source {
+          let added = &entries[entries.len() - 1];
+          let source = added.message.reader_metadata.as_ref()
+            .and_then(|m| m.source.as_deref());
+
+          group_position(&filtered_entries, source)
+        } else {
+          filtered_entries.len()
+        };
+
+        filtered_entries.insert(position, filtered_entry);
+        inserted = Some(position);
+        state.filtered_changed();
       } else {
         entries.push(Rc::new(entry));
       }
     }
 
+    // if the entry landed in the middle of its group, shift the selection so
+    // it stays on the same message
+    if let Some(position) = inserted {
+      let selection = match &state.context {
+        Some(context) => context.selection,
+        None => state.log.selection
+      };
+
+      if selection.filter(|s| position <= s.rel_index).is_some() {
+        let state_mut = Rc::make_mut(&mut state);
+        let selection = match &mut state_mut.context {
+          Some(context) => &mut context.selection,
+          None => &mut state_mut.log.selection
+        };
+
+        if let Some(selection) = selection {
+          selection.rel_index += 1;
+        }
+      }
+    }
+
+    state = evict(state);
+
+    // keep the latest message in view
+    if state.follow && state.log.selection.is_some() {
+      state = log::actions::clear_selection(state);
+    }
+
+    state
+  }
+
+  /// adds a newly received entry to the log, or holds it until resumed if
+  /// input is paused; internal messages, e.g. reader errors, are never held
+  pub fn receive(state: RcState, entry: MessageEntry) -> RcState {
+    if state.paused && entry.message.kind != MessageKind::Internal {
+      let limit = state.config.max_entries.unwrap_or(MAX_PAUSED_ENTRIES);
+
+      // older entries would be evicted on resume anyway
+      let mut held = state.paused_entries.borrow_mut();
+      held.push_back(entry);
+      while held.len() > limit {
+        held.pop_front();
+      }
+
+      drop(held);
+      return state;
+    }
+
+    add_entry(state, entry)
+  }
+
+  /// pauses or resumes input; resuming adds any held entries in order
+  pub fn toggle_pause(mut state: RcState) -> RcState {
+    if !state.paused {
+      Rc::make_mut(&mut state).paused = true;
+      return state;
+    }
+
+    Rc::make_mut(&mut state).paused = false;
+
+    let held = mem::take(&mut *state.paused_entries.borrow_mut());
+    for entry in held {
+      state = add_entry(state, entry);
+    }
+
+    state
+  }
+
+  /// drops the oldest entries beyond `--max-entries`, if set
+  ///
+  /// filtered lists only hold weak refs, so draining `entries` frees the
+  /// messages; the filtered lists and selections are then fixed up to match
+  fn evict(mut state: RcState) -> RcState {
+    let max_entries = match state.config.max_entries {
+      Some(max_entries) => max_entries,
+      None => return state
+    };
+
+    let count = state.entries.borrow().len().saturating_sub(max_entries);
+    if count == 0 {
+      return state;
+    }
+
+    state.entries.borrow_mut().drain(..count);
+
+    let grouped = state.group_by == GroupBy::Source;
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.evicted += count;
+    let evicted = state_mut.evicted;
+
+    // the stashed filtered list, if any, is kept up to date too
+    if let Some(context) = &mut state_mut.context {
+      let removed = remove_evicted(&context.filtered_entries, evicted, !grouped);
+      context.selection = context.selection.and_then(|mut selection| {
+        selection.rel_index = log::shift_index(selection.rel_index, &removed)?;
+        Some(selection)
+      });
+    }
+
+    // the context view is always sorted
+    let sorted = !grouped || state_mut.context.is_some();
+    let removed = remove_evicted(&state_mut.filtered_entries, evicted, sorted);
+    state_mut.filtered_changed();
+    log::actions::remove_indices(state, &removed)
+  }
+
+  /// switches between interleaved and grouped-by-source display, keeping the
+  /// current selection if possible
+  pub fn toggle_group_by(mut state: RcState) -> RcState {
+    state = close_context(state);
+
+    let selected = state.log.selection
+      .map(|s| state.filtered_entries.borrow()[s.rel_index].index);
+
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.group_by = match state_mut.group_by {
+      GroupBy::None => GroupBy::Source,
+      GroupBy::Source => GroupBy::None
+    };
+    state_mut.log.selection = None;
+    state_mut.follow = true;
+
+    let new_filtered = filter_entries(&state);
+    let rel_index = selected
+      .and_then(|index| new_filtered.iter().position(|e| e.index == index));
+    *state.filtered_entries.borrow_mut() = new_filtered;
+    state.filtered_changed();
+
+    match rel_index {
+      Some(rel_index) => log::actions::select_index(state, rel_index),
+      None => state
+    }
+  }
+
+  /// temporarily replaces the filtered entries with the unfiltered entries
+  /// surrounding the selected message, `--reveal-lines` in each direction
+  pub fn reveal_context(mut state: RcState) -> RcState {
+    let selection = match state.log.selection {
+      Some(selection) => selection,
+      None => return internal(state, "no message is selected")
+    };
+
+    let index = state.filtered_entries.borrow()[selection.rel_index].index;
+    let abs_index = index - state.evicted;
+    let lines = state.config.reveal_lines;
+
+    let context_entries: Vec<FilteredEntry> = {
+      let entries = state.entries.borrow();
+      let start = abs_index.saturating_sub(lines);
+      let end = min(abs_index + lines, entries.len() - 1);
+
+      (start..=end)
+        .map(|i| FilteredEntry {
+          index: state.evicted + i,
+          entry: Rc::downgrade(&entries[i])
+        })
+        .collect()
+    };
+
+    let rel_index = context_entries.iter()
+      .position(|e| e.index == index)
+      .unwrap_or(0);
+
+    {
+      let state_mut = Rc::make_mut(&mut state);
+
+      // if we're already showing context, keep the original filtered list
+      if state_mut.context.is_none() {
+        state_mut.context = Some(ContextState {
+          filtered_entries: Rc::clone(&state_mut.filtered_entries),
+          selection: Some(selection)
+        });
+      }
+
+      state_mut.filtered_entries = Rc::new(RefCell::new(context_entries));
+      state_mut.filtered_changed();
+    }
+
+    log::actions::select_index(state, rel_index)
+  }
+
+  /// closes the context view (if open), restoring the filtered entries
+  pub fn close_context(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+
+    if let Some(context) = state_mut.context.take() {
+      state_mut.filtered_entries = context.filtered_entries;
+      state_mut.filtered_changed();
+      state_mut.log.selection = context.selection;
+      state_mut.follow = context.selection.is_none();
+    }
+
     state
   }
 
   pub fn internal(state: RcState, text: &str) -> RcState {
-    add_entry(state, MessageEntry::internal(text))
+    let entry = MessageEntry::internal(&state.config, text);
+    add_entry(state, entry)
   }
 
   pub fn set_eof(mut state: RcState, eof: bool) -> RcState {
@@ -204,3 +618,351 @@ pub mod actions {
     state
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::filter::{FilterMode, SearchScope};
+  use crate::parser::ReaderMetadata;
+
+  fn add_messages(mut state: RcState, count: usize) -> RcState {
+    for i in 0..count {
+      let entry = MessageEntry::internal(&state.config, &format!("message {}", i));
+      state = actions::add_entry(state, entry);
+    }
+
+    state
+  }
+
+  fn test_state() -> RcState {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let mut state = Rc::new(RenderState::new(config));
+    {
+      let state_mut = Rc::make_mut(&mut state);
+      state_mut.width = 80;
+      state_mut.height = 11;
+
+      // as if nothing has been rendered yet
+      state_mut.log.range_min = 100;
+      state_mut.log.range_max = 100;
+    }
+
+    add_messages(state, 5)
+  }
+
+  #[test]
+  fn test_follow() {
+    let state = test_state();
+    assert_that!(state.follow).is_true();
+
+    let state = add_messages(state, 3);
+    assert_that!(state.follow).is_true();
+    assert_that!(state.log.selection.is_none()).is_true();
+  }
+
+  #[test]
+  fn test_follow_paused() {
+    let state = test_state();
+
+    // scrolling up stops following, and the selection stays put
+    let state = log::actions::move_selection(state, 2);
+    assert_that!(state.follow).is_false();
+
+    let state = add_messages(state, 3);
+    assert_that!(state.log.selection.map(|s| s.rel_index)).is_equal_to(Some(3));
+
+    // scrolling past the latest message resumes
+    let state = log::actions::move_selection_to_bottom(state);
+    assert_that!(state.follow).is_true();
+    assert_that!(state.log.selection.is_none()).is_true();
+  }
+
+  #[test]
+  fn test_toggle_follow() {
+    let state = test_state();
+
+    let state = log::actions::toggle_follow(state);
+    assert_that!(state.follow).is_false();
+    assert_that!(state.log.selection.map(|s| s.rel_index)).is_equal_to(Some(4));
+
+    let state = log::actions::toggle_follow(state);
+    assert_that!(state.follow).is_true();
+    assert_that!(state.log.selection.is_none()).is_true();
+  }
+
+  fn add_message(state: RcState, text: &str) -> RcState {
+    let entry = MessageEntry::internal(&state.config, text);
+    actions::add_entry(state, entry)
+  }
+
+  #[test]
+  fn test_dedup() {
+    let config = Config::from_iter_safe(vec!["", "--dedup"]).unwrap();
+    let state = Rc::new(RenderState::new(Arc::new(config)));
+
+    let state = add_message(state, "hello");
+    let state = add_message(state, "hello");
+    let state = add_message(state, "hello");
+
+    assert_that!(state.entries.borrow().len()).is_equal_to(1);
+    assert_that!(state.filtered_entries.borrow().len()).is_equal_to(1);
+    assert_that!(state.entries.borrow()[0].repeat.get()).is_equal_to(3);
+
+    // only consecutive repeats are collapsed
+    let state = add_message(state, "world");
+    let state = add_message(state, "hello");
+
+    let repeats: Vec<usize> = state.entries.borrow().iter()
+      .map(|e| e.repeat.get())
+      .collect();
+    assert_that!(repeats).is_equal_to(vec![3, 1, 1]);
+    assert_that!(state.filtered_entries.borrow().len()).is_equal_to(3);
+  }
+
+  #[test]
+  fn test_no_dedup() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let state = Rc::new(RenderState::new(Arc::new(config)));
+
+    let state = add_message(state, "hello");
+    let state = add_message(state, "hello");
+
+    assert_that!(state.entries.borrow().len()).is_equal_to(2);
+  }
+
+  fn capped_state(args: &[&str]) -> RcState {
+    let args = [&["", "--max-entries"], args].concat();
+    let config = Config::from_iter_safe(args).unwrap();
+    let mut state = Rc::new(RenderState::new(Arc::new(config)));
+    Rc::make_mut(&mut state).height = 11;
+
+    state
+  }
+
+  fn add_sourced(state: RcState, source: &str, text: &str) -> RcState {
+    let mut entry = MessageEntry::internal(&state.config, text);
+    entry.message.reader_metadata = Some(ReaderMetadata {
+      source: Some(source.to_string()),
+      ..Default::default()
+    });
+
+    actions::add_entry(state, entry)
+  }
+
+  #[test]
+  fn test_group_position() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let sourced = |source: Option<&str>| {
+      let mut entry = MessageEntry::internal(&config, "hello");
+      entry.message.reader_metadata = source.map(|s| ReaderMetadata {
+        source: Some(s.to_string()),
+        ..Default::default()
+      });
+
+      Rc::new(entry)
+    };
+
+    let entries = [
+      sourced(None), sourced(Some("a")), sourced(Some("a")), sourced(Some("c"))
+    ];
+    let filtered: Vec<FilteredEntry> = entries.iter()
+      .enumerate()
+      .map(|(index, e)| FilteredEntry { index, entry: Rc::downgrade(e) })
+      .collect();
+
+    assert_that!(group_position(&filtered, None)).is_equal_to(1);
+    assert_that!(group_position(&filtered, Some("a"))).is_equal_to(3);
+    assert_that!(group_position(&filtered, Some("b"))).is_equal_to(3);
+    assert_that!(group_position(&filtered, Some("c"))).is_equal_to(4);
+    assert_that!(group_position(&filtered, Some("d"))).is_equal_to(4);
+    assert_that!(group_position(&[], Some("a"))).is_equal_to(0);
+  }
+
+  fn filtered_texts(state: &RcState) -> Vec<String> {
+    state.filtered_entries.borrow().iter()
+      .filter_map(|e| e.entry.upgrade())
+      .filter_map(|e| e.message.text.clone())
+      .collect()
+  }
+
+  fn selected_text(state: &RcState) -> Option<String> {
+    let selection = state.log.selection?;
+    let entry = state.filtered_entries.borrow()[selection.rel_index].entry
+      .upgrade()?;
+
+    entry.message.text.clone()
+  }
+
+  #[test]
+  fn test_evict() {
+    let state = add_messages(capped_state(&["3"]), 3);
+    let oldest = Rc::downgrade(&state.entries.borrow()[0]);
+
+    let state = add_message(state, "message 3");
+    let state = add_message(state, "message 4");
+
+    assert_that!(state.entries.borrow().len()).is_equal_to(3);
+    assert_that!(state.evicted).is_equal_to(2);
+    assert_that!(filtered_texts(&state)).is_equal_to(vec![
+      String::from("message 2"),
+      String::from("message 3"),
+      String::from("message 4")
+    ]);
+
+    let indices: Vec<usize> = state.filtered_entries.borrow().iter()
+      .map(|e| e.index)
+      .collect();
+    assert_that!(indices).is_equal_to(vec![2, 3, 4]);
+
+    // nothing else holds a strong ref, so the message is freed
+    assert_that!(oldest.upgrade().is_none()).is_true();
+  }
+
+  #[test]
+  fn test_evict_selection() {
+    let state = add_messages(capped_state(&["5"]), 5);
+    let state = log::actions::select_index(state, 2);
+
+    let state = add_message(state, "message 5");
+    let state = add_message(state, "message 6");
+    assert_that!(state.log.selection.map(|s| s.rel_index)).is_equal_to(Some(0));
+    assert_that!(selected_text(&state))
+      .is_equal_to(Some(String::from("message 2")));
+
+    // the selected message itself is dropped
+    let state = add_message(state, "message 7");
+    assert_that!(state.log.selection.is_none()).is_true();
+    assert_that!(state.follow).is_true();
+  }
+
+  #[test]
+  fn test_evict_filtered() {
+    let state = capped_state(&["3"]);
+    let filter = FilterMode::Text.parse("keep", false, SearchScope::All)
+      .unwrap();
+    let state = actions::add_filter(state, filter);
+
+    let state = add_message(state, "keep 0");
+    let state = add_message(state, "drop 1");
+    let state = add_message(state, "keep 2");
+    let state = add_message(state, "keep 3");
+
+    assert_that!(filtered_texts(&state)).is_equal_to(vec![
+      String::from("keep 2"),
+      String::from("keep 3")
+    ]);
+  }
+
+  #[test]
+  fn test_evict_grouped() {
+    let state = capped_state(&["4", "--group-by", "source"]);
+    let state = add_sourced(state, "a", "a0");
+    let state = add_sourced(state, "b", "b1");
+    let state = add_sourced(state, "a", "a2");
+    let state = add_sourced(state, "b", "b3");
+    let state = log::actions::select_index(state, 3);
+
+    let state = add_sourced(state, "a", "a4");
+    assert_that!(filtered_texts(&state)).is_equal_to(vec![
+      String::from("a2"),
+      String::from("a4"),
+      String::from("b1"),
+      String::from("b3")
+    ]);
+    assert_that!(selected_text(&state)).is_equal_to(Some(String::from("b3")));
+
+    // regrouping still finds the selected message
+    let state = actions::toggle_group_by(state);
+    assert_that!(filtered_texts(&state)).is_equal_to(vec![
+      String::from("b1"),
+      String::from("a2"),
+      String::from("b3"),
+      String::from("a4")
+    ]);
+    assert_that!(selected_text(&state)).is_equal_to(Some(String::from("b3")));
+  }
+
+  #[test]
+  fn test_evict_context() {
+    let state = add_messages(capped_state(&["5"]), 8);
+    let state = log::actions::select_index(state, 1);
+    let state = actions::reveal_context(state);
+
+    assert_that!(filtered_texts(&state).len()).is_equal_to(5);
+    assert_that!(selected_text(&state))
+      .is_equal_to(Some(String::from("message 4")));
+
+    // the stashed list and selection are kept up to date
+    let state = add_message(state, "message 8");
+    let state = actions::close_context(state);
+    assert_that!(selected_text(&state))
+      .is_equal_to(Some(String::from("message 4")));
+    assert_that!(state.log.selection.map(|s| s.rel_index)).is_equal_to(Some(0));
+  }
+
+  fn receive(state: RcState, text: &str) -> RcState {
+    let entry = LogEntry::message(Arc::clone(&state.config), text, None)
+      .unwrap().unwrap().message.unwrap();
+    actions::receive(state, entry)
+  }
+
+  #[test]
+  fn test_pause() {
+    let state = actions::toggle_pause(test_state());
+    assert_that!(state.paused).is_true();
+
+    let state = receive(state, "a");
+    let state = receive(state, "b");
+    assert_that!(state.entries.borrow().len()).is_equal_to(5);
+    assert_that!(state.paused_entries.borrow().len()).is_equal_to(2);
+
+    // internal messages, e.g. errors, still show up immediately
+    let state = actions::internal(state, "hello");
+    assert_that!(state.entries.borrow().len()).is_equal_to(6);
+
+    let state = actions::toggle_pause(state);
+    assert_that!(state.paused).is_false();
+    assert_that!(state.paused_entries.borrow().len()).is_equal_to(0);
+
+    let texts = filtered_texts(&state);
+    assert_that!(texts[5..].to_vec()).is_equal_to(vec![
+      String::from("hello"),
+      String::from("a"),
+      String::from("b")
+    ]);
+
+    // once resumed, entries are added directly
+    let state = receive(state, "c");
+    assert_that!(state.entries.borrow().len()).is_equal_to(9);
+
+    // internal entries from readers aren't held
+    let state = actions::toggle_pause(state);
+    let entry = MessageEntry::internal(&state.config, "error: reader failed");
+    let state = actions::receive(state, entry);
+    assert_that!(state.entries.borrow().len()).is_equal_to(10);
+    assert_that!(state.paused_entries.borrow().len()).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_pause_max_entries() {
+    let config = Config::from_iter_safe(vec!["", "--max-entries", "3"]).unwrap();
+    let state = Rc::new(RenderState::new(Arc::new(config)));
+    let mut state = actions::toggle_pause(state);
+
+    for i in 0..5 {
+      state = receive(state, &format!("message {}", i));
+    }
+    assert_that!(state.paused_entries.borrow().len()).is_equal_to(3);
+
+    let state = actions::toggle_pause(state);
+    assert_that!(filtered_texts(&state)).is_equal_to(vec![
+      String::from("message 2"),
+      String::from("message 3"),
+      String::from("message 4")
+    ]);
+  }
+}
@@ -0,0 +1,188 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::rc::Rc;
+
+use crossterm::{Terminal, TerminalCursor};
+
+use crate::parser::LogLevel;
+use crate::renderer::common::slice_columns;
+use crate::renderer::types::MessageEntry;
+use super::state::{FilteredEntry, RcState};
+
+/// message counts shown in the stats panel
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+  /// all messages received, regardless of filters
+  pub total: usize,
+
+  /// messages passing the current filters
+  pub filtered: usize,
+
+  /// filtered messages per level
+  pub levels: BTreeMap<LogLevel, usize>,
+
+  /// filtered messages with no level
+  pub unleveled: usize
+}
+
+/// counts messages per level, respecting the current filters
+pub fn stats(entries: &[Rc<MessageEntry>], filtered: &[FilteredEntry]) -> Stats {
+  let mut stats = Stats {
+    total: entries.len(),
+    filtered: filtered.len(),
+    ..Stats::default()
+  };
+
+  for entry in filtered.iter().filter_map(|e| e.entry.upgrade()) {
+    match entry.message.level {
+      Some(level) => *stats.levels.entry(level).or_insert(0) += 1,
+      None => stats.unleveled += 1
+    }
+  }
+
+  stats
+}
+
+/// formats stats as label / count pairs
+fn stats_lines(stats: &Stats) -> Vec<(String, usize)> {
+  let mut lines = vec![
+    (String::from("total"), stats.total),
+    (String::from("filtered"), stats.filtered)
+  ];
+
+  for (level, count) in &stats.levels {
+    lines.push((level.to_string().to_lowercase(), *count));
+  }
+
+  if stats.unleveled > 0 {
+    lines.push((String::from("no level"), stats.unleveled));
+  }
+
+  lines
+}
+
+/// draws the stats panel, if open, in the top right corner of the log
+pub fn render(
+  state: RcState, terminal: &Terminal, cursor: &TerminalCursor
+) -> Result<RcState, Box<dyn Error>> {
+  if !state.show_stats {
+    return Ok(state);
+  }
+
+  let stats = stats(&state.entries.borrow(), &state.filtered_entries.borrow());
+  let lines = stats_lines(&stats);
+
+  let label_width = lines.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+  let count_width = lines.iter()
+    .map(|(_, c)| c.to_string().len())
+    .max()
+    .unwrap_or(0);
+
+  let inner_width = label_width + count_width + 2;
+  let box_width = min(inner_width + 2, state.width as usize);
+  let left = state.width as usize - box_width;
+  let rows = min(lines.len(), state.height.saturating_sub(1) as usize);
+
+  let style = state.config.style.selected.get_base();
+  for (i, (label, count)) in lines.iter().take(rows).enumerate() {
+    let line = format!(
+      " {:label_width$}  {:>count_width$} ",
+      label, count, label_width = label_width, count_width = count_width
+    );
+
+    cursor.goto(left as u16, i as u16)?;
+    terminal.write(style.paint(slice_columns(&line, 0, box_width)))?;
+  }
+
+  Ok(state)
+}
+
+pub mod actions {
+  use super::*;
+
+  pub fn toggle(mut state: RcState) -> RcState {
+    let state_mut = Rc::make_mut(&mut state);
+    state_mut.show_stats = !state_mut.show_stats;
+
+    state
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::config::Config;
+
+  fn entry(config: &Config, level: Option<LogLevel>) -> Rc<MessageEntry> {
+    let mut entry = MessageEntry::internal(config, "hello");
+    entry.message.level = level;
+
+    Rc::new(entry)
+  }
+
+  #[test]
+  fn test_stats() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let entries = vec![
+      entry(&config, Some(LogLevel::Info)),
+      entry(&config, Some(LogLevel::Error)),
+      entry(&config, Some(LogLevel::Info)),
+      entry(&config, None),
+      entry(&config, Some(LogLevel::Warning))
+    ];
+
+    // as if the warning were filtered out
+    let filtered: Vec<FilteredEntry> = entries.iter().enumerate()
+      .filter(|(i, _)| *i != 4)
+      .map(|(index, e)| FilteredEntry { index, entry: Rc::downgrade(e) })
+      .collect();
+
+    assert_that!(stats(&entries, &filtered)).is_equal_to(Stats {
+      total: 5,
+      filtered: 4,
+      levels: btreemap! {
+        LogLevel::Info => 2,
+        LogLevel::Error => 1
+      },
+      unleveled: 1
+    });
+  }
+
+  #[test]
+  fn test_stats_empty() {
+    assert_that!(stats(&[], &[])).is_equal_to(Stats::default());
+
+    let lines = stats_lines(&Stats::default());
+    assert_that!(lines).is_equal_to(vec![
+      (String::from("total"), 0),
+      (String::from("filtered"), 0)
+    ]);
+  }
+
+  #[test]
+  fn test_stats_lines() {
+    let config = Config::from_iter_safe(vec![""]).unwrap();
+    let entries = vec![
+      entry(&config, Some(LogLevel::Error)),
+      entry(&config, None)
+    ];
+    let filtered: Vec<FilteredEntry> = entries.iter().enumerate()
+      .map(|(index, e)| FilteredEntry { index, entry: Rc::downgrade(e) })
+      .collect();
+
+    let lines = stats_lines(&stats(&entries, &filtered));
+    assert_that!(lines).is_equal_to(vec![
+      (String::from("total"), 2),
+      (String::from("filtered"), 2),
+      (String::from("error"), 1),
+      (String::from("no level"), 1)
+    ]);
+  }
+}
@@ -1,13 +1,22 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::error::Error;
+use std::time::Instant;
 
 use crossterm::{Terminal, TerminalCursor, KeyEvent, ClearType};
 
 use crate::clip::{clip, clipboard_enabled};
+use crate::config::GroupBy;
+use crate::keys::Action;
 use crate::renderer::interactive::InputAction;
 use crate::renderer::interactive::bar::{self, BarType};
+use crate::renderer::interactive::detail;
+use crate::renderer::interactive::field_bar;
+use crate::renderer::interactive::help;
+use crate::renderer::interactive::jump_bar;
 use crate::renderer::interactive::log;
+use crate::renderer::interactive::source_bar;
+use crate::renderer::interactive::stats;
 use crate::renderer::interactive::state::RcState;
 use crate::renderer::interactive::state::actions as state_actions;
 use crate::renderer::plain::plain_render;
@@ -16,9 +25,25 @@ fn format_left(state: &RcState) -> (usize, String) {
   let mut buf = String::new();
   buf.push_str("q: quit | f: filter | /: find");
 
+  if !state.sources.borrow().is_empty() {
+    buf.push_str(" | s: sources | g: group");
+  }
+
+  if detail::is_open(state) {
+    buf.push_str(" | x: close raw");
+  } else if state.log.selection.is_some() {
+    buf.push_str(" | x: raw bytes");
+  }
+
+  if state.context.is_some() {
+    buf.push_str(" | esc: close context");
+  } else if state.log.selection.is_some() {
+    buf.push_str(" | r: reveal context");
+  }
+
   if clipboard_enabled() {
     if state.log.selection.is_some() {
-      buf.push_str(" | c: copy msg");
+      buf.push_str(" | c: copy msg | y: copy field");
     }
 
     buf.push_str(" | S-c: copy screen");
@@ -28,6 +53,14 @@ fn format_left(state: &RcState) -> (usize, String) {
     buf.push_str(" | p: pop filter");
   }
 
+  if !state.follow {
+    buf.push_str(" | S-f: follow");
+  }
+
+  if state.paused {
+    buf.push_str(" | space: resume");
+  }
+
   (buf.len(), buf)
 }
 
@@ -37,7 +70,24 @@ pub fn format_right(state: &RcState) -> (usize, String) {
   let len_filtered_entries = state.filtered_entries.borrow().len();
 
   let eof = if state.eof { " (eof)" } else { "" };
-  let filters = if len_filters == 0 {
+  let follow = if state.follow && !state.eof { " (follow)" } else { "" };
+  let paused = if state.paused {
+    format!(" [PAUSED] ({} held)", state.paused_entries.borrow().len())
+  } else {
+    String::new()
+  };
+  let rate = match state.rate.borrow_mut().rate(Instant::now()) {
+    Some((last, average)) if !state.eof => {
+      format!("{}/s ({:.1}/s avg) | ", last, average)
+    },
+    _ => String::new()
+  };
+  let context = if state.context.is_some() { " (context)" } else { "" };
+  let grouped = match state.group_by {
+    GroupBy::Source => " (by source)",
+    GroupBy::None => ""
+  };
+  let filters = if len_filters == 0 || state.context.is_some() {
     "".to_string()
   } else {
     format!(
@@ -55,7 +105,10 @@ pub fn format_right(state: &RcState) -> (usize, String) {
   };
 
   // this will need to change if any parts are styled in the future
-  let right = format!("{}{}{}", count, filters, eof);
+  let right = format!(
+    "{}{}{}{}{}{}{}{}",
+    rate, count, grouped, context, filters, follow, paused, eof
+  );
   (right.len(), right)
 }
 
@@ -103,41 +156,61 @@ pub fn render(
 }
 
 pub fn input(mut state: RcState, key: &KeyEvent) -> (RcState, InputAction) {
-  state = match key {
-    KeyEvent::Esc => {
-      if state.log.selection.is_some() {
-        log::actions::clear_selection(state)
+  let action = state.config.keybindings.action(key);
+
+  state = match action {
+    Some(Action::Quit) => return (state, InputAction::Exit),
+    Some(Action::Filter) => bar::actions::set_active(state, BarType::Filter),
+    Some(Action::Search) => bar::actions::set_active(state, BarType::Search),
+    Some(Action::PopFilter) => {
+      if state.filters.borrow().is_empty() {
+        state_actions::internal(state, "no filters to remove")
       } else {
+        state_actions::pop_filter(state)
+      }
+    },
+    Some(Action::Reveal) => state_actions::reveal_context(state),
+    Some(Action::Source) => source_bar::actions::open(state),
+    Some(Action::Detail) => detail::actions::toggle(state),
+    Some(Action::Group) => state_actions::toggle_group_by(state),
+    Some(Action::Follow) => log::actions::toggle_follow(state),
+    Some(Action::Pause) => state_actions::toggle_pause(state),
+    Some(Action::Stats) => stats::actions::toggle(state),
+    Some(Action::Copy) => {
+      // ctrl-c quits as usual unless a message is selected
+      if state.log.selection.is_none() && *key == KeyEvent::Ctrl('c') {
         return (state, InputAction::Exit)
       }
-    }
-    KeyEvent::Char(c) => match c {
-      'q' => return (state, InputAction::Exit),
-      '|' | 'f' => bar::actions::set_active(state, BarType::Filter),
-      '/' => bar::actions::set_active(state, BarType::Search),
-      'p' => {
-        if state.filters.borrow().is_empty() {
-          state_actions::internal(state, "no filters to remove")
-        } else {
-          state_actions::pop_filter(state)
-        }
-      },
-      'c' => actions::copy_selection(state),
-      'C' => actions::copy_view(state),
-      _ => return (state, InputAction::Unhandled)
+
+      actions::copy_selection(state)
+    },
+    Some(Action::CopyScreen) => actions::copy_view(state),
+    Some(Action::CopyField) => field_bar::actions::open(state),
+    Some(Action::CopyRaw) => actions::copy_raw(state),
+    Some(Action::Help) => help::actions::open(state),
+    Some(Action::ScrollLeft) => {
+      log::actions::scroll_horizontal(state, -log::SCROLL_STEP)
     },
-    KeyEvent::Ctrl(c) => match c {
-      'c' => {
-        if state.log.selection.is_some() {
-          actions::copy_selection(state)
+    Some(Action::ScrollRight) => {
+      log::actions::scroll_horizontal(state, log::SCROLL_STEP)
+    },
+    _ => match key {
+      KeyEvent::Esc => {
+        if detail::is_open(&state) {
+          detail::actions::close(state)
+        } else if state.show_stats {
+          stats::actions::toggle(state)
+        } else if state.context.is_some() {
+          state_actions::close_context(state)
+        } else if state.log.selection.is_some() {
+          log::actions::clear_selection(state)
         } else {
           return (state, InputAction::Exit)
         }
       },
-      'f' => bar::actions::set_active(state, BarType::Search),
+      KeyEvent::Char(c @ '0'..='9') => jump_bar::actions::open(state, *c),
       _ => return (state, InputAction::Unhandled)
     }
-    _ => return (state, InputAction::Unhandled)
   };
 
   (state, InputAction::Rerender)
@@ -151,13 +224,13 @@ pub mod actions {
       return state;
     }
 
-    if let Some(selection) = state.log.selection {
-      let plain = {
-        let entry = &state.filtered_entries.borrow()[selection.rel_index];
-        plain_render(&entry.entry.upgrade().unwrap())
-      }.join("\n");
+    let entry = state.log.selection.and_then(|selection| {
+      state.filtered_entries.borrow()[selection.rel_index].entry.upgrade()
+    });
+
+    if let Some(entry) = entry {
+      let plain = plain_render(&entry).join("\n");
 
-      // TODO: handle unset weak ref
       match clip(plain) {
         Ok(()) => state_actions::internal(state, "copied message to clipboard"),
         Err(e) => state_actions::internal(
@@ -169,6 +242,32 @@ pub mod actions {
     }
   }
 
+  /// copies the selected message's original line, exactly as it was read
+  pub fn copy_raw(state: RcState) -> RcState {
+    if !clipboard_enabled() {
+      return state;
+    }
+
+    let selection = match state.log.selection {
+      Some(selection) => selection,
+      None => return state_actions::internal(state, "no message is selected")
+    };
+
+    let raw = state.filtered_entries.borrow()[selection.rel_index].entry
+      .upgrade()
+      .map(|entry| entry.message.raw.clone());
+
+    match raw.map(clip) {
+      Some(Ok(())) => state_actions::internal(
+        state, "copied original line to clipboard"
+      ),
+      Some(Err(e)) => state_actions::internal(
+        state, &format!("error writing to clipboard: {:?}", e)
+      ),
+      None => state
+    }
+  }
+
   pub fn copy_view(state: RcState) -> RcState {
     if !clipboard_enabled() {
       return state;
@@ -176,17 +275,26 @@ pub mod actions {
 
     let mut lines = 0;
     let mut buf = String::new();
-    for i in state.log.range_min..=state.log.range_max {
-      let entry = &state.filtered_entries.borrow()[i];
-
-      // TODO: handle unset weak ref
-      for line in plain_render(&entry.entry.upgrade().unwrap()) {
-        buf.push_str(&line);
-        buf.push('\n');
-        lines += 1;
+    {
+      let filtered_entries = state.filtered_entries.borrow();
+      for i in log::visible_indices(&state) {
+        let entry = match filtered_entries[i].entry.upgrade() {
+          Some(entry) => entry,
+          None => continue
+        };
+
+        for line in plain_render(&entry) {
+          buf.push_str(&line);
+          buf.push('\n');
+          lines += 1;
+        }
       }
     }
 
+    if lines == 0 {
+      return state_actions::internal(state, "nothing on screen to copy");
+    }
+
     match clip(buf) {
       Ok(()) => state_actions::internal(
         state, &format!("copied {} lines to clipboard", lines)
@@ -1,6 +1,7 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::error::Error;
+use std::mem;
 
 use crossterm::{Terminal, TerminalCursor, KeyEvent};
 
@@ -8,11 +9,24 @@ use crate::style::Styler;
 use super::InputAction;
 use super::state::RcState;
 
+/// maximum number of previous inputs remembered by each text bar
+const HISTORY_LIMIT: usize = 100;
+
 #[derive(Clone)]
 pub struct TextBuffer {
   pub input: String,
   pub position: usize,
-  pub styler: Option<Styler>
+  pub styler: Option<Styler>,
+
+  /// previously submitted inputs, oldest first
+  pub history: Vec<String>,
+
+  /// the history entry currently recalled into `input`, if any
+  pub history_index: Option<usize>,
+
+  /// the input being edited before history was recalled, restored once the
+  /// user scrolls back past the newest entry
+  pub draft: String
 }
 
 impl TextBuffer {
@@ -20,7 +34,10 @@ impl TextBuffer {
     TextBuffer {
       input: String::new(),
       position: 1,
-      styler: None
+      styler: None,
+      history: Vec::new(),
+      history_index: None,
+      draft: String::new()
     }
   }
 
@@ -69,8 +86,17 @@ pub fn input(
     },
     KeyEvent::Char('\n') => {
       let input = state.input.to_string();
+      state = actions::push_history(state, &input);
       TextInputAction::Submit(InputAction::Rerender, input)
     },
+    KeyEvent::Up => {
+      state = actions::recall_history(state, true);
+      TextInputAction::Update(InputAction::Rerender)
+    },
+    KeyEvent::Down => {
+      state = actions::recall_history(state, false);
+      TextInputAction::Update(InputAction::Rerender)
+    },
     KeyEvent::Left => {
       state = actions::cursor_left(state);
       TextInputAction::Action(InputAction::Rerender)
@@ -122,6 +148,59 @@ pub mod actions {
   pub fn clear_input(mut state: TextBuffer) -> TextBuffer {
     state.input.clear();
     state.position = 1;
+    state.history_index = None;
+
+    state
+  }
+
+  /// records a submitted input, skipping empty inputs and repeats of the
+  /// latest entry
+  pub fn push_history(mut state: TextBuffer, input: &str) -> TextBuffer {
+    state.history_index = None;
+
+    let latest = state.history.last().map(String::as_str);
+    if input.is_empty() || latest == Some(input) {
+      return state;
+    }
+
+    state.history.push(input.to_string());
+    if state.history.len() > HISTORY_LIMIT {
+      state.history.remove(0);
+    }
+
+    state
+  }
+
+  /// replaces the input with an older or newer history entry
+  ///
+  /// the history wraps around, passing through the original input on the way
+  pub fn recall_history(mut state: TextBuffer, older: bool) -> TextBuffer {
+    let len = state.history.len();
+    if len == 0 {
+      return state;
+    }
+
+    // indexes 0..len are history entries, and len is the original input
+    let current = state.history_index.unwrap_or(len);
+    let next = if older {
+      (current + len) % (len + 1)
+    } else {
+      (current + 1) % (len + 1)
+    };
+
+    if state.history_index.is_none() {
+      state.draft = mem::take(&mut state.input);
+    }
+
+    if next == len {
+      state.history_index = None;
+      state.input = mem::take(&mut state.draft);
+    } else {
+      state.history_index = Some(next);
+      state.input = state.history[next].clone();
+    }
+
+    state.position = state.input.chars().count() + 1;
 
     state
   }
@@ -198,3 +277,76 @@ pub mod actions {
     state
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  fn submit(state: TextBuffer, input: &str) -> TextBuffer {
+    let state = actions::clear_input(state);
+    let state = input.chars().fold(state, actions::push_input);
+    let (state, _) = super::input(state, &KeyEvent::Char('\n'));
+
+    state
+  }
+
+  fn press(state: TextBuffer, key: KeyEvent) -> TextBuffer {
+    super::input(state, &key).0
+  }
+
+  #[test]
+  fn test_history() {
+    let state = submit(TextBuffer::new(), "foo");
+    let state = submit(state, "bar");
+    let state = submit(state, "bar");
+    let state = submit(state, "");
+    assert_that!(state.history).is_equal_to(vec![
+      String::from("foo"), String::from("bar")
+    ]);
+
+    let state = actions::push_input(actions::clear_input(state), 'x');
+
+    let state = press(state, KeyEvent::Up);
+    assert_that!(state.input.as_str()).is_equal_to("bar");
+    assert_that!(state.position).is_equal_to(4);
+
+    let state = press(state, KeyEvent::Up);
+    assert_that!(state.input.as_str()).is_equal_to("foo");
+
+    // wraps around through the original input
+    let state = press(state, KeyEvent::Up);
+    assert_that!(state.input.as_str()).is_equal_to("x");
+    assert_that!(state.history_index).is_none();
+
+    let state = press(state, KeyEvent::Up);
+    assert_that!(state.input.as_str()).is_equal_to("bar");
+
+    let state = press(state, KeyEvent::Down);
+    assert_that!(state.input.as_str()).is_equal_to("x");
+
+    let state = press(state, KeyEvent::Down);
+    assert_that!(state.input.as_str()).is_equal_to("foo");
+  }
+
+  #[test]
+  fn test_history_empty() {
+    let state = actions::push_input(TextBuffer::new(), 'x');
+
+    let state = press(state, KeyEvent::Up);
+    assert_that!(state.input.as_str()).is_equal_to("x");
+
+    let state = press(state, KeyEvent::Down);
+    assert_that!(state.input.as_str()).is_equal_to("x");
+  }
+
+  #[test]
+  fn test_history_limit() {
+    let state = (0..HISTORY_LIMIT + 5)
+      .fold(TextBuffer::new(), |state, i| submit(state, &i.to_string()));
+
+    assert_that!(state.history.len()).is_equal_to(HISTORY_LIMIT);
+    assert_that!(state.history[0].as_str()).is_equal_to("5");
+  }
+}
@@ -0,0 +1,196 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+use chrono::SecondsFormat;
+
+use crate::config::Config;
+use crate::parser::Message;
+use crate::parser::util::nicer_to_string;
+use crate::renderer::types::*;
+use crate::renderer::common::write_entries;
+
+/// true if a character can't appear in a bare logfmt key or value
+fn is_special(c: char) -> bool {
+  c.is_whitespace() || c.is_control() || c == '"' || c == '=' || c == '\\'
+}
+
+/// formats a key, replacing any characters logfmt doesn't allow in keys
+fn format_key(s: &str) -> String {
+  if s.is_empty() {
+    return String::from("_");
+  }
+
+  s.chars().map(|c| if is_special(c) { '_' } else { c }).collect()
+}
+
+/// formats a value, quoting it if it contains spaces, quotes, or `=`, and
+/// escaping any control characters
+fn format_value(s: &str) -> String {
+  let needs_quotes = s.is_empty() || s.chars().any(is_special);
+
+  if !needs_quotes {
+    return s.to_string();
+  }
+
+  let mut ret = String::with_capacity(s.len() + 2);
+  ret.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => ret.push_str(r#"\""#),
+      '\\' => ret.push_str(r"\\"),
+      '\n' => ret.push_str(r"\n"),
+      '\t' => ret.push_str(r"\t"),
+      '\r' => ret.push_str(r"\r"),
+      c if c.is_control() => ret.push_str(&format!(r"\u{:04x}", c as u32)),
+      c => ret.push(c)
+    }
+  }
+  ret.push('"');
+
+  ret
+}
+
+/// converts a message back into a logfmt line, e.g.
+/// `time=2020-01-01T00:00:00Z level=info msg="hello world" foo=bar`
+pub fn logfmt_line(message: &Message) -> String {
+  let mut pairs = Vec::new();
+
+  if let Some(timestamp) = message.timestamp {
+    let time = timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+    pairs.push((String::from("time"), time));
+  }
+
+  if let Some(level) = message.level {
+    pairs.push((String::from("level"), level.to_string().to_lowercase()));
+  }
+
+  if let Some(text) = &message.text {
+    pairs.push((String::from("msg"), text.clone()));
+  }
+
+  // keep the source when following multiple inputs, e.g. kubernetes pods
+  let source = message.reader_metadata.as_ref().and_then(|m| m.source.as_ref());
+  if let Some(source) = source {
+    if !message.metadata.contains_key("source") {
+      pairs.push((String::from("source"), source.clone()));
+    }
+  }
+
+  for (key, value) in &message.metadata {
+    let value = nicer_to_string(value);
+
+    // like logrus, prefix fields that clash with the keys added above
+    let key = format_key(key);
+    let key = if pairs.iter().any(|(k, _)| *k == key) {
+      format!("fields.{}", key)
+    } else {
+      key
+    };
+
+    pairs.push((key, value));
+  }
+
+  pairs.iter()
+    .map(|(key, value)| format!("{}={}", key, format_value(value)))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+pub fn logfmt_renderer(
+  _: Arc<Config>, rx: Receiver<LogEntry>
+) -> JoinHandle<()> {
+  thread::Builder::new().name("logfmt_renderer".to_string()).spawn(move || {
+    let stdout = io::stdout();
+
+    let render = |entry: &MessageEntry| vec![logfmt_line(&entry.message)];
+    if let Err(e) = write_entries(stdout.lock(), rx, render) {
+      eprintln!("error writing output: {}", e);
+    }
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::parser::{parse, MessageKind};
+
+  #[test]
+  fn test_format_value() {
+    assert_that!(format_value("bar")).is_equal_to(String::from("bar"));
+    assert_that!(format_value("")).is_equal_to(String::from(r#""""#));
+    assert_that!(format_value("hello world"))
+      .is_equal_to(String::from(r#""hello world""#));
+    assert_that!(format_value(r#"say "hi""#))
+      .is_equal_to(String::from(r#""say \"hi\"""#));
+    assert_that!(format_value("a=b")).is_equal_to(String::from(r#""a=b""#));
+    assert_that!(format_value("a\r\nb"))
+      .is_equal_to(String::from(r#""a\r\nb""#));
+    assert_that!(format_value("bell\u{7}"))
+      .is_equal_to(String::from(r#""bell\u0007""#));
+  }
+
+  #[test]
+  fn test_format_key() {
+    assert_that!(format_key("foo.bar")).is_equal_to(String::from("foo.bar"));
+    assert_that!(format_key("a b=c\"d\\e"))
+      .is_equal_to(String::from("a_b_c_d_e"));
+    assert_that!(format_key("tab\there\n"))
+      .is_equal_to(String::from("tab_here_"));
+    assert_that!(format_key("")).is_equal_to(String::from("_"));
+  }
+
+  #[test]
+  fn test_invalid_keys() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"msg":"hello","user id":"alice","a=b":"c"}"#;
+    let json = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+
+    let logfmt = logfmt_line(&json);
+    assert_that!(logfmt.as_str())
+      .is_equal_to("msg=hello user_id=alice a_b=c");
+
+    let parsed = parse(config, &logfmt, None).unwrap().unwrap();
+    assert_that!(parsed.kind).is_equal_to(MessageKind::Logrus);
+    assert_that!(parsed.metadata.len()).is_equal_to(2);
+  }
+
+  #[test]
+  fn test_reserved_keys() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"timestamp":"2020-01-01T00:00:00Z","level":"info","text":"hello","time":"soon","msg":"other"}"#;
+    let json = parse(config, line, None).unwrap().unwrap();
+
+    assert_that!(logfmt_line(&json).as_str()).is_equal_to(concat!(
+      r#"time=2020-01-01T00:00:00Z level=info msg=hello "#,
+      r#"fields.time=soon fields.msg=other"#
+    ));
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"time":"2020-01-01T00:00:00Z","level":"warn","msg":"hello world","foo":"bar","n":8,"quote":"say \"hi\""}"#;
+    let json = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+
+    let logfmt = logfmt_line(&json);
+    assert_that!(logfmt.as_str()).is_equal_to(concat!(
+      r#"time=2020-01-01T00:00:00Z level=warning msg="hello world" "#,
+      r#"foo=bar n=8 quote="say \"hi\"""#
+    ));
+
+    let parsed = parse(config, &logfmt, None).unwrap().unwrap();
+    assert_that!(parsed.kind).is_equal_to(MessageKind::Logrus);
+    assert_that!(parsed.timestamp).is_equal_to(json.timestamp);
+    assert_that!(parsed.level).is_equal_to(json.level);
+    assert_that!(parsed.text).is_equal_to(json.text);
+    assert_that!(parsed.metadata).is_equal_to(json.metadata);
+  }
+}
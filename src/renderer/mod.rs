@@ -3,14 +3,20 @@
 mod types;
 mod common;
 mod json;
+mod logfmt;
+mod ndjson;
 mod plain;
 mod styled;
 mod raw;
+pub mod glyphs;
 pub mod interactive;
 
 pub use types::*;
+pub use common::set_timestamp_format;
 pub use styled::styled_renderer;
 pub use interactive::interactive_renderer;
 pub use plain::plain_renderer;
 pub use json::json_renderer;
+pub use logfmt::logfmt_renderer;
+pub use ndjson::ndjson_renderer;
 pub use raw::raw_renderer;
@@ -0,0 +1,111 @@
+// (C) Copyright 2020 Hewlett Packard Enterprise Development LP
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+use chrono::SecondsFormat;
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use crate::parser::Message;
+use crate::renderer::types::*;
+
+/// converts a message into a compact json object with a stable field order:
+/// timestamp, level, text, source, metadata; missing fields are omitted, but
+/// metadata is always present
+pub fn ndjson_line(message: &Message) -> String {
+  let mut object = Map::new();
+
+  if let Some(timestamp) = message.timestamp {
+    let time = timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+    object.insert(String::from("timestamp"), Value::String(time));
+  }
+
+  if let Some(level) = message.level {
+    let level = level.to_string().to_lowercase();
+    object.insert(String::from("level"), Value::String(level));
+  }
+
+  if let Some(text) = &message.text {
+    object.insert(String::from("text"), Value::String(text.clone()));
+  }
+
+  let source = message.reader_metadata.as_ref().and_then(|m| m.source.as_ref());
+  if let Some(source) = source {
+    object.insert(String::from("source"), Value::String(source.clone()));
+  }
+
+  object.insert(
+    String::from("metadata"),
+    Value::Object(message.metadata.clone())
+  );
+
+  Value::Object(object).to_string()
+}
+
+pub fn ndjson_renderer(
+  _: Arc<Config>, rx: Receiver<LogEntry>
+) -> JoinHandle<()> {
+  thread::Builder::new().name("ndjson_renderer".to_string()).spawn(move || {
+    let stdout = io::stdout();
+
+    for entry in rx {
+      if entry.eof.is_some() {
+        break;
+      }
+
+      if let Some(message) = entry.message {
+        let mut handle = stdout.lock();
+
+        // flush each line so downstream tools (e.g. jq) see it immediately;
+        // stop quietly if the pipe is closed
+        let line = ndjson_line(&message.message);
+        if writeln!(handle, "{}", line).and_then(|_| handle.flush()).is_err() {
+          break;
+        }
+      }
+    }
+  }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::parser::{parse, ReaderMetadata};
+
+  fn message(line: &str) -> Message {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    parse(config, line, None).unwrap().unwrap()
+  }
+
+  #[test]
+  fn test_ndjson_json() {
+    let mut message = message(
+      r#"{"msg":"hello world","zeta":1,"level":"warn","alpha":"a","time":"2020-01-01T00:00:00Z"}"#
+    );
+    message.reader_metadata = Some(ReaderMetadata {
+      source: Some(String::from("web-1/app")),
+      ..Default::default()
+    });
+
+    assert_that!(ndjson_line(&message).as_str()).is_equal_to(concat!(
+      r#"{"timestamp":"2020-01-01T00:00:00Z","level":"warning","#,
+      r#""text":"hello world","source":"web-1/app","#,
+      r#""metadata":{"zeta":1,"alpha":"a"}}"#
+    ));
+  }
+
+  #[test]
+  fn test_ndjson_plain() {
+    let message = message("just some \"text\"");
+
+    assert_that!(ndjson_line(&message).as_str())
+      .is_equal_to(r#"{"text":"just some \"text\"","metadata":{}}"#);
+  }
+}
@@ -1,7 +1,7 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
 use std::cmp::max;
-use std::io::{self, Write};
+use std::io;
 use std::sync::Arc;
 use std::sync::mpsc::Receiver;
 use std::thread::{self, JoinHandle};
@@ -27,7 +27,7 @@ fn plain_render_chunk(chunk: &Chunk) -> Vec<RenderedChunk> {
       value.clone()
     };
 
-    let length = content.chars().count();
+    let length = display_width(&content);
     rendered_chunks.push(RenderedChunk {
       content,
       width: length,
@@ -88,20 +88,38 @@ pub fn plain_render(entry: &MessageEntry) -> Vec<String> {
 
 pub fn plain_renderer(_: Arc<Config>, rx: Receiver<LogEntry>) -> JoinHandle<()> {
   thread::Builder::new().name("plain_renderer".to_string()).spawn(move || {
-    for entry in rx {
-      if entry.eof.is_some() {
-        break;
-      }
-
-      if let Some(message) = entry.message {
-        for line in plain_render(&message) {
-          // println! may fail when piped to e.g. head
-          // see also: https://github.com/rust-lang/rust/issues/24821
-          if writeln!(io::stdout(), "{}", line).is_err() {
-            break;
-          }
-        }
-      }
+    let stdout = io::stdout();
+
+    if let Err(e) = write_entries(stdout.lock(), rx, plain_render) {
+      eprintln!("error writing output: {}", e);
     }
   }).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::cell::Cell;
+
+  use spectral::prelude::*;
+  use structopt::StructOpt;
+
+  use crate::classifier::classify;
+  use crate::parser::parse;
+
+  #[test]
+  fn test_plain_render_trace() {
+    let config = Arc::new(Config::from_iter_safe(vec![""]).unwrap());
+    let line = r#"{"level":"trace","msg":"hello"}"#;
+    let message = parse(Arc::clone(&config), line, None).unwrap().unwrap();
+    let chunks = classify(&config, &message);
+
+    let entry = MessageEntry { message, chunks, repeat: Cell::new(1) };
+    let rendered = plain_render(&entry);
+    assert_that!(rendered).has_length(1);
+
+    // levels are right-aligned in a fixed-width column
+    assert_that!(rendered[0].ends_with("   trace hello")).is_true();
+  }
+}
@@ -1,5 +1,8 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
+use std::cmp::min;
+use std::collections::BTreeSet;
+use std::io;
 use std::sync::Arc;
 use std::sync::mpsc::Receiver;
 use std::thread::{self, JoinHandle};
@@ -23,21 +26,37 @@ pub fn styled_renderer(config: Arc<Config>, rx: Receiver<LogEntry>) -> JoinHandl
 
     let profile = &config.style.normal;
 
-    for entry in rx {
-      if let Some(message_entry) = entry.message {
-        let term_width = match term.terminal_size().0 as usize {
-          0 => Some(config.fallback_width),
-          width => Some(width)
-        };
+    // displayed sources seen so far, for `--strip-source-common`
+    let mut labels = BTreeSet::new();
 
-        for line in styled_render(&message_entry, &profile, term_width) {
-          println!("{}", line);
-        }
+    let stdout = io::stdout();
+    let result = write_entries(stdout.lock(), rx, |message_entry| {
+      let term_width = match term.terminal_size().0 as usize {
+        0 => config.fallback_width,
+        width => width
+      };
+
+      let term_width = match config.max_width {
+        Some(max_width) => Some(min(term_width, max_width)),
+        None => Some(term_width)
+      };
+
+      if !config.strip_source_common {
+        return styled_render(message_entry, profile, term_width);
       }
 
-      if entry.eof.is_some() {
-        break;
+      if let Some(label) = source_label(message_entry) {
+        if !labels.contains(label) {
+          labels.insert(label.to_string());
+        }
       }
+
+      let message_entry = strip_source_common(message_entry, &labels);
+      styled_render(&message_entry, profile, term_width)
+    });
+
+    if let Err(e) = result {
+      eprintln!("error writing output: {}", e);
     }
   }).unwrap()
 }
@@ -1,5 +1,6 @@
 // (C) Copyright 2019 Hewlett Packard Enterprise Development LP
 
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::error::Error;
 use std::sync::Arc;
@@ -7,39 +8,75 @@ use std::sync::mpsc::Receiver;
 use std::thread::JoinHandle;
 
 use chrono::offset::Utc;
+use serde_json::Map;
 
 use crate::config::Config;
 use crate::parser::{LogLevel, Message, MessageKind, ReaderMetadata, parse};
 use crate::classifier::{Chunk, classify};
 
+/// infers the level of an internal message from its prefix, e.g. `error: ...`
+fn internal_level(message: &str) -> LogLevel {
+  if message.starts_with("error:") {
+    LogLevel::Error
+  } else if message.starts_with("warning:") {
+    LogLevel::Warning
+  } else {
+    LogLevel::Info
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct MessageEntry {
   pub message: Message,
   pub chunks: Vec<Chunk>,
+
+  /// number of consecutive times this message was received, if deduplicated
+  pub repeat: Cell<usize>
 }
 
 impl MessageEntry {
   /// creates an internal log message, e.g. to inform the user of an internal
   /// error
-  pub fn internal(message: &str) -> MessageEntry {
+  ///
+  /// the level is inferred from an `error:` or `warning:` prefix, otherwise
+  /// it's `info`
+  pub fn internal(config: &Config, message: &str) -> MessageEntry {
+    MessageEntry::internal_level(config, internal_level(message), message)
+  }
+
+  /// creates an internal log message with the given level
+  pub fn internal_level(
+    config: &Config, level: LogLevel, message: &str
+  ) -> MessageEntry {
     let m = Message {
       kind: MessageKind::Internal,
       timestamp: Some(Utc::now()),
-      level: Some(LogLevel::Int),
+      level: Some(level),
       raw: message.to_string(),
       text: Some(message.to_string()),
-      metadata: HashMap::new(),
+      metadata: Map::new(),
       reader_metadata: None,
       mapped_fields: HashMap::new(),
     };
 
-    let chunks = classify(&m);
+    let chunks = classify(config, &m);
 
     MessageEntry {
       message: m,
-      chunks
+      chunks,
+      repeat: Cell::new(1)
     }
   }
+
+  /// true if `other` is a repeat of this message, ignoring its timestamp
+  pub fn is_repeat(&self, other: &MessageEntry) -> bool {
+    let source = |e: &MessageEntry| e.message.reader_metadata.as_ref()
+      .and_then(|m| m.source.clone());
+
+    self.message.text == other.message.text
+      && self.message.level == other.message.level
+      && source(self) == source(other)
+  }
 }
 
 /// A LogEntry sent when the end of input is reached
@@ -72,23 +109,43 @@ impl LogEntry {
   pub fn message(
     config: Arc<Config>, line: &str, meta: Option<ReaderMetadata>
   ) -> Result<Option<LogEntry>, Box<dyn Error>> {
-    let message = match parse(config, &line, meta)? {
+    let message = match parse(Arc::clone(&config), &line, meta)? {
       Some(message) => message,
       None => return Ok(None)
     };
 
-    let chunks = classify(&message);
+    Ok(Some(LogEntry::parsed(&config, message)))
+  }
 
-    Ok(Some(LogEntry {
-      message: Some(MessageEntry { message, chunks }),
+  /// classifies an already-parsed message, e.g. one a reader has added fields
+  /// to
+  pub fn parsed(config: &Config, message: Message) -> LogEntry {
+    let chunks = classify(config, &message);
+
+    LogEntry {
+      message: Some(MessageEntry {
+        message,
+        chunks,
+        repeat: Cell::new(1)
+      }),
 
       ..Default::default()
-    }))
+    }
+  }
+
+  pub fn internal(config: &Config, message: &str) -> LogEntry {
+    LogEntry {
+      message: Some(MessageEntry::internal(config, message)),
+
+      ..Default::default()
+    }
   }
 
-  pub fn internal(message: &str) -> LogEntry {
+  pub fn internal_level(
+    config: &Config, level: LogLevel, message: &str
+  ) -> LogEntry {
     LogEntry {
-      message: Some(MessageEntry::internal(message)),
+      message: Some(MessageEntry::internal_level(config, level, message)),
 
       ..Default::default()
     }
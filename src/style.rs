@@ -10,11 +10,11 @@ use std::str::FromStr;
 
 use ansi_term::{Style, Color};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 use serde::de::{self, Visitor, Unexpected, Deserializer};
 use shellexpand;
 
-use crate::classifier::ChunkKind;
+use crate::classifier::{Chunk, ChunkKind, ValueKind};
 use crate::parser::LogLevel;
 
 struct ColorFromStr;
@@ -88,12 +88,21 @@ impl Base16 {
       ChunkKind::Date => base.fg(self.base03),
       ChunkKind::Time => base.fg(self.base03),
       ChunkKind::FieldKey => base.fg(self.base0C),
+      ChunkKind::FieldValue(ValueKind::Number) => base.fg(self.base09),
+      ChunkKind::FieldValue(ValueKind::Bool) => base.fg(self.base0C),
+      ChunkKind::FieldValue(ValueKind::Null) => base.fg(self.base03),
+      ChunkKind::Quoted => base.fg(self.base0B),
       ChunkKind::Context => base.fg(self.base03),
+      ChunkKind::Repeat => base.fg(self.base0E),
+      ChunkKind::Unparsed => base.fg(self.base03),
 
+      ChunkKind::Level(LogLevel::Trace) => base.fg(self.base03),
       ChunkKind::Level(LogLevel::Debug) => base.fg(self.base0C),
       ChunkKind::Level(LogLevel::Info) => base.fg(self.base0B),
+      ChunkKind::Level(LogLevel::Notice) => base.fg(self.base0D),
       ChunkKind::Level(LogLevel::Warning) => base.fg(self.base0A),
       ChunkKind::Level(LogLevel::Error) => base.fg(self.base09),
+      ChunkKind::Level(LogLevel::Critical) => base.fg(self.base08),
       ChunkKind::Level(LogLevel::Fatal) => base.fg(self.base08),
       ChunkKind::Level(LogLevel::Plain) => base,
       ChunkKind::Level(LogLevel::Int) => base.fg(self.base0F)
@@ -105,7 +114,9 @@ impl Base16 {
     StyleProfile {
       base_style: base,
       opaque: false, // TODO: make opaque configurable?
-      chunk_styles: self.chunk_styles(base)
+      error_style: base.fg(self.base09),
+      chunk_styles: self.chunk_styles(base),
+      field_styles: btreemap!{}
     }
   }
 
@@ -114,7 +125,9 @@ impl Base16 {
     StyleProfile {
       base_style: base,
       opaque: true,
-      chunk_styles: self.chunk_styles(base)
+      error_style: base.fg(self.base09),
+      chunk_styles: self.chunk_styles(base),
+      field_styles: btreemap!{}
     }
   }
 
@@ -123,7 +136,9 @@ impl Base16 {
     StyleProfile {
       base_style: base,
       opaque: false,
-      chunk_styles: self.chunk_styles(base)
+      error_style: base.fg(self.base09),
+      chunk_styles: self.chunk_styles(base),
+      field_styles: btreemap!{}
     }
   }
 }
@@ -132,7 +147,14 @@ pub struct StyleProfile {
   base_style: Style,
   opaque: bool,
 
-  chunk_styles: BTreeMap<ChunkKind, Style>
+  /// the style of interactive UI errors, e.g. an invalid filter; unlike level
+  /// styles, it isn't affected by `--colorize-from`
+  error_style: Style,
+
+  chunk_styles: BTreeMap<ChunkKind, Style>,
+
+  /// per-key overrides for `ChunkKind::FieldKey` chunks
+  field_styles: BTreeMap<String, Style>
 }
 
 impl fmt::Debug for StyleProfile {
@@ -163,7 +185,9 @@ impl StyleProfile {
     StyleProfile {
       base_style: Style::new(),
       opaque: false,
-      chunk_styles: btreemap! {}
+      error_style: Style::new(),
+      chunk_styles: btreemap! {},
+      field_styles: btreemap! {}
     }
   }
 
@@ -173,20 +197,31 @@ impl StyleProfile {
     StyleProfile {
       base_style: base,
       opaque: false,
+      error_style: base.fg(Color::Red),
       chunk_styles: btreemap!{
         ChunkKind::Date => base.fg(Color::White).dimmed(),
         ChunkKind::Time => base.fg(Color::White).dimmed(),
         ChunkKind::FieldKey => base.fg(Color::Cyan).dimmed(),
+        ChunkKind::FieldValue(ValueKind::Number) => base.fg(Color::Purple),
+        ChunkKind::FieldValue(ValueKind::Bool) => base.fg(Color::Cyan),
+        ChunkKind::FieldValue(ValueKind::Null) => base.dimmed(),
+        ChunkKind::Quoted => base.fg(Color::Green),
         ChunkKind::Context => base.fg(Color::Black).bold(),
+        ChunkKind::Repeat => base.fg(Color::Yellow).bold(),
+        ChunkKind::Unparsed => base.dimmed(),
 
+        ChunkKind::Level(LogLevel::Trace) => base.fg(Color::Cyan).dimmed(),
         ChunkKind::Level(LogLevel::Debug) => base.fg(Color::Cyan),
         ChunkKind::Level(LogLevel::Info) => base.fg(Color::Green),
+        ChunkKind::Level(LogLevel::Notice) => base.fg(Color::Blue),
         ChunkKind::Level(LogLevel::Warning) => base.fg(Color::Yellow),
         ChunkKind::Level(LogLevel::Error) => base.fg(Color::Red),
+        ChunkKind::Level(LogLevel::Critical) => base.fg(Color::Red).bold(),
         ChunkKind::Level(LogLevel::Fatal) => base.fg(Color::Red).bold(),
         ChunkKind::Level(LogLevel::Plain) => base,
         ChunkKind::Level(LogLevel::Int) => base.fg(Color::Purple).bold()
-      }
+      },
+      field_styles: btreemap!{}
     }
   }
 
@@ -196,16 +231,27 @@ impl StyleProfile {
     StyleProfile {
       base_style: base,
       opaque: true,
+      error_style: base.fg(Color::Red).dimmed(),
       chunk_styles: btreemap!{
         ChunkKind::FieldKey => base.fg(Color::Blue).dimmed(),
-
+        ChunkKind::FieldValue(ValueKind::Number) => base.fg(Color::Purple),
+        ChunkKind::FieldValue(ValueKind::Bool) => base.fg(Color::Blue),
+        ChunkKind::FieldValue(ValueKind::Null) => base.dimmed(),
+        ChunkKind::Quoted => base.fg(Color::Green).dimmed(),
+        ChunkKind::Repeat => base.fg(Color::Purple).bold(),
+        ChunkKind::Unparsed => base.dimmed(),
+
+        ChunkKind::Level(LogLevel::Trace) => base.fg(Color::Blue).dimmed(),
         ChunkKind::Level(LogLevel::Debug) => base.fg(Color::Blue),
         ChunkKind::Level(LogLevel::Info) => base.fg(Color::Green).dimmed(),
+        ChunkKind::Level(LogLevel::Notice) => base.fg(Color::Blue).dimmed(),
         ChunkKind::Level(LogLevel::Warning) => base.fg(Color::Purple).dimmed(),
         ChunkKind::Level(LogLevel::Error) => base.fg(Color::Red).dimmed(),
+        ChunkKind::Level(LogLevel::Critical) => base.fg(Color::Red).dimmed().bold(),
         ChunkKind::Level(LogLevel::Fatal) => base.fg(Color::Red).dimmed().bold(),
         ChunkKind::Level(LogLevel::Int) => base.fg(Color::Purple).bold()
-      }
+      },
+      field_styles: btreemap!{}
     }
   }
 
@@ -215,20 +261,31 @@ impl StyleProfile {
     StyleProfile {
       base_style: base,
       opaque: false,
+      error_style: base.fg(Color::Red),
       chunk_styles: btreemap!{
         ChunkKind::Date => base.fg(Color::White).dimmed(),
         ChunkKind::Time => base.fg(Color::White).dimmed(),
         ChunkKind::FieldKey => base.fg(Color::Cyan).dimmed(),
+        ChunkKind::FieldValue(ValueKind::Number) => base.fg(Color::Purple),
+        ChunkKind::FieldValue(ValueKind::Bool) => base.fg(Color::Cyan),
+        ChunkKind::FieldValue(ValueKind::Null) => base.dimmed(),
+        ChunkKind::Quoted => base.fg(Color::Green),
         ChunkKind::Context => base.fg(Color::Black),
+        ChunkKind::Repeat => base.fg(Color::Yellow),
+        ChunkKind::Unparsed => base.dimmed(),
 
+        ChunkKind::Level(LogLevel::Trace) => base.fg(Color::Cyan),
         ChunkKind::Level(LogLevel::Debug) => base.fg(Color::Cyan),
         ChunkKind::Level(LogLevel::Info) => base.fg(Color::Green),
+        ChunkKind::Level(LogLevel::Notice) => base.fg(Color::Blue),
         ChunkKind::Level(LogLevel::Warning) => base.fg(Color::Yellow),
         ChunkKind::Level(LogLevel::Error) => base.fg(Color::Red),
+        ChunkKind::Level(LogLevel::Critical) => base.fg(Color::Red),
         ChunkKind::Level(LogLevel::Fatal) => base.fg(Color::Red),
         ChunkKind::Level(LogLevel::Plain) => base,
         ChunkKind::Level(LogLevel::Int) => base.fg(Color::Purple)
-      }
+      },
+      field_styles: btreemap!{}
     }
   }
 
@@ -240,12 +297,61 @@ impl StyleProfile {
     &self.base_style
   }
 
+  pub fn get_error(&self) -> &Style {
+    &self.error_style
+  }
+
   pub fn get_style(&self, kind: &ChunkKind) -> &Style {
-    match self.chunk_styles.get(kind) {
+    // badges share the colors of their full-width counterparts, and sources
+    // are just another sort of context
+    let kind = match *kind {
+      ChunkKind::LevelBadge(level) => ChunkKind::Level(level),
+      ChunkKind::Source => ChunkKind::Context,
+      kind => kind
+    };
+
+    match self.chunk_styles.get(&kind) {
       Some(chunk_style) => chunk_style,
       None => &self.base_style
     }
   }
+
+  /// gets the style for a particular chunk, preferring per-key field styles
+  /// over the generic style for the chunk's kind
+  pub fn get_chunk_style(&self, chunk: &Chunk) -> &Style {
+    if let (ChunkKind::FieldKey, Some(value)) = (chunk.kind, &chunk.value) {
+      // field key values are formatted as `key=`
+      if let Some(style) = self.field_styles.get(value.trim_end_matches('=')) {
+        return style;
+      }
+    }
+
+    self.get_style(&chunk.kind)
+  }
+
+  /// adds per-key field styles in the given colors on top of this profile's
+  /// base style
+  pub fn set_field_colors(&mut self, colors: &FieldColors) {
+    for (key, color) in &colors.0 {
+      self.field_styles.insert(key.clone(), self.base_style.fg(*color));
+    }
+  }
+
+  /// drops the colors of levels below `threshold` so they're shown in the base
+  /// style
+  pub fn set_colorize_from(&mut self, threshold: LogLevel) {
+    let below: Vec<ChunkKind> = self.chunk_styles.keys()
+      .filter(|kind| match kind {
+        ChunkKind::Level(level) => *level < threshold,
+        _ => false
+      })
+      .cloned()
+      .collect();
+
+    for kind in below {
+      self.chunk_styles.remove(&kind);
+    }
+  }
 }
 
 #[derive(Copy, Clone)]
@@ -257,6 +363,9 @@ pub enum StyleProfileKind {
 
 #[derive(Debug)]
 pub struct StyleConfig {
+  /// the style name as given in `--style`, e.g. `default`
+  pub name: String,
+
   pub normal: StyleProfile,
   pub selected: StyleProfile,
   pub highlighted: StyleProfile
@@ -265,6 +374,7 @@ pub struct StyleConfig {
 impl StyleConfig {
   pub fn default() -> Self {
     StyleConfig {
+      name: String::from("default"),
       normal: StyleProfile::default_normal(),
       selected: StyleProfile::default_selected(),
       highlighted: StyleProfile::default_highlighted()
@@ -273,12 +383,38 @@ impl StyleConfig {
 
   pub fn from_base16(base16: &Base16) -> Self {
     StyleConfig {
+      name: String::from("base16"),
       normal: base16.to_profile_normal(),
       selected: base16.to_profile_selected(),
       highlighted: base16.to_profile_highlighted()
     }
   }
 
+  /// replaces all profiles with uncolored ones for `--no-color`, keeping the
+  /// selected message visible with reverse video
+  pub fn set_plain(&mut self) {
+    self.normal = StyleProfile::plain();
+    self.highlighted = StyleProfile::plain();
+    self.selected = StyleProfile {
+      base_style: Style::new().reverse(),
+      opaque: true,
+      error_style: Style::new().reverse(),
+      ..StyleProfile::plain()
+    };
+  }
+
+  pub fn set_field_colors(&mut self, colors: &FieldColors) {
+    self.normal.set_field_colors(colors);
+    self.selected.set_field_colors(colors);
+    self.highlighted.set_field_colors(colors);
+  }
+
+  pub fn set_colorize_from(&mut self, threshold: LogLevel) {
+    self.normal.set_colorize_from(threshold);
+    self.selected.set_colorize_from(threshold);
+    self.highlighted.set_colorize_from(threshold);
+  }
+
   pub fn get_profile(&self, kind: StyleProfileKind) -> &StyleProfile {
     match kind {
       //StyleProfileKind::Normal => &self.normal,
@@ -288,6 +424,12 @@ impl StyleConfig {
   }
 }
 
+impl Serialize for StyleConfig {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.name)
+  }
+}
+
 fn load_base16(path: &str) -> Result<StyleConfig, Box<dyn Error>> {
   let expanded_path = shellexpand::full(path)?;
   let file = File::open(&expanded_path.to_string())?;
@@ -307,7 +449,12 @@ impl FromStr for StyleConfig {
 
     if let Some(groups) = RE.captures(s) {
       if let Some(path) = groups.get(1) {
-        load_base16(path.as_str())
+        let mut style = load_base16(path.as_str()).map_err(|e| format!(
+          "error loading base16 style {}: {}", path.as_str(), e
+        ))?;
+        style.name = s.to_string();
+
+        Ok(style)
       } else {
         bail!(format!("invalid b16: {}", s))
       }
@@ -320,6 +467,84 @@ impl FromStr for StyleConfig {
   }
 }
 
+/// parses a color by name or as a hex RGB string, e.g. `red` or `#ff0000`
+fn parse_color(s: &str) -> Option<Color> {
+  let color = match s.to_lowercase().as_str() {
+    "black" => Color::Black,
+    "red" => Color::Red,
+    "green" => Color::Green,
+    "yellow" => Color::Yellow,
+    "blue" => Color::Blue,
+    "purple" => Color::Purple,
+    "cyan" => Color::Cyan,
+    "white" => Color::White,
+    _ => {
+      let hex = s.trim_start_matches('#');
+      if hex.len() != 6 {
+        return None;
+      }
+
+      let rgb = u32::from_str_radix(hex, 16).ok()?;
+      Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+  };
+
+  Some(color)
+}
+
+/// formats a color such that `parse_color` can parse it again
+fn format_color(color: Color) -> String {
+  match color {
+    Color::Black => String::from("black"),
+    Color::Red => String::from("red"),
+    Color::Green => String::from("green"),
+    Color::Yellow => String::from("yellow"),
+    Color::Blue => String::from("blue"),
+    Color::Purple => String::from("purple"),
+    Color::Cyan => String::from("cyan"),
+    Color::White => String::from("white"),
+    Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+
+    // never produced by parse_color
+    Color::Fixed(n) => n.to_string()
+  }
+}
+
+/// A map of field key names to colors, e.g. `error=red,trace_id=#5fafff`
+#[derive(Debug)]
+pub struct FieldColors(BTreeMap<String, Color>);
+
+impl Serialize for FieldColors {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let pairs: Vec<String> = self.0.iter()
+      .map(|(key, color)| format!("{}={}", key, format_color(*color)))
+      .collect();
+
+    serializer.serialize_str(&pairs.join(","))
+  }
+}
+
+impl FromStr for FieldColors {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut colors = BTreeMap::new();
+
+    for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+      let mut parts = pair.splitn(2, '=');
+      let key = parts.next().unwrap_or("");
+      let color = match parts.next().and_then(parse_color) {
+        Some(color) => color,
+        None => bail!(format!("invalid field color: {}", pair))
+      };
+
+      colors.insert(key.to_string(), color);
+    }
+
+    Ok(FieldColors(colors))
+  }
+}
+
 pub type Styler = Box<Rc<dyn Fn(&StyleConfig) -> Style>>;
 
 pub fn styler_base(kind: StyleProfileKind) -> Styler {
@@ -328,6 +553,99 @@ pub fn styler_base(kind: StyleProfileKind) -> Styler {
 
 pub fn styler_error(kind: StyleProfileKind) -> Styler {
   Box::new(Rc::new(move |c| {
-    *c.get_profile(kind).get_style(&ChunkKind::Level(LogLevel::Error))
+    *c.get_profile(kind).get_error()
   }))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_colorize_from() {
+    let mut profile = StyleProfile::default_normal();
+    profile.set_colorize_from(LogLevel::Warning);
+
+    let base = *profile.get_base();
+    assert_that!(*profile.get_style(&ChunkKind::Level(LogLevel::Info)))
+      .is_equal_to(base);
+    assert_that!(*profile.get_style(&ChunkKind::LevelBadge(LogLevel::Debug)))
+      .is_equal_to(base);
+    assert_that!(*profile.get_style(&ChunkKind::Level(LogLevel::Error)))
+      .is_equal_to(base.fg(Color::Red));
+  }
+
+  #[test]
+  fn test_colorize_from_errors() {
+    // ui errors keep their color even if error levels aren't colorized
+    let mut config = StyleConfig::default();
+    config.set_colorize_from(LogLevel::Fatal);
+
+    let selected = config.get_profile(StyleProfileKind::Selected);
+    assert_that!(*selected.get_style(&ChunkKind::Level(LogLevel::Error)))
+      .is_equal_to(*selected.get_base());
+    assert_that!(styler_error(StyleProfileKind::Selected)(&config))
+      .is_equal_to(selected.get_base().fg(Color::Red).dimmed());
+  }
+
+  #[test]
+  fn test_value_kind_styles() {
+    let profile = StyleProfile::default_normal();
+    let style = |kind| *profile.get_style(&ChunkKind::FieldValue(kind));
+
+    // strings and nested values keep the base style
+    let base = *profile.get_base();
+    assert_that!(style(ValueKind::String)).is_equal_to(base);
+    assert_that!(style(ValueKind::Other)).is_equal_to(base);
+
+    assert_that!(style(ValueKind::Number)).is_equal_to(base.fg(Color::Purple));
+    assert_that!(style(ValueKind::Bool)).is_equal_to(base.fg(Color::Cyan));
+    assert_that!(style(ValueKind::Null)).is_equal_to(base.dimmed());
+  }
+
+  fn field_key(key: &str) -> Chunk {
+    Chunk {
+      kind: ChunkKind::FieldKey,
+      value: Some(format!("{}=", key)),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_field_colors_parse() {
+    let colors: FieldColors = "error=red, trace_id=#5fafff,,".parse().unwrap();
+    assert_that!(colors.0).is_equal_to(btreemap! {
+      String::from("error") => Color::Red,
+      String::from("trace_id") => Color::RGB(0x5f, 0xaf, 0xff)
+    });
+
+    assert_that!("error".parse::<FieldColors>()).is_err();
+    assert_that!("error=blurple".parse::<FieldColors>()).is_err();
+    assert_that!("error=#fff".parse::<FieldColors>()).is_err();
+    assert_that!("".parse::<FieldColors>().unwrap().0.len()).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_field_colors_style() {
+    let mut profile = StyleProfile::default_normal();
+    profile.set_field_colors(&"error=red".parse().unwrap());
+
+    let base = *profile.get_base();
+    let key_style = *profile.get_style(&ChunkKind::FieldKey);
+    assert_that!(*profile.get_chunk_style(&field_key("error")))
+      .is_equal_to(base.fg(Color::Red));
+    assert_that!(*profile.get_chunk_style(&field_key("status")))
+      .is_equal_to(key_style);
+
+    // only field keys are overridden, not values that happen to match
+    let value = Chunk {
+      kind: ChunkKind::Text,
+      value: Some(String::from("error=")),
+      ..Default::default()
+    };
+    assert_that!(*profile.get_chunk_style(&value))
+      .is_equal_to(*profile.get_style(&ChunkKind::Text));
+  }
+}